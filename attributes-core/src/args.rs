@@ -0,0 +1,143 @@
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, LitStr, Path, Token};
+
+/// The names of the arguments accepted by `#[framed(...)]`, in the order they
+/// are listed in diagnostics.
+const EXPECTED: &str =
+    "name, crate, with_type, lazy, root_only, barrier, detached, detail, must_poll, record_err, boxed, boxed_local";
+
+/// The parsed arguments of a `#[framed(...)]` attribute.
+#[derive(Default)]
+pub(crate) struct Args {
+    /// `name = "..."`: overrides the name of the frame.
+    pub(crate) name: Option<LitStr>,
+    /// `crate = path`: the path at which `async_backtrace` is reachable.
+    pub(crate) krate: Option<Path>,
+    /// `with_type`: qualifies the name of the frame with the name of `Self`.
+    pub(crate) with_type: Option<Ident>,
+    /// `lazy`: only initializes the frame if the first poll is pending.
+    pub(crate) lazy: Option<Ident>,
+    /// `root_only`: only initializes the frame if it is the root of a task.
+    pub(crate) root_only: Option<Ident>,
+    /// `barrier`: stops the backtraces captured beneath the frame.
+    pub(crate) barrier: Option<Ident>,
+    /// `detached`: initializes the frame as the root of a task, regardless of
+    /// the frame that polls it.
+    pub(crate) detached: Option<Ident>,
+    /// `detail`: marks the frame as a detail, collapsed in taskdumps.
+    pub(crate) detail: Option<Ident>,
+    /// `must_poll`: warns if the future is dropped without ever being polled.
+    pub(crate) must_poll: Option<Ident>,
+    /// `record_err`: records errors returned by the function on the frame of
+    /// its caller.
+    pub(crate) record_err: Option<Ident>,
+    /// `boxed`: the function returns a pinned, boxed, `Send` future.
+    pub(crate) boxed: Option<Ident>,
+    /// `boxed_local`: the function returns a pinned, boxed future.
+    pub(crate) boxed_local: Option<Ident>,
+}
+
+impl Args {
+    /// The path at which `async_backtrace` is reachable from the expansion.
+    pub(crate) fn krate(&self) -> Path {
+        self.krate
+            .clone()
+            .unwrap_or_else(|| syn::parse_quote!(async_backtrace))
+    }
+
+    /// The `boxed` or `boxed_local` argument, if either was given.
+    pub(crate) fn boxed(&self) -> Option<&Ident> {
+        self.boxed.as_ref().or(self.boxed_local.as_ref())
+    }
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let mut args = Args::default();
+
+        while !input.is_empty() {
+            // `crate` is a keyword, so parse the argument name permissively.
+            let key = input.call(Ident::parse_any)?;
+            match key.to_string().as_str() {
+                "name" => {
+                    expect_eq(input, &key)?;
+                    let value = input.parse::<LitStr>()?;
+                    set_once(&mut args.name, &key, value)?;
+                }
+                "crate" => {
+                    expect_eq(input, &key)?;
+                    let value = input.call(Path::parse_mod_style)?;
+                    set_once(&mut args.krate, &key, value)?;
+                }
+                "with_type" => set_once(&mut args.with_type, &key, key.clone())?,
+                "lazy" => set_once(&mut args.lazy, &key, key.clone())?,
+                "root_only" => set_once(&mut args.root_only, &key, key.clone())?,
+                "barrier" => set_once(&mut args.barrier, &key, key.clone())?,
+                "detached" => set_once(&mut args.detached, &key, key.clone())?,
+                "detail" => set_once(&mut args.detail, &key, key.clone())?,
+                "must_poll" => set_once(&mut args.must_poll, &key, key.clone())?,
+                "record_err" => set_once(&mut args.record_err, &key, key.clone())?,
+                "boxed" => set_once(&mut args.boxed, &key, key.clone())?,
+                "boxed_local" => set_once(&mut args.boxed_local, &key, key.clone())?,
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!("unknown argument `{key}`; expected one of: {EXPECTED}"),
+                    ))
+                }
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        if let (Some(_), Some(root_only)) = (&args.lazy, &args.root_only) {
+            return Err(syn::Error::new(
+                root_only.span(),
+                "`root_only` cannot be combined with `lazy`",
+            ));
+        }
+
+        if let (Some(_), Some(detached)) = (&args.lazy, &args.detached) {
+            return Err(syn::Error::new(
+                detached.span(),
+                "`detached` cannot be combined with `lazy`",
+            ));
+        }
+
+        if let (Some(_), Some(boxed_local)) = (&args.boxed, &args.boxed_local) {
+            return Err(syn::Error::new(
+                boxed_local.span(),
+                "`boxed_local` cannot be combined with `boxed`",
+            ));
+        }
+
+        Ok(args)
+    }
+}
+
+/// Parses the `=` separating `key` from its value.
+fn expect_eq(input: ParseStream<'_>, key: &Ident) -> syn::Result<()> {
+    if !input.peek(Token![=]) {
+        return Err(syn::Error::new(
+            key.span(),
+            format!("expected a value for argument `{key}`"),
+        ));
+    }
+    input.parse::<Token![=]>().map(drop)
+}
+
+/// Sets `slot` to `value`, or errors if `slot` was already set.
+fn set_once<T>(slot: &mut Option<T>, key: &Ident, value: T) -> syn::Result<()> {
+    if slot.is_some() {
+        return Err(syn::Error::new(
+            key.span(),
+            format!("duplicate argument `{key}`"),
+        ));
+    }
+    *slot = Some(value);
+    Ok(())
+}
@@ -0,0 +1,247 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+use syn::spanned::Spanned;
+use syn::visit_mut::{self, VisitMut};
+use syn::{
+    parse_quote, Attribute, FnArg, GenericArgument, GenericParam, Lifetime, LifetimeParam, Pat,
+    PatIdent, PathArguments, ReturnType, Signature, Type, TypeImplTrait, TypeParamBound,
+    TypeReference, Visibility, WherePredicate,
+};
+
+use crate::Args;
+
+/// Given an `async fn`, generate a non-async function that returns its framed
+/// future as a `Pin<Box<dyn Future<Output = _> (+ Send) + 'async_backtrace>>`.
+///
+/// The lifetime `'async_backtrace` is bounded by every lifetime and type
+/// parameter of the function, including those that are elided in its
+/// arguments (which are given names, for the purpose).
+pub(crate) fn gen_function(
+    args: &Args,
+    attrs: &[Attribute],
+    vis: &Visibility,
+    sig: &Signature,
+    block: &TokenStream,
+) -> TokenStream {
+    let mut sig = sig.clone();
+    sig.asyncness = None;
+
+    let bound = Lifetime::new("'async_backtrace", Span::call_site());
+
+    // name the elided lifetimes of the arguments
+    let mut elided = ElidedLifetimes::default();
+    let mut receiver = None;
+    for arg in sig.inputs.iter_mut() {
+        match arg {
+            FnArg::Receiver(arg) if arg.colon_token.is_some() => {
+                // `self: &Self`, `self: Box<Self>`, etc.
+                elided.visit_type_mut(&mut arg.ty);
+                receiver = match &*arg.ty {
+                    Type::Reference(ty) => ty.lifetime.clone(),
+                    _ => None,
+                };
+            }
+            FnArg::Receiver(arg) => {
+                // `&self`, `&mut self`, `self`, etc.
+                if let Some((_, lifetime)) = &mut arg.reference {
+                    let lifetime = lifetime.get_or_insert_with(|| elided.next());
+                    receiver = Some(lifetime.clone());
+                }
+            }
+            FnArg::Typed(arg) => {
+                elided.bound = Some(bound.clone());
+                elided.visit_type_mut(&mut arg.ty);
+                elided.bound = None;
+            }
+        }
+    }
+
+    // rebind the arguments within the future, so that they are moved into it
+    // (and dropped with it) regardless of whether they are used, and so that
+    // their patterns are matched within it, as they would be by an `async fn`
+    let mut rebinds = Vec::new();
+    for (i, arg) in sig.inputs.iter_mut().enumerate() {
+        if let FnArg::Typed(arg) = arg {
+            // spanned, so that diagnostics (e.g., of non-`Send` arguments)
+            // point at the argument
+            let ident = format_ident!("__backtrace_attr_arg{}", i, span = arg.pat.span());
+            let pat = std::mem::replace(
+                &mut *arg.pat,
+                Pat::Ident(PatIdent {
+                    attrs: Vec::new(),
+                    by_ref: None,
+                    mutability: None,
+                    ident: ident.clone(),
+                    subpat: None,
+                }),
+            );
+            rebinds.push(quote!(let #pat = #ident;));
+        } else {
+            rebinds.push(quote!(let _ = &self;));
+        }
+    }
+
+    // elided lifetimes in the output refer to that of the receiver or, if
+    // there is none, the sole lifetime of the arguments (if any)
+    let mut output = match &sig.output {
+        ReturnType::Default => parse_quote!(()),
+        ReturnType::Type(_, ty) => (**ty).clone(),
+    };
+    let output_lifetime = receiver.or_else(|| match &elided.named[..] {
+        [lifetime] if sig.generics.lifetimes().next().is_none() => Some(lifetime.clone()),
+        [] => match sig.generics.lifetimes().collect::<Vec<_>>()[..] {
+            [param] => Some(param.lifetime.clone()),
+            _ => None,
+        },
+        _ => None,
+    });
+    if let Some(lifetime) = output_lifetime {
+        ElidedOutput(lifetime).visit_type_mut(&mut output);
+    }
+
+    // bound `'async_backtrace` by every lifetime and type parameter
+    let mut predicates: Vec<WherePredicate> = Vec::new();
+    for param in sig.generics.params.iter() {
+        match param {
+            GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                predicates.push(parse_quote!(#lifetime: #bound));
+            }
+            GenericParam::Type(param) => {
+                let ident = &param.ident;
+                predicates.push(parse_quote!(#ident: #bound));
+            }
+            GenericParam::Const(_) => {}
+        }
+    }
+    for lifetime in &elided.named {
+        predicates.push(parse_quote!(#lifetime: #bound));
+    }
+    if sig.receiver().is_some() {
+        predicates.push(parse_quote!(Self: #bound));
+    }
+
+    let lifetimes = elided
+        .named
+        .iter()
+        .chain(Some(&bound))
+        .map(|lifetime| GenericParam::Lifetime(LifetimeParam::new(lifetime.clone())));
+    // lifetime parameters must precede the others
+    let first_non_lifetime = sig
+        .generics
+        .params
+        .iter()
+        .position(|param| !matches!(param, GenericParam::Lifetime(_)))
+        .unwrap_or(sig.generics.params.len());
+    let mut params: Vec<GenericParam> = sig.generics.params.iter().cloned().collect();
+    params.splice(first_non_lifetime..first_non_lifetime, lifetimes);
+    sig.generics.params = params.into_iter().collect();
+    sig.generics
+        .make_where_clause()
+        .predicates
+        .extend(predicates);
+
+    let send = if args.boxed.is_some() {
+        quote!(+ ::core::marker::Send)
+    } else {
+        quote!()
+    };
+    sig.output = parse_quote!(
+        -> ::core::pin::Pin<::std::boxed::Box<
+            dyn ::core::future::Future<Output = #output> #send + #bound
+        >>
+    );
+
+    let framed = crate::expand::gen_framed(
+        args,
+        &quote!({ #(#rebinds)* #block }),
+        &sig.ident.to_string(),
+        None,
+    );
+    let sig = sig.into_token_stream();
+    quote!(
+        #(#attrs) *
+        #vis #sig
+        {
+            ::std::boxed::Box::pin(#framed)
+        }
+    )
+}
+
+/// Names the elided lifetimes of argument types, and bounds their anonymous
+/// (`impl Trait`) types by `bound`.
+#[derive(Default)]
+struct ElidedLifetimes {
+    /// The lifetimes named so far.
+    named: Vec<Lifetime>,
+    /// The lifetime by which `impl Trait` types are bounded, if any.
+    bound: Option<Lifetime>,
+}
+
+impl ElidedLifetimes {
+    /// Produces a new name for an elided lifetime.
+    fn next(&mut self) -> Lifetime {
+        let lifetime = Lifetime::new(&format!("'life{}", self.named.len()), Span::call_site());
+        self.named.push(lifetime.clone());
+        lifetime
+    }
+}
+
+impl VisitMut for ElidedLifetimes {
+    fn visit_type_reference_mut(&mut self, ty: &mut TypeReference) {
+        if ty.lifetime.is_none() {
+            ty.lifetime = Some(self.next());
+        }
+        visit_mut::visit_type_reference_mut(self, ty);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime.ident == "_" {
+            *lifetime = self.next();
+        }
+    }
+
+    fn visit_type_impl_trait_mut(&mut self, ty: &mut TypeImplTrait) {
+        visit_mut::visit_type_impl_trait_mut(self, ty);
+        if let Some(bound) = &self.bound {
+            ty.bounds.push(TypeParamBound::Lifetime(bound.clone()));
+        }
+    }
+
+    // elided lifetimes within these are bound by the types themselves
+    fn visit_type_bare_fn_mut(&mut self, _: &mut syn::TypeBareFn) {}
+
+    fn visit_path_arguments_mut(&mut self, arguments: &mut PathArguments) {
+        if let PathArguments::AngleBracketed(arguments) = arguments {
+            for argument in arguments.args.iter_mut() {
+                if let GenericArgument::Type(ty) = argument {
+                    self.visit_type_mut(ty);
+                } else if let GenericArgument::Lifetime(lifetime) = argument {
+                    self.visit_lifetime_mut(lifetime);
+                }
+            }
+        }
+    }
+}
+
+/// Names the elided lifetimes of the output type.
+struct ElidedOutput(Lifetime);
+
+impl VisitMut for ElidedOutput {
+    fn visit_type_reference_mut(&mut self, ty: &mut TypeReference) {
+        if ty.lifetime.is_none() {
+            ty.lifetime = Some(self.0.clone());
+        }
+        visit_mut::visit_type_reference_mut(self, ty);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime.ident == "_" {
+            *lifetime = self.0.clone();
+        }
+    }
+
+    fn visit_type_bare_fn_mut(&mut self, _: &mut syn::TypeBareFn) {}
+
+    fn visit_type_impl_trait_mut(&mut self, _: &mut TypeImplTrait) {}
+}
@@ -1,16 +1,17 @@
-use proc_macro2::TokenStream;
+use proc_macro2::{Span, TokenStream};
 use quote::{quote, quote_spanned, ToTokens};
 use syn::visit_mut::VisitMut;
 use syn::{
-    punctuated::Punctuated, spanned::Spanned, Block, Expr, ExprAsync, ExprCall, FnArg, Ident, Item,
-    ItemFn, Pat, PatIdent, Path, ReturnType, Signature, Stmt, Token, Type, TypePath,
+    punctuated::Punctuated, spanned::Spanned, Expr, ExprAsync, ExprCall, FnArg, Item, ItemFn,
+    LitStr, Pat, PatIdent, Path, ReturnType, Signature, Stmt, Token, Type, TypePath,
 };
 
-use crate::MaybeItemFnRef;
+use crate::{Args, MaybeItemFnRef};
 
 /// Given an existing function, generate an instrumented version of that
 /// function
 pub(crate) fn gen_function<'a, B: ToTokens + 'a>(
+    args: &Args,
     input: MaybeItemFnRef<'a, B>,
     instrumented_function_name: &str,
     self_type: Option<&TypePath>,
@@ -68,7 +69,30 @@ pub(crate) fn gen_function<'a, B: ToTokens + 'a>(
         }
     };
 
+    if let (Some(must_poll), Some(_), None) = (&args.must_poll, asyncness, args.boxed()) {
+        // the future of an `async fn` does not run any code (such as the
+        // construction of its frame) until it is first polled
+        return syn::Error::new(
+            must_poll.span(),
+            "`must_poll` requires `boxed` or `boxed_local` on `async fn`s, whose futures are \
+             otherwise only framed once they are polled",
+        )
+        .to_compile_error();
+    }
+
+    if let Some(boxed) = args.boxed() {
+        if asyncness.is_none() {
+            return syn::Error::new(
+                boxed.span(),
+                format!("`{boxed}` can only be applied to `async fn`s"),
+            )
+            .to_compile_error();
+        }
+        return crate::boxed::gen_function(args, attrs, vis, sig, &block);
+    }
+
     let body = gen_block(
+        args,
         &block,
         params,
         asyncness.is_some(),
@@ -76,6 +100,15 @@ pub(crate) fn gen_function<'a, B: ToTokens + 'a>(
         self_type,
     );
 
+    // An async fn's `#[inline]` attributes apply only to the function that
+    // constructs its future, not to the future's `poll`; since its body is
+    // now wrapped in a `Framed` future, that `poll` cannot be inlined
+    // through regardless. The attributes are stripped, rather than left to
+    // provoke warnings from toolchains that lint them.
+    let attrs = attrs
+        .iter()
+        .filter(|attr| asyncness.is_none() || !attr.path().is_ident("inline"));
+
     quote!(
         #(#attrs) *
         #vis #constness #unsafety #asyncness #abi fn #ident<#gen_params>(#params) #output
@@ -86,26 +119,85 @@ pub(crate) fn gen_function<'a, B: ToTokens + 'a>(
     )
 }
 
-/// Instrument a block
 /// Instrument a block
 fn gen_block<B: ToTokens>(
+    args: &Args,
     block: &B,
     _params: &Punctuated<FnArg, Token![,]>,
     async_context: bool,
-    _instrumented_function_name: &str,
-    _self_type: Option<&TypePath>,
+    instrumented_function_name: &str,
+    self_type: Option<&TypePath>,
 ) -> proc_macro2::TokenStream {
     // Generate the instrumented function body.
     // If the function is an `async fn`, this will wrap it in an async block,
-    // which is `instrument`ed using `tracing-futures`. Otherwise, this will
-    // enter the span and then perform the rest of the body.
+    // which is `frame`d. Otherwise, the body is emitted unchanged.
     if async_context {
-        quote!(async_backtrace::frame!(async move { #block }).await)
+        let framed = gen_framed(args, block, instrumented_function_name, self_type);
+        quote!(#framed.await)
     } else {
         quote_spanned!(block.span() => #block)
     }
 }
 
+/// Generate an expression that wraps `block` in an async block, which is
+/// `frame`d.
+///
+/// `self_type` is the type that `Self` denotes in `block`, if `Self` itself
+/// is not in scope (as in the inner function generated by `async-trait`).
+pub(crate) fn gen_framed<B: ToTokens>(
+    args: &Args,
+    block: &B,
+    instrumented_function_name: &str,
+    self_type: Option<&TypePath>,
+) -> proc_macro2::TokenStream {
+    let krate = args.krate();
+    let location = if let Some(with_type) = &args.with_type {
+        let name = args
+            .name
+            .clone()
+            .unwrap_or_else(|| LitStr::new(instrumented_function_name, Span::call_site()));
+        // spanned, so that the error of a function outside of an `impl` or
+        // `trait` points at the argument
+        let self_type = match self_type {
+            Some(self_type) => quote!(#self_type),
+            None => quote_spanned!(with_type.span()=> Self),
+        };
+        quote!(#krate::Location::from_components(
+            #krate::ඞ::qualified_name(::core::any::type_name::<#self_type>(), #name),
+            &(file!(), line!(), column!()),
+        ))
+    } else if let Some(name) = &args.name {
+        quote!(#krate::Location::from_components(#name, &(file!(), line!(), column!())))
+    } else {
+        quote!(#krate::location!())
+    };
+    let block = if let Some(record_err) = &args.record_err {
+        let record =
+            quote_spanned!(record_err.span()=> #krate::ඞ::record_err(&__backtrace_attr_result));
+        quote!({
+            let __backtrace_attr_result = async move { #block }.await;
+            #record;
+            __backtrace_attr_result
+        })
+    } else {
+        quote!(#block)
+    };
+    // each argument sets the option of the same name
+    let mut config = quote!(#krate::FrameConfig::new().origin(#krate::Origin::Attribute));
+    let options = [
+        &args.lazy,
+        &args.root_only,
+        &args.barrier,
+        &args.detached,
+        &args.detail,
+        &args.must_poll,
+    ];
+    for option in options.iter().copied().flatten() {
+        config = quote!(#config.#option());
+    }
+    quote!(#location.frame_with(async move { #block }, #config))
+}
+
 /// The specific async code pattern that was detected
 enum AsyncKind<'a> {
     /// Immediately-invoked async fn, as generated by `async-trait <= 0.1.43`:
@@ -281,7 +373,7 @@ impl<'block> AsyncInfo<'block> {
         })
     }
 
-    pub(crate) fn gen_async(self, instrumented_function_name: &str) -> proc_macro::TokenStream {
+    pub(crate) fn gen_async(self, args: &Args, instrumented_function_name: &str) -> TokenStream {
         // let's rewrite some statements!
         let mut out_stmts: Vec<TokenStream> = self
             .input
@@ -303,6 +395,7 @@ impl<'block> AsyncInfo<'block> {
             out_stmts[iter] = match self.kind {
                 // `Box::pin(immediately_invoked_async_fn())`
                 AsyncKind::Function(fun) => gen_function(
+                    args,
                     fun.into(),
                     instrumented_function_name,
                     self.self_type.as_ref(),
@@ -313,6 +406,7 @@ impl<'block> AsyncInfo<'block> {
                     pinned_box,
                 } => {
                     let instrumented_block = gen_block(
+                        args,
                         &async_expr.block,
                         &self.input.sig.inputs,
                         true,
@@ -342,7 +436,6 @@ impl<'block> AsyncInfo<'block> {
                 #(#out_stmts) *
             }
         )
-        .into()
     }
 }
 
@@ -361,52 +454,6 @@ fn path_to_string(path: &Path) -> String {
     res
 }
 
-/// A visitor struct to replace idents and types in some piece
-/// of code (e.g. the "self" and "Self" tokens in user-supplied
-/// fields expressions when the function is generated by an old
-/// version of async-trait).
-struct IdentAndTypesRenamer<'a> {
-    types: Vec<(&'a str, TypePath)>,
-    idents: Vec<(Ident, Ident)>,
-}
-
-impl<'a> VisitMut for IdentAndTypesRenamer<'a> {
-    // we deliberately compare strings because we want to ignore the spans
-    // If we apply clippy's lint, the behavior changes
-    #[allow(clippy::cmp_owned)]
-    fn visit_ident_mut(&mut self, id: &mut Ident) {
-        for (old_ident, new_ident) in &self.idents {
-            if id.to_string() == old_ident.to_string() {
-                *id = new_ident.clone();
-            }
-        }
-    }
-
-    fn visit_type_mut(&mut self, ty: &mut Type) {
-        for (type_name, new_type) in &self.types {
-            if let Type::Path(TypePath { path, .. }) = ty {
-                if path_to_string(path) == *type_name {
-                    *ty = Type::Path(new_type.clone());
-                }
-            }
-        }
-    }
-}
-
-// A visitor struct that replace an async block by its patched version
-struct AsyncTraitBlockReplacer<'a> {
-    block: &'a Block,
-    patched_block: Block,
-}
-
-impl<'a> VisitMut for AsyncTraitBlockReplacer<'a> {
-    fn visit_block_mut(&mut self, i: &mut Block) {
-        if i == self.block {
-            *i = self.patched_block.clone();
-        }
-    }
-}
-
 // Replaces any `impl Trait` with `_` so it can be used as the type in
 // a `let` statement's LHS.
 struct ImplTraitEraser;
@@ -0,0 +1,157 @@
+//! The expansion of [`async-backtrace`]'s `#[framed]` attribute, as a library.
+//!
+//! [`async-backtrace-attributes`] provides `#[framed]` as a procedural macro,
+//! which is a thin wrapper around [`expand_framed`]. This crate lets build
+//! scripts (e.g., that generate async glue) and other procedural macros emit
+//! already-framed functions, without invoking the macro.
+//!
+//! The generated code refers to `async_backtrace` (or to the path given by
+//! the `crate = path` option), on which the crate that compiles it must
+//! depend.
+//!
+//! ## Example
+//! ```
+//! use async_backtrace_attributes_core::{expand_framed, Options};
+//! use quote::quote;
+//!
+//! let options: Options = syn::parse2(quote!(name = "handshake")).unwrap();
+//! let framed = expand_framed(quote!(async fn connect() {}), &options).unwrap();
+//! assert!(framed.to_string().contains("\"handshake\""));
+//! ```
+//!
+//! [`async-backtrace`]: https://docs.rs/async-backtrace
+//! [`async-backtrace-attributes`]: https://docs.rs/async-backtrace-attributes
+
+use proc_macro2::TokenStream;
+use quote::ToTokens;
+use syn::parse::{Parse, ParseStream};
+use syn::{Attribute, Block, ItemFn, Signature, Visibility};
+
+mod args;
+mod boxed;
+mod expand;
+
+use args::Args;
+
+/// The options of `#[framed(...)]`; e.g., `name = "..."`, `lazy`.
+///
+/// Options are parsed from the tokens between the parentheses of the
+/// attribute (e.g., with [`syn::parse2`]); the [default](Options::default)
+/// options are those of a bare `#[framed]`.
+#[derive(Default)]
+pub struct Options(Args);
+
+impl Parse for Options {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        input.parse().map(Self)
+    }
+}
+
+/// Expands `item` (a function) as does `#[framed(...)]`, with `options`.
+///
+/// Misuses of `options` that are detected once `item` is parsed (e.g.,
+/// `boxed` on a function that is not `async`) are expanded to
+/// `compile_error!` invocations, as they are by the macro; an `Err` is
+/// produced only if `item` is not a function.
+pub fn expand_framed(item: TokenStream, options: &Options) -> Result<TokenStream, syn::Error> {
+    let args = &options.0;
+    // `TokenStream`s are reference counted internally, so cloning is cheap.
+    match instrument_precise(args, item.clone()) {
+        Ok(expanded) => Ok(expanded),
+        Err(_) => instrument_speculative(args, item),
+    }
+}
+
+/// Instrument the function, without parsing the function body (instead using
+/// the raw tokens).
+fn instrument_speculative(args: &Args, item: TokenStream) -> Result<TokenStream, syn::Error> {
+    let input = syn::parse2::<MaybeItemFn>(item)?;
+    let instrumented_function_name = input.sig.ident.to_string();
+    Ok(expand::gen_function(
+        args,
+        input.as_ref(),
+        instrumented_function_name.as_str(),
+        None,
+    ))
+}
+
+/// Instrument the function, by fully parsing the function body,
+/// which allows us to rewrite some statements related to async-like patterns.
+fn instrument_precise(args: &Args, item: TokenStream) -> Result<TokenStream, syn::Error> {
+    let input = syn::parse2::<ItemFn>(item)?;
+    let instrumented_function_name = input.sig.ident.to_string();
+
+    // check for async_trait-like patterns in the block, and instrument
+    // the future instead of the wrapper (which is already boxed, if at all)
+    if let Some(async_like) = expand::AsyncInfo::from_fn(&input).filter(|_| args.boxed().is_none())
+    {
+        return Ok(async_like.gen_async(args, instrumented_function_name.as_str()));
+    }
+
+    Ok(expand::gen_function(
+        args,
+        (&input).into(),
+        instrumented_function_name.as_str(),
+        None,
+    ))
+}
+
+/// This is a more flexible/imprecise `ItemFn` type,
+/// which's block is just a `TokenStream` (it may contain invalid code).
+#[derive(Debug, Clone)]
+struct MaybeItemFn {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    sig: Signature,
+    block: TokenStream,
+}
+
+impl MaybeItemFn {
+    fn as_ref(&self) -> MaybeItemFnRef<'_, TokenStream> {
+        MaybeItemFnRef {
+            attrs: &self.attrs,
+            vis: &self.vis,
+            sig: &self.sig,
+            block: &self.block,
+        }
+    }
+}
+
+/// This parses a `TokenStream` into a `MaybeItemFn`
+/// (just like `ItemFn`, but skips parsing the body).
+impl Parse for MaybeItemFn {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let attrs = input.call(syn::Attribute::parse_outer)?;
+        let vis: Visibility = input.parse()?;
+        let sig: Signature = input.parse()?;
+        let block: TokenStream = input.parse()?;
+        Ok(Self {
+            attrs,
+            vis,
+            sig,
+            block,
+        })
+    }
+}
+
+/// A generic reference type for `MaybeItemFn`,
+/// that takes a generic block type `B` that implements `ToTokens` (eg.
+/// `TokenStream`, `Block`).
+#[derive(Debug, Clone)]
+struct MaybeItemFnRef<'a, B: ToTokens> {
+    attrs: &'a Vec<Attribute>,
+    vis: &'a Visibility,
+    sig: &'a Signature,
+    block: &'a B,
+}
+
+impl<'a> From<&'a ItemFn> for MaybeItemFnRef<'a, Box<Block>> {
+    fn from(val: &'a ItemFn) -> Self {
+        MaybeItemFnRef {
+            attrs: &val.attrs,
+            vis: &val.vis,
+            sig: &val.sig,
+            block: &val.block,
+        }
+    }
+}
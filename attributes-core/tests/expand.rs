@@ -0,0 +1,140 @@
+/// Tests of `expand_framed`, called directly on token fixtures.
+use async_backtrace_attributes_core::{expand_framed, Options};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+fn expand(options: TokenStream, item: TokenStream) -> Result<String, syn::Error> {
+    let options: Options = syn::parse2(options)?;
+    expand_framed(item, &options).map(|expanded| expanded.to_string())
+}
+
+#[test]
+fn async_fn() {
+    let expanded = expand(
+        quote!(),
+        quote!(
+            async fn serve(port: u16) {}
+        ),
+    )
+    .unwrap();
+    let item: syn::ItemFn = syn::parse_str(&expanded).unwrap();
+    assert!(item.sig.asyncness.is_some());
+    assert_eq!(item.sig.ident, "serve");
+    assert!(expanded.contains("async_backtrace :: location ! ()"));
+    assert!(expanded.contains(". frame_with (async move"));
+    assert!(expanded.contains("async_backtrace :: FrameConfig :: new ()"));
+    assert!(expanded.contains("async_backtrace :: Origin :: Attribute"));
+}
+
+#[test]
+fn options() {
+    let expanded = expand(
+        quote!(
+            name = "handshake",
+            crate = ::reexported::async_backtrace,
+            lazy
+        ),
+        quote!(
+            async fn connect() {}
+        ),
+    )
+    .unwrap();
+    assert!(expanded.contains("\"handshake\""));
+    assert!(expanded.contains(":: reexported :: async_backtrace :: Location"));
+    assert!(expanded.contains(". lazy ()"));
+}
+
+#[test]
+fn boxed() {
+    let expanded = expand(
+        quote!(boxed),
+        quote!(
+            async fn fetch(url: &str) -> u8 {
+                0
+            }
+        ),
+    )
+    .unwrap();
+    let item: syn::ItemFn = syn::parse_str(&expanded).unwrap();
+    assert!(item.sig.asyncness.is_none());
+    assert!(expanded.contains(":: std :: boxed :: Box :: pin"));
+}
+
+#[test]
+fn async_trait_like() {
+    // the future is framed, rather than the function that boxes it
+    let expanded = expand(
+        quote!(),
+        quote!(
+            fn run(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+                Box::pin(async move { self.step().await })
+            }
+        ),
+    )
+    .unwrap();
+    let item: syn::ItemFn = syn::parse_str(&expanded).unwrap();
+    assert!(item.sig.asyncness.is_none());
+    assert!(expanded.contains("Box :: pin (async move"));
+    assert!(expanded.contains(". frame_with (async move"));
+}
+
+#[test]
+fn with_type() {
+    let expanded = expand(
+        quote!(with_type),
+        quote!(
+            async fn run(&self) {}
+        ),
+    )
+    .unwrap();
+    assert!(expanded.contains("type_name :: < Self > ()"));
+    assert!(expanded.contains("\"run\""));
+
+    // within the inner function generated by `async-trait <= 0.1.43`, in
+    // which `Self` is not in scope, the type of `_self` is used instead
+    let expanded = expand(
+        quote!(with_type),
+        quote!(
+            fn run<'a>(&'a self) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+                async fn run(_self: &Handler) {}
+                Box::pin(run(self))
+            }
+        ),
+    )
+    .unwrap();
+    assert!(expanded.contains("type_name :: < Handler > ()"));
+}
+
+#[test]
+fn misuse_is_a_compile_error() {
+    let expanded = expand(
+        quote!(boxed),
+        quote!(
+            fn not_async() {}
+        ),
+    )
+    .unwrap();
+    assert!(expanded.contains("compile_error !"));
+    assert!(expanded.contains("can only be applied to `async fn`s"));
+}
+
+#[test]
+fn errors() {
+    let err = expand(
+        quote!(foo),
+        quote!(
+            async fn f() {}
+        ),
+    )
+    .unwrap_err();
+    assert!(err.to_string().starts_with("unknown argument `foo`"));
+
+    let err = expand(
+        quote!(),
+        quote!(
+            struct NotAFunction;
+        ),
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "expected `fn`");
+}
@@ -0,0 +1,64 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// The depth of the chain of frames beneath which backtraces are captured.
+const DEPTH: usize = 10;
+
+/// BNCHMRK-8
+///
+/// Benchmark capturing the backtrace of a frame ten frames deep, as an error
+/// path that attaches context to every failure would.
+///
+/// `backtrace` allocates a boxed slice of the locations of the frames;
+/// `backtrace_with` lends an iterator over them to a callback, which here
+/// counts or formats them without allocating; and `backtrace_depth` counts
+/// them without copying any location.
+fn bench_backtrace(c: &mut Criterion) {
+    let mut group = c.benchmark_group("backtrace");
+    let mut buf = String::with_capacity(4096);
+    nest(DEPTH, &mut || {
+        group.bench_function("backtrace (count)", |b| {
+            b.iter(|| black_box(async_backtrace::backtrace().map_or(0, |frames| frames.len())))
+        });
+        group.bench_function("backtrace_with (count)", |b| {
+            b.iter(|| {
+                black_box(async_backtrace::backtrace_with(|frames| {
+                    frames.map_or(0, |frames| frames.count())
+                }))
+            })
+        });
+        group.bench_function("backtrace_depth", |b| {
+            b.iter(|| black_box(async_backtrace::backtrace_depth()))
+        });
+        group.bench_function("backtrace (write_to)", |b| {
+            b.iter(|| {
+                buf.clear();
+                for location in async_backtrace::backtrace().unwrap().iter() {
+                    location.write_to(&mut buf).unwrap();
+                }
+                black_box(&buf);
+            })
+        });
+        group.bench_function("backtrace_with (write_to)", |b| {
+            b.iter(|| {
+                buf.clear();
+                async_backtrace::backtrace_with(|frames| {
+                    for location in frames.unwrap() {
+                        location.write_to(&mut buf).unwrap();
+                    }
+                });
+                black_box(&buf);
+            })
+        });
+    });
+    group.finish();
+}
+
+/// Invokes `f` within a chain of `depth` frames.
+fn nest(depth: usize, f: &mut dyn FnMut()) {
+    let frame = async_backtrace::raw::Frame::new(async_backtrace::location!());
+    tokio::pin!(frame);
+    frame.in_scope(|| if depth == 1 { f() } else { nest(depth - 1, f) })
+}
+
+criterion_group!(benches, bench_backtrace);
+criterion_main!(benches);
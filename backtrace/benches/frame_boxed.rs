@@ -0,0 +1,85 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A global allocator that counts allocations.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+type Job = Pin<Box<dyn Future<Output = u64> + Send>>;
+
+async fn work() -> u64 {
+    42
+}
+
+/// The number of jobs enqueued per iteration.
+const JOBS: usize = 1024;
+
+/// BNCHMRK-5
+///
+/// Benchmark enqueueing `JOBS` framed `Job`s into a preallocated queue, as
+/// trait objects.
+///
+/// `Location::frame_boxed` frames an unboxed future with one allocation. A
+/// future that is already boxed cannot be framed in-place, and so requires a
+/// second allocation, for its frame. The number of allocations per job of each
+/// approach is printed before it is benchmarked.
+fn bench_frame_boxed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("`Location::frame_boxed`");
+    let mut queue: Vec<Job> = Vec::with_capacity(JOBS);
+
+    let mut bench = |name: &str, enqueue: &dyn Fn(&mut Vec<Job>)| {
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        enqueue(&mut queue);
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        queue.clear();
+        println!("{name}: {} allocation(s) per job", allocations / JOBS);
+
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                enqueue(&mut queue);
+                black_box(&mut queue).clear();
+            })
+        });
+    };
+
+    bench("unframed", &|queue| {
+        queue.extend((0..JOBS).map(|_| Box::pin(work()) as Job))
+    });
+    bench("frame_boxed(unboxed)", &|queue| {
+        queue.extend((0..JOBS).map(|_| async_backtrace::location!().frame_boxed(work())))
+    });
+    bench("frame_boxed(boxed)", &|queue| {
+        queue.extend((0..JOBS).map(|_| {
+            let job: Job = Box::pin(work());
+            async_backtrace::location!().frame_boxed(job)
+        }))
+    });
+    bench("Box::pin(frame!(..))", &|queue| {
+        queue.extend((0..JOBS).map(|_| Box::pin(async_backtrace::frame!(work())) as Job))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_frame_boxed);
+criterion_main!(benches);
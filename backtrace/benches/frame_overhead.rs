@@ -39,12 +39,16 @@ macro_rules! parbench {
     }
 }
 
+/// Run with `--features sequence-numbers` to include the overhead of recording
+/// sequence numbers (one atomic increment upon the initialization, and upon
+/// each poll, of every frame).
 fn bench_frame_overhead(c: &mut Criterion) {
     let mut group = c.benchmark_group("`Frame` overhead");
     bench_root_poll_first(&mut group);
     bench_root_poll_rest(&mut group);
     bench_subframe_poll_first(&mut group);
     bench_subframe_poll_rest(&mut group);
+    bench_root_poll_ready(&mut group);
     group.finish();
 }
 
@@ -72,7 +76,7 @@ fn bench_root_poll_first<M: Measurement<Value = Duration>>(c: &mut BenchmarkGrou
             setup {}
             bench {
                 // initialize a `Frame`
-                let frame = async_backtrace::ඞ::Frame::new(async_backtrace::location!());
+                let frame = async_backtrace::raw::Frame::new(async_backtrace::location!());
                 tokio::pin!(frame);
                 // invoke `Frame::in_scope` once
                 let _ = black_box(frame.as_mut().in_scope(|| black_box(42)));
@@ -107,7 +111,7 @@ fn bench_root_poll_rest<M: Measurement<Value = Duration>>(c: &mut BenchmarkGroup
             b;
             setup {
                 // initialize a `Frame`
-                let frame = async_backtrace::ඞ::Frame::new(async_backtrace::location!());
+                let frame = async_backtrace::raw::Frame::new(async_backtrace::location!());
                 tokio::pin!(frame);
                 // invoke `Frame::in_scope` once
                 let _ = black_box(frame.as_mut().in_scope(|| black_box(42)));
@@ -133,13 +137,13 @@ fn bench_root_poll_rest<M: Measurement<Value = Duration>>(c: &mut BenchmarkGroup
 /// locking.
 fn bench_subframe_poll_first<M: Measurement<Value = Duration>>(c: &mut BenchmarkGroup<'_, M>) {
     c.bench_function("Frame::in_scope (subframe, first)", move |b| {
-        let root = async_backtrace::ඞ::Frame::new(async_backtrace::location!());
+        let root = async_backtrace::raw::Frame::new(async_backtrace::location!());
         tokio::pin!(root);
         root.in_scope(|| {
             // within the scope of a root `Frame`, benchmark:
             b.iter(|| {
                 // ...initializing a sub-`Frame`,
-                let frame = async_backtrace::ඞ::Frame::new(async_backtrace::location!());
+                let frame = async_backtrace::raw::Frame::new(async_backtrace::location!());
                 tokio::pin!(frame);
                 // ...and invoking `Frame::in_scope` once on it.
                 let _ = black_box(frame.as_mut().in_scope(|| black_box(42)));
@@ -156,11 +160,11 @@ fn bench_subframe_poll_first<M: Measurement<Value = Duration>>(c: &mut Benchmark
 /// sub-`#[framed]` functions. It should be virtually free.
 fn bench_subframe_poll_rest<M: Measurement<Value = Duration>>(c: &mut BenchmarkGroup<'_, M>) {
     c.bench_function("Frame::in_scope (subframe, rest)", move |b| {
-        let root = async_backtrace::ඞ::Frame::new(async_backtrace::location!());
+        let root = async_backtrace::raw::Frame::new(async_backtrace::location!());
         tokio::pin!(root);
         root.in_scope(|| {
             // within the scope of a root `Frame`, initialize a subframe,
-            let frame = async_backtrace::ඞ::Frame::new(async_backtrace::location!());
+            let frame = async_backtrace::raw::Frame::new(async_backtrace::location!());
             tokio::pin!(frame);
             // invoke `Frame::in_scope` on it
             let _ = black_box(frame.as_mut().in_scope(|| black_box(42)));
@@ -172,5 +176,39 @@ fn bench_subframe_poll_rest<M: Measurement<Value = Duration>>(c: &mut BenchmarkG
     });
 }
 
+/// BNCHMRK-4
+///
+/// Benchmark polling an immediately-ready root future, framed eagerly and
+/// lazily.
+///
+/// The results of this benchmark reflect the savings of `#[framed(lazy)]` for
+/// spawned functions that usually complete upon their first poll (e.g., cache
+/// hits). The lazy variant should be nearly free, since it neither initializes
+/// nor registers its `Frame`.
+fn bench_root_poll_ready<M: Measurement<Value = Duration>>(c: &mut BenchmarkGroup<'_, M>) {
+    use std::future::Future;
+    use std::task::Context;
+
+    let waker = futures::task::noop_waker();
+
+    c.bench_function("Framed::poll (root, ready, eager)", |b| {
+        let mut cx = Context::from_waker(&waker);
+        b.iter(|| {
+            let future = async_backtrace::location!().frame(async { black_box(42) });
+            tokio::pin!(future);
+            let _ = black_box(future.poll(&mut cx));
+        })
+    });
+
+    c.bench_function("Framed::poll (root, ready, lazy)", |b| {
+        let mut cx = Context::from_waker(&waker);
+        b.iter(|| {
+            let future = async_backtrace::location!().frame_lazy(async { black_box(42) });
+            tokio::pin!(future);
+            let _ = black_box(future.poll(&mut cx));
+        })
+    });
+}
+
 criterion_group!(benches, bench_frame_overhead);
 criterion_main!(benches);
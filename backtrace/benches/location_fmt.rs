@@ -0,0 +1,48 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::fmt::Write;
+
+/// BNCHMRK-6
+///
+/// Benchmark rendering a `Location` into a reused buffer, as an exporter that
+/// formats many locations would.
+///
+/// `Location::write_to` and `CompactLocation` write directly into the buffer,
+/// which `Location::len_hint` allows to be sized up front; `to_string`
+/// allocates a new `String` per location.
+fn bench_location_fmt(c: &mut Criterion) {
+    let mut group = c.benchmark_group("`Location` formatting");
+    let location = async_backtrace::location!();
+    let mut buf = String::with_capacity(location.len_hint());
+
+    group.bench_function("Location::to_string", |b| {
+        b.iter(|| black_box(black_box(location).to_string()))
+    });
+    group.bench_function("Location::write_to", |b| {
+        b.iter(|| {
+            buf.clear();
+            black_box(location).write_to(&mut buf).unwrap();
+            black_box(&buf);
+        })
+    });
+    group.bench_function("write!(Location)", |b| {
+        b.iter(|| {
+            buf.clear();
+            write!(buf, "{}", black_box(location)).unwrap();
+            black_box(&buf);
+        })
+    });
+    group.bench_function("write!(CompactLocation)", |b| {
+        b.iter(|| {
+            buf.clear();
+            write!(buf, "{}", black_box(location).as_compact()).unwrap();
+            black_box(&buf);
+        })
+    });
+    group.bench_function("Location::len_hint", |b| {
+        b.iter(|| black_box(black_box(location).len_hint()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_location_fmt);
+criterion_main!(benches);
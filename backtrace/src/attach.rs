@@ -0,0 +1,242 @@
+//! Carrying the context of a framed future into synchronous code that runs on
+//! another thread, such as a closure passed to `tokio::task::spawn_blocking`.
+
+use std::{cell::RefCell, fmt, sync::Arc};
+
+use crate::{AnnotatedFrame, Frame, Location, TaskInfo};
+
+/// The location that marks the innermost entry of a backtrace produced while
+/// a [`ContextHandle`] is [attached](ContextHandle::attach).
+const SNAPSHOT: Location = Location::from_components(
+    "async_backtrace::ContextHandle::attach [snapshot]",
+    &(file!(), line!(), column!()),
+);
+
+std::thread_local! {
+    /// The context attached to this thread by [`ContextHandle::attach`], if
+    /// any.
+    static ATTACHED: RefCell<Option<Arc<Snapshot>>> = const { RefCell::new(None) };
+}
+
+/// A snapshot of the context of the currently-active frame, produced by
+/// [`capture_context`].
+///
+/// The snapshot may be sent to another thread and
+/// [attached](ContextHandle::attach) there, so that synchronous code (which
+/// cannot be framed) reports where it was called from.
+#[derive(Clone, Default)]
+pub struct ContextHandle {
+    snapshot: Option<Arc<Snapshot>>,
+}
+
+/// The context captured by a [`ContextHandle`].
+#[derive(Debug)]
+struct Snapshot {
+    backtrace: Box<[Location]>,
+    /// The locations of the frames beyond the nearest barrier of the
+    /// backtrace (if any), for [`backtrace_through_barriers`].
+    beyond_barrier: Box<[Location]>,
+    /// The frames of the backtrace, with their annotations, for
+    /// [`backtrace_annotated`].
+    annotated: Box<[AnnotatedFrame]>,
+    root: Location,
+    task_id: Option<u64>,
+}
+
+/// Captures the context of the currently-active frame (if any), to be
+/// [attached](ContextHandle::attach) elsewhere.
+///
+/// Outside of the poll of a framed future, the handle captures the context
+/// attached to this thread (if any) and is otherwise empty; attaching an
+/// empty handle has no effect.
+///
+/// ## Example
+/// ```
+/// #[tokio::main]
+/// async fn main() {
+///     read_config().await;
+/// }
+///
+/// #[async_backtrace::framed]
+/// async fn read_config() {
+///     let context = async_backtrace::capture_context();
+///     tokio::task::spawn_blocking(move || {
+///         context.attach(|| {
+///             let backtrace = async_backtrace::backtrace().unwrap();
+///             // the innermost location marks the backtrace as a snapshot
+///             assert!(backtrace[0].name().unwrap().ends_with("[snapshot]"));
+///             assert_eq!(backtrace[1].name(), Some("rust_out::read_config::{{closure}}"));
+///         })
+///     })
+///     .await
+///     .unwrap();
+/// }
+/// ```
+pub fn capture_context() -> ContextHandle {
+    let snapshot = Frame::with_active(|maybe_frame| {
+        maybe_frame.map(|frame| {
+            let root = frame.root();
+            let backtrace = frame.backtrace_locations();
+            Arc::new(Snapshot {
+                beyond_barrier: frame
+                    .ancestors()
+                    .skip(backtrace.len())
+                    .map(Frame::location)
+                    .collect(),
+                annotated: frame
+                    .backtrace()
+                    // SAFETY: The active frame's root is locked for the
+                    // duration of its `in_scope`, which encloses this call.
+                    .map(|frame| unsafe { AnnotatedFrame::capture(frame) })
+                    .collect(),
+                backtrace,
+                root: root.location(),
+                task_id: root.task_id(),
+            })
+        })
+    });
+    ContextHandle {
+        snapshot: snapshot.or_else(attached),
+    }
+}
+
+impl ContextHandle {
+    /// Invokes `f` with this context attached to the current thread.
+    ///
+    /// Within `f`, outside of the poll of any framed future,
+    /// [`backtrace`](crate::backtrace) (and each of its variants, such as
+    /// [`backtrace_annotated`](crate::backtrace_annotated), whose annotations
+    /// are those of the captured frames) produces the captured backtrace,
+    /// beneath an entry that marks it as a snapshot, and
+    /// [`root_location`](crate::root_location) and
+    /// [`root_task_id`](crate::root_task_id) produce those of the captured
+    /// task. The previously-attached context (if any) is restored once `f`
+    /// returns or unwinds.
+    pub fn attach<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        if self.snapshot.is_none() {
+            return f();
+        }
+        let previous = ATTACHED.with(|attached| attached.replace(self.snapshot.clone()));
+        let _restore = crate::defer(|| ATTACHED.with(|attached| *attached.borrow_mut() = previous));
+        f()
+    }
+
+    /// Produces `true` if no context was captured.
+    pub fn is_empty(&self) -> bool {
+        self.snapshot.is_none()
+    }
+
+    /// The captured backtrace (if any), innermost location first, without the
+    /// entry that marks it as a snapshot.
+    pub fn backtrace(&self) -> Option<&[Location]> {
+        self.snapshot
+            .as_ref()
+            .map(|snapshot| &snapshot.backtrace[..])
+    }
+
+    /// The location of the root frame of the captured task (if any).
+    pub fn root_location(&self) -> Option<Location> {
+        self.snapshot.as_ref().map(|snapshot| snapshot.root)
+    }
+
+    /// The [id](crate::Task::id) of the captured task (if any).
+    pub fn task_id(&self) -> Option<u64> {
+        self.snapshot.as_ref().and_then(|snapshot| snapshot.task_id)
+    }
+}
+
+impl fmt::Debug for ContextHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContextHandle")
+            .field("backtrace", &self.backtrace())
+            .field("task_id", &self.task_id())
+            .finish()
+    }
+}
+
+/// The context attached to this thread (if any).
+fn attached() -> Option<Arc<Snapshot>> {
+    ATTACHED.with(|attached| attached.borrow().clone())
+}
+
+/// The backtrace of the context attached to this thread (if any), beneath the
+/// entry that marks it as a snapshot.
+pub(crate) fn backtrace() -> Option<Box<[Location]>> {
+    attached().map(|snapshot| {
+        let mut backtrace = Vec::with_capacity(snapshot.backtrace.len() + 1);
+        backtrace.push(SNAPSHOT);
+        backtrace.extend_from_slice(&snapshot.backtrace);
+        backtrace.into_boxed_slice()
+    })
+}
+
+/// The backtrace of the context attached to this thread (if any), beneath the
+/// entry that marks it as a snapshot, continuing past barriers as
+/// [`backtrace_through_barriers`](crate::backtrace_through_barriers) does.
+pub(crate) fn backtrace_through_barriers() -> Option<Box<[Location]>> {
+    attached().map(|snapshot| {
+        std::iter::once(SNAPSHOT)
+            .chain(snapshot.backtrace.iter().copied())
+            .chain(snapshot.beyond_barrier.iter().copied())
+            .collect()
+    })
+}
+
+/// The annotated backtrace of the context attached to this thread (if any),
+/// as of its capture, beneath the entry that marks it as a snapshot.
+pub(crate) fn backtrace_annotated() -> Option<Vec<AnnotatedFrame>> {
+    attached().map(|snapshot| {
+        std::iter::once(AnnotatedFrame::unannotated(SNAPSHOT))
+            .chain(snapshot.annotated.iter().cloned())
+            .collect()
+    })
+}
+
+/// The length of the [backtrace](backtrace) of the context attached to this
+/// thread (if any), without allocating.
+pub(crate) fn backtrace_depth() -> Option<usize> {
+    ATTACHED.with(|attached| {
+        let attached = attached.borrow();
+        attached
+            .as_ref()
+            .map(|snapshot| snapshot.backtrace.len() + 1)
+    })
+}
+
+/// Invokes `f` with an iterator over the backtrace of the context attached to
+/// this thread (if any), beneath the entry that marks it as a snapshot.
+pub(crate) fn backtrace_with<F, R>(f: F) -> R
+where
+    F: FnOnce(Option<&mut dyn Iterator<Item = Location>>) -> R,
+{
+    match attached() {
+        Some(snapshot) => {
+            let mut backtrace = std::iter::once(SNAPSHOT).chain(snapshot.backtrace.iter().copied());
+            f(Some(&mut backtrace))
+        }
+        None => f(None),
+    }
+}
+
+/// The location of the root of the context attached to this thread (if any).
+pub(crate) fn root_location() -> Option<Location> {
+    attached().map(|snapshot| snapshot.root)
+}
+
+/// The task id of the context attached to this thread (if any).
+pub(crate) fn root_task_id() -> Option<u64> {
+    attached().and_then(|snapshot| snapshot.task_id)
+}
+
+/// The task of the context attached to this thread (if any).
+pub(crate) fn task() -> Option<TaskInfo> {
+    attached().and_then(|snapshot| {
+        snapshot.task_id.map(|id| TaskInfo {
+            id,
+            location: snapshot.root,
+        })
+    })
+}
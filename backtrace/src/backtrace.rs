@@ -0,0 +1,139 @@
+//! The backtraces produced by [`backtrace`](crate::backtrace).
+
+use std::{fmt, ops::Deref};
+
+use crate::Location;
+
+/// A backtrace, as produced by [`backtrace`](crate::backtrace): the locations
+/// of the active frame and its ancestors, innermost first.
+///
+/// It dereferences to a slice of the locations, and is rendered with a
+/// numbered line per location; e.g.:
+/// ```text
+/// #0 app::read::{{closure}} at src/main.rs:20:1
+/// #1 app::serve::{{closure}} at src/main.rs:8:1
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct AsyncBacktrace {
+    locations: Box<[Location]>,
+}
+
+impl AsyncBacktrace {
+    pub(crate) fn new(locations: Box<[Location]>) -> Self {
+        Self { locations }
+    }
+
+    /// The number of locations in the backtrace.
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    /// Produces `true` if the backtrace has no locations.
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    /// The locations of the backtrace, innermost first.
+    pub fn locations(&self) -> &[Location] {
+        &self.locations
+    }
+}
+
+impl Deref for AsyncBacktrace {
+    type Target = [Location];
+
+    fn deref(&self) -> &[Location] {
+        &self.locations
+    }
+}
+
+impl From<AsyncBacktrace> for Box<[Location]> {
+    fn from(backtrace: AsyncBacktrace) -> Self {
+        backtrace.locations
+    }
+}
+
+impl IntoIterator for AsyncBacktrace {
+    type Item = Location;
+    type IntoIter = std::vec::IntoIter<Location>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.locations.into_vec().into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a AsyncBacktrace {
+    type Item = &'a Location;
+    type IntoIter = std::slice::Iter<'a, Location>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.locations.iter()
+    }
+}
+
+impl fmt::Display for AsyncBacktrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, location) in self.locations.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "#{} {}", i, location)?;
+        }
+        Ok(())
+    }
+}
+
+/// A native backtrace of the current thread, along with the async backtrace
+/// of the active frame (if any), as produced by
+/// [`backtrace_full`](crate::backtrace_full). Requires the
+/// `native-backtrace` feature.
+///
+/// It is rendered as a single report: the native frames, then a separator,
+/// then the async backtrace; e.g.:
+/// ```text
+///    0: app::read::{{closure}}
+///              at ./src/main.rs:22:5
+///    ...
+/// == async backtrace ==
+/// #0 app::read::{{closure}} at src/main.rs:20:1
+/// #1 app::serve::{{closure}} at src/main.rs:8:1
+/// ```
+/// Each half may also be inspected (e.g., logged as a separate field) on its
+/// own.
+#[cfg(feature = "native-backtrace")]
+#[derive(Debug)]
+pub struct FullBacktrace {
+    native: std::backtrace::Backtrace,
+    logical: Option<AsyncBacktrace>,
+}
+
+#[cfg(feature = "native-backtrace")]
+impl FullBacktrace {
+    pub(crate) fn new(native: std::backtrace::Backtrace, logical: Option<AsyncBacktrace>) -> Self {
+        Self { native, logical }
+    }
+
+    /// The native backtrace of the thread on which this was captured.
+    pub fn native(&self) -> &std::backtrace::Backtrace {
+        &self.native
+    }
+
+    /// The async backtrace, as produced by [`backtrace`](crate::backtrace),
+    /// or `None` if no frame was active.
+    pub fn logical(&self) -> Option<&AsyncBacktrace> {
+        self.logical.as_ref()
+    }
+}
+
+#[cfg(feature = "native-backtrace")]
+impl fmt::Display for FullBacktrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // (the native backtrace ends with a newline of its own)
+        writeln!(f, "{}", self.native.to_string().trim_end())?;
+        f.write_str("== async backtrace ==\n")?;
+        match &self.logical {
+            Some(logical) => write!(f, "{}", logical),
+            None => f.write_str("(no active frame)"),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{Mutex, Once, RwLock},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::Location;
+
+/// The default minimum interval between reports of panics at the same
+/// location.
+const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+std::thread_local! {
+    /// The number of [`catching`] futures being polled on this thread.
+    static CATCHING: Cell<usize> = const { Cell::new(0) };
+
+    /// The report of the most recent panic on this thread, captured by the
+    /// panic hook while a [`catching`] future was being polled.
+    static CAPTURED: RefCell<Option<PanicReport>> = const { RefCell::new(None) };
+}
+
+/// The destination of [`PanicReport`]s.
+struct Sink {
+    min_interval: Duration,
+    report: Box<dyn Fn(&PanicReport) + Send + Sync>,
+}
+
+static SINK: Lazy<RwLock<Sink>> = Lazy::new(|| {
+    RwLock::new(Sink {
+        min_interval: DEFAULT_MIN_INTERVAL,
+        report: Box::new(|report| eprintln!("{report}")),
+    })
+});
+
+/// When each location last had a panic reported.
+static LAST_REPORTED: Lazy<Mutex<HashMap<Location, Instant>>> = Lazy::new(Default::default);
+
+/// The backtrace of a panic within a [`catching_frame!`](crate::catching_frame).
+#[derive(Debug, Clone)]
+pub struct PanicReport {
+    backtrace: Box<[Location]>,
+    message: Option<String>,
+}
+
+impl PanicReport {
+    /// The async backtrace at the site of the panic, innermost frame first.
+    pub fn backtrace(&self) -> &[Location] {
+        &self.backtrace
+    }
+
+    /// The panic's message, if its payload was a string.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+impl fmt::Display for PanicReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "panicked: {}", self.message().unwrap_or("Box<dyn Any>"))?;
+        for location in self.backtrace.iter() {
+            write!(f, "\n  at {location}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Sets the destination of the [`PanicReport`]s of
+/// [`catching_frame!`](crate::catching_frame)s.
+///
+/// A panic is reported only if no other panic has been reported at the same
+/// location (the innermost frame of its backtrace) within `min_interval`. By
+/// default, reports are printed to standard error, at most once a minute per
+/// location.
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+///
+/// async_backtrace::set_panic_sink(Duration::from_secs(300), |report| {
+///     eprintln!("task panicked: {}", report);
+/// });
+/// ```
+pub fn set_panic_sink<F>(min_interval: Duration, sink: F)
+where
+    F: Fn(&PanicReport) + Send + Sync + 'static,
+{
+    let mut guard = SINK.write().unwrap_or_else(|err| err.into_inner());
+    *guard = Sink {
+        min_interval,
+        report: Box::new(sink),
+    };
+}
+
+/// Installs (once) a panic hook that captures the backtrace of panics that
+/// occur while a [`catching`] future is polled, before chaining to the
+/// previous hook.
+fn install_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if CATCHING.with(Cell::get) > 0 {
+                // the hook runs at the panic site, while its frame is active
+                if let Some(backtrace) = crate::backtrace() {
+                    let payload = info.payload();
+                    let message = payload
+                        .downcast_ref::<&str>()
+                        .map(|message| message.to_string())
+                        .or_else(|| payload.downcast_ref::<String>().cloned());
+                    let report = PanicReport {
+                        backtrace: backtrace.into(),
+                        message,
+                    };
+                    CAPTURED.with(|captured| *captured.borrow_mut() = Some(report));
+                }
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// Sends `report` to the sink, unless a panic at the same location was
+/// reported too recently.
+fn report(report: PanicReport) {
+    let sink = SINK.read().unwrap_or_else(|err| err.into_inner());
+    if let Some(&location) = report.backtrace.first() {
+        let mut last_reported = LAST_REPORTED.lock().unwrap_or_else(|err| err.into_inner());
+        let now = Instant::now();
+        match last_reported.get(&location) {
+            Some(last) if now.duration_since(*last) < sink.min_interval => return,
+            _ => last_reported.insert(location, now),
+        };
+    }
+    (sink.report)(&report);
+}
+
+pin_project_lite::pin_project! {
+    /// A future that reports the backtraces of panics within it.
+    struct Catching<F> {
+        #[pin]
+        future: F,
+    }
+}
+
+impl<F: Future> Future for Catching<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let future = self.project().future;
+        CATCHING.with(|catching| catching.set(catching.get() + 1));
+        let _restore = crate::defer(|| CATCHING.with(|catching| catching.set(catching.get() - 1)));
+        match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(poll) => poll,
+            Err(payload) => {
+                if let Some(captured) = CAPTURED.with(|captured| captured.borrow_mut().take()) {
+                    report(captured);
+                }
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+}
+
+/// Reports the backtraces of panics within `future`; see
+/// [`catching_frame!`](crate::catching_frame).
+pub fn catching<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    install_hook();
+    Catching { future }
+}
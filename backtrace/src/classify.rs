@@ -0,0 +1,166 @@
+//! Coarse classification of what the leaf frames of tasks are waiting on, for
+//! dashboards; e.g., how many tasks are waiting on timers, rather than on
+//! I/O.
+//!
+//! Each leaf frame is classified by the first matching pattern of a registry,
+//! which includes patterns for common libraries (e.g., `tokio::time::sleep`),
+//! and to which applications [register](register) the locations of their own
+//! leaf futures. A pattern is a path (e.g., `tokio::sync::mpsc`, or
+//! `app::db::query`), which matches the [key](crate::Location::metric_key) of
+//! a location if it appears in the key as a run of whole path segments; so,
+//! `sync::mpsc` matches `tokio::sync::mpsc::Receiver::recv`, but `sync::mp`
+//! does not.
+//!
+//! ## Example
+//! ```
+//! use async_backtrace::classify::{self, LeafState};
+//!
+//! #[async_backtrace::framed]
+//! async fn read_frame() {
+//!     // ...
+//! }
+//!
+//! classify::register("read_frame", LeafState::PendingOnIo);
+//!
+//! for (state, count) in classify::leaf_state_histogram() {
+//!     println!("{}: {}", state, count);
+//! }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{PoisonError, RwLock},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{FrameTree, Location};
+
+/// The coarse state of a leaf frame.
+///
+/// The [names](LeafState::as_str) of the states are stable, for use as metric
+/// labels.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[non_exhaustive]
+pub enum LeafState {
+    /// Waiting for a timer; e.g., a sleep, an interval or a timeout.
+    PendingOnTimer,
+    /// Waiting for a message on a channel.
+    PendingOnChannel,
+    /// Waiting for I/O; e.g., on a socket or a file.
+    PendingOnIo,
+    /// Waiting on something that no pattern matched.
+    Unknown,
+}
+
+impl LeafState {
+    /// All states, in order.
+    pub const ALL: [LeafState; 4] = [
+        LeafState::PendingOnTimer,
+        LeafState::PendingOnChannel,
+        LeafState::PendingOnIo,
+        LeafState::Unknown,
+    ];
+
+    /// Produces the stable name of this state; e.g., `pending_on_timer`.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            LeafState::PendingOnTimer => "pending_on_timer",
+            LeafState::PendingOnChannel => "pending_on_channel",
+            LeafState::PendingOnIo => "pending_on_io",
+            LeafState::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for LeafState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The patterns registered by default.
+const DEFAULT_PATTERNS: &[(&str, LeafState)] = &[
+    ("tokio::time", LeafState::PendingOnTimer),
+    ("async_io::Timer", LeafState::PendingOnTimer),
+    ("futures_timer::Delay", LeafState::PendingOnTimer),
+    ("tokio::sync::mpsc", LeafState::PendingOnChannel),
+    ("tokio::sync::oneshot", LeafState::PendingOnChannel),
+    ("tokio::sync::broadcast", LeafState::PendingOnChannel),
+    ("tokio::sync::watch", LeafState::PendingOnChannel),
+    ("futures_channel", LeafState::PendingOnChannel),
+    ("futures::channel", LeafState::PendingOnChannel),
+    ("async_channel", LeafState::PendingOnChannel),
+    ("flume", LeafState::PendingOnChannel),
+    ("tokio::net", LeafState::PendingOnIo),
+    ("tokio::io", LeafState::PendingOnIo),
+    ("tokio::fs", LeafState::PendingOnIo),
+    ("async_io::Async", LeafState::PendingOnIo),
+];
+
+/// The registered patterns, most recently registered first.
+static PATTERNS: Lazy<RwLock<Vec<(String, LeafState)>>> = Lazy::new(|| {
+    let patterns = DEFAULT_PATTERNS.iter().rev();
+    RwLock::new(
+        patterns
+            .map(|&(pattern, state)| (pattern.to_string(), state))
+            .collect(),
+    )
+});
+
+/// Classifies the locations that match `pattern` as `state`.
+///
+/// Patterns registered later take precedence over those registered earlier
+/// (and over the default patterns); so, a general pattern (e.g., `app::db`)
+/// may be refined by a more specific one (e.g., `app::db::pool`).
+pub fn register(pattern: impl Into<String>, state: LeafState) {
+    let mut patterns = PATTERNS.write().unwrap_or_else(PoisonError::into_inner);
+    patterns.insert(0, (pattern.into(), state));
+}
+
+/// Produces the state of a leaf frame at `location`, as classified by the
+/// first matching pattern; or [`LeafState::Unknown`], if none match.
+pub fn classify(location: Location) -> LeafState {
+    let key = location.metric_key();
+    let patterns = PATTERNS.read().unwrap_or_else(PoisonError::into_inner);
+    patterns
+        .iter()
+        .find(|(pattern, _)| matches(pattern, &key))
+        .map_or(LeafState::Unknown, |&(_, state)| state)
+}
+
+/// Produces the number of leaf frames of all tasks in each state (including
+/// those in which there are none).
+///
+/// Tasks are not waited for; tasks that are being polled are not waiting on
+/// anything, and are not counted.
+pub fn leaf_state_histogram() -> BTreeMap<LeafState, usize> {
+    fn count(frame: &FrameTree, histogram: &mut BTreeMap<LeafState, usize>) {
+        if frame.children().is_empty() {
+            *histogram.entry(classify(frame.location())).or_default() += 1;
+        }
+        for child in frame.children() {
+            count(child, histogram);
+        }
+    }
+
+    let mut histogram = LeafState::ALL.iter().map(|&state| (state, 0)).collect();
+    for tree in crate::snapshot(false) {
+        if !tree.is_polling() {
+            count(tree.root(), &mut histogram);
+        }
+    }
+    histogram
+}
+
+/// Produces `true` if `pattern` appears in `key` as a run of whole path
+/// segments.
+fn matches(pattern: &str, key: &str) -> bool {
+    !pattern.is_empty()
+        && key.match_indices(pattern).any(|(start, _)| {
+            let end = start + pattern.len();
+            (start == 0 || key[..start].ends_with("::"))
+                && (end == key.len() || key[end..].starts_with("::"))
+        })
+}
@@ -0,0 +1,89 @@
+//! Coalescing of concurrent taskdumps.
+//!
+//! Every taskdump traverses every task, and contends with their polls for
+//! the locks of their roots. When several callers (e.g., a panic hook, a
+//! watchdog and a diagnostics endpoint) request dumps at once, the dumps that
+//! would be identical share one traversal: the first caller traverses, and the
+//! others wait for, and receive a copy of, its result.
+
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::taskdump::Dump;
+
+/// What a taskdump renders, and how; only dumps with equal keys are shared.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Key {
+    pub(crate) format: crate::taskdump::Format,
+    pub(crate) settings: crate::taskdump::Settings,
+}
+
+/// The state of the dump of some [`Key`].
+enum Entry {
+    /// The dump is being traversed.
+    InFlight,
+    /// The dump was traversed.
+    Done(Dump),
+}
+
+/// The most recent dump of each key.
+static ENTRIES: Mutex<Vec<(Key, Entry)>> = Mutex::new(Vec::new());
+
+/// Notified whenever a dump is no longer in flight.
+static LANDED: Condvar = Condvar::new();
+
+/// Locks the entries of every key.
+fn lock() -> MutexGuard<'static, Vec<(Key, Entry)>> {
+    ENTRIES.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Produces the dump of `key`.
+///
+/// If a dump of `key` is in flight, this waits for and shares its result; if
+/// one began within `window` of now, its result is shared immediately.
+/// Otherwise, this produces the dump with `traverse`, sharing its result with
+/// the callers that arrive while it is in flight.
+pub(crate) fn dump<T>(key: Key, window: Duration, traverse: T) -> Dump
+where
+    T: FnOnce() -> Dump,
+{
+    let mut entries = lock();
+    let mut waited = false;
+    loop {
+        let entry = entries.iter().find(|(k, _)| *k == key).map(|(_, e)| e);
+        match entry {
+            Some(Entry::InFlight) => {
+                entries = LANDED.wait(entries).unwrap_or_else(|err| err.into_inner());
+                waited = true;
+            }
+            Some(Entry::Done(dump)) if waited || dump.epoch.elapsed() <= window => {
+                return dump.clone()
+            }
+            _ => break,
+        }
+    }
+    set(&mut entries, key, Entry::InFlight);
+    drop(entries);
+
+    // if the traversal unwinds, the waiters must not wait forever; one of
+    // them takes over
+    let unwound = crate::defer(|| {
+        let mut entries = lock();
+        entries.retain(|(k, _)| *k != key);
+        LANDED.notify_all();
+    });
+    let dump = traverse();
+    core::mem::forget(unwound);
+
+    set(&mut lock(), key, Entry::Done(dump.clone()));
+    LANDED.notify_all();
+    dump
+}
+
+/// Sets the entry of `key` to `entry`.
+fn set(entries: &mut Vec<(Key, Entry)>, key: Key, entry: Entry) {
+    match entries.iter_mut().find(|(k, _)| *k == key) {
+        Some((_, slot)) => *slot = entry,
+        None => entries.push((key, entry)),
+    }
+}
@@ -0,0 +1,159 @@
+//! Storage of the active frame of each execution context.
+//!
+//! By default, each OS thread is an execution context: the active frame is
+//! kept in a thread-local. Runtimes that interleave several logical contexts
+//! on one thread (e.g., by switching between fibers' stacks) may supply their
+//! own [`ContextStorage`], with [`set_context_storage`].
+
+use core::fmt;
+use core::ptr::NonNull;
+
+use once_cell::sync::OnceCell;
+
+use crate::{cell::Cell, Frame};
+
+/// An opaque handle to the active frame of an execution context, as stored by
+/// a [`ContextStorage`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ActiveFrame(NonNull<Frame>);
+
+// SAFETY: An `ActiveFrame` cannot be dereferenced outside of this crate; it
+// is only an opaque value, which `ContextStorage`s may keep wherever they keep
+// the state of their contexts.
+unsafe impl Send for ActiveFrame {}
+unsafe impl Sync for ActiveFrame {}
+
+/// Storage of the active frame of the current execution context.
+///
+/// ## Example
+/// A storage for a runtime that switches between fibers, each of which is an
+/// execution context:
+/// ```
+/// use async_backtrace::{ActiveFrame, ContextStorage};
+/// use std::{collections::HashMap, sync::Mutex};
+///
+/// # mod fiber { pub fn current() -> u64 { 0 } }
+/// struct FiberStorage(Mutex<HashMap<u64, ActiveFrame>>);
+///
+/// // SAFETY: fibers are only switched between polls of frames, or else
+/// // resumed on the thread that suspended them.
+/// unsafe impl ContextStorage for FiberStorage {
+///     fn get(&self) -> Option<ActiveFrame> {
+///         self.0.lock().unwrap().get(&fiber::current()).copied()
+///     }
+///
+///     fn set(&self, frame: Option<ActiveFrame>) {
+///         let mut frames = self.0.lock().unwrap();
+///         match frame {
+///             Some(frame) => frames.insert(fiber::current(), frame),
+///             None => frames.remove(&fiber::current()),
+///         };
+///     }
+/// }
+/// ```
+///
+/// # Safety
+/// [`get`](ContextStorage::get) must produce the value most recently passed to
+/// [`set`](ContextStorage::set) from the current execution context (or `None`,
+/// if there is no such value); the frames of one context must never be
+/// produced in another.
+///
+/// While a frame is active, its context must not be resumed on an OS thread
+/// other than the one on which it was suspended: the active frame of a task
+/// holds the (thread-affine) lock of its root.
+pub unsafe trait ContextStorage: Send + Sync + 'static {
+    /// Produces the active frame of the current execution context.
+    fn get(&self) -> Option<ActiveFrame>;
+
+    /// Sets the active frame of the current execution context.
+    fn set(&self, frame: Option<ActiveFrame>);
+}
+
+/// The default [`ContextStorage`], in which each OS thread is an execution
+/// context.
+struct ThreadLocal;
+
+mod thread_local {
+    use super::ActiveFrame;
+    use crate::cell::Cell;
+
+    #[cfg(loom)]
+    loom::thread_local! {
+        /// The [`Frame`](crate::raw::Frame) of the currently-executing framed
+        /// future on this thread (if any).
+        pub(super) static ACTIVE_FRAME: Cell<Option<ActiveFrame>> = Cell::new(None);
+    }
+
+    #[cfg(not(loom))]
+    std::thread_local! {
+        /// The [`Frame`](crate::raw::Frame) of the currently-executing framed
+        /// future on this thread (if any).
+        #[allow(clippy::declare_interior_mutable_const)]
+        pub(super) static ACTIVE_FRAME: Cell<Option<ActiveFrame>> = const { Cell::new(None) };
+    }
+}
+
+// SAFETY: Each OS thread is its own execution context.
+unsafe impl ContextStorage for ThreadLocal {
+    fn get(&self) -> Option<ActiveFrame> {
+        thread_local::ACTIVE_FRAME.with(Cell::get)
+    }
+
+    fn set(&self, frame: Option<ActiveFrame>) {
+        thread_local::ACTIVE_FRAME.with(|active| active.set(frame))
+    }
+}
+
+static STORAGE: OnceCell<&'static dyn ContextStorage> = OnceCell::new();
+
+/// Produces the storage of active frames, fixing it if it has not been set.
+fn storage() -> &'static dyn ContextStorage {
+    *STORAGE.get_or_init(|| &ThreadLocal)
+}
+
+/// An error produced by [`set_context_storage`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ContextStorageError {
+    /// The storage of active frames has already been fixed, either by a
+    /// previous call to [`set_context_storage`] or by the activation of a
+    /// frame.
+    AlreadyInitialized,
+}
+
+impl fmt::Display for ContextStorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyInitialized => f.write_str("the context storage is already initialized"),
+        }
+    }
+}
+
+impl std::error::Error for ContextStorageError {}
+
+/// Sets the process-wide storage of the active frame of each execution
+/// context, in place of the default thread-local storage.
+///
+/// This must be called before any frame is activated (or any backtrace is
+/// taken); otherwise, it fails with
+/// [`ContextStorageError::AlreadyInitialized`].
+pub fn set_context_storage(
+    storage: &'static dyn ContextStorage,
+) -> Result<(), ContextStorageError> {
+    STORAGE
+        .set(storage)
+        .map_err(|_| ContextStorageError::AlreadyInitialized)
+}
+
+/// Produces the active frame of the current execution context.
+pub(crate) fn get() -> Option<NonNull<Frame>> {
+    storage().get().map(|frame| frame.0)
+}
+
+/// Sets the active frame of the current execution context.
+///
+/// # Safety
+/// Until it is replaced, `frame` must be dereferenceable.
+pub(crate) unsafe fn set(frame: Option<NonNull<Frame>>) {
+    storage().set(frame.map(ActiveFrame))
+}
@@ -0,0 +1,174 @@
+//! Counters of user-defined statistics (e.g., retries, or bytes processed),
+//! attributed to the locations of the frames that count them.
+//!
+//! Unlike [annotations](crate::annotate), counters are not attached to
+//! frames, but kept in a global map by location and key; so, they outlive the
+//! frames that count them, and those of every frame at a location are summed.
+//! Increments are atomic, and take no lock once the counter exists.
+//!
+//! Counters are rendered in taskdumps that set
+//! [`TaskdumpOptions::counters`](crate::TaskdumpOptions::counters), beside
+//! each frame whose location has any; e.g.:
+//! ```text
+//! ╼ app::serve::{{closure}} at src/main.rs:8:1
+//!   └╼ app::fetch::{{closure}} at src/main.rs:20:1 [bytes=5120] [retries=12]
+//! ```
+//!
+//! ## Example
+//! ```
+//! use async_backtrace::counters;
+//!
+//! #[async_backtrace::framed]
+//! async fn fetch() {
+//!     for _ in 0..3 {
+//!         counters::increment("retries", 1);
+//!     }
+//! }
+//!
+//! futures::executor::block_on(fetch());
+//! for counter in counters::report() {
+//!     // prints: rust_out::fetch::{{closure}} at src/lib.rs:4:1 [retries=3]
+//!     println!("{}", counter);
+//! }
+//! ```
+
+use std::{
+    fmt,
+    hash::BuildHasherDefault,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use rustc_hash::FxHasher;
+
+use crate::{Frame, Location};
+
+/// The counters of a location, by key, ordered by key.
+type Counters = Vec<(&'static str, AtomicU64)>;
+
+/// The counters of each location to which any are attributed.
+static COUNTERS: Lazy<DashMap<Location, Counters, BuildHasherDefault<FxHasher>>> =
+    Lazy::new(DashMap::default);
+
+/// Adds `by` to the counter `key` of the location of the currently-active
+/// frame. Produces `false` (and counts nothing) if there is no active frame.
+///
+/// Counters wrap on overflow.
+pub fn increment(key: &'static str, by: u64) -> bool {
+    Frame::with_active(|maybe_frame| maybe_frame.map(Frame::location))
+        .map(|location| add(location, key, by))
+        .is_some()
+}
+
+/// Adds `by` to the counter `key` of the location of the root frame of the
+/// task of the currently-active frame, as [`root_location`](crate::root_location)
+/// produces it; e.g., to count per task, rather than per function. Produces
+/// `false` (and counts nothing) if there is no active frame.
+pub fn increment_root(key: &'static str, by: u64) -> bool {
+    Frame::with_active(|maybe_frame| maybe_frame.map(|frame| frame.root().location()))
+        .map(|location| add(location, key, by))
+        .is_some()
+}
+
+/// Adds `by` to the counter `key` of `location`.
+fn add(location: Location, key: &'static str, by: u64) {
+    // the shard is only write-locked to insert the counter
+    if let Some(counters) = COUNTERS.get(&location) {
+        if let Some(counter) = find(&counters, key) {
+            counter.fetch_add(by, Ordering::Relaxed);
+            return;
+        }
+    }
+    let mut counters = COUNTERS.entry(location).or_default();
+    if let Some(counter) = find(&counters, key) {
+        counter.fetch_add(by, Ordering::Relaxed);
+    } else {
+        counters.push((key, AtomicU64::new(by)));
+        counters.sort_unstable_by_key(|&(key, _)| key);
+    }
+}
+
+/// Produces the counter `key` of `counters`, if any.
+fn find<'a>(counters: &'a Counters, key: &str) -> Option<&'a AtomicU64> {
+    counters
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, counter)| counter)
+}
+
+/// Produces the value of the counter `key` of `location`; zero, if it has
+/// never been incremented.
+pub fn get(location: Location, key: &'static str) -> u64 {
+    COUNTERS.get(&location).map_or(0, |counters| {
+        find(&counters, key).map_or(0, |counter| counter.load(Ordering::Relaxed))
+    })
+}
+
+/// Produces every counter, ordered by location (by file, line, column and
+/// name), and then by key.
+pub fn report() -> Vec<Counter> {
+    let mut counters: Vec<Counter> = COUNTERS
+        .iter()
+        .flat_map(|entry| {
+            let location = *entry.key();
+            of_entry(entry.value())
+                .map(move |(key, value)| Counter {
+                    location,
+                    key,
+                    value,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    // (the counters of each location are already ordered by key)
+    counters.sort_by(|a, b| crate::tasks::root_order((a.location, 0), (b.location, 0)));
+    counters
+}
+
+/// Produces the counters of `location`, ordered by key.
+pub(crate) fn of(location: Location) -> Vec<(&'static str, u64)> {
+    COUNTERS
+        .get(&location)
+        .map_or_else(Vec::new, |counters| of_entry(&counters).collect())
+}
+
+/// Produces the values of `counters`.
+fn of_entry<'a>(counters: &'a Counters) -> impl Iterator<Item = (&'static str, u64)> + 'a {
+    counters
+        .iter()
+        .map(|(key, counter)| (*key, counter.load(Ordering::Relaxed)))
+}
+
+/// A counter of a location, as produced by [`report`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Counter {
+    location: Location,
+    key: &'static str,
+    value: u64,
+}
+
+impl Counter {
+    /// The location to which the counter is attributed.
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    /// The key of the counter.
+    pub fn key(&self) -> &'static str {
+        self.key
+    }
+
+    /// The value of the counter, as of the [`report`].
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+}
+
+impl fmt::Display for Counter {
+    /// Renders the counter as, e.g.,
+    /// `app::fetch::{{closure}} at src/main.rs:20:1 [retries=12]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}={}]", self.location, self.key, self.value)
+    }
+}
@@ -0,0 +1,123 @@
+//! Integrity checks of the frame forest, for use while developing custom
+//! combinators atop [`Frame`](crate::raw::Frame) or [`Framed`](crate::Framed).
+//!
+//! This module is only available in builds with `debug_assertions`, or with
+//! the `debug-validate` feature enabled.
+
+use std::{collections::HashSet, fmt, ptr::NonNull};
+
+use crate::{sync::TryLockError, tasks, Frame, Location};
+
+/// A violation of the structural invariants of a tree of frames.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum IntegrityError {
+    /// `child` is among the children of `parent`, but does not point back to
+    /// `parent` as its parent.
+    ParentMismatch {
+        /// The location of the child frame.
+        child: Location,
+        /// The location of the frame listing it as a child.
+        parent: Location,
+    },
+    /// The previous and next links between the children of `parent` do not
+    /// agree with each other, or with the ends of its list of children.
+    BrokenSiblingLink {
+        /// The location of the frame whose children are mislinked.
+        parent: Location,
+    },
+    /// `frame` was reached more than once while walking a tree.
+    Cycle {
+        /// The location of the frame that was revisited.
+        frame: Location,
+    },
+    /// The active frame does not belong to the tree of any registered task.
+    UnregisteredActiveFrame {
+        /// The location of the active frame.
+        frame: Location,
+    },
+}
+
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ParentMismatch { child, parent } => write!(
+                f,
+                "{child} is a child of {parent}, but does not point to it as its parent"
+            ),
+            Self::BrokenSiblingLink { parent } => {
+                write!(f, "the children of {parent} are not consistently linked")
+            }
+            Self::Cycle { frame } => write!(f, "{frame} was reached more than once"),
+            Self::UnregisteredActiveFrame { frame } => write!(
+                f,
+                "the active frame, {frame}, does not belong to a registered task"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IntegrityError {}
+
+/// Checks that the trees of all tasks are consistent.
+///
+/// Walks the tree of every registered task, checking that each child's parent
+/// pointer matches the frame it is a child of, that the links between
+/// siblings agree with each other, and that no frame is reached twice. The
+/// active frame on this thread (if any) must belong to one of those trees.
+///
+/// Each tree is locked while it is walked. Tasks that are being polled on
+/// other threads are skipped, rather than waited upon; the task of the active
+/// frame, which this thread has already locked, is always checked.
+///
+/// ## Example
+/// ```
+/// #[async_backtrace::framed]
+/// async fn foo() {
+///     async_backtrace::debug::validate().unwrap();
+/// }
+/// # futures::executor::block_on(foo());
+/// ```
+pub fn validate() -> Result<(), Vec<IntegrityError>> {
+    let mut errors = Vec::new();
+    let mut visited = HashSet::new();
+
+    let active: Option<(NonNull<Frame>, NonNull<Frame>)> = Frame::with_active(|maybe_frame| {
+        maybe_frame.map(|frame| (NonNull::from(frame), NonNull::from(frame.root())))
+    });
+
+    for task in tasks() {
+        let frame = task.frame();
+        let is_current = active.map(|(_, root)| root) == Some(NonNull::from(frame));
+        let maybe_lock = frame
+            .mutex()
+            // don't grab a lock if we're *in* the active task (it's already locked, then)
+            .filter(|_| !is_current)
+            .map(|mutex| mutex.try_lock());
+        let _guard = match maybe_lock {
+            None => None,
+            Some(Ok(guard)) => Some(guard),
+            Some(Err(TryLockError::Poisoned(err))) => Some(err.into_inner()),
+            Some(Err(TryLockError::WouldBlock)) => continue,
+        };
+        // SAFETY: The root of `frame` is locked, either above or by this
+        // thread's active frame.
+        unsafe { frame.validate(&mut visited, &mut errors) };
+    }
+
+    if let Some((frame, _)) = active {
+        if !visited.contains(&frame) {
+            errors.push(IntegrityError::UnregisteredActiveFrame {
+                // SAFETY: The active frame is valid for the duration of its
+                // `in_scope`, which encloses this call.
+                frame: unsafe { frame.as_ref() }.location(),
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
@@ -0,0 +1,268 @@
+//! The changes to the set of tasks, and to where each is waiting, between
+//! consecutive samples; e.g., for watchdogs that log what changed, rather than
+//! whole taskdumps.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{BuildHasherDefault, Hash, Hasher},
+};
+
+use rustc_hash::FxHasher;
+
+use crate::{FrameTree, Location, TaskTree};
+
+/// The most leaf locations retained, per task, for rendering.
+const MAX_LEAVES: usize = 4;
+
+/// Tracks the tasks between consecutive [samples](DeltaTracker::sample), and
+/// produces the [`Delta`] between each sample and the last.
+///
+/// Trees are not retained: of each task, only the location of its root, a
+/// fingerprint of the locations of its leaves, and (at most four of) those
+/// locations are.
+///
+/// ## Example
+/// ```
+/// let mut tracker = async_backtrace::DeltaTracker::new();
+/// for _ in 0..3 {
+///     let delta = tracker.sample();
+///     if !delta.is_empty() {
+///         println!("{}", delta);
+///     }
+///     std::thread::sleep(std::time::Duration::from_millis(10));
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct DeltaTracker {
+    tasks: HashMap<u64, TaskState, BuildHasherDefault<FxHasher>>,
+}
+
+/// What a [`DeltaTracker`] retains of a task.
+#[derive(Debug, Clone)]
+struct TaskState {
+    root: Location,
+    /// The leaves of the task, unless it has only been sampled while it was
+    /// being polled.
+    leaves: Option<Leaves>,
+}
+
+/// The locations of the leaves of a task's tree.
+#[derive(Debug, Clone)]
+struct Leaves {
+    /// A hash of the locations of all leaves, in order.
+    fingerprint: u64,
+    /// The first few distinct locations of the leaves.
+    locations: Vec<Location>,
+}
+
+impl Leaves {
+    fn of(frame: &FrameTree) -> Self {
+        fn visit(frame: &FrameTree, hasher: &mut FxHasher, locations: &mut Vec<Location>) {
+            if frame.children().is_empty() {
+                let location = frame.location();
+                location.hash(hasher);
+                if locations.len() < MAX_LEAVES && !locations.contains(&location) {
+                    locations.push(location);
+                }
+            }
+            for child in frame.children() {
+                visit(child, hasher, locations);
+            }
+        }
+
+        let mut hasher = FxHasher::default();
+        let mut locations = Vec::new();
+        visit(frame, &mut hasher, &mut locations);
+        Self {
+            fingerprint: hasher.finish(),
+            locations,
+        }
+    }
+}
+
+impl TaskState {
+    fn capture(tree: &TaskTree) -> Self {
+        Self {
+            root: tree.root().location(),
+            // (the subframes of a polling task are not captured)
+            leaves: Some(tree)
+                .filter(|tree| !tree.is_polling())
+                .map(|tree| Leaves::of(tree.root())),
+        }
+    }
+
+    fn locations(&self) -> &[Location] {
+        self.leaves
+            .as_ref()
+            .map_or(&[], |leaves| &leaves.locations[..])
+    }
+}
+
+impl DeltaTracker {
+    /// Produces a tracker that has not yet sampled any tasks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Samples the tasks, without waiting for running tasks, and produces
+    /// what changed since the last sample (or, upon the first sample, every
+    /// task, as added).
+    ///
+    /// The leaves of tasks that are being polled are not sampled; such tasks
+    /// are not reported as changed.
+    pub fn sample(&mut self) -> Delta {
+        let mut delta = Delta::default();
+        let mut tasks = HashMap::default();
+        for tree in crate::snapshot(false) {
+            let id = tree.id();
+            let mut state = TaskState::capture(&tree);
+            match self.tasks.remove(&id) {
+                None => delta.added.push(TaskDelta::new(id, None, Some(&state))),
+                Some(previous) => {
+                    if let (Some(before), Some(after)) = (&previous.leaves, &state.leaves) {
+                        if before.fingerprint != after.fingerprint {
+                            let change = TaskDelta::new(id, Some(&previous), Some(&state));
+                            delta.changed.push(change);
+                        }
+                    }
+                    if state.leaves.is_none() {
+                        state.leaves = previous.leaves;
+                    }
+                }
+            }
+            tasks.insert(id, state);
+        }
+        for (id, previous) in self.tasks.drain() {
+            delta
+                .removed
+                .push(TaskDelta::new(id, Some(&previous), None));
+        }
+        self.tasks = tasks;
+        for tasks in [&mut delta.added, &mut delta.removed, &mut delta.changed] {
+            tasks.sort_by_key(TaskDelta::id);
+        }
+        delta
+    }
+}
+
+/// The changes between two samples of a [`DeltaTracker`]: the tasks that
+/// were added or removed, and those whose leaves changed. Each list is
+/// ordered by task id.
+///
+/// It is rendered as a short summary, with a line per task; e.g.:
+/// ```text
+/// 1 added, 1 removed, 1 changed
+/// + task 9: app::serve::{{closure}} at src/main.rs:20:1
+/// - task 4: app::warmup::{{closure}} at src/main.rs:40:1
+/// ~ task 7: app::handle::{{closure}} at src/main.rs:12:1: app::read::{{closure}} at src/main.rs:30:1 → app::write::{{closure}} at src/main.rs:34:1
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Delta {
+    added: Vec<TaskDelta>,
+    removed: Vec<TaskDelta>,
+    changed: Vec<TaskDelta>,
+}
+
+impl Delta {
+    /// The tasks that appeared since the last sample.
+    pub fn added(&self) -> &[TaskDelta] {
+        &self.added
+    }
+
+    /// The tasks that disappeared since the last sample.
+    pub fn removed(&self) -> &[TaskDelta] {
+        &self.removed
+    }
+
+    /// The tasks whose leaves changed since the last sample.
+    pub fn changed(&self) -> &[TaskDelta] {
+        &self.changed
+    }
+
+    /// Produces `true` if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The change to a task in a [`Delta`].
+#[derive(Debug, Clone)]
+pub struct TaskDelta {
+    id: u64,
+    root: Location,
+    before: Vec<Location>,
+    after: Vec<Location>,
+}
+
+impl TaskDelta {
+    fn new(id: u64, before: Option<&TaskState>, after: Option<&TaskState>) -> Self {
+        Self {
+            id,
+            root: after.or(before).map(|state| state.root).unwrap(),
+            before: before.map_or_else(Vec::new, |state| state.locations().to_vec()),
+            after: after.map_or_else(Vec::new, |state| state.locations().to_vec()),
+        }
+    }
+
+    /// The [id](crate::Task::id) of the task.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The location of the task's root.
+    pub fn root(&self) -> Location {
+        self.root
+    }
+
+    /// The (first few distinct) locations of the task's leaves as of the last
+    /// sample; empty if the task was added, or if its leaves were not sampled.
+    pub fn leaves_before(&self) -> &[Location] {
+        &self.before
+    }
+
+    /// The (first few distinct) locations of the task's leaves as of this
+    /// sample; empty if the task was removed, or if its leaves were not
+    /// sampled.
+    pub fn leaves_after(&self) -> &[Location] {
+        &self.after
+    }
+}
+
+impl fmt::Display for Delta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_leaves(f: &mut fmt::Formatter<'_>, leaves: &[Location]) -> fmt::Result {
+            for (i, leaf) in leaves.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{}", leaf)?;
+            }
+            Ok(())
+        }
+
+        if self.is_empty() {
+            return f.write_str("no changes");
+        }
+        write!(
+            f,
+            "{} added, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        )?;
+        for task in &self.added {
+            write!(f, "\n+ task {}: {}", task.id, task.root)?;
+        }
+        for task in &self.removed {
+            write!(f, "\n- task {}: {}", task.id, task.root)?;
+        }
+        for task in &self.changed {
+            write!(f, "\n~ task {}: {}: ", task.id, task.root)?;
+            write_leaves(f, &task.before)?;
+            f.write_str(" → ")?;
+            write_leaves(f, &task.after)?;
+        }
+        Ok(())
+    }
+}
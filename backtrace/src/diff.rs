@@ -0,0 +1,434 @@
+//! Structured differences between two [snapshots](crate::snapshot) of tasks;
+//! e.g., to report what changed while waiting, which a textual `diff` of two
+//! dumps reports noisily, as tasks are reordered between them.
+
+use std::{collections::HashMap, fmt};
+
+use crate::{FrameTree, Location, ParsedFrame, ParsedLocation, TaskTree};
+use sealed::DiffLocation;
+
+/// Produces the differences between the tasks of two
+/// [snapshots](crate::snapshot), or of two [parsed](crate::ParsedDump)
+/// taskdumps: those added, those removed, and those whose trees changed, with
+/// the paths to their leaves that changed.
+///
+/// Tasks are identified across snapshots by their [id](crate::Task::id), which
+/// is never reused within a process; so, a task that exited and another that
+/// was spawned in its place are reported as one removed and one added. The
+/// text of a taskdump has no ids, so the tasks of parsed dumps are instead
+/// identified by their root, in order; e.g., the second task rooted at
+/// `app::handle` in one dump is the second task rooted at `app::handle` in the
+/// other. The trees of tasks that were being polled (in either snapshot) have
+/// no subframes to compare, and so are never reported as changed.
+///
+/// ## Example
+/// ```
+/// let before = async_backtrace::snapshot(true);
+/// std::thread::sleep(std::time::Duration::from_millis(10));
+/// let after = async_backtrace::snapshot(true);
+/// let diff = async_backtrace::diff(&before, &after);
+/// if !diff.is_empty() {
+///     println!("{}", diff);
+/// }
+/// ```
+pub fn diff<T: DiffTask>(before: &[T], after: &[T]) -> DumpDiff<T::Location> {
+    let before_keys = keys(before);
+    let after_keys = keys(after);
+    let before_by_key: HashMap<_, _> = before_keys.iter().zip(before).collect();
+    let after_by_key: HashMap<_, _> = after_keys.iter().zip(after).collect();
+    let mut diff = DumpDiff::default();
+    for (key, tree) in after_keys.iter().zip(after) {
+        let task = |paths_before, paths_after| TaskDiff {
+            id: tree.id(),
+            root: tree.root_location(),
+            paths_before,
+            paths_after,
+        };
+        match before_by_key.get(key) {
+            None => diff.added.push(task(Vec::new(), tree.paths())),
+            Some(previous) if !previous.is_polling() && !tree.is_polling() => {
+                let (paths_before, paths_after) = difference(previous.paths(), tree.paths());
+                if !paths_before.is_empty() || !paths_after.is_empty() {
+                    diff.changed.push(task(paths_before, paths_after));
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    for (key, tree) in before_keys.iter().zip(before) {
+        if !after_by_key.contains_key(key) {
+            diff.removed.push(TaskDiff {
+                id: tree.id(),
+                root: tree.root_location(),
+                paths_before: tree.paths(),
+                paths_after: Vec::new(),
+            });
+        }
+    }
+    // (tasks without ids remain in the order of their dumps)
+    for tasks in [&mut diff.added, &mut diff.removed, &mut diff.changed] {
+        tasks.sort_by_key(TaskDiff::id);
+    }
+    diff
+}
+
+/// The trees of the tasks that [`diff`] compares: [`TaskTree`]s, captured by
+/// [`snapshot`](crate::snapshot), and the roots of the tasks of a
+/// [`ParsedDump`](crate::ParsedDump), [`ParsedFrame`]s.
+///
+/// This trait is sealed, and cannot be implemented outside of this crate.
+pub trait DiffTask: sealed::DiffTask {}
+
+impl DiffTask for TaskTree {}
+
+impl DiffTask for ParsedFrame {}
+
+mod sealed {
+    use std::{fmt, hash::Hash};
+
+    /// The internals of [`DiffTask`](super::DiffTask).
+    pub trait DiffTask {
+        /// The type of the locations of the frames of the tree.
+        type Location: DiffLocation;
+
+        /// Produces the id of the task, if it has one.
+        fn id(&self) -> Option<u64>;
+
+        /// Produces `true` if the task was being polled, and so its subframes
+        /// were not captured.
+        fn is_polling(&self) -> bool;
+
+        /// Produces the location of the task's root.
+        fn root_location(&self) -> Self::Location;
+
+        /// Produces the paths from the root of the tree to each of its
+        /// leaves, in the order of the tree, with a path for each copy of a
+        /// consolidated frame; none, if its subframes were not captured.
+        fn paths(&self) -> Vec<Vec<Self::Location>>;
+    }
+
+    /// The locations of the frames of a [`DiffTask`].
+    pub trait DiffLocation: Clone + Eq + Hash + fmt::Debug {
+        /// Writes the compact rendering of this location.
+        fn write_compact(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+    }
+}
+
+impl sealed::DiffTask for TaskTree {
+    type Location = Location;
+
+    fn id(&self) -> Option<u64> {
+        Some(self.id())
+    }
+
+    fn is_polling(&self) -> bool {
+        self.is_polling()
+    }
+
+    fn root_location(&self) -> Location {
+        self.root().location()
+    }
+
+    fn paths(&self) -> Vec<Vec<Location>> {
+        fn visit(frame: &FrameTree, path: &mut Vec<Location>, paths: &mut Vec<Vec<Location>>) {
+            path.push(frame.location());
+            if frame.children().is_empty() {
+                paths.push(path.clone());
+            }
+            for child in frame.children() {
+                visit(child, path, paths);
+            }
+            path.pop();
+        }
+
+        let mut paths = Vec::new();
+        if !self.is_polling() {
+            visit(self.root(), &mut Vec::new(), &mut paths);
+        }
+        paths
+    }
+}
+
+impl sealed::DiffTask for ParsedFrame {
+    type Location = ParsedLocation;
+
+    fn id(&self) -> Option<u64> {
+        None
+    }
+
+    fn is_polling(&self) -> bool {
+        self.is_polling()
+    }
+
+    fn root_location(&self) -> ParsedLocation {
+        self.location().clone()
+    }
+
+    fn paths(&self) -> Vec<Vec<ParsedLocation>> {
+        fn visit(
+            frame: &ParsedFrame,
+            path: &mut Vec<ParsedLocation>,
+            paths: &mut Vec<Vec<ParsedLocation>>,
+        ) {
+            path.push(frame.location().clone());
+            for _ in 0..frame.copies() {
+                if frame.children().is_empty() {
+                    paths.push(path.clone());
+                }
+                for child in frame.children() {
+                    visit(child, path, paths);
+                }
+            }
+            path.pop();
+        }
+
+        let mut paths = Vec::new();
+        if !self.is_polling() {
+            visit(self, &mut Vec::new(), &mut paths);
+        }
+        paths
+    }
+}
+
+impl sealed::DiffLocation for Location {
+    fn write_compact(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_compact())
+    }
+}
+
+impl sealed::DiffLocation for ParsedLocation {
+    fn write_compact(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_compact())
+    }
+}
+
+/// The key by which a task is identified across snapshots: its id or, if it
+/// has none, its root and the number of tasks with that root before it.
+#[derive(PartialEq, Eq, Hash)]
+enum Key<L> {
+    Id(u64),
+    Nth(L, usize),
+}
+
+/// Produces the key of each of `tasks`, in order.
+fn keys<T: DiffTask>(tasks: &[T]) -> Vec<Key<T::Location>> {
+    let mut counts: HashMap<T::Location, usize> = HashMap::new();
+    tasks
+        .iter()
+        .map(|tree| match tree.id() {
+            Some(id) => Key::Id(id),
+            None => {
+                let root = tree.root_location();
+                let count = counts.entry(root.clone()).or_default();
+                *count += 1;
+                Key::Nth(root, *count - 1)
+            }
+        })
+        .collect()
+}
+
+/// Produces the paths of `before` that are not in `after`, and those of
+/// `after` that are not in `before`, counting duplicates.
+fn difference<L: DiffLocation>(
+    before: Vec<Vec<L>>,
+    after: Vec<Vec<L>>,
+) -> (Vec<Vec<L>>, Vec<Vec<L>>) {
+    let mut counts: HashMap<&[L], isize> = HashMap::new();
+    for path in &before {
+        *counts.entry(path).or_default() += 1;
+    }
+    for path in &after {
+        *counts.entry(path).or_default() -= 1;
+    }
+    // each path is kept as many times as it is in excess, in order
+    let mut excess = |path: &Vec<L>, sign: isize| {
+        let count = counts.get_mut(&path[..]).unwrap();
+        let keep = *count * sign > 0;
+        if keep {
+            *count -= sign;
+        }
+        keep
+    };
+    let removed: Vec<_> = before
+        .iter()
+        .filter(|path| excess(path, 1))
+        .cloned()
+        .collect();
+    let added: Vec<_> = after
+        .iter()
+        .filter(|path| excess(path, -1))
+        .cloned()
+        .collect();
+    (removed, added)
+}
+
+/// The differences between two snapshots of tasks, as produced by [`diff`].
+/// Each list is ordered by task id or, for parsed dumps, by the order of the
+/// tasks in the dumps.
+///
+/// Its locations are [`Location`]s, or for parsed dumps, [`ParsedLocation`]s.
+///
+/// It is rendered tersely, with a line per root of the tasks added or
+/// removed, and per task changed; e.g.:
+/// ```text
+/// + 3 new tasks rooted at app::handle::{{closure}}@src/main.rs:20
+/// - 1 task rooted at app::warmup::{{closure}}@src/main.rs:40 exited
+/// ~ task #42 advanced from app::read::{{closure}}@src/main.rs:10 to app::write::{{closure}}@src/main.rs:33
+/// ```
+#[derive(Debug, Clone)]
+pub struct DumpDiff<L = Location> {
+    added: Vec<TaskDiff<L>>,
+    removed: Vec<TaskDiff<L>>,
+    changed: Vec<TaskDiff<L>>,
+}
+
+impl<L> Default for DumpDiff<L> {
+    fn default() -> Self {
+        Self {
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        }
+    }
+}
+
+impl<L> DumpDiff<L> {
+    /// The tasks that are only in the later snapshot.
+    pub fn added(&self) -> &[TaskDiff<L>] {
+        &self.added
+    }
+
+    /// The tasks that are only in the earlier snapshot.
+    pub fn removed(&self) -> &[TaskDiff<L>] {
+        &self.removed
+    }
+
+    /// The tasks whose trees changed between the snapshots.
+    pub fn changed(&self) -> &[TaskDiff<L>] {
+        &self.changed
+    }
+
+    /// Produces `true` if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A task of a [`DumpDiff`].
+///
+/// Its paths run from the root of the task to a leaf, listing the location
+/// of each frame between them.
+#[derive(Debug, Clone)]
+pub struct TaskDiff<L = Location> {
+    id: Option<u64>,
+    root: L,
+    paths_before: Vec<Vec<L>>,
+    paths_after: Vec<Vec<L>>,
+}
+
+impl<L> TaskDiff<L> {
+    /// The [id](crate::Task::id) of the task; `None` if it was parsed from
+    /// the text of a taskdump, which has no ids.
+    pub fn id(&self) -> Option<u64> {
+        self.id
+    }
+
+    /// The location of the task's root.
+    pub fn root(&self) -> &L {
+        &self.root
+    }
+
+    /// The paths to the leaves of the task in the earlier snapshot that are
+    /// not in the later one; all of them, if the task was removed.
+    pub fn paths_before(&self) -> &[Vec<L>] {
+        &self.paths_before
+    }
+
+    /// The paths to the leaves of the task in the later snapshot that were
+    /// not in the earlier one; all of them, if the task was added.
+    pub fn paths_after(&self) -> &[Vec<L>] {
+        &self.paths_after
+    }
+}
+
+impl<L: DiffLocation> fmt::Display for DumpDiff<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        /// Produces the tasks of `tasks` by root, in order of their first
+        /// task.
+        fn by_root<L: DiffLocation>(tasks: &[TaskDiff<L>]) -> Vec<(&L, usize)> {
+            let mut roots: Vec<(&L, usize)> = Vec::new();
+            for task in tasks {
+                match roots.iter_mut().find(|(root, _)| **root == task.root) {
+                    Some((_, count)) => *count += 1,
+                    None => roots.push((&task.root, 1)),
+                }
+            }
+            roots
+        }
+
+        fn leaf<L>(paths: &[Vec<L>]) -> Option<&L> {
+            paths.first().and_then(|path| path.last())
+        }
+
+        /// Renders a location compactly.
+        struct Compact<'a, L>(&'a L);
+
+        impl<L: DiffLocation> fmt::Display for Compact<'_, L> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.write_compact(f)
+            }
+        }
+
+        if self.is_empty() {
+            return f.write_str("no changes");
+        }
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        // (each line but the first is preceded by a newline)
+        let mut separator = "";
+        for (root, count) in by_root(&self.added) {
+            f.write_str(separator)?;
+            separator = "\n";
+            write!(
+                f,
+                "+ {} new task{} rooted at {}",
+                count,
+                plural(count),
+                Compact(root)
+            )?;
+        }
+        for (root, count) in by_root(&self.removed) {
+            f.write_str(separator)?;
+            separator = "\n";
+            write!(
+                f,
+                "- {} task{} rooted at {} exited",
+                count,
+                plural(count),
+                Compact(root)
+            )?;
+        }
+        for task in &self.changed {
+            f.write_str(separator)?;
+            separator = "\n";
+            match task.id {
+                Some(id) => write!(f, "~ task #{} ", id)?,
+                None => write!(f, "~ task rooted at {} ", Compact(&task.root))?,
+            }
+            match (leaf(&task.paths_before), leaf(&task.paths_after)) {
+                (Some(before), Some(after)) => {
+                    write!(f, "advanced from {} to {}", Compact(before), Compact(after))?
+                }
+                (Some(before), None) => write!(f, "no longer at {}", Compact(before))?,
+                (None, Some(after)) => write!(f, "now also at {}", Compact(after))?,
+                (None, None) => unreachable!("changed tasks have changed paths"),
+            }
+            let more = task.paths_before.len().max(task.paths_after.len()) - 1;
+            match more {
+                0 => {}
+                1 => f.write_str(" (and 1 more path)")?,
+                n => write!(f, " (and {} more paths)", n)?,
+            }
+        }
+        Ok(())
+    }
+}
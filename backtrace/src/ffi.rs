@@ -0,0 +1,93 @@
+//! A C ABI, for dumping tasks from foreign code (e.g., the crash handler of a
+//! C++ program that calls into async Rust). Requires the `ffi` feature.
+//!
+//! The functions may be declared in C as:
+//! ```c
+//! #include <stddef.h>
+//! #include <stdint.h>
+//!
+//! /*
+//!  * Writes a taskdump, as rendered by async-backtrace's `taskdump_tree`,
+//!  * into the `len` bytes at `buf`, and returns the number of bytes written.
+//!  *
+//!  * - The dump does not wait for tasks that are being polled; it may be
+//!  *   taken from any thread, whether or not it runs async Rust code.
+//!  * - The dump is UTF-8, and is not NUL-terminated. If it does not fit, it
+//!  *   is truncated, at the end of the last whole character that fits.
+//!  * - `buf` may be NULL only if `len` is 0, in which case nothing is
+//!  *   written, and 0 is returned.
+//!  * - If the dump fails (e.g., if rendering it panics), 0 is returned.
+//!  */
+//! size_t async_backtrace_dump(uint8_t *buf, size_t len);
+//!
+//! /*
+//!  * Returns the number of registered tasks, without locking the registry
+//!  * of tasks.
+//!  */
+//! size_t async_backtrace_task_count(void);
+//! ```
+//! Both may be called once the Rust side of the program has been loaded;
+//! neither requires any initialization.
+
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+};
+
+use crate::TaskdumpOptions;
+
+/// Writes a taskdump into the `len` bytes at `buf`, truncating it if it does
+/// not fit, and produces the number of bytes written; see the [module
+/// documentation](self) for the contract of this function.
+///
+/// # Safety
+/// Unless `len` is zero, `buf` must be valid for writes of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn async_backtrace_dump(buf: *mut u8, len: usize) -> usize {
+    if len == 0 {
+        return 0;
+    }
+    // SAFETY: The caller ensures that `buf` is valid for writes of `len`
+    // bytes.
+    let buf = unsafe { std::slice::from_raw_parts_mut(buf, len) };
+    let mut writer = Truncating { buf, written: 0 };
+    // a panic must not unwind into foreign code
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        // (the writer fails the dump once it is full, ending it early)
+        let _ = TaskdumpOptions::defaults()
+            .wait_for_running_tasks(false)
+            .dump_to(&mut writer);
+        writer.written
+    }))
+    .unwrap_or(0)
+}
+
+/// Produces the number of registered tasks, without locking the registry of
+/// tasks.
+#[no_mangle]
+pub extern "C" fn async_backtrace_task_count() -> usize {
+    crate::tasks::live_tasks()
+}
+
+/// Writes into a fixed buffer, truncating (at a character boundary) what
+/// does not fit.
+struct Truncating<'b> {
+    buf: &'b mut [u8],
+    written: usize,
+}
+
+impl fmt::Write for Truncating<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let available = self.buf.len() - self.written;
+        let mut end = s.len().min(available);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.buf[self.written..][..end].copy_from_slice(&s.as_bytes()[..end]);
+        self.written += end;
+        if end < s.len() {
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,12 @@
-use std::{iter::FusedIterator, marker::PhantomPinned, pin::Pin, ptr::NonNull};
+use std::{iter::FusedIterator, marker::PhantomPinned, pin::Pin, ptr::NonNull, sync::Arc};
 
 use crate::{
     cell::{Cell, UnsafeCell},
+    hooks::Outcome,
     linked_list,
+    metadata::{Annotation, AnnotationValue, Metadata, MAX_ANNOTATIONS},
+    sequence::Sequence,
+    size::FutureSize,
     sync::Mutex,
     Location,
 };
@@ -13,12 +17,43 @@ pub struct Frame {
     // The location associated with this frame.
     location: Location,
 
+    // How this frame was instrumented.
+    origin: Origin,
+
+    // How the future of this frame ended (or, while it is being polled, how
+    // it ends if the poll unwinds). It is set through a shared reference,
+    // since a re-entrant poll sets it while the enclosing poll has the frame
+    // active.
+    outcome: Cell<Outcome>,
+
+    // Whether this frame is an incidental detail (e.g., of a helper crate),
+    // which dumps collapse by default.
+    detail: bool,
+
+    // Whether this frame is a barrier, beyond which backtraces do not
+    // propagate.
+    barrier: bool,
+
+    // Whether this frame is initialized as the root of a task, regardless of
+    // the frame that is active when it is first polled.
+    detached: bool,
+
+    // The sequence numbers of this frame's initialization and last poll (if
+    // they are recorded).
+    sequence: Sequence,
+
+    // The size of the future wrapped by this frame (if it is recorded).
+    future_size: FutureSize,
+
     // The kind of this frame — either a root or a node.
     kind: Kind,
 
     // The children of this frame.
     children: UnsafeCell<Children>,
 
+    // The annotations of this frame.
+    metadata: UnsafeCell<Metadata>,
+
     // The siblings of this frame.
     #[pin]
     siblings: Siblings,
@@ -37,6 +72,25 @@ impl PinnedDrop for Frame {
         let this = this.into_ref().get_ref();
 
         if let Some(parent) = this.parent() {
+            // If this frame is dropped outside of a poll of its task (e.g., if
+            // the task is dropped by its runtime), the root of its task must
+            // be locked to unlink it, since the children of its parent may be
+            // concurrently traversed by a taskdump. Poisoning is ignored, as
+            // in `activate`.
+            //
+            // Within a poll of its own task, the root is already locked by
+            // this thread, and locking it again would deadlock; so, it is not.
+            // Otherwise, the drop waits for any poll or dump of the task on
+            // another thread to finish. (It may deadlock only if two tasks,
+            // polled at once on two threads, each drop a frame of the other.)
+            let root = this.root();
+            let in_task = Frame::with_active(|active| {
+                active.is_some_and(|active| core::ptr::eq(active.root(), root))
+            });
+            let _guard = root
+                .mutex()
+                .filter(|_| !in_task)
+                .map(|mutex| mutex.lock().unwrap_or_else(|err| err.into_inner()));
             // remove this frame as a child of its parent
             unsafe {
                 parent.children.with_mut(|children| (*children).remove(this.into()));
@@ -44,6 +98,7 @@ impl PinnedDrop for Frame {
         } else {
             // this is a task; deregister it
             crate::tasks::deregister(this);
+            crate::hooks::task_exited(this);
         }
     }
 }
@@ -52,33 +107,112 @@ impl PinnedDrop for Frame {
 // It is safe to transfer a `Frame` across thread boundaries, as it does not
 // contain any pointers to thread-local storage, nor does it enable interior
 // mutation on shared pointers without locking.
+//
+// In particular, the frames of a task may be polled on a different thread upon
+// each poll (e.g., by a work-stealing runtime), and traversed by taskdumps on
+// any thread. No atomics are needed for this: every access to the parent
+// pointers, children and metadata of the frames of a task occurs while the
+// mutex of its root is held, either by the thread polling the task (which
+// locks it upon entering the root, and initializes, links and unlinks
+// subframes within), by a thread dumping the task, or by a thread dropping the
+// task outside of a poll (see `PinnedDrop`). The release of that mutex by one
+// thread happens-before its acquisition by the next, which makes the writes
+// of the former visible to the latter.
 unsafe impl Send for Frame {}
 
-mod active_frame {
-    use super::Frame;
-    use crate::cell::Cell;
-    use core::ptr::NonNull;
-
-    #[cfg(loom)]
-    loom::thread_local! {
-        /// The [`Frame`] of the currently-executing [traced future](crate::Traced) (if any).
-        static ACTIVE_FRAME: crate::cell::Cell<Option<NonNull<Frame>>> = Cell::new(None);
+// This non-generic preparation routine has been factored out of `in_scope`'s
+// body, so as to reduce the monomorphization burden on the compiler.
+//
+// The soundness of other routines in this module depend on this function *not*
+// being leaked from `in_scope` (or `enter`). In general, the drop-guard pattern cannot
+// safely and soundly be used for frame management. If we attempt to provide
+// such an API, we must ensure that unsoudness does not occur if child frames
+// are dropped before their parents, or if a drop-guard is held across an
+// `await` point.
+unsafe fn activate<'a>(mut frame: Pin<&'a mut Frame>) -> impl Drop + 'a {
+    // If needed, initialize this frame.
+    if frame.is_uninitialized() {
+        let maybe_parent = crate::context::get()
+            .filter(|_| !frame.detached)
+            .map(|parent| parent.as_ref());
+        frame.as_mut().initialize_unchecked(maybe_parent)
     }
 
-    #[cfg(not(loom))]
-    std::thread_local! {
-        /// The [`Frame`] of the currently-executing [traced future](crate::Traced) (if any).
-        #[allow(clippy::declare_interior_mutable_const)]
-        static ACTIVE_FRAME: crate::cell::Cell<Option<NonNull<Frame>>> = const { Cell::new(None) };
-    }
+    let frame = frame.into_ref().get_ref();
+    let previously_active = crate::context::get();
+    // (a frame may be activated by a destructor run during an unwind)
+    let unwinding = std::thread::panicking();
+
+    // If this is the root frame, lock its children. This lock is inherited by
+    // `f()`.
+    let maybe_mutex_guard = match &frame.kind {
+        // If this task is already being polled in this context (i.e., this is
+        // a re-entrant activation, as by a waker that synchronously polls its
+        // task), its root is already locked; locking it again would deadlock.
+        Kind::Root { .. }
+            if previously_active
+                .is_some_and(|active| core::ptr::eq(active.as_ref().root(), frame)) =>
+        {
+            None
+        }
+        Kind::Root { mutex, .. } => {
+            // Ignore poisoning. This is fine, since absolutely nothing between this line,
+            // and the execution of `drop(maybe_mutex_guard)` can unwind-panic, *except* for
+            // the execution of the user-provided function `f`. An unwind-panic of `f` will
+            // not make this crate's state inconsistent, since the parent frame is always
+            // restored by the below invocation of `crate::defer` upon its drop.
+            Some(match mutex.lock() {
+                Ok(guard) => guard,
+                Err(err) => err.into_inner(),
+            })
+        }
+        _ => None,
+    };
+
+    frame.sequence.polled();
+
+    // Replace the previously-active frame with this frame.
+    crate::context::set(Some(frame.into()));
+    let mirror = crate::threads::Mirror::enter(frame);
+
+    // At the end of this scope, restore the previously-active frame.
+    crate::defer(move || {
+        crate::context::set(previously_active);
+        mirror.restore();
+        if !unwinding && std::thread::panicking() {
+            // SAFETY: The root of `frame` is still locked, either by this
+            // activation or by an enclosing one.
+            frame.with_metadata_mut(|metadata| metadata.panicked = true);
+        }
+        if maybe_mutex_guard.is_some() && crate::tasks::cache_last_tree() {
+            // SAFETY: `frame` is a root, and is still locked.
+            crate::snapshot::remember(frame);
+        }
+        drop(maybe_mutex_guard);
+    })
+}
 
-    /// By calling this function, you pinky-swear to ensure that the value of
-    /// `ACTIVE_FRAME` is always a valid (dereferenceable) `NonNull<Frame>`.
-    pub(crate) unsafe fn with<F, R>(f: F) -> R
-    where
-        F: FnOnce(&Cell<Option<NonNull<Frame>>>) -> R,
-    {
-        ACTIVE_FRAME.with(f)
+/// How a framed future was instrumented.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Origin {
+    /// The future was produced by a function annotated with
+    /// [`#[framed]`](crate::framed).
+    Attribute,
+    /// The future was wrapped with [`frame!`](crate::frame).
+    Macro,
+    /// The future was wrapped with [`Location::frame`], or the frame was
+    /// constructed directly.
+    Manual,
+}
+
+impl core::fmt::Display for Origin {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Origin::Attribute => "#[framed]",
+            Origin::Macro => "frame!",
+            Origin::Manual => "manual",
+        })
     }
 }
 
@@ -92,7 +226,13 @@ enum Kind {
         /// This mutex must be locked when accessing the
         /// [children][Frame::children] or [siblings][Frame::siblings] of this
         /// frame.
-        mutex: Mutex<()>,
+        ///
+        /// It is shared so that a taskdump may wait for a poll of the task to
+        /// finish without keeping the task registered, and so alive, meanwhile
+        /// (see [`retry_by_id`](crate::tasks::retry_by_id)).
+        mutex: Arc<Mutex<()>>,
+        /// The unique identifier of the task rooted at this frame.
+        id: u64,
     },
     /// The frame is *not* the root node of its tree.
     Node {
@@ -110,10 +250,23 @@ type Children = linked_list::LinkedList<Frame, <Frame as linked_list::Link>::Tar
 impl Frame {
     /// Construct a new, uninitialized `Frame`.
     pub fn new(location: Location) -> Self {
+        Self::with_origin(location, Origin::Manual)
+    }
+
+    /// Construct a new, uninitialized `Frame` with the given [`Origin`].
+    pub fn with_origin(location: Location, origin: Origin) -> Self {
         Self {
             location,
+            origin,
+            outcome: Cell::new(Outcome::Cancelled),
+            detail: false,
+            barrier: false,
+            detached: false,
+            sequence: Sequence::default(),
+            future_size: FutureSize::default(),
             kind: Kind::Uninitialized,
             children: UnsafeCell::new(linked_list::LinkedList::new()),
+            metadata: UnsafeCell::new(Metadata::default()),
             siblings: linked_list::Pointers::new(),
             _pinned: PhantomPinned,
         }
@@ -123,82 +276,238 @@ impl Frame {
     ///
     /// If an invocation of `Frame::in_scope` is nested within `f`, those frames
     /// will be initialized with this frame as their parent.
+    ///
+    /// Invocations may be nested on the same task (e.g., by a waker that
+    /// synchronously polls its task); a nested invocation does not lock the
+    /// task again, but passes through to `f`, and then restores the previously
+    /// active frame.
     pub fn in_scope<F, R>(self: Pin<&mut Self>, f: F) -> R
     where
         F: FnOnce() -> R,
     {
-        // This non-generic preparation routine has been factored out of `in_scope`'s
-        // body, so as to reduce the monomorphization burden on the compiler.
-        //
-        // The soundness of other routines in this module depend on this function *not*
-        // being leaked from `in_scope`. In general, the drop-guard pattern cannot
-        // safely and soundly be used for frame management. If we attempt to provide
-        // such an API, we must ensure that unsoudness does not occur if child frames
-        // are dropped before their parents, or if a drop-guard is held across an
-        // `await` point.
-        unsafe fn activate<'a>(
-            mut frame: Pin<&'a mut Frame>,
-            active: &'a Cell<Option<NonNull<Frame>>>,
-        ) -> impl Drop + 'a {
-            // If needed, initialize this frame.
-            if frame.is_uninitialized() {
-                let maybe_parent = active.get().map(|parent| parent.as_ref());
-                frame.as_mut().initialize_unchecked(maybe_parent)
-            }
-
-            let frame = frame.into_ref().get_ref();
-
-            // If this is the root frame, lock its children. This lock is inherited by
+        unsafe {
+            // SAFETY: `_restore` is neither leaked nor held across an `await`;
+            // it restores the previously-active frame after the execution of
             // `f()`.
-            let maybe_mutex_guard = if let Kind::Root { mutex } = &frame.kind {
-                // Ignore poisoning. This is fine, since absolutely nothing between this line,
-                // and the execution of `drop(maybe_mutex_guard)` can unwind-panic, *except* for
-                // the execution of the user-provided function `f`. An unwind-panic of `f` will
-                // not make this crate's state inconsistent, since the parent frame is always
-                // restored by the below invocation of `crate::defer` upon its drop.
-                Some(match mutex.lock() {
-                    Ok(guard) => guard,
-                    Err(err) => err.into_inner(),
-                })
-            } else {
-                None
-            };
-
-            // Replace the previously-active frame with this frame.
-            let previously_active = active.replace(Some(frame.into()));
-
-            // At the end of this scope, restore the previously-active frame.
-            crate::defer(move || {
-                active.set(previously_active);
-                drop(maybe_mutex_guard);
-            })
+            let _restore = self.enter();
+            // Finally, execute the given function.
+            f()
         }
+    }
 
-        unsafe {
-            // SAFETY: We uphold `with`'s invariants by restoring the previously active
-            // frame after the execution of `f()`.
-            active_frame::with(|active| {
-                // Activate this frame.
-                let _restore = activate(self, active);
-                // Finally, execute the given function.
-                f()
-            })
+    /// Activates this frame until the returned guard is dropped.
+    ///
+    /// This is the closure-free counterpart of [`Frame::in_scope`], for
+    /// callers that must remain transparent to `#[track_caller]`: a closure
+    /// cannot propagate its caller's location.
+    ///
+    /// # Safety
+    /// The returned guard must be dropped in the scope that created it, and
+    /// must not be leaked or held across an `await` point.
+    pub(crate) unsafe fn enter<'a>(self: Pin<&'a mut Self>) -> impl Drop + 'a {
+        // SAFETY: We uphold `context::set`'s invariants by restoring the
+        // previously active frame when the guard is dropped.
+        activate(self)
+    }
+
+    /// Activates this frame (if it has been initialized) until the returned
+    /// guard is dropped; e.g., so that the destructors of its future observe
+    /// it as the active frame.
+    ///
+    /// Unlike [`enter`](Frame::enter), this locks the root of the frame's task
+    /// even if the frame is not the root, unless the task is already active
+    /// on this thread: the subframes dropped while it is active are unlinked
+    /// without locking the root again.
+    ///
+    /// # Safety
+    /// As for [`enter`](Frame::enter).
+    pub(crate) unsafe fn enter_for_drop<'a>(self: Pin<&'a mut Self>) -> Option<impl Drop + 'a> {
+        if self.is_uninitialized() {
+            return None;
         }
+        let maybe_guard = if self.parent().is_some() {
+            // SAFETY: The root of a frame outlives it.
+            let root: &'a Frame = &*(self.root() as *const Frame);
+            let in_task = Frame::with_active(|active| {
+                active.is_some_and(|active| core::ptr::eq(active.root(), root))
+            });
+            // Poisoning is ignored, as in `activate`.
+            root.mutex()
+                .filter(|_| !in_task)
+                .map(|mutex| mutex.lock().unwrap_or_else(|err| err.into_inner()))
+        } else {
+            None
+        };
+        let restore = activate(self);
+        // (the previously-active frame is restored before the root is unlocked)
+        Some(crate::defer(move || {
+            drop(restore);
+            drop(maybe_guard);
+        }))
     }
 
     /// Produces a boxed slice over this frame's ancestors.
     pub fn backtrace_locations(&self) -> Box<[Location]> {
-        let len = self.backtrace().count();
-        let mut vec = Vec::with_capacity(len);
+        let mut vec = Vec::with_capacity(self.backtrace_depth());
         vec.extend(self.backtrace().map(Frame::location));
         vec.into_boxed_slice()
     }
 
+    /// Produces the number of frames in this frame's backtrace (i.e., of its
+    /// ancestors, up to the nearest barrier), without allocating.
+    pub(crate) fn backtrace_depth(&self) -> usize {
+        self.backtrace().count()
+    }
+
     /// Produces the [`Location`] associated with this frame.
     pub fn location(&self) -> Location {
         self.location
     }
 
+    /// Executes the given function with a reference to this frame's
+    /// annotations.
+    ///
+    /// # Safety
+    /// The caller must ensure that the corresponding Kind::Root{mutex} is
+    /// locked.
+    pub(crate) unsafe fn with_metadata<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Metadata) -> R,
+    {
+        self.metadata.with(|metadata| f(&*metadata))
+    }
+
+    /// Executes the given function with a mutable reference to this frame's
+    /// annotations.
+    ///
+    /// # Safety
+    /// The caller must ensure that the corresponding Kind::Root{mutex} is
+    /// locked.
+    pub(crate) unsafe fn with_metadata_mut<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Metadata) -> R,
+    {
+        self.metadata.with_mut(|metadata| f(&mut *metadata))
+    }
+
+    /// Annotates this frame with `key = value`, replacing any previous value
+    /// of `key`. If `inherited`, descendants of this frame inherit the
+    /// annotation. Produces `false` (and annotates nothing) if `key` is new,
+    /// but this frame already carries `MAX_ANNOTATIONS` annotations.
+    ///
+    /// # Safety
+    /// The caller must ensure that the corresponding Kind::Root{mutex} is
+    /// locked.
+    pub(crate) unsafe fn annotate(
+        &self,
+        key: &'static str,
+        value: AnnotationValue,
+        inherited: bool,
+    ) -> bool {
+        self.with_metadata_mut(|metadata| {
+            let annotations = &mut metadata.annotations;
+            if let Some(slot) = annotations.iter_mut().find(|a| a.key == key) {
+                slot.value = value;
+                slot.inherited = inherited;
+            } else if annotations.len() < MAX_ANNOTATIONS {
+                annotations.push(Annotation {
+                    key,
+                    value,
+                    inherited,
+                });
+            } else {
+                return false;
+            }
+            true
+        })
+    }
+
+    /// Produces the annotations of this frame (but not those it inherits).
+    ///
+    /// # Safety
+    /// The caller must ensure that the corresponding Kind::Root{mutex} is
+    /// locked.
+    pub(crate) unsafe fn annotations(&self) -> Vec<(&'static str, AnnotationValue)> {
+        self.with_metadata(|metadata| {
+            metadata
+                .annotations
+                .iter()
+                .map(|annotation| (annotation.key, annotation.value.clone()))
+                .collect()
+        })
+    }
+
+    /// Produces the [`Origin`] of this frame.
+    pub fn origin(&self) -> Origin {
+        self.origin
+    }
+
+    /// Marks this (not yet pinned) frame as a barrier, or not.
+    pub(crate) fn set_barrier(&mut self, barrier: bool) {
+        self.barrier = barrier;
+    }
+
+    /// Produces `true` if this frame is initialized as the root of a task,
+    /// regardless of the frame that is active when it is first polled; see
+    /// [`set_detached`](Frame::set_detached).
+    pub(crate) fn is_detached(&self) -> bool {
+        self.detached
+    }
+
+    /// Marks this (not yet pinned) frame as detached, or not.
+    pub(crate) fn set_detached(&mut self, detached: bool) {
+        self.detached = detached;
+    }
+
+    /// Records the size of the future wrapped by this frame.
+    pub(crate) fn with_future_size(mut self, future_size: FutureSize) -> Self {
+        self.future_size = future_size;
+        self
+    }
+
+    /// Produces the size of the future wrapped by this frame, if it is
+    /// recorded.
+    pub(crate) fn future_size(&self) -> Option<u32> {
+        self.future_size.get()
+    }
+
+    /// Produces the sequence numbers of this frame.
+    pub(crate) fn sequence(&self) -> &Sequence {
+        &self.sequence
+    }
+
+    /// Produces `true` if an activation of this frame was unwound by a panic.
+    ///
+    /// # Safety
+    /// The caller must ensure that the corresponding Kind::Root{mutex} is
+    /// locked.
+    pub(crate) unsafe fn has_panicked(&self) -> bool {
+        self.with_metadata(|metadata| metadata.panicked)
+    }
+
+    /// Produces `true` if this frame is a detail, which dumps collapse by
+    /// default; see [`set_detail`](Frame::set_detail).
+    pub(crate) fn is_detail(&self) -> bool {
+        self.detail
+    }
+
+    /// Marks this (not yet pinned) frame as a detail, or not.
+    pub(crate) fn set_detail(&mut self, detail: bool) {
+        self.detail = detail;
+    }
+
+    /// Produces how the future of this frame ended; see
+    /// [`set_outcome`](Frame::set_outcome).
+    pub(crate) fn outcome(&self) -> Outcome {
+        self.outcome.get()
+    }
+
+    /// Sets how the future of this frame ended. Until this is first called,
+    /// the outcome is [`Outcome::Cancelled`].
+    pub(crate) fn set_outcome(&self, outcome: Outcome) {
+        self.outcome.set(outcome);
+    }
+
     /// Produces `true` if this `Frame` is uninitialized, otherwise false.
     fn is_uninitialized(&self) -> bool {
         self.kind.is_uninitialized()
@@ -210,11 +519,13 @@ impl Frame {
     /// This method must only be called, at most, once.
     #[inline(never)]
     unsafe fn initialize_unchecked(mut self: Pin<&mut Self>, maybe_parent: Option<&Frame>) {
+        // (before this frame is reachable from its parent, or the registry)
+        self.as_mut().project().sequence.initialized();
         match maybe_parent {
             // This frame has no parent...
             None => {
                 // ...it is the root of its tree,
-                *self.as_mut().project().kind = Kind::root();
+                *self.as_mut().project().kind = Kind::root(crate::tasks::next_id());
                 // ...and must be registered as a task.
                 crate::tasks::register(self.into_ref().get_ref());
             }
@@ -227,6 +538,12 @@ impl Frame {
                 parent
                     .children
                     .with_mut(|children| (*children).push_front(this));
+                // ...and, if its parent is probing for children, be recorded there.
+                parent.with_metadata_mut(|metadata| {
+                    if let Some(probed) = &mut metadata.probed {
+                        probed.push(this.as_ref().location);
+                    }
+                });
             }
         };
     }
@@ -237,110 +554,95 @@ impl Frame {
     where
         F: FnOnce(Option<&Frame>) -> R,
     {
-        Frame::with_active_cell(|cell| f(cell.get()))
+        // SAFETY: The active frame remains dereferenceable until it is
+        // replaced, which cannot occur before `f` returns.
+        f(crate::context::get().map(|frame| unsafe { frame.as_ref() }))
     }
 
-    pub(crate) fn with_active_cell<F, R>(f: F) -> R
-    where
-        F: FnOnce(&Cell<Option<&Frame>>) -> R,
-    {
-        #[allow(clippy::needless_lifetimes)]
-        unsafe fn into_ref<'a, 'b>(
-            cell: &'a Cell<Option<NonNull<Frame>>>,
-        ) -> &'a Cell<Option<&'b Frame>> {
-            // SAFETY: `Cell<NonNull<Frame>>` has the same layout has `Cell<&Frame>`,
-            // because both `Cell` and `NonNull` are `#[repr(transparent)]`, and because
-            // `*const Frame` has the same layout as `&Frame`.
-            core::mem::transmute(cell)
-        }
-
-        unsafe {
-            // SAFETY: We uphold `with`'s invariants, by only providing `f` with a
-            // *reference* to the frame.
-            active_frame::with(|cell| {
-                let cell = into_ref(cell);
-                f(cell)
-            })
-        }
+    /// Produces `true` if a frame initialized now, on this thread, would be the
+    /// root of a task; i.e., if there is no active frame.
+    pub(crate) fn would_be_root() -> bool {
+        crate::context::get().is_none()
     }
 
     /// Produces the mutex (if any) guarding this frame's children.
-    pub(crate) fn mutex(&self) -> Option<&Mutex<()>> {
-        if let Kind::Root { mutex } = &self.kind {
+    pub(crate) fn mutex(&self) -> Option<&Arc<Mutex<()>>> {
+        if let Kind::Root { mutex, .. } = &self.kind {
             Some(mutex)
         } else {
             None
         }
     }
 
-    pub(crate) unsafe fn fmt<W: core::fmt::Write>(
+    /// Produces the unique identifier of the task rooted at this frame, if
+    /// this frame is a root.
+    pub(crate) fn task_id(&self) -> Option<u64> {
+        if let Kind::Root { id, .. } = &self.kind {
+            Some(*id)
+        } else {
+            None
+        }
+    }
+
+    /// Checks the integrity of the tree beneath this frame, appending any
+    /// violations to `errors`. Each frame visited is recorded in `visited`.
+    ///
+    /// # Safety
+    /// The caller must ensure that the corresponding Kind::Root{mutex} is
+    /// locked.
+    #[cfg(any(debug_assertions, feature = "debug-validate"))]
+    pub(crate) unsafe fn validate(
         &self,
-        w: &mut W,
-        subframes_locked: bool,
-    ) -> std::fmt::Result {
-        unsafe fn fmt_helper<W: core::fmt::Write>(
-            f: &mut W,
-            frame: &Frame,
-            is_last: bool,
-            prefix: &str,
-            subframes_locked: bool,
-            copies: usize,
-        ) -> core::fmt::Result {
-            let location = frame.location();
-            let current;
-            let next;
-
-            if is_last {
-                if copies != 1 {
-                    current = format!("{prefix}└╼ {copies}x {location}");
-                } else {
-                    current = format!("{prefix}└╼ {location}");
-                }
-                next = format!("{prefix}   ");
-            } else {
-                if copies != 1 {
-                    current = format!("{prefix}├╼ {copies}x {location}");
-                } else {
-                    current = format!("{prefix}├╼ {location}");
-                }
-                next = format!("{prefix}│  ");
-            }
+        visited: &mut std::collections::HashSet<NonNull<Frame>>,
+        errors: &mut Vec<crate::debug::IntegrityError>,
+    ) {
+        use crate::debug::IntegrityError;
+
+        let this = NonNull::from(self);
+        if !visited.insert(this) {
+            errors.push(IntegrityError::Cycle {
+                frame: self.location,
+            });
+            return;
+        }
 
-            // print all but the first three codepoints of current
-            write!(f, "{}", {
-                let mut current = current.chars();
-                current.next().unwrap();
-                current.next().unwrap();
-                current.next().unwrap();
-                &current.as_str()
-            })?;
-
-            if subframes_locked {
-                let mut subframes = frame.subframes().peekable();
-                let mut copies = 1;
-                while let Some(subframe) = subframes.next() {
-                    if subframes
-                        .peek()
-                        .map(|next| next.deep_eq(subframe))
-                        .unwrap_or(false)
-                    {
-                        copies += 1;
-                    } else {
-                        writeln!(f)?;
-                        let is_last = subframes.peek().is_none();
-                        fmt_helper(f, subframe, is_last, &next, true, copies)?;
-                        copies = 1;
-                    }
-                }
-            } else {
-                writeln!(f)?;
-                write!(f, "{prefix}└┈ [POLLING]")?;
+        let (head, tail) = self
+            .children
+            .with(|children| ((*children).head(), (*children).tail()));
+
+        // Walk the children by hand, rather than with `subframes`, so that a
+        // cycle among siblings is detected rather than followed forever.
+        let mut prev = None;
+        let mut next = head;
+        while let Some(child) = next {
+            if visited.contains(&child) {
+                errors.push(IntegrityError::Cycle {
+                    frame: child.as_ref().location,
+                });
+                return;
             }
-
-            Ok(())
+            let child_ref = child.as_ref();
+            if child_ref.parent().map(NonNull::from) != Some(this) {
+                errors.push(IntegrityError::ParentMismatch {
+                    child: child_ref.location,
+                    parent: self.location,
+                });
+            }
+            if child_ref.siblings.get_prev() != prev {
+                errors.push(IntegrityError::BrokenSiblingLink {
+                    parent: self.location,
+                });
+            }
+            child_ref.validate(visited, errors);
+            prev = Some(child);
+            next = child_ref.siblings.get_next();
         }
 
-        fmt_helper(w, self, true, "  ", subframes_locked, 1)
+        if tail != prev {
+            errors.push(IntegrityError::BrokenSiblingLink {
+                parent: self.location,
+            });
+        }
     }
 
     /// Produces the parent frame of this frame.
@@ -363,33 +665,19 @@ impl Frame {
         frame
     }
 
-    /// Produces an iterator over this frame's ancestors.
+    /// Produces an iterator over this frame's ancestors, up to (and
+    /// including) the nearest barrier, if any.
+    ///
+    /// The ancestors of a barrier frame are omitted, as though the barrier
+    /// were the root of its task; see [`Frame::ancestors`].
     pub fn backtrace(&self) -> impl FusedIterator<Item = &Frame> {
-        /// An iterator that traverses up the tree of [`Frame`]s from a leaf.
-        #[derive(Clone)]
-        pub(crate) struct Backtrace<'a> {
-            frame: Option<&'a Frame>,
-        }
-
-        impl<'a> Backtrace<'a> {
-            pub(crate) fn from_leaf(frame: &'a Frame) -> Self {
-                Self { frame: Some(frame) }
-            }
-        }
-
-        impl<'a> Iterator for Backtrace<'a> {
-            type Item = &'a Frame;
-
-            fn next(&mut self) -> Option<Self::Item> {
-                let curr = self.frame;
-                self.frame = curr.and_then(Frame::parent);
-                curr
-            }
-        }
-
-        impl<'a> FusedIterator for Backtrace<'a> {}
+        Backtrace::from_leaf(self, false)
+    }
 
-        Backtrace::from_leaf(self)
+    /// Produces an iterator over all of this frame's ancestors, regardless of
+    /// barriers.
+    pub(crate) fn ancestors(&self) -> impl FusedIterator<Item = &Frame> {
+        Backtrace::from_leaf(self, true)
     }
 
     /// Produces an iterator over this frame's children, in order from
@@ -424,41 +712,44 @@ impl Frame {
 
         Subframes::from_parent(self)
     }
+}
 
-    /// # Safety
-    /// The caller must ensure that the corresponding Kind::Root{mutex} is
-    /// locked.
-    pub(crate) unsafe fn deep_eq(&self, other: &Frame) -> bool {
-        if self.location() != other.location() {
-            return false;
+/// An iterator that traverses up the tree of [`Frame`]s from a leaf.
+#[derive(Clone)]
+struct Backtrace<'a> {
+    frame: Option<&'a Frame>,
+    through_barriers: bool,
+}
+
+impl<'a> Backtrace<'a> {
+    fn from_leaf(frame: &'a Frame, through_barriers: bool) -> Self {
+        Self {
+            frame: Some(frame),
+            through_barriers,
         }
+    }
+}
 
-        let mut self_subframes = self.subframes();
-        let mut other_subframes = other.subframes();
+impl<'a> Iterator for Backtrace<'a> {
+    type Item = &'a Frame;
 
-        loop {
-            match (self_subframes.next(), other_subframes.next()) {
-                (Some(self_subframe), Some(other_subframe)) => {
-                    if !self_subframe.deep_eq(other_subframe) {
-                        return false;
-                    }
-                }
-                (None, None) => {
-                    return true;
-                }
-                _ => {
-                    return false;
-                }
-            }
-        }
+    fn next(&mut self) -> Option<Self::Item> {
+        let curr = self.frame;
+        self.frame = curr
+            .filter(|frame| self.through_barriers || !frame.barrier)
+            .and_then(Frame::parent);
+        curr
     }
 }
 
+impl<'a> FusedIterator for Backtrace<'a> {}
+
 impl Kind {
     /// Produces a new [`Kind::Root`].
-    fn root() -> Self {
+    fn root(id: u64) -> Self {
         Kind::Root {
-            mutex: Mutex::new(()),
+            mutex: Arc::new(Mutex::new(())),
+            id,
         }
     }
 
@@ -1,24 +1,71 @@
 use core::future::Future;
 use core::pin::Pin;
 use core::task::{Context, Poll};
-use std::marker::PhantomPinned;
+use std::time::Instant;
+use std::{marker::PhantomPinned, mem::ManuallyDrop};
 
-use crate::frame::Frame;
+use crate::cell::Cell;
+use crate::frame::{Frame, Origin};
+use crate::hooks::{self, Outcome, Warning};
 use crate::location::Location;
+use crate::size::FutureSize;
+#[cfg(feature = "tokio")]
+use crate::snapshot::TaskTree;
 
 use pin_project_lite::pin_project;
 
 pin_project! {
     /// A future whose [`Location`] is included in [taskdumps][crate::tasks] and [backtraces][crate::backtrace].
     pub struct Framed<F> {
-        // The wrapped future.
+        // The wrapped future, which is dropped (in place) by `drop`, with
+        // `frame` active.
         #[pin]
-        future: F,
+        future: ManuallyDrop<F>,
         // Metadata about the wrapped future.
         #[pin]
         frame: Frame,
+        // How (and whether) the next poll of the wrapped future initializes
+        // `frame`.
+        //
+        // This, and the other state of polls, is kept in cells, since a
+        // re-entrant poll (as by a waker that synchronously polls its task)
+        // accesses it while the enclosing poll borrows the future.
+        mode: Cell<Mode>,
+        // If the future must be polled, and has not yet been, when it was
+        // created.
+        unpolled_since: Cell<Option<Instant>>,
+        // Whether a poll of the wrapped future panicked.
+        poisoned: Cell<bool>,
         _pinned: PhantomPinned,
     }
+
+    impl<F> PinnedDrop for Framed<F> {
+        fn drop(this: Pin<&mut Self>) {
+            let this = this.project();
+            // futures dropped by unwinding were likely to be polled, but for
+            // the panic
+            if let (Some(created), false) = (this.unpolled_since.get(), std::thread::panicking()) {
+                hooks::warn(Warning::NeverPolled {
+                    location: this.frame.location(),
+                    age: created.elapsed(),
+                });
+            }
+            // The destructors of the future (e.g., of guards held across
+            // `await`s) run with its frame active, if it was initialized, so
+            // that they may take backtraces; but not while unwinding, as the
+            // lock of its task may be poisoned by the panic.
+            // SAFETY: The guard is dropped at the end of this scope. The
+            // future is dropped in place, and never used again.
+            unsafe {
+                let _restore = if std::thread::panicking() {
+                    None
+                } else {
+                    this.frame.enter_for_drop()
+                };
+                ManuallyDrop::drop(this.future.get_unchecked_mut());
+            }
+        }
+    }
 }
 
 impl<F: core::panic::UnwindSafe> core::panic::UnwindSafe for Framed<F> {}
@@ -27,12 +74,217 @@ impl<F> Framed<F> {
     /// Include the given `future` in taskdumps and
     /// backtraces with the given `location`.
     pub fn new(future: F, location: Location) -> Self {
+        Self::with_origin(future, location, Origin::Manual)
+    }
+
+    /// Include the given `future` in taskdumps and
+    /// backtraces with the given `location` and `origin`.
+    pub fn with_origin(future: F, location: Location, origin: Origin) -> Self {
+        Self::with_config(future, location, FrameConfig::new().origin(origin))
+    }
+
+    /// Include the given `future` in taskdumps and
+    /// backtraces with the given `location`, configured by `config`.
+    pub fn with_config(future: F, location: Location, config: FrameConfig) -> Self {
+        let mut frame =
+            Frame::with_origin(location, config.origin).with_future_size(FutureSize::of::<F>());
+        frame.set_barrier(config.barrier);
+        frame.set_detached(config.detached);
+        frame.set_detail(config.detail);
         Self {
-            future,
-            frame: Frame::new(location),
+            future: ManuallyDrop::new(future),
+            frame,
+            mode: Cell::new(config.mode),
+            unpolled_since: Cell::new(config.must_poll.then(Instant::now)),
+            poisoned: Cell::new(false),
             _pinned: PhantomPinned,
         }
     }
+
+    /// Defers the initialization of this future's frame until after its
+    /// first poll, skipping it entirely if that poll is ready; unless the frame
+    /// would be the root of a task. See [`Location::frame_lazy`].
+    ///
+    /// Overrides [`root_only`](Framed::root_only).
+    pub fn lazy(self) -> Self {
+        self.mode.set(Mode::Lazy);
+        self
+    }
+
+    /// Marks this future's frame as a detail; a chain of details is collapsed
+    /// in taskdumps, unless they are [verbose](crate::Verbosity::Full).
+    pub fn detail(mut self) -> Self {
+        self.frame.set_detail(true);
+        self
+    }
+
+    /// Marks this future's frame as a barrier, beyond which backtraces
+    /// captured beneath it do not propagate.
+    pub fn barrier(mut self) -> Self {
+        self.frame.set_barrier(true);
+        self
+    }
+
+    /// Initializes this future's frame as the root of a task, even if another
+    /// frame is active when it is first polled.
+    pub fn detached(mut self) -> Self {
+        self.frame.set_detached(true);
+        self
+    }
+
+    /// Reports a [`Warning::NeverPolled`] if this future is dropped without
+    /// ever being polled.
+    pub fn must_poll(self) -> Self {
+        self.unpolled_since.set(Some(Instant::now()));
+        self
+    }
+
+    /// Only initializes this future's frame if, upon its first poll, it would
+    /// be the root of a task; otherwise, the wrapped future is polled directly
+    /// from then on, as if it were not framed.
+    ///
+    /// Overrides [`lazy`](Framed::lazy).
+    pub fn root_only(self) -> Self {
+        self.mode.set(Mode::RootOnly);
+        self
+    }
+}
+
+impl<F> Framed<F> {
+    /// Produces the id of the task rooted at this future's frame, if its frame
+    /// has been initialized as the root of a task.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn task_id(self: Pin<&Self>) -> Option<u64> {
+        self.project_ref().frame.task_id()
+    }
+
+    /// Captures the trees of the subframes of this future's frame, as they
+    /// are at this instant; e.g., just before the future is dropped.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn capture_subframes(self: Pin<&mut Self>) -> Vec<TaskTree> {
+        let epoch = Instant::now();
+        self.project().frame.in_scope(|| {
+            Frame::with_active(|frame| {
+                let frame = frame.expect("`in_scope` activates the frame");
+                // SAFETY: The root of the active frame is locked for the
+                // duration of `in_scope`.
+                unsafe { TaskTree::capture_subframes(frame, epoch) }
+            })
+        })
+    }
+}
+
+/// How the frame of a [`Framed`] future is initialized.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Mode {
+    /// The frame is initialized (if it is not already) by the next poll.
+    Eager,
+    /// The next poll occurs outside of the frame, which is only initialized if
+    /// that poll is pending.
+    Lazy,
+    /// The frame is initialized by the next poll only if it would be the root
+    /// of a task.
+    RootOnly,
+    /// The frame is never initialized.
+    Bypassed,
+}
+
+/// How a frame is constructed by [`Location::frame_with`]; e.g.:
+/// ```
+/// use async_backtrace::FrameConfig;
+///
+/// # async fn handler() {}
+/// # futures::executor::block_on(async {
+/// let config = FrameConfig::new().barrier().must_poll();
+/// async_backtrace::location!().frame_with(handler(), config).await;
+/// # });
+/// ```
+///
+/// Each option corresponds to an argument of [`framed`](crate::framed), and
+/// to one of the `frame_*` methods of [`Location`]; the attribute configures
+/// its frames by this very type. By default, a frame is initialized eagerly,
+/// upon its first poll, as by [`Location::frame`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct FrameConfig {
+    mode: Mode,
+    barrier: bool,
+    detached: bool,
+    detail: bool,
+    must_poll: bool,
+    origin: Origin,
+}
+
+impl FrameConfig {
+    /// The default configuration, of [`Origin::Manual`].
+    pub const fn new() -> Self {
+        Self {
+            mode: Mode::Eager,
+            barrier: false,
+            detached: false,
+            detail: false,
+            must_poll: false,
+            origin: Origin::Manual,
+        }
+    }
+
+    /// Defers the initialization of the frame until after the first poll,
+    /// skipping it entirely if that poll is ready, unless the frame would be
+    /// the root of a task; see [`Location::frame_lazy`].
+    ///
+    /// Overrides [`root_only`](FrameConfig::root_only).
+    pub const fn lazy(mut self) -> Self {
+        self.mode = Mode::Lazy;
+        self
+    }
+
+    /// Only initializes the frame if, upon its first poll, it would be the
+    /// root of a task; see [`Location::frame_root_only`].
+    ///
+    /// Overrides [`lazy`](FrameConfig::lazy).
+    pub const fn root_only(mut self) -> Self {
+        self.mode = Mode::RootOnly;
+        self
+    }
+
+    /// Marks the frame as a barrier to backtraces; see
+    /// [`Location::frame_barrier`].
+    pub const fn barrier(mut self) -> Self {
+        self.barrier = true;
+        self
+    }
+
+    /// Initializes the frame as the root of a task, whatever frame first
+    /// polls it; see [`Location::frame_detached`].
+    pub const fn detached(mut self) -> Self {
+        self.detached = true;
+        self
+    }
+
+    /// Marks the frame as a detail, collapsed in taskdumps; see
+    /// [`Location::frame_detail`].
+    pub const fn detail(mut self) -> Self {
+        self.detail = true;
+        self
+    }
+
+    /// Warns if the future is dropped without ever being polled; see
+    /// [`Location::frame_must_poll`].
+    pub const fn must_poll(mut self) -> Self {
+        self.must_poll = true;
+        self
+    }
+
+    /// Sets how the frame is reported to have been instrumented.
+    pub const fn origin(mut self, origin: Origin) -> Self {
+        self.origin = origin;
+        self
+    }
+}
+
+impl Default for FrameConfig {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<F> Future for Framed<F>
@@ -41,11 +293,71 @@ where
 {
     type Output = <F as Future>::Output;
 
+    /// Polls the wrapped future.
+    ///
+    /// # Panics
+    /// If a previous poll panicked. The frame of a future that panicked
+    /// remains in taskdumps, marked `[panicked]`, until it is dropped.
     #[track_caller]
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<<Self as Future>::Output> {
-        let this = self.project();
-        let frame = this.frame;
-        let future = this.future;
-        frame.in_scope(|| future.poll(cx))
+        // The fields are not projected (as by `project`), which would borrow
+        // the state of the poll uniquely; a re-entrant poll would invalidate
+        // those borrows.
+        // SAFETY: Neither the future nor the frame is moved out of `this`.
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.poisoned.get() {
+            panic!("{} polled after panic", this.frame.location());
+        }
+        // If the poll unwinds, the future is poisoned. (A future may be
+        // polled by a destructor run during an unwind.)
+        let poisoned = &this.poisoned;
+        let unwinding = std::thread::panicking();
+        let _poison = crate::defer(move || {
+            if !unwinding && std::thread::panicking() {
+                poisoned.set(true);
+            }
+        });
+        // SAFETY: The frame and the future (within its `ManuallyDrop`) are
+        // pinned, as `self` is.
+        let mut frame = unsafe { Pin::new_unchecked(&mut this.frame) };
+        let future = unsafe { Pin::new_unchecked(&mut *this.future) };
+        this.unpolled_since.set(None);
+        match this.mode.replace(Mode::Eager) {
+            Mode::Eager => {}
+            // Roots are never lazy: the subframes first polled by their first
+            // poll would otherwise each become the root of a task of its own.
+            Mode::Lazy if frame.is_detached() || Frame::would_be_root() => {}
+            Mode::Lazy => {
+                // Poll the future outside of its frame...
+                let poll = future.poll(cx);
+                if poll.is_pending() {
+                    // ...and only initialize the frame if it is still pending.
+                    frame.in_scope(|| {});
+                }
+                return poll;
+            }
+            Mode::RootOnly if frame.is_detached() || Frame::would_be_root() => {}
+            Mode::RootOnly | Mode::Bypassed => {
+                // The decision is cached, rather than made upon each poll, so
+                // that the future is never framed partway through.
+                this.mode.set(Mode::Bypassed);
+                return future.poll(cx);
+            }
+        }
+        // If the poll unwinds, the outcome remains `Panicked`.
+        frame.set_outcome(Outcome::Panicked);
+        let poll = {
+            // Poll the future directly, rather than from a closure passed to
+            // `in_scope`, so that `#[track_caller]` locations pass through.
+            // SAFETY: `_restore` is dropped at the end of this block; it is
+            // neither leaked nor held across an `await`.
+            let _restore = unsafe { frame.as_mut().enter() };
+            future.poll(cx)
+        };
+        frame.set_outcome(match poll {
+            Poll::Ready(_) => Outcome::Completed,
+            Poll::Pending => Outcome::Cancelled,
+        });
+        poll
     }
 }
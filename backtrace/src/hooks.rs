@@ -0,0 +1,150 @@
+//! Hooks invoked upon events in the lifecycle of tasks.
+
+use std::fmt;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::{Frame, Location};
+
+/// A hook invoked upon the exit of each task.
+type ExitHook = Box<dyn Fn(&TaskExit) + Send + Sync>;
+
+static EXIT_HOOK: RwLock<Option<ExitHook>> = RwLock::new(None);
+
+/// A hook invoked upon each [`Warning`].
+type WarningHook = Box<dyn Fn(&Warning) + Send + Sync>;
+
+static WARNING_HOOK: RwLock<Option<WarningHook>> = RwLock::new(None);
+
+/// How the future of a task ended.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Outcome {
+    /// The future completed.
+    Completed,
+    /// The future was dropped before it completed.
+    Cancelled,
+    /// A poll of the future panicked.
+    Panicked,
+}
+
+/// The exit of a task, reported to the hook set by [`set_task_exit_hook`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TaskExit {
+    id: u64,
+    location: Location,
+    outcome: Outcome,
+}
+
+impl TaskExit {
+    /// The [id](crate::Task::id) of the task.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The location of the root frame of the task.
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    /// How the future of the task ended.
+    ///
+    /// The outcome of tasks rooted at a [`Frame`](crate::raw::Frame) that is
+    /// not the frame of a [framed](crate::framed) future is always
+    /// [`Outcome::Cancelled`].
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+}
+
+/// Sets the hook invoked upon the exit of each task (i.e., when the root
+/// frame of a task is dropped), replacing any previously set hook.
+///
+/// The hook is invoked after the task has been deregistered, on the thread
+/// that dropped it. It must not panic (nor call [`set_task_exit_hook`]), and
+/// should return quickly, since it delays the drop of every task.
+///
+/// ## Example
+/// ```
+/// use async_backtrace::{set_task_exit_hook, Outcome};
+///
+/// set_task_exit_hook(|exit| {
+///     if exit.outcome() == Outcome::Panicked {
+///         eprintln!("task {} at {} panicked", exit.id(), exit.location());
+///     }
+/// });
+/// ```
+pub fn set_task_exit_hook<H>(hook: H)
+where
+    H: Fn(&TaskExit) + Send + Sync + 'static,
+{
+    *EXIT_HOOK.write().unwrap_or_else(|err| err.into_inner()) = Some(Box::new(hook));
+}
+
+/// Reports the exit of the task rooted at `root` to the exit hook (if any).
+pub(crate) fn task_exited(root: &Frame) {
+    let hook = EXIT_HOOK.read().unwrap_or_else(|err| err.into_inner());
+    if let (Some(hook), Some(id)) = (&*hook, root.task_id()) {
+        hook(&TaskExit {
+            id,
+            location: root.location(),
+            outcome: root.outcome(),
+        });
+    }
+}
+
+/// A likely misuse of a framed future, reported to the hook set by
+/// [`set_warning_hook`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// A future that [must be polled](crate::Location::frame_must_poll) was
+    /// dropped without ever being polled; e.g., because it was stored, but
+    /// never spawned nor `.await`ed.
+    #[non_exhaustive]
+    NeverPolled {
+        /// The location of the future's frame.
+        location: Location,
+        /// How long the future existed.
+        age: Duration,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NeverPolled { location, age } => write!(
+                f,
+                "{location} was dropped without ever being polled, {age:?} after it was created"
+            ),
+        }
+    }
+}
+
+/// Sets the hook invoked upon each [`Warning`], replacing any previously set
+/// hook. Until a hook is set, warnings are printed to standard error.
+///
+/// The hook is invoked on the thread that caused the warning (e.g., that
+/// dropped a future). It must not panic (nor call [`set_warning_hook`]).
+///
+/// ## Example
+/// ```
+/// use async_backtrace::{set_warning_hook, Warning};
+///
+/// set_warning_hook(|warning: &Warning| eprintln!("async-backtrace: {}", warning));
+/// ```
+pub fn set_warning_hook<H>(hook: H)
+where
+    H: Fn(&Warning) + Send + Sync + 'static,
+{
+    *WARNING_HOOK.write().unwrap_or_else(|err| err.into_inner()) = Some(Box::new(hook));
+}
+
+/// Reports `warning` to the warning hook, or else to standard error.
+pub(crate) fn warn(warning: Warning) {
+    let hook = WARNING_HOOK.read().unwrap_or_else(|err| err.into_inner());
+    match &*hook {
+        Some(hook) => hook(&warning),
+        None => eprintln!("async-backtrace: {warning}"),
+    }
+}
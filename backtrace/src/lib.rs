@@ -89,17 +89,167 @@
 //! to the benchmarks and interpretive guidance in
 //! `./backtrace/benches/frame_overhead.rs`. You can run these benchmarks with
 //! `cargo bench`.
+//!
+//! ## Testing with Loom
+//! Crates that model their concurrent code with [loom] may include framed
+//! futures in their models, by building them with `--cfg loom` (as for tokio),
+//! as by `RUSTFLAGS="--cfg loom" cargo test`. The crate then
+//! synchronizes its frames and its registry of tasks with loom's primitives, so
+//! that loom explores the interleavings of polls, registration and taskdumps.
+//! Built so, framed futures may only be polled, and taskdumps taken, within
+//! `loom::model`.
+//!
+//! Build such models with `--release`: loom runs each of its threads on a
+//! small, fixed stack, which the deeper frames of debug builds may overflow.
+//!
+//! [loom]: https://docs.rs/loom
+//!
+//! ## `no_std`
+//! Without its default `std` feature, the crate is `no_std` (but requires
+//! `alloc`), and provides only `Location`s, the `location!` and
+//! `location_named!` macros, and the styles in which locations are rendered;
+//! e.g., for firmware that records the locations of its tasks itself. Frames,
+//! the registry of tasks, and taskdumps require `std`, as do the crate's other
+//! features.
+
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub(crate) mod attach;
+#[cfg(feature = "std")]
+pub(crate) mod backtrace;
+#[cfg(feature = "std")]
+pub(crate) mod catch;
+#[cfg(feature = "std")]
+pub mod classify;
+#[cfg(feature = "std")]
+pub(crate) mod coalesce;
+#[cfg(feature = "std")]
+pub(crate) mod context;
+#[cfg(feature = "std")]
+pub mod counters;
+#[cfg(all(feature = "std", any(debug_assertions, feature = "debug-validate")))]
+pub mod debug;
+#[cfg(feature = "std")]
+pub(crate) mod delta;
+#[cfg(feature = "std")]
+pub(crate) mod diff;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
 pub(crate) mod frame;
+#[cfg(feature = "std")]
 pub(crate) mod framed;
+#[cfg(feature = "std")]
+pub(crate) mod hooks;
+#[cfg(feature = "std")]
 pub(crate) mod linked_list;
 pub(crate) mod location;
+#[cfg(feature = "std")]
+pub(crate) mod metadata;
+#[cfg(feature = "std")]
+pub(crate) mod probe;
+#[cfg(feature = "std")]
+pub mod raw;
+#[cfg(feature = "std")]
+pub(crate) mod registry;
+#[cfg(feature = "std")]
+pub mod report;
+#[cfg(feature = "std")]
+pub(crate) mod sequence;
+#[cfg(feature = "tokio")]
+pub mod shutdown_watch;
+#[cfg(feature = "std")]
+pub(crate) mod size;
+#[cfg(feature = "std")]
+pub(crate) mod snapshot;
+#[cfg(feature = "std")]
+pub(crate) mod source;
+#[cfg(feature = "tokio")]
+pub(crate) mod spawn;
+#[cfg(feature = "std")]
+pub(crate) mod taskdump;
+#[cfg(feature = "std")]
 pub(crate) mod tasks;
+#[cfg(feature = "test-utils")]
+pub mod testing;
+#[cfg(feature = "std")]
+pub(crate) mod threads;
+#[cfg(feature = "tokio")]
+pub(crate) mod timeout;
+#[cfg(feature = "std")]
+pub(crate) mod traced;
+#[cfg(feature = "std")]
+pub(crate) mod tree_text;
 
+#[cfg(feature = "std")]
+pub use attach::{capture_context, ContextHandle};
+#[cfg(feature = "std")]
+pub use backtrace::AsyncBacktrace;
+#[cfg(feature = "native-backtrace")]
+pub use backtrace::FullBacktrace;
+#[cfg(feature = "std")]
+pub use catch::{set_panic_sink, PanicReport};
+#[cfg(feature = "std")]
+pub use context::{set_context_storage, ActiveFrame, ContextStorage, ContextStorageError};
+#[cfg(feature = "std")]
+pub use delta::{Delta, DeltaTracker, TaskDelta};
+#[cfg(feature = "std")]
+pub use diff::{diff, DiffTask, DumpDiff, TaskDiff};
+#[cfg(feature = "std")]
 pub(crate) use frame::Frame;
+#[cfg(feature = "std")]
+pub use frame::Origin;
+#[cfg(feature = "std")]
+pub use framed::FrameConfig;
+#[cfg(feature = "std")]
 pub(crate) use framed::Framed;
-pub use location::Location;
-pub use tasks::{tasks, Task};
+#[cfg(feature = "std")]
+pub use hooks::{set_task_exit_hook, set_warning_hook, Outcome, TaskExit, Warning};
+#[cfg(feature = "serde")]
+pub use location::OwnedLocation;
+pub use location::{
+    location_style, set_location_style, CompactLocation, Location, LocationStyle,
+    LocationStyleError,
+};
+#[cfg(feature = "std")]
+pub use metadata::{
+    annotate, annotate_inherited, annotate_value, annotate_value_inherited, AnnotatedFrame,
+    AnnotationValue,
+};
+#[cfg(feature = "std")]
+pub use probe::probe_child_frames;
+#[cfg(feature = "future-sizes")]
+pub use size::largest_futures;
+#[cfg(feature = "std")]
+pub use snapshot::{snapshot, FrameTree, TaskTree};
+#[cfg(feature = "serde")]
+pub use snapshot::{OwnedFrameTree, OwnedLastKnown, OwnedTaskTree};
+#[cfg(feature = "std")]
+pub use source::SourceSnippets;
+#[cfg(feature = "tokio")]
+pub use spawn::{abort_task, spawn_framed_abortable};
+#[cfg(feature = "std")]
+pub use taskdump::{
+    dump_tasks, set_default_dump_options, DefaultDumpOptionsError, DumpStats, TaskdumpOptions,
+    Verbosity,
+};
+#[cfg(feature = "std")]
+pub use tasks::{
+    configure_registry, memory_estimate, reset_high_water_mark, tasks, tasks_containing,
+    tasks_snapshot_ids, MemoryEstimate, RegistryConfig, RegistryConfigError, Task, TaskInfo,
+    TaskRef, TasksContaining,
+};
+#[cfg(feature = "thread-report")]
+pub use threads::{thread_report, ThreadActivity};
+#[cfg(feature = "tokio")]
+pub use timeout::{Elapsed, TimeoutExt, TimeoutFramed};
+#[cfg(feature = "std")]
+pub use traced::{ResultExt, TracedError};
+#[cfg(feature = "std")]
+pub use tree_text::{ParseError, ParsedDump, ParsedFrame, ParsedLocation};
 
 /// Include the annotated async function in backtraces and taskdumps.
 ///
@@ -124,6 +274,71 @@ pub use tasks::{tasks, Task};
 ///     }).await;
 /// }
 /// ```
+///
+/// ## Arguments
+/// - `name = "..."`: labels the frame with the given name, instead of the
+///   name of the annotated function.
+/// - `crate = path`: the path at which `async_backtrace` can be found, if it
+///   has been renamed or re-exported.
+/// - `with_type`: qualifies the name of the frame (or the given `name`) with
+///   the name of `Self`, as computed by [`core::any::type_name`] for each
+///   implementation; e.g., `my_crate::Postgres::run`, rather than `run`. Only
+///   valid on methods and associated functions.
+/// - `lazy`: polls the function's future once *before* initializing its
+///   frame, and only initializes the frame if that poll is pending; unless the
+///   frame would be the root of a task. See [`Location::frame_lazy`] for the
+///   implications. Cannot be combined with `detached`.
+/// - `root_only`: only frames the function's future if it is the root of a
+///   task; when awaited within another frame, it is polled as if it were not
+///   annotated. See [`Location::frame_root_only`]. Cannot be combined with
+///   `lazy`.
+/// - `barrier`: marks the frame as a barrier, at which backtraces captured
+///   beneath it stop. See [`Location::frame_barrier`].
+/// - `detached`: makes the frame the root of a task, even if the function's
+///   future is first polled within another frame. See
+///   [`Location::frame_detached`].
+/// - `detail`: marks the frame as an implementation detail (e.g., of a
+///   retry or instrumentation helper). Taskdumps collapse chains of detail
+///   frames that each have a single child into a note on the frame beneath
+///   them, unless rendered with [`Verbosity::Full`]. See
+///   [`Location::frame_detail`].
+/// - `must_poll`: reports a [`Warning::NeverPolled`] if the function's future
+///   is dropped without ever being polled (e.g., because it was never
+///   `.await`ed). See [`Location::frame_must_poll`]. Since the future of an
+///   `async fn` is only framed once it is polled, this requires `boxed` or
+///   `boxed_local` on `async fn`s.
+/// - `record_err`: for functions returning `Result<_, E: Display>`, records
+///   the (truncated) rendering of any error they return on the frame of their
+///   caller. Taskdumps show the last recorded error, and how long ago it
+///   occurred, alongside subsequent invocations of the function by the same
+///   caller; e.g.: `fetch_block at src/lib.rs:8:1 [last error 8s ago:
+///   connection reset]`.
+/// - `boxed`: the function returns its framed future as a
+///   `Pin<Box<dyn Future<Output = T> + Send + 'async_backtrace>>`, where
+///   `'async_backtrace` is outlived by the lifetimes and type parameters of
+///   the function (including the elided lifetimes of its arguments, which are
+///   given names). The frame is boxed along with the future. Since the
+///   function itself is no longer `async`, its frame is named after the
+///   function (e.g., `my_crate::fetch<'_>`), rather than its body.
+/// - `boxed_local`: like `boxed`, but without the `Send` bound.
+///
+/// ## Parentage
+/// A framed future becomes a child of the frame that is active when it is
+/// *first polled*, not of the frame in which it was constructed; see
+/// [`Location::frame`].
+///
+/// ## Inlining
+/// `#[inline]` attributes of annotated async functions are removed. On an
+/// async function, they only govern the (trivial) function that constructs
+/// its future, and not the future's `poll`; and, once framed, that `poll`
+/// cannot be inlined through the frame's regardless. Prefer framing the
+/// callers of tiny async wrappers, rather than the wrappers themselves.
+///
+/// ```
+/// #[async_backtrace::framed(name = "handshake", crate = ::async_backtrace)]
+/// async fn foo() {}
+/// ```
+#[cfg(feature = "std")]
 pub use async_backtrace_attributes::framed;
 
 /// Include the annotated async expression in backtraces and taskdumps.
@@ -150,10 +365,143 @@ pub use async_backtrace_attributes::framed;
 /// })).await;
 /// # }
 /// ```
+///
+/// As for [`Location::frame`], the frame's parent is whichever frame is
+/// active when the expression is first polled (here, none: it is the root of
+/// the spawned task).
+#[cfg(feature = "std")]
 #[macro_export]
 macro_rules! frame {
     ($async_expr:expr) => {
-        $crate::location!().frame($async_expr)
+        $crate::location!().frame_with_origin($async_expr, $crate::Origin::Macro)
+    };
+}
+
+/// Include the annotated async expression in backtraces and taskdumps, and
+/// report the backtraces of panics within it.
+///
+/// When a panic unwinds out of a spawned task, the `JoinError` that
+/// eventually observes it has lost all async context. Wrapping the task with
+/// `catching_frame!` captures the [`backtrace`] at the site of such a panic,
+/// and sends it, as a [`PanicReport`], to the sink configured with
+/// [`set_panic_sink`], before resuming the unwind with the original payload.
+///
+/// ## Example
+/// ```
+/// # #[tokio::main] async fn main() {
+/// #[async_backtrace::framed]
+/// async fn fallible() {
+///     panic!("boom");
+/// }
+///
+/// // prints, e.g.:
+/// // panicked: boom
+/// //   at rust_out::fallible::{{closure}} at src/lib.rs:4:1
+/// //   at rust_out::main::{{closure}} at src/lib.rs:11:24
+/// let result = tokio::spawn(async_backtrace::catching_frame!(fallible())).await;
+/// assert!(result.unwrap_err().is_panic());
+/// # }
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! catching_frame {
+    ($async_expr:expr) => {
+        $crate::ඞ::catching(
+            $crate::location!().frame_with_origin($async_expr, $crate::Origin::Macro),
+        )
+    };
+}
+
+/// Asserts that a function is [framed](crate::framed).
+///
+/// The function is invoked, and the future it produces is polled once with
+/// [`probe_child_frames`]. The assertion passes if a frame was initialized
+/// whose name includes the last segment of the function's path. Supply the
+/// function's arguments with the closure form; a bare path is invoked without
+/// arguments.
+///
+/// Use this in tests, to guard against the accidental removal of `#[framed]`
+/// from important entry points.
+///
+/// ## Example
+/// ```
+/// mod handlers {
+///     #[async_backtrace::framed]
+///     pub async fn ping() {}
+///
+///     #[async_backtrace::framed]
+///     pub async fn query(id: u64, verbose: bool) {}
+/// }
+///
+/// async_backtrace::assert_framed!(handlers::ping);
+/// async_backtrace::assert_framed!(|| handlers::query(Default::default(), true));
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! assert_framed {
+    (|| $($function:ident)::+ ($($arg:expr),* $(,)?)) => {
+        $crate::ඞ::assert_framed(
+            &[$(stringify!($function)),+],
+            &$crate::probe_child_frames($($function)::+($($arg),*)),
+        )
+    };
+    ($($function:ident)::+) => {
+        $crate::assert_framed!(|| $($function)::+())
+    };
+}
+
+/// Asserts that, in the given taskdump, a frame named `ancestor` has a
+/// descendant named `descendant`.
+///
+/// The taskdump may be the text produced by [`taskdump_tree`], or anything
+/// else implementing [`testing::Taskdump`]. Frames are matched by the names of
+/// their functions, as by [`testing::ParsedFrame::matches`]; e.g., `handle`
+/// and `my_crate::handle` both match `my_crate::handle::{{closure}}`.
+///
+/// Requires the `test-utils` feature.
+///
+/// ## Example
+/// ```
+/// #[async_backtrace::framed]
+/// async fn serve() {
+///     handle().await
+/// }
+///
+/// #[async_backtrace::framed]
+/// async fn handle() {
+///     let dump = async_backtrace::taskdump_tree(true);
+///     async_backtrace::assert_ancestor!(dump, "serve", "handle");
+/// }
+/// # futures::executor::block_on(serve());
+/// ```
+#[cfg(feature = "test-utils")]
+#[macro_export]
+macro_rules! assert_ancestor {
+    ($dump:expr, $ancestor:expr, $descendant:expr $(,)?) => {
+        $crate::testing::assert_ancestor(&$dump, $ancestor, $descendant)
+    };
+}
+
+/// Asserts that two taskdumps are equal, once [normalized](testing::normalize)
+/// (i.e., but for the lines and columns of their locations).
+///
+/// Requires the `test-utils` feature.
+///
+/// ## Example
+/// ```
+/// async_backtrace::assert_dump_eq!(
+///     "╼ app::serve::{{closure}} at src/main.rs:8:1",
+///     "╼ app::serve::{{closure}} at src/main.rs:LINE:COL",
+/// );
+/// ```
+#[cfg(feature = "test-utils")]
+#[macro_export]
+macro_rules! assert_dump_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::testing::assert_dump_eq(
+            ::core::convert::AsRef::<str>::as_ref(&$left),
+            ::core::convert::AsRef::<str>::as_ref(&$right),
+        )
     };
 }
 
@@ -162,20 +510,302 @@ macro_rules! frame {
 /// If `wait_for_running_tasks` is `false`, this routine will display only the
 /// top-level location of currently-running tasks and a note that they are
 /// "POLLING". Otherwise, this routine will wait for currently-running tasks to
-/// become idle.
+/// become idle. If [`RegistryConfig::cache_last_tree`] is set, the (possibly
+/// stale) last-known tree of each currently-running task is also displayed.
+///
+/// Ages (e.g., of [recorded errors](crate::framed#arguments)) are computed
+/// relative to a single instant taken when the dump begins, so that they are
+/// comparable across tasks, however long the dump takes.
+///
+/// The dump is otherwise rendered with the options set by
+/// [`set_default_dump_options`] (if any). Unless they set
+/// [`TaskdumpOptions::trailing_newline`], the trees of tasks are separated by
+/// newlines, but the dump does not end with one.
+///
+/// See [`TaskdumpOptions`] to report the progress of (and cancel) long dumps,
+/// and [`dump`] for a taskdump that picks whether to wait automatically.
 ///
 /// # Safety
 /// If `wait_for_running_tasks` is `true`, this routine may deadlock if any
 /// non-async lock is held which may also be held by a Framed task.
+#[cfg(feature = "std")]
 pub fn taskdump_tree(wait_for_running_tasks: bool) -> String {
-    tasks()
-        .map(|task| task.pretty_tree(wait_for_running_tasks))
-        .collect::<Vec<String>>()
-        .join("\n")
+    TaskdumpOptions::defaults()
+        .wait_for_running_tasks(wait_for_running_tasks)
+        .dump()
+}
+
+/// Produces a human-readable tree of task states, as [`taskdump_tree`] does,
+/// but rendering frames at most `depth_limit` (if any) beneath the root of
+/// each task, and noting the number of frames omitted beneath those at the
+/// limit; see [`TaskdumpOptions::max_depth`].
+#[cfg(feature = "std")]
+pub fn taskdump_tree_with(depth_limit: Option<usize>, wait_for_running_tasks: bool) -> String {
+    let options = TaskdumpOptions::defaults().wait_for_running_tasks(wait_for_running_tasks);
+    match depth_limit {
+        Some(depth) => options.max_depth(depth).dump(),
+        None => options.dump(),
+    }
+}
+
+/// Produces a human-readable tree of task states, as [`taskdump_tree`] does,
+/// along with the counts of the tasks and frames it rendered; see
+/// [`TaskdumpOptions::dump_with_stats`], and
+/// [`TaskdumpOptions::summary_header`], which renders the counts atop the
+/// dump.
+#[cfg(feature = "std")]
+pub fn taskdump_tree_with_stats(wait_for_running_tasks: bool) -> (String, DumpStats) {
+    TaskdumpOptions::defaults()
+        .wait_for_running_tasks(wait_for_running_tasks)
+        .dump_with_stats()
+}
+
+/// Produces a human-readable tree of the states of the tasks whose roots'
+/// locations satisfy `filter`, as [`taskdump_tree`] does for all tasks.
+///
+/// Tasks that do not satisfy `filter` are skipped before any is locked, and
+/// are never waited for; see [`TaskdumpOptions::filter`], and
+/// [`TaskdumpOptions::roots_containing`], which filters by a substring of the
+/// roots' names or files.
+///
+/// ## Example
+/// ```
+/// let dump = async_backtrace::taskdump_tree_filtered(true, |root| {
+///     root.name().is_some_and(|name| name.starts_with("my_crate::server"))
+/// });
+/// ```
+#[cfg(feature = "std")]
+pub fn taskdump_tree_filtered<F>(wait_for_running_tasks: bool, filter: F) -> String
+where
+    F: Fn(&Location) -> bool,
+{
+    TaskdumpOptions::defaults()
+        .wait_for_running_tasks(wait_for_running_tasks)
+        .filter(filter)
+        .dump()
+}
+
+/// Writes a human-readable tree of task states to `w`, as produced by
+/// [`taskdump_tree`], but without first building the whole dump in memory.
+///
+/// `wait_for_running_tasks` is as for [`taskdump_tree`], and carries the same
+/// risk of deadlock. See [`TaskdumpOptions::dump_to`].
+///
+/// ## Example
+/// ```
+/// let mut dump = String::new();
+/// async_backtrace::taskdump_write(&mut dump, false).unwrap();
+/// ```
+#[cfg(feature = "std")]
+pub fn taskdump_write<W: std::fmt::Write>(
+    w: &mut W,
+    wait_for_running_tasks: bool,
+) -> std::fmt::Result {
+    TaskdumpOptions::defaults()
+        .wait_for_running_tasks(wait_for_running_tasks)
+        .dump_to(w)
+}
+
+/// Writes a human-readable tree of task states to `w`, as does
+/// [`taskdump_write`]; e.g., to a file, or an encoder.
+///
+/// ## Example
+/// ```no_run
+/// use std::{fs::File, io::BufWriter};
+///
+/// let mut file = BufWriter::new(File::create("taskdump.txt")?);
+/// async_backtrace::taskdump_write_io(&mut file, false)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub fn taskdump_write_io<W: std::io::Write>(
+    w: &mut W,
+    wait_for_running_tasks: bool,
+) -> std::io::Result<()> {
+    TaskdumpOptions::defaults()
+        .wait_for_running_tasks(wait_for_running_tasks)
+        .dump_to_io(w)
+}
+
+/// How long, in total, [`dump`] waits for running tasks when it is called
+/// from an async context.
+#[cfg(feature = "std")]
+const DUMP_WAIT_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Produces a human-readable tree of task states, choosing how long to wait
+/// for running tasks by where it is called from.
+///
+/// - Within a framed future, or (with the `tokio` feature) within a tokio
+///   task (including blocking work, e.g. of `spawn_blocking`), the caller may well hold locks that running tasks are
+///   waiting on, and may be blocking a runtime thread that other tasks need.
+///   So, the dump waits at most 100ms, in total, for running tasks to become
+///   idle; those that are still running are shown as by
+///   [`taskdump_tree(false)`](taskdump_tree). The tree of the caller's own
+///   task is always shown in full, without waiting.
+/// - Elsewhere (e.g., on a dedicated diagnostics thread), the dump waits for running tasks to become idle, as does
+///   [`taskdump_tree(true)`](taskdump_tree).
+///
+/// The dump is otherwise rendered with the options set by
+/// [`set_default_dump_options`] (if any).
+///
+/// Since the first case only bounds the wait, this never deadlocks within an
+/// async context; but it may still deadlock on a plain thread that holds a
+/// non-async lock which may also be held by a Framed task. Use
+/// [`taskdump_tree`] or [`TaskdumpOptions`] to choose explicitly.
+///
+/// ## Example
+/// ```
+/// #[async_backtrace::framed]
+/// async fn handle() {
+///     // safe to call, despite being within a task
+///     println!("{}", async_backtrace::dump());
+/// }
+/// # futures::executor::block_on(handle());
+/// ```
+#[cfg(feature = "std")]
+pub fn dump() -> String {
+    let options = TaskdumpOptions::defaults().wait_for_running_tasks(true);
+    if in_async_context() {
+        options.wait_timeout(DUMP_WAIT_TIMEOUT).dump()
+    } else {
+        options.dump()
+    }
+}
+
+/// Produces `true` if the caller is (probably) running within an async
+/// context; i.e., within a framed future, or (with the `tokio` feature) within
+/// a tokio task.
+///
+/// This is a heuristic: tokio runs blocking work (e.g., of `spawn_blocking`)
+/// as tasks, too, and does not distinguish the threads that run them from its
+/// workers; so, a dump on such a thread waits only briefly, although it need
+/// not. Within `block_on`, but outside of any task, a dump waits as it would
+/// on any other thread.
+#[cfg(feature = "std")]
+fn in_async_context() -> bool {
+    let framed = Frame::with_active(|frame| frame.is_some());
+    #[cfg(feature = "tokio")]
+    let framed = framed || tokio::task::try_id().is_some();
+    framed
+}
+
+/// Produces a taskdump with one line per task, as rendered by
+/// [`Task::compact_line`].
+///
+/// `wait_for_running_tasks` is as for [`taskdump_tree`], and carries the same
+/// risk of deadlock. As for [`taskdump_tree`], the dump is otherwise rendered
+/// with the options set by [`set_default_dump_options`] (if any).
+#[cfg(feature = "std")]
+pub fn taskdump_compact(wait_for_running_tasks: bool) -> String {
+    TaskdumpOptions::defaults()
+        .wait_for_running_tasks(wait_for_running_tasks)
+        .dump_compact()
+}
+
+/// Produces a taskdump as JSON, as described by
+/// [`TaskdumpOptions::dump_json`].
+///
+/// `wait_for_running_tasks` is as for [`taskdump_tree`], and carries the same
+/// risk of deadlock. As for [`taskdump_tree`], the dump is otherwise rendered
+/// with the options set by [`set_default_dump_options`] (if any).
+#[cfg(feature = "std")]
+pub fn taskdump_json(wait_for_running_tasks: bool) -> String {
+    TaskdumpOptions::defaults()
+        .wait_for_running_tasks(wait_for_running_tasks)
+        .dump_json()
+}
+
+/// Writes a taskdump to `w` as JSON Lines, one task per line, as described by
+/// [`TaskdumpOptions::dump_jsonl_to`]; e.g., to stream the dump of many tasks
+/// into a file, for processing line by line.
+///
+/// Unlike [`taskdump_json`], the dump is rendered with the given `options`,
+/// rather than with those set by [`set_default_dump_options`].
+///
+/// ## Example
+/// ```no_run
+/// use async_backtrace::TaskdumpOptions;
+/// use std::{fs::File, io::BufWriter};
+///
+/// let mut file = BufWriter::new(File::create("taskdump.jsonl")?);
+/// let options = TaskdumpOptions::new().wait_for_running_tasks(true);
+/// async_backtrace::taskdump_jsonl(&mut file, options)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub fn taskdump_jsonl<W: std::io::Write>(
+    w: &mut W,
+    options: TaskdumpOptions<'_>,
+) -> std::io::Result<()> {
+    options.dump_jsonl_to(w)
+}
+
+/// Produces a taskdump as Markdown, as described by
+/// [`TaskdumpOptions::dump_markdown`]; e.g., to paste into an issue.
+///
+/// `wait_for_running_tasks` is as for [`taskdump_tree`], and carries the same
+/// risk of deadlock. As for [`taskdump_tree`], the dump is otherwise rendered
+/// with the options set by [`set_default_dump_options`] (if any).
+#[cfg(feature = "std")]
+pub fn taskdump_markdown(wait_for_running_tasks: bool) -> String {
+    TaskdumpOptions::defaults()
+        .wait_for_running_tasks(wait_for_running_tasks)
+        .dump_markdown()
+}
+
+/// Produces the [numbered stacks](Task::pretty_stacks) of every task, each
+/// headed by its [id](Task::id), and separated by blank lines; e.g.:
+/// ```text
+/// Task 1:
+/// #0 app::read::{{closure}} at src/main.rs:30:1
+/// #1 app::serve::{{closure}} at src/main.rs:8:1
+///
+/// Task 2:
+/// #0 app::tick::{{closure}} at src/main.rs:40:1
+/// ```
+///
+/// `wait_for_running_tasks` is as for [`taskdump_tree`], and carries the same
+/// risk of deadlock. The dump does not end with a newline, and is empty if
+/// there are no tasks.
+#[cfg(feature = "std")]
+pub fn taskdump_stacks(wait_for_running_tasks: bool) -> String {
+    let mut dump = String::new();
+    // the tasks are listed, and then rendered one at a time, so that the
+    // registry is not held while waiting for a task to become idle
+    for (id, _) in tasks::snapshot_roots(|_| true) {
+        let stacks = tasks::retry_by_id(
+            id,
+            wait_for_running_tasks,
+            |task| task.pretty_stacks_if_idle(),
+            |task| task.pretty_stacks(false),
+        );
+        // (tasks that have exited since they were listed are omitted)
+        if let Some(stacks) = stacks {
+            if !dump.is_empty() {
+                dump.push_str("\n\n");
+            }
+            dump.push_str(&format!("Task {}:\n{}", id, stacks));
+        }
+    }
+    dump
 }
 
 /// Produces a backtrace starting at the currently-active frame (if any).
 ///
+/// The backtrace stops at the nearest [barrier](Location::frame_barrier)
+/// frame, if any; see [`backtrace_through_barriers`].
+///
+/// Outside of the poll of a framed future, this produces the backtrace of the
+/// context [attached](ContextHandle::attach) to this thread (if any). The
+/// destructors of values held by a framed future (e.g., guards held across
+/// `await`s) observe its frame as active when the future is dropped, even if
+/// it is dropped outside of a poll (e.g., when its task is cancelled); but
+/// not while a panic unwinds.
+///
+/// The backtrace dereferences to a slice of locations, and is rendered with a
+/// numbered line per location (e.g., `format!("{}", backtrace)`); see
+/// [`AsyncBacktrace`].
+///
 /// ## Example
 /// ```
 /// use async_backtrace::{framed, backtrace, Location};
@@ -205,10 +835,304 @@ pub fn taskdump_tree(wait_for_running_tasks: bool) -> String {
 ///     ]);
 /// }
 /// ```
-pub fn backtrace() -> Option<Box<[Location]>> {
+#[cfg(feature = "std")]
+pub fn backtrace() -> Option<AsyncBacktrace> {
     Frame::with_active(|maybe_frame| maybe_frame.map(Frame::backtrace_locations))
+        .or_else(attach::backtrace)
+        .map(AsyncBacktrace::new)
+}
+
+/// Invokes `f` with an iterator over the backtrace starting at the
+/// currently-active frame (if any), innermost location first; e.g., to format
+/// or count the frames of a backtrace on a hot error path.
+///
+/// The iterator produces the same locations as [`backtrace`] (including
+/// those of an [attached](ContextHandle::attach) context), but nothing is
+/// allocated. `f` is invoked with `None` if there is no active frame.
+///
+/// ## Example
+/// ```
+/// #[tokio::main]
+/// async fn main() {
+///     foo().await;
+/// }
+///
+/// #[async_backtrace::framed]
+/// async fn foo() {
+///     bar().await;
+/// }
+///
+/// #[async_backtrace::framed]
+/// async fn bar() {
+///     let depth = async_backtrace::backtrace_with(|frames| frames.map_or(0, |frames| frames.count()));
+///     assert_eq!(depth, 2);
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn backtrace_with<F, R>(f: F) -> R
+where
+    F: FnOnce(Option<&mut dyn Iterator<Item = Location>>) -> R,
+{
+    Frame::with_active(|maybe_frame| match maybe_frame {
+        Some(frame) => f(Some(&mut frame.backtrace().map(Frame::location))),
+        None => attach::backtrace_with(f),
+    })
+}
+
+/// Produces the number of locations in the [`backtrace`] of the
+/// currently-active frame (if any), without allocating; e.g., to sample the
+/// depth of async stacks.
+///
+/// This counts the frames of the backtrace (up to the nearest
+/// [barrier](Location::frame_barrier)) as [`backtrace`] would collect them,
+/// and so takes time proportional to their number; but neither allocates nor
+/// copies their locations. Like [`backtrace`], it falls back to the context
+/// [attached](ContextHandle::attach) to this thread (if any), and produces
+/// `None` if there is neither.
+///
+/// ## Example
+/// ```
+/// #[async_backtrace::framed]
+/// async fn outer() {
+///     inner().await
+/// }
+///
+/// #[async_backtrace::framed]
+/// async fn inner() {
+///     assert_eq!(async_backtrace::backtrace_depth(), Some(2));
+/// }
+/// # futures::executor::block_on(outer());
+/// ```
+#[cfg(feature = "std")]
+pub fn backtrace_depth() -> Option<usize> {
+    Frame::with_active(|maybe_frame| maybe_frame.map(Frame::backtrace_depth))
+        .or_else(attach::backtrace_depth)
+}
+
+/// Produces a backtrace starting at the currently-active frame (if any),
+/// which, unlike [`backtrace`], continues past
+/// [barrier](Location::frame_barrier) frames to the root of the task. Like
+/// [`backtrace`], it falls back to the context
+/// [attached](ContextHandle::attach) to this thread (if any).
+#[cfg(feature = "std")]
+pub fn backtrace_through_barriers() -> Option<Box<[Location]>> {
+    Frame::with_active(|maybe_frame| {
+        maybe_frame.map(|frame| frame.ancestors().map(Frame::location).collect())
+    })
+    .or_else(attach::backtrace_through_barriers)
+}
+
+/// Captures a native backtrace of the current thread along with the
+/// [`backtrace`] of the currently-active frame (if any), to be rendered as a
+/// single report; see [`FullBacktrace`]. Requires the `native-backtrace`
+/// feature.
+///
+/// The native backtrace is captured whatever the `RUST_BACKTRACE` and
+/// `RUST_LIB_BACKTRACE` environment variables, as by
+/// [`Backtrace::force_capture`](std::backtrace::Backtrace::force_capture);
+/// so, like it, this is slow, and best reserved for error paths.
+///
+/// ## Example
+/// ```
+/// #[async_backtrace::framed]
+/// async fn fails() {
+///     let report = async_backtrace::backtrace_full();
+///     assert_eq!(report.logical().unwrap().len(), 1);
+///     eprintln!("{}", report);
+/// }
+/// # futures::executor::block_on(fails());
+/// ```
+#[cfg(feature = "native-backtrace")]
+pub fn backtrace_full() -> FullBacktrace {
+    let native = std::backtrace::Backtrace::force_capture();
+    FullBacktrace::new(native, backtrace())
+}
+
+/// Produces the location of the root frame of the task of the currently-active
+/// frame (if any); e.g., to tag log lines with the top-level task that emits
+/// them, without capturing a whole [`backtrace`].
+///
+/// The root is found by walking the ancestors of the active frame, past any
+/// [barrier](Location::frame_barrier) frames, in time proportional to the
+/// depth of the active frame.
+///
+/// Like [`backtrace`], this reflects only the poll in progress on this thread:
+/// outside of the poll of a framed future, it produces the root of the context
+/// [attached](ContextHandle::attach) to this thread (if any), and a future
+/// polled by different tasks (e.g., one handed from task to task) reports
+/// the root of whichever polls it.
+///
+/// ## Example
+/// ```
+/// #[async_backtrace::framed]
+/// async fn serve() {
+///     handle().await
+/// }
+///
+/// #[async_backtrace::framed]
+/// async fn handle() {
+///     let root = async_backtrace::root_location().unwrap();
+///     assert_eq!(root.name(), Some("rust_out::serve::{{closure}}"));
+/// }
+/// # fn main() {
+/// # futures::executor::block_on(serve());
+/// # }
+/// ```
+#[cfg(feature = "std")]
+pub fn root_location() -> Option<Location> {
+    Frame::with_active(|maybe_frame| maybe_frame.map(|frame| frame.root().location()))
+        .or_else(attach::root_location)
 }
 
+/// Produces the [id](Task::id) of the task of the currently-active frame (if
+/// any), as [`root_location`] produces the location of its root.
+#[cfg(feature = "std")]
+pub fn root_task_id() -> Option<u64> {
+    Frame::with_active(|maybe_frame| maybe_frame.map(|frame| frame.root().task_id()))
+        .unwrap_or_else(attach::root_task_id)
+}
+
+/// Produces the task of the currently-active frame (if any): the
+/// [id](TaskInfo::id) and [location](TaskInfo::location) of its root, as
+/// [`root_task_id`] and [`root_location`] produce them, without walking the
+/// rest of the backtrace.
+///
+/// Outside of the poll of a framed future (e.g., in a closure passed to
+/// `tokio::task::spawn_blocking`), this produces `None`, unless a context is
+/// [attached](ContextHandle::attach) to the thread.
+///
+/// ## Example
+/// ```
+/// #[async_backtrace::framed]
+/// async fn serve() {
+///     handle().await
+/// }
+///
+/// #[async_backtrace::framed]
+/// async fn handle() {
+///     let task = async_backtrace::current_task().unwrap();
+///     // prints, e.g.: task #3 (rust_out::serve::{{closure}} at src/lib.rs:3:1)
+///     println!("task #{} ({})", task.id(), task.location());
+/// }
+/// # fn main() {
+/// assert!(async_backtrace::current_task().is_none());
+/// futures::executor::block_on(serve());
+/// # }
+/// ```
+#[cfg(feature = "std")]
+pub fn current_task() -> Option<TaskInfo> {
+    Frame::with_active(|maybe_frame| {
+        maybe_frame.map(|frame| {
+            let root = frame.root();
+            root.task_id().map(|id| TaskInfo {
+                id,
+                location: root.location(),
+            })
+        })
+    })
+    .unwrap_or_else(attach::task)
+}
+
+/// Pretty-prints the whole tree of the task of the currently-active frame (if
+/// any), as [`Task::pretty_tree`] does; e.g., so that an arm of a `select!`
+/// may report the state of its siblings.
+///
+/// Unlike [`backtrace`], this includes every branch of the task, not just the
+/// ancestors of the active frame. It never blocks, as the task's root is
+/// already locked by the poll in progress. Outside of the poll of a framed
+/// future, this produces `None`; attached [contexts](ContextHandle) carry only
+/// a backtrace, and so are not rendered.
+///
+/// ## Example
+/// ```
+/// #[async_backtrace::framed]
+/// async fn race() {
+///     tokio::select! {
+///         biased;
+///         _ = pending() => {}
+///         _ = ready() => {}
+///     }
+/// }
+///
+/// #[async_backtrace::framed]
+/// async fn pending() {
+///     std::future::pending::<()>().await
+/// }
+///
+/// #[async_backtrace::framed]
+/// async fn ready() {
+///     // prints, e.g.:
+///     // ╼ rust_out::race::{{closure}} at src/lib.rs:3:1
+///     //   ├╼ rust_out::ready::{{closure}} at src/lib.rs:17:1
+///     //   └╼ rust_out::pending::{{closure}} at src/lib.rs:12:1
+///     println!("{}", async_backtrace::current_task_tree().unwrap());
+/// }
+/// # fn main() {
+/// # futures::executor::block_on(race());
+/// # }
+/// ```
+#[cfg(feature = "std")]
+pub fn current_task_tree() -> Option<String> {
+    current_task_snapshot().map(|tree| tree.to_string())
+}
+
+/// Captures the whole tree of the task of the currently-active frame (if
+/// any), as [`current_task_tree`] renders it.
+#[cfg(feature = "std")]
+pub fn current_task_snapshot() -> Option<TaskTree> {
+    tasks::current_snapshot(std::time::Instant::now())
+}
+
+/// Produces a backtrace starting at the currently-active frame (if any),
+/// including the [annotations](annotate) of each frame.
+///
+/// As for [`backtrace`], the backtrace stops at the nearest
+/// [barrier](Location::frame_barrier) frame, if any; annotations inherited
+/// from beyond it are nonetheless included.
+/// Like [`backtrace`], it falls back to the context
+/// [attached](ContextHandle::attach) to this thread (if any), with the
+/// annotations its frames had when it was captured.
+///
+/// ## Example
+/// ```
+/// #[tokio::main]
+/// async fn main() {
+///     foo().await;
+/// }
+///
+/// #[async_backtrace::framed]
+/// async fn foo() {
+///     async_backtrace::annotate("request_id", 7);
+///     bar().await;
+/// }
+///
+/// #[async_backtrace::framed]
+/// async fn bar() {
+///     let backtrace = async_backtrace::backtrace_annotated().unwrap();
+///     assert_eq!(
+///         backtrace[1].get("request_id").and_then(|value| value.as_str()),
+///         Some("7")
+///     );
+///     // prints, e.g.: rust_out::foo::{{closure}} at src/lib.rs:8:1 [request_id=7]
+///     println!("{}", backtrace[1]);
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn backtrace_annotated() -> Option<Vec<AnnotatedFrame>> {
+    Frame::with_active(|maybe_frame| {
+        maybe_frame.map(|frame| {
+            frame
+                .backtrace()
+                // SAFETY: The active frame's root is locked for the duration of
+                // its `in_scope`, which encloses this call.
+                .map(|frame| unsafe { AnnotatedFrame::capture(frame) })
+                .collect()
+        })
+    })
+    .or_else(attach::backtrace_annotated)
+}
+
+#[cfg(feature = "std")]
 pub(crate) mod sync {
     #[cfg(loom)]
     pub(crate) use loom::sync::Mutex;
@@ -217,8 +1141,21 @@ pub(crate) mod sync {
     pub(crate) use std::sync::Mutex;
 
     pub(crate) use std::sync::TryLockError;
+
+    /// Briefly pauses a thread that is waiting for a lock to be released.
+    #[cfg(loom)]
+    pub(crate) fn backoff() {
+        loom::thread::yield_now()
+    }
+
+    /// Briefly pauses a thread that is waiting for a lock to be released.
+    #[cfg(not(loom))]
+    pub(crate) fn backoff() {
+        std::thread::sleep(std::time::Duration::from_micros(100))
+    }
 }
 
+#[cfg(feature = "std")]
 pub(crate) mod cell {
     #[cfg(loom)]
     pub(crate) use loom::cell::{Cell, UnsafeCell};
@@ -247,6 +1184,7 @@ pub(crate) mod cell {
     }
 }
 
+#[cfg(feature = "std")]
 pub(crate) fn defer<F: FnOnce() -> R, R>(f: F) -> impl Drop {
     struct Defer<F: FnOnce() -> R, R>(Option<F>);
 
@@ -259,9 +1197,29 @@ pub(crate) fn defer<F: FnOnce() -> R, R>(f: F) -> impl Drop {
     Defer(Some(f))
 }
 
+#[cfg(feature = "std")]
 #[doc(hidden)]
 /** NOT STABLE! DO NOT USE! */
 pub mod ඞ {
     //  ^ kudos to Daniel Henry-Mantilla
-    pub use crate::frame::Frame;
+    pub use crate::catch::catching;
+    pub use crate::framed::Framed;
+    pub use crate::location::qualified_name;
+    pub use crate::metadata::record_err;
+    pub use crate::probe::assert_framed;
+
+    /// Superseded by [`raw::Frame`](crate::raw::Frame), whose API is covered
+    /// by semver.
+    #[deprecated(since = "0.2.8", note = "use `async_backtrace::raw::Frame`")]
+    pub type Frame = crate::frame::Frame;
+
+    /// The number of tasks the registry can hold without reallocating.
+    pub fn registry_capacity() -> usize {
+        crate::tasks::capacity()
+    }
+
+    /// The number of traversals of the tasks made by taskdumps so far.
+    pub fn taskdump_traversals() -> usize {
+        crate::taskdump::traversals()
+    }
 }
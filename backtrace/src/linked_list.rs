@@ -153,6 +153,18 @@ impl<L, T> LinkedList<L, T> {
         }
     }
 
+    /// Produces the first element of the list (if any).
+    #[cfg(any(debug_assertions, feature = "debug-validate"))]
+    pub(crate) fn head(&self) -> Option<NonNull<T>> {
+        self.head
+    }
+
+    /// Produces the last element of the list (if any).
+    #[cfg(any(debug_assertions, feature = "debug-validate"))]
+    pub(crate) fn tail(&self) -> Option<NonNull<T>> {
+        self.tail
+    }
+
     pub(crate) fn iter(&self) -> Iter<'_, L>
     where
         L: Link<Target = T>,
@@ -1,6 +1,20 @@
-use std::fmt::Display;
+use alloc::string::{String, ToString};
+use core::{
+    fmt::{Display, Write as _},
+    sync::atomic::{AtomicU8, Ordering},
+};
+#[cfg(feature = "std")]
+use std::{future::Future, hash::BuildHasherDefault, pin::Pin};
 
-use futures::Future;
+#[cfg(feature = "std")]
+use dashmap::DashMap;
+#[cfg(feature = "std")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "std")]
+use rustc_hash::FxHasher;
+
+#[cfg(feature = "std")]
+use crate::FrameConfig;
 
 /// Produces a [`Location`] when invoked in a function body.
 ///
@@ -23,20 +37,49 @@ use futures::Future;
 #[macro_export]
 macro_rules! location {
     () => {{
+        // paths are absolute, and lints are allowed, so that this expands
+        // cleanly in modules with restricted preludes or strict lints
+        #[allow(unused)]
         macro_rules! fn_name {
             () => {{
-                fn type_name_of_val<T: ?Sized>(_: &T) -> &'static str {
-                    core::any::type_name::<T>()
+                #[allow(unused)]
+                fn type_name_of_val<T: ?::core::marker::Sized>(_: &T) -> &'static str {
+                    ::core::any::type_name::<T>()
                 }
                 type_name_of_val(&|| {})
                     .strip_suffix("::{{closure}}")
                     .unwrap()
             }};
         }
-        $crate::Location::from_components(fn_name!(), &(file!(), line!(), column!()))
+        $crate::Location::from_components(
+            fn_name!(),
+            &(::core::file!(), ::core::line!(), ::core::column!()),
+        )
     }};
 }
 
+/// Produces a [`Location`] with the given name, without inspecting the name of
+/// the surrounding function.
+///
+/// Unlike [`location!()`], this can be used in `const`s and `static`s.
+///
+/// ```
+/// use async_backtrace::{location_named, Location};
+///
+/// static ROOT: Location = location_named!("app::root");
+///
+/// assert_eq!(ROOT.to_string(), "app::root at backtrace/src/location.rs:7:25");
+/// ```
+#[macro_export]
+macro_rules! location_named {
+    ($name:literal) => {
+        $crate::Location::from_components(
+            $name,
+            &(::core::file!(), ::core::line!(), ::core::column!()),
+        )
+    };
+}
+
 /// A source code location in a function body.
 ///
 /// To construct a `Location`, use [`location!()`].
@@ -63,9 +106,22 @@ impl Location {
             rest,
         }
     }
+}
 
+/// The framing of futures, which requires the `std` feature.
+#[cfg(feature = "std")]
+impl Location {
     /// Include the given future in taskdumps with this location.
     ///
+    /// The frame's parent is determined when the produced future is first
+    /// polled, not when it is constructed: it becomes a child of whichever
+    /// frame is active on the polling thread at that point, or, if none is,
+    /// the root of a new task. So, a future constructed in one place but
+    /// polled by an unrelated framed future (e.g., an executor that is itself
+    /// framed, and polls the futures sent to it) is adopted by that future's
+    /// task. Use [`frame_detached`](Location::frame_detached) for futures
+    /// whose logical owner is not the task that polls them.
+    ///
     /// ## Examples
     /// ```
     /// # async fn bar() {}
@@ -81,7 +137,357 @@ impl Location {
     where
         F: Future,
     {
-        crate::Framed::new(f, self)
+        self.frame_with(f, FrameConfig::new())
+    }
+
+    /// Include the given future in taskdumps with this location, configured
+    /// by `config`.
+    ///
+    /// Each of the other `frame_*` methods (e.g.,
+    /// [`frame_barrier`](Location::frame_barrier)) is this, with a single
+    /// option of [`FrameConfig`] set; this combines them.
+    ///
+    /// ## Examples
+    /// ```
+    /// use async_backtrace::FrameConfig;
+    ///
+    /// # async fn work() {}
+    /// // a job that is its own task, and whose backtraces stop at its frame,
+    /// // and that warns if it is never run
+    /// let job = async_backtrace::location!()
+    ///     .frame_with(work(), FrameConfig::new().detached().must_poll());
+    /// # futures::executor::block_on(job);
+    /// ```
+    pub fn frame_with<F>(self, f: F, config: FrameConfig) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        crate::Framed::with_config(f, self, config)
+    }
+
+    /// Include the given future in taskdumps with this location, but only if
+    /// it is not ready upon its first poll.
+    ///
+    /// The first poll of `f` occurs *outside* of this location's frame, which
+    /// is initialized only if `f` returns `Pending`; subsequent polls occur
+    /// within this frame, as usual. This avoids the overhead of initializing
+    /// frames for futures that usually complete immediately.
+    ///
+    /// Since a frame's parent is fixed upon its first poll, any framed futures
+    /// first polled by that first poll are children of *this frame's parent*,
+    /// rather than of this frame, for as long as they live; taskdumps show
+    /// them as its siblings.
+    ///
+    /// A frame that would be the root of a task (i.e., that is first polled
+    /// while no other frame is active, or is
+    /// [detached](Location::frame_detached)) is never lazy: were it, the
+    /// framed futures first polled by its first poll would each become the
+    /// root of a task of its own. So, it is initialized before its first poll,
+    /// as by [`frame`](Location::frame).
+    ///
+    /// ## Examples
+    /// ```
+    /// # async fn lookup() -> u32 { 42 }
+    /// async fn cached() -> u32 {
+    ///     async_backtrace::location!().frame_lazy(async move {
+    ///         lookup().await
+    ///     }).await
+    /// }
+    /// ```
+    pub fn frame_lazy<F>(self, f: F) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        self.frame_with(f, FrameConfig::new().lazy())
+    }
+
+    /// Include the given future in taskdumps with this location, but only if
+    /// it is the root of a task.
+    ///
+    /// Upon its first poll, if another frame is active on the polling thread,
+    /// this frame is never initialized, and `f` is polled directly from then
+    /// on: it is attributed to the frames of its caller, without the overhead
+    /// of a frame of its own. Otherwise, `f` is framed as usual, as the root
+    /// of a task. This suits utilities that are both spawned as tasks and
+    /// awaited within hot loops.
+    ///
+    /// ## Examples
+    /// ```
+    /// # async fn flush() {}
+    /// async fn flush_all() {
+    ///     async_backtrace::location!().frame_root_only(async move {
+    ///         flush().await
+    ///     }).await
+    /// }
+    /// ```
+    pub fn frame_root_only<F>(self, f: F) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        self.frame_with(f, FrameConfig::new().root_only())
+    }
+
+    /// Include the given future in taskdumps with this location, as a detail.
+    ///
+    /// Taskdumps collapse chains of detail frames (each of which has a single
+    /// child) into a note on the first frame beneath them that is not a
+    /// detail; e.g., `(via 3 helper frames)`. This suits frames within helper
+    /// crates, which are incidental to the applications that use them. See
+    /// [`Verbosity`](crate::Verbosity).
+    ///
+    /// ## Examples
+    /// ```
+    /// # async fn send() {}
+    /// async fn retry() {
+    ///     async_backtrace::location!().frame_detail(async move {
+    ///         send().await
+    ///     }).await
+    /// }
+    /// ```
+    pub fn frame_detail<F>(self, f: F) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        self.frame_with(f, FrameConfig::new().detail())
+    }
+
+    /// Include the given future in taskdumps with this location, as a
+    /// barrier to backtraces.
+    ///
+    /// [`backtrace`](crate::backtrace) and
+    /// [`backtrace_annotated`](crate::backtrace_annotated), when invoked
+    /// within `f`, stop at this frame: its ancestors are omitted, as though it
+    /// were the root of its task. This suits the boundaries of frameworks,
+    /// whose internal frames should not leak into the backtraces of the
+    /// applications they call. The ancestors are otherwise unaffected: they
+    /// remain in taskdumps, annotations are still inherited from them, and
+    /// [`backtrace_through_barriers`](crate::backtrace_through_barriers)
+    /// includes them.
+    ///
+    /// ## Examples
+    /// ```
+    /// # async fn handler() {}
+    /// async fn dispatch() {
+    ///     async_backtrace::location!().frame_barrier(async move {
+    ///         handler().await
+    ///     }).await
+    /// }
+    /// ```
+    pub fn frame_barrier<F>(self, f: F) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        self.frame_with(f, FrameConfig::new().barrier())
+    }
+
+    /// Include the given future in taskdumps with this location, as the root
+    /// of a task, regardless of the frame that is active when it is first
+    /// polled.
+    ///
+    /// The produced future appears in taskdumps as a task of its own, rather
+    /// than beneath the frame that polls it, and backtraces captured within
+    /// it stop at its frame. This suits futures that are constructed by one
+    /// component and driven by another, such as the jobs of a framed,
+    /// channel-driven executor.
+    ///
+    /// ## Examples
+    /// ```
+    /// # async fn work() {}
+    /// let job = async_backtrace::location!().frame_detached(work());
+    /// # futures::executor::block_on(job);
+    /// ```
+    pub fn frame_detached<F>(self, f: F) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        self.frame_with(f, FrameConfig::new().detached())
+    }
+
+    /// Include the given future in taskdumps with this location, warning if
+    /// it is dropped without ever being polled.
+    ///
+    /// Futures that are never polled (e.g., that are stored, but never spawned
+    /// nor `.await`ed) never appear in taskdumps. If the produced future is
+    /// dropped before its first poll, a [`Warning::NeverPolled`] naming this
+    /// location is reported to the [warning hook](crate::set_warning_hook),
+    /// unless it is dropped by unwinding.
+    ///
+    /// [`Warning::NeverPolled`]: crate::Warning::NeverPolled
+    ///
+    /// ## Examples
+    /// ```
+    /// # async fn send() {}
+    /// let request = async_backtrace::location!().frame_must_poll(send());
+    /// // oops; prints a warning to standard error
+    /// drop(request);
+    /// ```
+    pub fn frame_must_poll<F>(self, f: F) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        self.frame_with(f, FrameConfig::new().must_poll())
+    }
+
+    /// Include the given future in taskdumps with this location, producing a
+    /// pinned, boxed trait object.
+    ///
+    /// If `f` is not yet boxed, this allocates once: the frame is constructed
+    /// around `f`, and then boxed together with it. Prefer this over boxing a
+    /// [`frame`](Location::frame)d future, and over framing a boxed future.
+    ///
+    /// If `f` is *already* a `Pin<Box<dyn Future>>`, it cannot be framed
+    /// in-place: a frame must be pinned alongside the future it instruments,
+    /// and the existing box has no room for it. In that case, this allocates
+    /// once more, to box the frame together with the existing (boxed) future.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::{future::Future, pin::Pin};
+    ///
+    /// type Job = Pin<Box<dyn Future<Output = ()> + Send>>;
+    ///
+    /// # async fn work() {}
+    /// let mut queue: Vec<Job> = Vec::new();
+    /// // one allocation
+    /// queue.push(async_backtrace::location!().frame_boxed(work()));
+    /// // two allocations; one for the job, and one for its frame
+    /// let job: Job = Box::pin(work());
+    /// queue.push(async_backtrace::location!().frame_boxed(job));
+    /// ```
+    pub fn frame_boxed<'a, F>(self, f: F) -> Pin<Box<dyn Future<Output = F::Output> + Send + 'a>>
+    where
+        F: Future + Send + 'a,
+    {
+        Box::pin(crate::Framed::new(f, self))
+    }
+
+    /// **DO NOT USE!** The signature of this method may change between
+    /// non-breaking releases.
+    #[doc(hidden)]
+    pub fn frame_lazy_with_origin<F>(
+        self,
+        f: F,
+        origin: crate::Origin,
+    ) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        self.frame_with(f, FrameConfig::new().lazy().origin(origin))
+    }
+
+    /// **DO NOT USE!** The signature of this method may change between
+    /// non-breaking releases.
+    #[doc(hidden)]
+    pub fn frame_root_only_with_origin<F>(
+        self,
+        f: F,
+        origin: crate::Origin,
+    ) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        self.frame_with(f, FrameConfig::new().root_only().origin(origin))
+    }
+
+    /// **DO NOT USE!** The signature of this method may change between
+    /// non-breaking releases.
+    #[doc(hidden)]
+    pub fn frame_with_origin<F>(
+        self,
+        f: F,
+        origin: crate::Origin,
+    ) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        self.frame_with(f, FrameConfig::new().origin(origin))
+    }
+}
+
+impl Location {
+    /// Writes this location to `w`, without allocating.
+    ///
+    /// The rendering is the same as that of [`Display`]: `name at
+    /// file:line:column`, or `file:line:column` if this location has no name,
+    /// unless another [style](set_location_style) is set. Exporters may rely
+    /// on this format.
+    ///
+    /// ## Example
+    /// ```
+    /// let location = async_backtrace::location!();
+    /// let mut buf = String::with_capacity(location.len_hint());
+    /// location.write_to(&mut buf).unwrap();
+    /// assert_eq!(buf, location.to_string());
+    /// assert_eq!(buf.len(), location.len_hint());
+    /// ```
+    pub fn write_to<W: core::fmt::Write>(&self, w: &mut W) -> core::fmt::Result {
+        write_location(w, self.name(), self.file(), self.line(), self.column())
+    }
+
+    /// Produces the exact length, in bytes, of this location's rendering by
+    /// [`write_to`](Location::write_to) (or [`Display`]).
+    pub fn len_hint(&self) -> usize {
+        let style = location_style();
+        let name = self.name().map(sanitized_len);
+        if let (LocationStyle::NameOnly, Some(name)) = (style, name) {
+            return name;
+        }
+        let position = sanitized_len(self.file()) + ":".len() + decimal_len(self.line());
+        let position = match style {
+            LocationStyle::FileLineCol => position + ":".len() + decimal_len(self.column()),
+            LocationStyle::FileLine | LocationStyle::NameOnly => position,
+        };
+        name.map_or(0, |name| name + " at ".len()) + position
+    }
+
+    /// Produces a compact rendering of this location, `name@file:line`
+    /// (or `file:line` if this location has no name), which omits the column.
+    ///
+    /// ## Example
+    /// ```
+    /// let location = async_backtrace::location!();
+    /// assert_eq!(
+    ///     location.as_compact().to_string(),
+    ///     format!("{}@{}:{}", location.name().unwrap(), location.file(), location.line()),
+    /// );
+    /// ```
+    pub fn as_compact(&self) -> CompactLocation {
+        CompactLocation(*self)
+    }
+
+    /// Produces a normalized, low-cardinality key for this location, for use
+    /// as a metric label; e.g., `my_crate::server::handle`.
+    ///
+    /// Unlike the [name](Location::name) of this location, the key omits:
+    /// - closures (`::{{closure}}`), including those of `async` bodies;
+    /// - generic arguments (`<u8>`, `<'_>`);
+    /// - trait qualifications, in favor of the implementing type (e.g.,
+    ///   `<my_crate::Pg as my_crate::Db>::query` becomes
+    ///   `my_crate::Pg::query`), and references to it.
+    ///
+    /// Unlike its [`Display`] rendering, the key omits the file, line and
+    /// column, which shift across deploys. A location without a name is keyed
+    /// by its file.
+    ///
+    /// ## Example
+    /// ```
+    /// mod server {
+    ///     pub async fn handle() -> async_backtrace::Location {
+    ///         async_backtrace::location!()
+    ///     }
+    /// }
+    ///
+    /// fn main() {
+    ///     let location = futures::executor::block_on(server::handle());
+    ///     assert_eq!(location.name(), Some("rust_out::server::handle::{{closure}}"));
+    ///     assert_eq!(location.metric_key(), "rust_out::server::handle");
+    /// }
+    /// ```
+    pub fn metric_key(&self) -> String {
+        match self.name() {
+            Some(name) => normalize_path(name),
+            None => self.file().to_string(),
+        }
     }
 
     /// Produces the function name associated with this location.
@@ -105,15 +511,351 @@ impl Location {
     }
 }
 
+/// How [`Location`]s are rendered, as set by [`set_location_style`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum LocationStyle {
+    /// `name at file:line:column` (the default).
+    #[default]
+    FileLineCol,
+    /// `name at file:line`.
+    FileLine,
+    /// `name`; or, for a location with no name, `file:line`.
+    NameOnly,
+}
+
+impl LocationStyle {
+    /// The styles, by their indices in [`LOCATION_STYLE`].
+    const ALL: [Self; 3] = [Self::FileLineCol, Self::FileLine, Self::NameOnly];
+}
+
+/// The index, plus one, of the style set by [`set_location_style`] in
+/// [`LocationStyle::ALL`], or zero if none is set.
+///
+/// (An atomic, rather than a `OnceCell`, so that the style can be set without
+/// `std`.)
+static LOCATION_STYLE: AtomicU8 = AtomicU8::new(0);
+
+/// An error produced by [`set_location_style`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum LocationStyleError {
+    /// The style has already been set, by a previous call to
+    /// [`set_location_style`].
+    AlreadyInitialized,
+}
+
+impl Display for LocationStyleError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::AlreadyInitialized => f.write_str("the location style is already initialized"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LocationStyleError {}
+
+/// Sets how every [`Location`] in the process is rendered: by its
+/// [`Display`] (and so in backtraces and taskdumps), by
+/// [`Location::write_to`], and in the JSON of
+/// [`TaskdumpOptions::dump_json`](crate::TaskdumpOptions::dump_json), whose
+/// omitted fields are `null`.
+///
+/// The style may be set only once, typically at startup; subsequent calls
+/// fail with [`LocationStyleError::AlreadyInitialized`]. Until it is set,
+/// locations are rendered as [`LocationStyle::FileLineCol`]. The
+/// [compact](Location::as_compact) rendering, which never includes the
+/// column, and the serialization of locations, which is data rather than a
+/// rendering, are unaffected.
+///
+/// ## Example
+/// ```
+/// use async_backtrace::{location_named, set_location_style, LocationStyle};
+///
+/// set_location_style(LocationStyle::FileLine).unwrap();
+/// let location = location_named!("app::serve");
+/// assert_eq!(location.to_string(), format!("app::serve at {}:{}", file!(), line!() - 1));
+/// ```
+pub fn set_location_style(style: LocationStyle) -> Result<(), LocationStyleError> {
+    let index = LocationStyle::ALL.iter().position(|s| *s == style).unwrap() as u8;
+    LOCATION_STYLE
+        .compare_exchange(0, index + 1, Ordering::AcqRel, Ordering::Acquire)
+        .map(drop)
+        .map_err(|_| LocationStyleError::AlreadyInitialized)
+}
+
+/// Produces the style set by [`set_location_style`], or the default.
+pub fn location_style() -> LocationStyle {
+    match LOCATION_STYLE.load(Ordering::Acquire) {
+        0 => LocationStyle::default(),
+        index => LocationStyle::ALL[usize::from(index - 1)],
+    }
+}
+
+/// Writes a location of the given components to `w`, in the
+/// [style](set_location_style) of the process.
+fn write_location<W: core::fmt::Write + ?Sized>(
+    w: &mut W,
+    name: Option<&str>,
+    file: &str,
+    line: u32,
+    column: u32,
+) -> core::fmt::Result {
+    let style = location_style();
+    if let Some(name) = name {
+        write_sanitized(w, name)?;
+        if style == LocationStyle::NameOnly {
+            return Ok(());
+        }
+        w.write_str(" at ")?;
+    }
+    write_sanitized(w, file)?;
+    match style {
+        LocationStyle::FileLineCol => write!(w, ":{}:{}", line, column),
+        LocationStyle::FileLine | LocationStyle::NameOnly => write!(w, ":{}", line),
+    }
+}
+
 impl Display for Location {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let file = self.file();
-        let line = self.line();
-        let column = self.column();
-        if let Some(name) = self.name() {
-            f.write_fmt(format_args!("{name} at {file}:{line}:{column}"))
-        } else {
-            f.write_fmt(format_args!("{file}:{line}:{column}"))
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.write_to(f)
+    }
+}
+
+/// Serializes a location as a struct of its `name` (which may be `None`),
+/// `file`, `line` and `column`; deserialize it as an [`OwnedLocation`].
+///
+/// Requires the `serde` feature.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Location {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut location = serializer.serialize_struct("Location", 4)?;
+        location.serialize_field("name", &self.name())?;
+        location.serialize_field("file", self.file())?;
+        location.serialize_field("line", &self.line())?;
+        location.serialize_field("column", &self.column())?;
+        location.end()
+    }
+}
+
+/// An owned copy of a [`Location`], into which serialized locations are
+/// deserialized; e.g., for the offline analysis of backtraces.
+///
+/// Requires the `serde` feature.
+///
+/// ## Example
+/// ```
+/// use async_backtrace::{location, OwnedLocation};
+///
+/// let location = location!();
+/// let json = serde_json::to_string(&location).unwrap();
+/// let owned: OwnedLocation = serde_json::from_str(&json).unwrap();
+/// assert_eq!(owned, OwnedLocation::from(location));
+/// assert_eq!(owned.to_string(), location.to_string());
+/// ```
+#[cfg(feature = "serde")]
+#[derive(
+    Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub struct OwnedLocation {
+    /// The name of the surrounding function, if any.
+    pub name: Option<String>,
+    /// The file name on which the surrounding function is defined.
+    pub file: String,
+    /// The line number on which the surrounding function is defined.
+    pub line: u32,
+    /// The column number on which the surrounding function is defined.
+    pub column: u32,
+}
+
+#[cfg(feature = "serde")]
+impl From<Location> for OwnedLocation {
+    fn from(location: Location) -> Self {
+        Self {
+            name: location.name().map(str::to_string),
+            file: location.file().to_string(),
+            line: location.line(),
+            column: location.column(),
         }
     }
 }
+
+/// Renders as does [`Location`].
+#[cfg(feature = "serde")]
+impl Display for OwnedLocation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write_location(f, self.name.as_deref(), &self.file, self.line, self.column)
+    }
+}
+
+/// A compact rendering of a [`Location`]: `name@file:line`.
+///
+/// Produced by [`Location::as_compact`].
+#[derive(Debug, Copy, Clone)]
+pub struct CompactLocation(Location);
+
+impl CompactLocation {
+    /// Produces the exact length, in bytes, of this location's rendering.
+    pub fn len_hint(&self) -> usize {
+        let location = self.0;
+        let name = location
+            .name()
+            .map_or(0, |name| sanitized_len(name) + "@".len());
+        name + sanitized_len(location.file()) + ":".len() + decimal_len(location.line())
+    }
+}
+
+impl Display for CompactLocation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let location = self.0;
+        if let Some(name) = location.name() {
+            write_sanitized(f, name)?;
+            f.write_char('@')?;
+        }
+        write_sanitized(f, location.file())?;
+        f.write_char(':')?;
+        write!(f, "{}", location.line())
+    }
+}
+
+/// Normalizes a path, as produced by [`core::any::type_name`], for
+/// [`Location::metric_key`].
+fn normalize_path(path: &str) -> String {
+    let mut normalized = String::with_capacity(path.len());
+    for segment in split_top_level(path, "::") {
+        if segment.starts_with("{{") {
+            // e.g., `{{closure}}`
+            continue;
+        }
+        if let Some(qualified) = segment.strip_prefix('<').and_then(|s| s.strip_suffix('>')) {
+            // `<T as Trait>`, `<impl Trait for T>` or `<impl T>`, each of
+            // which is keyed by the (absolute) path of `T`
+            let self_type = match qualified.strip_prefix("impl ") {
+                Some(implemented) => split_top_level(implemented, " for ").last(),
+                None => split_top_level(qualified, " as ").next(),
+            };
+            normalized = normalize_type(self_type.unwrap_or(qualified));
+            continue;
+        }
+        if !normalized.is_empty() {
+            normalized.push_str("::");
+        }
+        // strip any generic arguments; e.g., of `Wrapper<u8>`
+        let end = segment.find('<').unwrap_or(segment.len());
+        normalized.push_str(&segment[..end]);
+    }
+    normalized
+}
+
+/// Normalizes a type, as produced by [`core::any::type_name`], for
+/// [`Location::metric_key`].
+fn normalize_type(mut ty: &str) -> String {
+    loop {
+        let stripped = ["&", "mut ", "dyn ", "*const ", "*mut "]
+            .iter()
+            .find_map(|prefix| ty.strip_prefix(prefix));
+        match stripped {
+            Some(stripped) => ty = stripped.trim_start(),
+            None => return normalize_path(ty),
+        }
+    }
+}
+
+/// Splits `s` at each occurrence of `separator` that is not nested within
+/// brackets of any kind.
+fn split_top_level<'a>(s: &'a str, separator: &'a str) -> impl Iterator<Item = &'a str> {
+    let mut rest = Some(s);
+    core::iter::from_fn(move || {
+        let s = rest?;
+        let mut depth = 0usize;
+        let mut chars = s.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                // the arrow of a function pointer's return type
+                '-' if chars.peek().map(|&(_, c)| c) == Some('>') => {
+                    chars.next();
+                }
+                '<' | '(' | '[' => depth += 1,
+                '>' | ')' | ']' => depth = depth.saturating_sub(1),
+                _ if depth == 0 && s[i..].starts_with(separator) => {
+                    rest = Some(&s[i + separator.len()..]);
+                    return Some(&s[..i]);
+                }
+                _ => {}
+            }
+        }
+        rest = None;
+        Some(s)
+    })
+}
+
+/// The number of decimal digits of `n`.
+fn decimal_len(n: u32) -> usize {
+    n.checked_ilog10().map_or(1, |log| log as usize + 1)
+}
+
+/// Produces the replacement of `c` in renderings of names, files and errors,
+/// if it would otherwise corrupt the structure of a taskdump (e.g., a newline,
+/// which would break the tree's lines and prefixes).
+///
+/// C0 control characters and DEL are replaced by their Unicode control
+/// pictures (e.g., `\n` by `␊`); other control characters, and line and
+/// paragraph separators, are replaced by `�`.
+fn replacement(c: char) -> Option<char> {
+    match c {
+        '\0'..='\x1f' => char::from_u32(0x2400 + c as u32),
+        '\x7f' => Some('␡'),
+        '\u{2028}' | '\u{2029}' => Some(char::REPLACEMENT_CHARACTER),
+        c if c.is_control() => Some(char::REPLACEMENT_CHARACTER),
+        _ => None,
+    }
+}
+
+/// Writes `s` to `w`, replacing the characters that could corrupt the
+/// structure of a taskdump; see [`replacement`].
+pub(crate) fn write_sanitized<W: core::fmt::Write + ?Sized>(
+    w: &mut W,
+    s: &str,
+) -> core::fmt::Result {
+    let mut rest = s;
+    while let Some((i, c, replacement)) = rest
+        .char_indices()
+        .find_map(|(i, c)| replacement(c).map(|r| (i, c, r)))
+    {
+        w.write_str(&rest[..i])?;
+        w.write_char(replacement)?;
+        rest = &rest[i + c.len_utf8()..];
+    }
+    w.write_str(rest)
+}
+
+/// Produces the length, in bytes, of `s` as written by [`write_sanitized`].
+fn sanitized_len(s: &str) -> usize {
+    s.chars()
+        .map(|c| replacement(c).unwrap_or(c).len_utf8())
+        .sum()
+}
+
+/// The names produced by [`qualified_name`], by the type and name they
+/// qualify.
+#[cfg(feature = "std")]
+static QUALIFIED_NAMES: Lazy<
+    DashMap<(&'static str, &'static str), &'static str, BuildHasherDefault<FxHasher>>,
+> = Lazy::new(DashMap::default);
+
+/// Produces `name`, qualified by `self_type`; e.g., `my_crate::Handler::run`.
+///
+/// Each distinct qualified name is allocated (and leaked) once, upon its
+/// first use, and then reused; the number of such names is bounded by the
+/// number of monomorphizations of `#[framed(with_type)]` functions.
+#[cfg(feature = "std")]
+pub fn qualified_name(self_type: &'static str, name: &'static str) -> &'static str {
+    if let Some(qualified) = QUALIFIED_NAMES.get(&(self_type, name)) {
+        return *qualified;
+    }
+    *QUALIFIED_NAMES
+        .entry((self_type, name))
+        .or_insert_with(|| Box::leak(format!("{self_type}::{name}").into_boxed_str()))
+}
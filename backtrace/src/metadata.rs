@@ -0,0 +1,484 @@
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
+
+use crate::{Frame, Location};
+
+/// The maximum number of characters of a recorded error that are retained.
+const MAX_ERROR_LEN: usize = 120;
+
+/// The maximum number of annotations of a frame.
+pub(crate) const MAX_ANNOTATIONS: usize = 16;
+
+/// The metadata attached to a [`Frame`].
+#[derive(Default)]
+pub(crate) struct Metadata {
+    /// `key = value` annotations.
+    pub(crate) annotations: Vec<Annotation>,
+    /// The last errors returned by `#[framed(record_err)]` children of this
+    /// frame, by the location of the child.
+    errors: Vec<RecordedError>,
+    /// If this frame is the root of a
+    /// [`probe_child_frames`](crate::probe_child_frames), the locations of
+    /// the children initialized beneath it so far.
+    pub(crate) probed: Option<Vec<Location>>,
+    /// `true` if an activation of this frame was unwound by a panic.
+    pub(crate) panicked: bool,
+}
+
+/// A `key = value` annotation of a frame.
+pub(crate) struct Annotation {
+    pub(crate) key: &'static str,
+    pub(crate) value: AnnotationValue,
+    /// Whether descendants of the annotated frame inherit this annotation.
+    pub(crate) inherited: bool,
+}
+
+/// An error returned by a `#[framed(record_err)]` function.
+struct RecordedError {
+    /// The location of the function that returned the error.
+    location: Location,
+    /// The (truncated) rendering of the error.
+    message: String,
+    /// When the error was returned.
+    at: Instant,
+}
+
+impl Metadata {
+    /// Produces a description of the last error recorded for `location`, if
+    /// any, with its age as of `epoch`.
+    pub(crate) fn last_error(&self, location: Location, epoch: Instant) -> Option<String> {
+        self.errors
+            .iter()
+            .find(|error| error.location == location)
+            .map(|error| {
+                let ago = epoch.saturating_duration_since(error.at).as_secs();
+                let mut description = format!("last error {}s ago: ", ago);
+                // a newline in the error would corrupt the structure of dumps
+                crate::location::write_sanitized(&mut description, &error.message).unwrap();
+                description
+            })
+    }
+
+    /// Records `message` as the last error returned by `location`.
+    fn record_error(&mut self, location: Location, message: String) {
+        let error = RecordedError {
+            location,
+            message,
+            at: Instant::now(),
+        };
+        if let Some(slot) = self.errors.iter_mut().find(|e| e.location == location) {
+            *slot = error;
+        } else {
+            self.errors.push(error);
+        }
+    }
+}
+
+/// The value of an annotation; e.g., of [`annotate_value`].
+///
+/// Integers and booleans keep their types, so that they are exported as
+/// native JSON values by [`TaskdumpOptions::annotations`](crate::TaskdumpOptions::annotations);
+/// the values of [`annotate`] are rendered to strings. The two string
+/// variants compare (and hash) equal if their text is.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum AnnotationValue {
+    /// A signed integer.
+    I64(i64),
+    /// An unsigned integer.
+    U64(u64),
+    /// A boolean.
+    Bool(bool),
+    /// A static string, which is stored without allocating.
+    Str(&'static str),
+    /// An owned string.
+    String(String),
+}
+
+impl AnnotationValue {
+    /// Produces this value as an `i64`, if it is an integer that fits.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Self::I64(n) => Some(n),
+            Self::U64(n) => i64::try_from(n).ok(),
+            _ => None,
+        }
+    }
+
+    /// Produces this value as a `u64`, if it is an integer that fits.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Self::I64(n) => u64::try_from(n).ok(),
+            Self::U64(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Produces this value as a `bool`, if it is one.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Self::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Produces this value as a `&str`, if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Str(s) => Some(s),
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Serializes the value as the native value of its type; e.g., a JSON number
+/// for an integer.
+///
+/// Requires the `serde` feature.
+#[cfg(feature = "serde")]
+impl serde::Serialize for AnnotationValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::I64(n) => serializer.serialize_i64(*n),
+            Self::U64(n) => serializer.serialize_u64(*n),
+            Self::Bool(b) => serializer.serialize_bool(*b),
+            Self::Str(s) => serializer.serialize_str(s),
+            Self::String(s) => serializer.serialize_str(s),
+        }
+    }
+}
+
+/// Deserializes an integer, boolean or string, as serialized; a string as an
+/// owned [`String`](AnnotationValue::String), and an integer as a
+/// [`U64`](AnnotationValue::U64) if it is not negative (as JSON does not
+/// distinguish them).
+///
+/// Requires the `serde` feature.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for AnnotationValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = AnnotationValue;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("an integer, boolean or string")
+            }
+
+            fn visit_i64<E>(self, n: i64) -> Result<Self::Value, E> {
+                Ok(match u64::try_from(n) {
+                    Ok(n) => AnnotationValue::U64(n),
+                    Err(_) => AnnotationValue::I64(n),
+                })
+            }
+
+            fn visit_u64<E>(self, n: u64) -> Result<Self::Value, E> {
+                Ok(AnnotationValue::U64(n))
+            }
+
+            fn visit_bool<E>(self, b: bool) -> Result<Self::Value, E> {
+                Ok(AnnotationValue::Bool(b))
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E> {
+                Ok(AnnotationValue::String(s.to_string()))
+            }
+
+            fn visit_string<E>(self, s: String) -> Result<Self::Value, E> {
+                Ok(AnnotationValue::String(s))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+impl PartialEq for AnnotationValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::I64(a), Self::I64(b)) => a == b,
+            (Self::U64(a), Self::U64(b)) => a == b,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            _ => matches!((self.as_str(), other.as_str()), (Some(a), Some(b)) if a == b),
+        }
+    }
+}
+
+impl Eq for AnnotationValue {}
+
+impl Hash for AnnotationValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Self::I64(n) => (0u8, n).hash(state),
+            Self::U64(n) => (1u8, n).hash(state),
+            Self::Bool(b) => (2u8, b).hash(state),
+            Self::Str(s) => (3u8, s).hash(state),
+            Self::String(s) => (3u8, s).hash(state),
+        }
+    }
+}
+
+impl Display for AnnotationValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::I64(n) => Display::fmt(n, f),
+            Self::U64(n) => Display::fmt(n, f),
+            Self::Bool(b) => Display::fmt(b, f),
+            Self::Str(s) => f.write_str(s),
+            Self::String(s) => f.write_str(s),
+        }
+    }
+}
+
+macro_rules! from_integers {
+    ($variant:ident as $wide:ty: $($ty:ty),*) => {
+        $(
+            impl From<$ty> for AnnotationValue {
+                fn from(n: $ty) -> Self {
+                    Self::$variant(n as $wide)
+                }
+            }
+        )*
+    };
+}
+
+from_integers!(I64 as i64: i8, i16, i32, i64, isize);
+from_integers!(U64 as u64: u8, u16, u32, u64, usize);
+
+impl From<bool> for AnnotationValue {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl From<&'static str> for AnnotationValue {
+    fn from(s: &'static str) -> Self {
+        Self::Str(s)
+    }
+}
+
+impl From<String> for AnnotationValue {
+    fn from(s: String) -> Self {
+        Self::String(s)
+    }
+}
+
+/// Annotates the currently-active frame with `key = value`.
+///
+/// Annotations are included in [`backtrace_annotated`](crate::backtrace_annotated),
+/// and in taskdumps that set [`TaskdumpOptions::annotations`](crate::TaskdumpOptions::annotations).
+/// Annotating a frame with a key it already carries replaces the previous
+/// value. A frame carries at most 16 annotations; further keys are ignored.
+/// Produces `false` if there is no active frame, or if the annotation was
+/// ignored.
+///
+/// The value is rendered to a string; see [`annotate_value`] for values that
+/// keep their types.
+///
+/// ## Example
+/// ```
+/// #[async_backtrace::framed]
+/// async fn handle(query_id: u64) {
+///     async_backtrace::annotate("query_id", query_id);
+/// }
+/// ```
+pub fn annotate(key: &'static str, value: impl Display) -> bool {
+    annotate_active(key, AnnotationValue::String(value.to_string()), false)
+}
+
+/// Annotates the currently-active frame with `key = value`, keeping the type
+/// of `value`.
+///
+/// Like [`annotate`], but integers and booleans are stored as such (and
+/// exported as native JSON values), and static strings are stored without
+/// allocating.
+///
+/// ## Example
+/// ```
+/// #[async_backtrace::framed]
+/// async fn handle(query_id: u64, retry: bool) {
+///     async_backtrace::annotate_value("query_id", query_id);
+///     async_backtrace::annotate_value("retry", retry);
+///     async_backtrace::annotate_value("route", "/users");
+/// }
+/// ```
+pub fn annotate_value(key: &'static str, value: impl Into<AnnotationValue>) -> bool {
+    annotate_active(key, value.into(), false)
+}
+
+/// Annotates the currently-active frame with `key = value`, which its
+/// descendants inherit.
+///
+/// Like [`annotate`], but the annotation also appears on every descendant of
+/// the frame in [`backtrace_annotated`](crate::backtrace_annotated), unless
+/// that descendant (or a nearer ancestor of it) carries its own annotation of
+/// `key`. Inherited annotations are resolved when they are read, by walking
+/// up the tree; they are not copied into each descendant.
+///
+/// ## Example
+/// ```
+/// #[async_backtrace::framed]
+/// async fn handle(query_id: u64) {
+///     async_backtrace::annotate_inherited("query_id", query_id);
+///     lookup().await;
+/// }
+///
+/// #[async_backtrace::framed]
+/// async fn lookup() {
+///     let backtrace = async_backtrace::backtrace_annotated().unwrap();
+///     assert_eq!(
+///         backtrace[0].get("query_id").and_then(|value| value.as_str()),
+///         Some("7")
+///     );
+/// }
+/// # futures::executor::block_on(handle(7));
+/// ```
+pub fn annotate_inherited(key: &'static str, value: impl Display) -> bool {
+    annotate_active(key, AnnotationValue::String(value.to_string()), true)
+}
+
+/// Annotates the currently-active frame with `key = value`, which its
+/// descendants inherit, keeping the type of `value`; see [`annotate_value`]
+/// and [`annotate_inherited`].
+pub fn annotate_value_inherited(key: &'static str, value: impl Into<AnnotationValue>) -> bool {
+    annotate_active(key, value.into(), true)
+}
+
+fn annotate_active(key: &'static str, value: AnnotationValue, inherited: bool) -> bool {
+    Frame::with_active(|maybe_frame| {
+        // SAFETY: The active frame is only annotated from within its own
+        // `in_scope`, which holds the lock of its root.
+        maybe_frame.is_some_and(|frame| unsafe { frame.annotate(key, value, inherited) })
+    })
+}
+
+/// Records the error (if any) in `result` on the parent of the active frame,
+/// where it is shown alongside subsequent frames with the same location.
+///
+/// Invoked by the expansion of `#[framed(record_err)]`.
+pub fn record_err<T, E: Display>(result: &Result<T, E>) {
+    let error = match result {
+        Ok(_) => return,
+        Err(error) => error,
+    };
+    Frame::with_active(|maybe_frame| {
+        let frame = match maybe_frame {
+            Some(frame) => frame,
+            None => return,
+        };
+        // Errors of tasks have nowhere to be shown once the task completes.
+        let parent = match frame.parent() {
+            Some(parent) => parent,
+            None => return,
+        };
+        let mut message = error.to_string();
+        if let Some((end, _)) = message.char_indices().nth(MAX_ERROR_LEN) {
+            message.truncate(end);
+            message.push('…');
+        }
+        // SAFETY: The parent of the active frame belongs to the same tree,
+        // whose root is locked for the duration of the active frame's
+        // `in_scope`.
+        unsafe {
+            parent.with_metadata_mut(|metadata| metadata.record_error(frame.location(), message))
+        }
+    })
+}
+
+/// A [`Location`] in a backtrace, along with the annotations of its frame.
+///
+/// Produced by [`backtrace_annotated`](crate::backtrace_annotated).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct AnnotatedFrame {
+    location: Location,
+    metadata: Vec<(&'static str, AnnotationValue)>,
+}
+
+impl AnnotatedFrame {
+    /// Captures the location and annotations of `frame`, including those it
+    /// inherits from its ancestors.
+    ///
+    /// # Safety
+    /// The caller must ensure that the root of `frame` is locked.
+    pub(crate) unsafe fn capture(frame: &Frame) -> Self {
+        let mut metadata: Vec<(&'static str, AnnotationValue)> = frame.with_metadata(|metadata| {
+            metadata
+                .annotations
+                .iter()
+                .map(|annotation| (annotation.key, annotation.value.clone()))
+                .collect()
+        });
+        // the nearest ancestor's annotation of each key wins; annotations are
+        // inherited through barriers
+        for ancestor in frame.ancestors().skip(1) {
+            ancestor.with_metadata(|ancestor| {
+                for annotation in ancestor.annotations.iter().filter(|a| a.inherited) {
+                    if !metadata.iter().any(|(key, _)| *key == annotation.key) {
+                        metadata.push((annotation.key, annotation.value.clone()));
+                    }
+                }
+            })
+        }
+        Self {
+            location: frame.location(),
+            metadata,
+        }
+    }
+
+    /// Produces an annotated frame of `location`, without annotations.
+    pub(crate) fn unannotated(location: Location) -> Self {
+        Self {
+            location,
+            metadata: Vec::new(),
+        }
+    }
+
+    /// Produces the [`Location`] of this frame.
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    /// Produces the `key = value` annotations of this frame, in the order in
+    /// which they were first attached, followed by those it inherits from its
+    /// ancestors (nearest first).
+    pub fn metadata(&self) -> &[(&'static str, AnnotationValue)] {
+        &self.metadata
+    }
+
+    /// Produces the value of the annotation `key` of this frame (including
+    /// those it inherits), if any.
+    pub fn get(&self, key: &str) -> Option<&AnnotationValue> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+}
+
+impl Display for AnnotatedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.location)?;
+        write_annotations(f, &self.metadata)
+    }
+}
+
+/// Writes `annotations` as ` [key=value, key=value]`; nothing, if there are
+/// none.
+pub(crate) fn write_annotations<W: fmt::Write>(
+    w: &mut W,
+    annotations: &[(&'static str, AnnotationValue)],
+) -> fmt::Result {
+    let mut pairs = annotations.iter();
+    if let Some((key, value)) = pairs.next() {
+        write!(w, " [{key}={value}")?;
+        for (key, value) in pairs {
+            write!(w, ", {key}={value}")?;
+        }
+        w.write_str("]")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use core::future::Future;
+use core::task::Context;
+
+use crate::{Frame, Location};
+
+/// Polls `future` once within a fresh root frame, and produces the
+/// [`Location`]s of the frames initialized as its immediate children, in the
+/// order in which they were initialized.
+///
+/// Frames that complete within this poll are included, too. Frames of
+/// [lazy](Location::frame_lazy) futures that are ready upon their first poll
+/// are never initialized, and so are not observed. `future` is dropped before
+/// this function returns.
+///
+/// This is the runtime half of [`assert_framed!`](crate::assert_framed).
+///
+/// ## Example
+/// ```
+/// #[async_backtrace::framed]
+/// async fn foo() {}
+///
+/// # fn main() {
+/// let children = async_backtrace::probe_child_frames(foo());
+/// assert_eq!(children.len(), 1);
+/// assert_eq!(children[0].name(), Some("rust_out::foo::{{closure}}"));
+/// # }
+/// ```
+pub fn probe_child_frames<F: Future>(future: F) -> Vec<Location> {
+    // `root` is declared before `future`, so that `future` (and any frames it
+    // owns) is dropped first.
+    let root = Frame::new(crate::location!());
+    futures::pin_mut!(root);
+    futures::pin_mut!(future);
+
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    root.in_scope(|| {
+        Frame::with_active(|root| {
+            let root = root.unwrap();
+            // SAFETY: `root` is the active frame, and so is locked for the
+            // duration of this `in_scope`.
+            unsafe { root.with_metadata_mut(|metadata| metadata.probed = Some(Vec::new())) };
+            let _ = future.as_mut().poll(&mut cx);
+            // SAFETY: See above.
+            unsafe { root.with_metadata_mut(|metadata| metadata.probed.take()) }.unwrap_or_default()
+        })
+    })
+}
+
+/// Panics unless `children` includes a frame for the function at `path`,
+/// whose last segment is matched against the segments of each frame's name.
+#[track_caller]
+pub fn assert_framed(path: &[&str], children: &[Location]) {
+    let function = path.join("::");
+    let ident = path.last().copied().unwrap_or_default();
+    let framed = children
+        .iter()
+        .filter_map(Location::name)
+        .any(|name| name.split("::").any(|segment| segment == ident));
+    if !framed {
+        let observed = children
+            .iter()
+            .map(Location::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        panic!(
+            "expected `{}` to be framed, but its first poll produced no frame named `{}`; observed child frames: [{}]",
+            function, ident, observed
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! Low-level entry points for embedders (e.g., runtimes or combinators) that
+//! manage frames by hand, rather than by [`framed`](crate::framed) or
+//! [`Location::frame`](crate::Location::frame).
+//!
+//! Unlike the hidden internals used by the crate's macros, this module is
+//! covered by semver, like the rest of the public API.
+//!
+//! ## Contract
+//! A [`Frame`] is initialized upon the first call of [`Frame::in_scope`]: if
+//! another frame is in scope on the thread at that moment, the new frame
+//! becomes its child; otherwise, it becomes the root of a new task, which is
+//! [registered](crate::tasks) until the frame is dropped.
+//!
+//! A child frame must be dropped before its parent; nesting frames as futures
+//! nest (so that a child is owned, transitively, by the state of its parent)
+//! upholds this. Frames may otherwise be sent to and dropped on any thread.
+//! The frame passed to `in_scope` must be pinned, and so cannot move once it
+//! has been initialized.
+//!
+//! ## Example
+//! ```
+//! use async_backtrace::{location, raw::Frame};
+//!
+//! let mut root = Box::pin(Frame::new(location!()));
+//! root.as_mut().in_scope(|| {
+//!     // initialized within the scope of `root`, so its child
+//!     let mut child = Box::pin(Frame::new(location!()));
+//!     child.as_mut().in_scope(|| {
+//!         assert_eq!(async_backtrace::backtrace().unwrap().len(), 2);
+//!     });
+//!     // (dropped before `root`)
+//! });
+//! // the task of `root` is registered until `root` is dropped
+//! assert_eq!(async_backtrace::tasks().count(), 1);
+//! drop(root);
+//! assert_eq!(async_backtrace::tasks().count(), 0);
+//! ```
+//!
+//! A frame must be pinned to be put in scope:
+//! ```compile_fail
+//! use async_backtrace::{location, raw::Frame};
+//!
+//! let mut frame = Frame::new(location!());
+//! frame.in_scope(|| {});
+//! ```
+//! ...and, as it is `!Unpin`, it can only be pinned safely by [`Box::pin`] or
+//! [`std::pin::pin!`], not by [`Pin::new`]:
+//! ```compile_fail
+//! use async_backtrace::{location, raw::Frame};
+//!
+//! let mut frame = Frame::new(location!());
+//! std::pin::Pin::new(&mut frame).in_scope(|| {});
+//! ```
+
+use std::pin::Pin;
+
+use crate::{Location, Origin};
+
+pin_project_lite::pin_project! {
+/// A frame of a task, as the future of a [framed](crate::framed) function
+/// holds; see the [module documentation](self) for its contract.
+#[repr(transparent)]
+pub struct Frame {
+    #[pin]
+    frame: crate::frame::Frame,
+}
+}
+
+impl Frame {
+    /// Constructs a new, uninitialized frame, of [`Origin::Manual`].
+    pub fn new(location: Location) -> Self {
+        Self::with_origin(location, Origin::Manual)
+    }
+
+    /// Constructs a new, uninitialized frame, of the given origin.
+    pub fn with_origin(location: Location, origin: Origin) -> Self {
+        Self {
+            frame: crate::frame::Frame::with_origin(location, origin),
+        }
+    }
+
+    /// Invokes `f` with this frame in scope on the current thread, initializing
+    /// it (as a child of the frame in scope, if any) upon the first call.
+    ///
+    /// Calls may be nested; once `f` returns (or unwinds), the previously
+    /// in-scope frame is restored.
+    pub fn in_scope<F, R>(self: Pin<&mut Self>, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        self.project().frame.in_scope(f)
+    }
+
+    /// The location of this frame.
+    pub fn location(&self) -> Location {
+        self.frame.location()
+    }
+
+    /// How this frame was instrumented.
+    pub fn origin(&self) -> Origin {
+        self.frame.origin()
+    }
+}
@@ -0,0 +1,283 @@
+//! The global set of registered tasks.
+
+use std::ops::Deref;
+
+use crate::Task;
+
+/// A set of registered [`Task`]s.
+///
+/// Production builds use a sharded, concurrent map (`DashRegistry`). Since
+/// that map is not loom-aware, loom builds instead use a lock-guarded vector
+/// (`LoomRegistry`), so that the interleavings of registration,
+/// deregistration and iteration can be modeled.
+pub(crate) trait TaskRegistry {
+    /// A reference to a registered task. While it is live, the task cannot be
+    /// deregistered.
+    type Ref<'a>: Deref<Target = Task>
+    where
+        Self: 'a;
+
+    /// An iterator over registered tasks.
+    type Iter<'a>: Iterator<Item = Self::Ref<'a>>
+    where
+        Self: 'a;
+
+    /// Adds `task` to the set, or, if the set cannot hold it, produces it
+    /// back. Neither backend refuses tasks, but a backend that allocates
+    /// fallibly (or within a fixed budget) may, instead of aborting.
+    fn try_register(&self, task: Task) -> Result<(), Task>;
+
+    /// Removes `task` from the set, blocking until no references to it are
+    /// live. Produces `false` if `task` was not registered.
+    fn deregister(&self, task: &Task) -> bool;
+
+    /// Produces a reference to the task with the given `id`, if it is
+    /// registered.
+    fn get(&self, id: u64) -> Option<Self::Ref<'_>>;
+
+    /// Iterates over the set. The registration and deregistration of some or
+    /// all tasks is blocked for as long as the iterator (or any reference it
+    /// produced) is live.
+    fn iter(&self) -> Self::Iter<'_>;
+
+    /// The number of tasks the set can hold without reallocating.
+    fn capacity(&self) -> usize;
+}
+
+#[cfg(not(loom))]
+pub(crate) use dash::DashRegistry;
+
+#[cfg(not(loom))]
+mod dash {
+    use super::TaskRegistry;
+    use crate::tasks::{RegistryConfig, Task};
+    use dashmap::{
+        mapref::{multiple::RefMulti, one::Ref},
+        DashMap,
+    };
+    use rustc_hash::FxHasher;
+    use std::{hash::BuildHasherDefault, iter::Map, ops::Deref};
+
+    type Hasher = BuildHasherDefault<FxHasher>;
+
+    /// A [`TaskRegistry`] backed by a sharded, concurrent map of tasks by id.
+    pub(crate) struct DashRegistry(DashMap<u64, Task, Hasher>);
+
+    impl DashRegistry {
+        pub(crate) fn new(config: &RegistryConfig) -> Self {
+            Self(DashMap::with_capacity_and_hasher_and_shard_amount(
+                config.initial_capacity,
+                Hasher::default(),
+                config.shard_amount,
+            ))
+        }
+    }
+
+    /// A reference to a task registered in a [`DashRegistry`].
+    pub(crate) enum DashRef<'a> {
+        /// A reference produced by an iteration.
+        Multi(RefMulti<'a, u64, Task, Hasher>),
+        /// A reference produced by a lookup.
+        One(Ref<'a, u64, Task, Hasher>),
+    }
+
+    impl<'a> Deref for DashRef<'a> {
+        type Target = Task;
+
+        fn deref(&self) -> &Task {
+            match self {
+                Self::Multi(task) => task.value(),
+                Self::One(task) => task.value(),
+            }
+        }
+    }
+
+    type DashIter<'a> = dashmap::iter::Iter<'a, u64, Task, Hasher>;
+
+    impl TaskRegistry for DashRegistry {
+        type Ref<'a> = DashRef<'a>;
+        type Iter<'a> = Map<DashIter<'a>, fn(RefMulti<'a, u64, Task, Hasher>) -> DashRef<'a>>;
+
+        fn try_register(&self, task: Task) -> Result<(), Task> {
+            let previous = self.0.insert(task.id(), task);
+            debug_assert!(previous.is_none());
+            Ok(())
+        }
+
+        fn deregister(&self, task: &Task) -> bool {
+            self.0.remove(&task.id()).is_some()
+        }
+
+        fn get(&self, id: u64) -> Option<DashRef<'_>> {
+            self.0.get(&id).map(DashRef::One)
+        }
+
+        fn iter(&self) -> Self::Iter<'_> {
+            self.0.iter().map(DashRef::Multi)
+        }
+
+        fn capacity(&self) -> usize {
+            self.0.capacity()
+        }
+    }
+}
+
+#[cfg(loom)]
+pub(crate) use readers::LoomRegistry;
+
+#[cfg(loom)]
+mod readers {
+    use super::TaskRegistry;
+    use crate::tasks::{RegistryConfig, Task};
+    use loom::{
+        cell::UnsafeCell,
+        sync::{Condvar, Mutex},
+    };
+    use std::{ops::Deref, rc::Rc};
+
+    /// A loom-aware [`TaskRegistry`] backed by a lock-guarded vector.
+    ///
+    /// Like the shards of a `DashMap`, the vector may be read by any number of
+    /// iterations at once (including several on the same thread), while
+    /// registration and deregistration wait for all iterations to end.
+    pub(crate) struct LoomRegistry {
+        /// The number of live iterations, including the references they
+        /// produced. Held while `tasks` is modified.
+        readers: Mutex<usize>,
+        /// Notified when `readers` falls to zero.
+        idle: Condvar,
+        /// The registered tasks.
+        tasks: UnsafeCell<Vec<Task>>,
+    }
+
+    // SAFETY: `tasks` is only modified while `readers` is locked and zero, and
+    // only read while `readers` is non-zero.
+    unsafe impl Sync for LoomRegistry {}
+
+    impl LoomRegistry {
+        pub(crate) fn new(config: &RegistryConfig) -> Self {
+            Self {
+                readers: Mutex::new(0),
+                idle: Condvar::new(),
+                tasks: UnsafeCell::new(Vec::with_capacity(config.initial_capacity)),
+            }
+        }
+
+        /// Modifies the registered tasks, once no iterations are live.
+        fn write<R>(&self, f: impl FnOnce(&mut Vec<Task>) -> R) -> R {
+            let mut readers = self.readers.lock().unwrap();
+            while *readers > 0 {
+                readers = self.idle.wait(readers).unwrap();
+            }
+            // SAFETY: There are no readers, and `readers` is locked, so no
+            // reader can begin until the modification is complete.
+            self.tasks.with_mut(|tasks| f(unsafe { &mut *tasks }))
+        }
+    }
+
+    /// A read lock on a [`LoomRegistry`], shared by an iteration and the
+    /// references it produces.
+    struct ReadGuard<'a>(&'a LoomRegistry);
+
+    impl<'a> ReadGuard<'a> {
+        fn new(registry: &'a LoomRegistry) -> Self {
+            *registry.readers.lock().unwrap() += 1;
+            Self(registry)
+        }
+
+        fn tasks(&self) -> &'a Vec<Task> {
+            // SAFETY: `tasks` is not modified while this guard is live.
+            self.0.tasks.with(|tasks| unsafe { &*tasks })
+        }
+    }
+
+    impl<'a> Drop for ReadGuard<'a> {
+        fn drop(&mut self) {
+            let mut readers = self.0.readers.lock().unwrap();
+            *readers -= 1;
+            if *readers == 0 {
+                self.0.idle.notify_all();
+            }
+        }
+    }
+
+    /// A reference to a task registered in a [`LoomRegistry`].
+    pub(crate) struct LoomRef<'a> {
+        guard: Rc<ReadGuard<'a>>,
+        index: usize,
+    }
+
+    impl<'a> Deref for LoomRef<'a> {
+        type Target = Task;
+
+        fn deref(&self) -> &Task {
+            &self.guard.tasks()[self.index]
+        }
+    }
+
+    /// An iterator over the tasks of a [`LoomRegistry`].
+    pub(crate) struct LoomIter<'a> {
+        guard: Rc<ReadGuard<'a>>,
+        index: usize,
+    }
+
+    impl<'a> Iterator for LoomIter<'a> {
+        type Item = LoomRef<'a>;
+
+        fn next(&mut self) -> Option<LoomRef<'a>> {
+            let index = self.index;
+            if index < self.guard.tasks().len() {
+                self.index += 1;
+                Some(LoomRef {
+                    guard: self.guard.clone(),
+                    index,
+                })
+            } else {
+                None
+            }
+        }
+    }
+
+    impl TaskRegistry for LoomRegistry {
+        type Ref<'a> = LoomRef<'a>;
+        type Iter<'a> = LoomIter<'a>;
+
+        fn try_register(&self, task: Task) -> Result<(), Task> {
+            self.write(|tasks| {
+                debug_assert!(!tasks.contains(&task));
+                tasks.push(task);
+            });
+            Ok(())
+        }
+
+        fn deregister(&self, task: &Task) -> bool {
+            self.write(|tasks| {
+                let index = tasks.iter().position(|t| t == task);
+                if let Some(index) = index {
+                    tasks.swap_remove(index);
+                }
+                index.is_some()
+            })
+        }
+
+        fn get(&self, id: u64) -> Option<LoomRef<'_>> {
+            let guard = ReadGuard::new(self);
+            let index = guard.tasks().iter().position(|task| task.id() == id)?;
+            Some(LoomRef {
+                guard: Rc::new(guard),
+                index,
+            })
+        }
+
+        fn iter(&self) -> LoomIter<'_> {
+            LoomIter {
+                guard: Rc::new(ReadGuard::new(self)),
+                index: 0,
+            }
+        }
+
+        fn capacity(&self) -> usize {
+            ReadGuard::new(self).tasks().capacity()
+        }
+    }
+}
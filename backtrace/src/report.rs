@@ -0,0 +1,86 @@
+//! Sections of crash reports (e.g., those written by a panic hook), in which
+//! the trees of tasks are embedded alongside other diagnostics.
+//!
+//! ## Example
+//! ```no_run
+//! use std::{fs::File, io::Write};
+//!
+//! let previous = std::panic::take_hook();
+//! std::panic::set_hook(Box::new(move |info| {
+//!     if let Ok(mut report) = File::create("crash-report.txt") {
+//!         let _ = writeln!(report, "{}\n", info);
+//!         let _ = async_backtrace::report::write_crash_section(&mut report);
+//!     }
+//!     previous(info);
+//! }));
+//! ```
+
+use std::{
+    io,
+    ops::ControlFlow,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::TaskdumpOptions;
+
+/// The first line of a section written by [`write_crash_section`].
+pub const BEGIN: &str = "---------------- BEGIN ASYNC BACKTRACE ----------------";
+
+/// The last line of a section written by [`write_crash_section`].
+pub const END: &str = "----------------- END ASYNC BACKTRACE -----------------";
+
+/// The default [budget](set_crash_section_budget) of the taskdump of a crash
+/// section, in bytes.
+pub const DEFAULT_BUDGET: usize = 64 * 1024;
+
+/// The budget of the taskdump of a crash section, in bytes.
+static BUDGET: AtomicUsize = AtomicUsize::new(DEFAULT_BUDGET);
+
+/// Sets the number of bytes of the taskdump of each section written by
+/// [`write_crash_section`]; by default, [`DEFAULT_BUDGET`].
+pub fn set_crash_section_budget(bytes: usize) {
+    BUDGET.store(bytes, Ordering::Relaxed);
+}
+
+/// Writes a section with the trees of all tasks to `w`, for embedding in a
+/// larger crash report.
+///
+/// The section begins with a line of [`BEGIN`] and ends with a line of
+/// [`END`]. Between these, a summary line (e.g., `3 tasks`) precedes the
+/// trees of the tasks, as rendered by [`TaskdumpOptions::dump`]. The dump does
+/// not wait for running tasks, which are shown as `[POLLING]`, and so is safe
+/// to take from a panic hook, within or outside of an async runtime.
+///
+/// If the trees exceed the [budget](set_crash_section_budget) of the section,
+/// they are truncated at the end of the last line that fits, and followed by
+/// a marker of how many of their bytes were written; e.g., `[TRUNCATED: 1024
+/// of 5000 bytes]`.
+pub fn write_crash_section<W: io::Write>(w: &mut W) -> io::Result<()> {
+    let mut tasks = 0;
+    let dump = TaskdumpOptions::new()
+        .progress(|_, total| {
+            tasks = total;
+            ControlFlow::Continue(())
+        })
+        .dump();
+    let budget = BUDGET.load(Ordering::Relaxed);
+
+    writeln!(w, "{}", BEGIN)?;
+    writeln!(w, "{} {}", tasks, if tasks == 1 { "task" } else { "tasks" })?;
+    if dump.len() <= budget {
+        if !dump.is_empty() {
+            writeln!(w, "{}", dump)?;
+        }
+    } else {
+        let mut end = budget;
+        while !dump.is_char_boundary(end) {
+            end -= 1;
+        }
+        let end = dump[..end].rfind('\n').unwrap_or(0);
+        if end > 0 {
+            writeln!(w, "{}", &dump[..end])?;
+        }
+        writeln!(w, "[TRUNCATED: {} of {} bytes]", end, dump.len())?;
+    }
+    writeln!(w, "{}", END)
+}
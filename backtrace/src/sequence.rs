@@ -0,0 +1,76 @@
+//! Sequence numbers of the initializations and polls of frames, from which
+//! the order of events can be reconstructed in postmortems (e.g., which of
+//! two sibling frames was created first, or polled last).
+//!
+//! The numbers are drawn from a single, global counter, and are only recorded
+//! with the `sequence-numbers` feature; otherwise, [`Sequence`] is empty, and
+//! recording is free.
+
+#[cfg(feature = "sequence-numbers")]
+mod enabled {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// The next sequence number.
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+
+    /// Produces the next sequence number.
+    fn next() -> u64 {
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// The sequence numbers of a frame.
+    #[derive(Debug, Default)]
+    pub(crate) struct Sequence {
+        // Set once, before the frame is reachable from other threads.
+        init: u64,
+        // Set upon each poll, while the root of the frame is locked; but read
+        // by taskdumps that may fail to lock it.
+        last_poll: AtomicU64,
+    }
+
+    impl Sequence {
+        /// Records the initialization of the frame.
+        pub(crate) fn initialized(&mut self) {
+            self.init = next();
+        }
+
+        /// Records a poll of the frame.
+        pub(crate) fn polled(&self) {
+            self.last_poll.store(next(), Ordering::Relaxed);
+        }
+
+        /// The sequence number of the initialization of the frame.
+        pub(crate) fn init(&self) -> Option<u64> {
+            Some(self.init).filter(|&seq| seq != 0)
+        }
+
+        /// The sequence number of the last poll of the frame.
+        pub(crate) fn last_poll(&self) -> Option<u64> {
+            Some(self.last_poll.load(Ordering::Relaxed)).filter(|&seq| seq != 0)
+        }
+    }
+}
+
+#[cfg(not(feature = "sequence-numbers"))]
+mod enabled {
+    /// The (unrecorded) sequence numbers of a frame.
+    // (braced, so that it is constructed as the recording variant is)
+    #[derive(Debug, Default)]
+    pub(crate) struct Sequence {}
+
+    impl Sequence {
+        pub(crate) fn initialized(&mut self) {}
+
+        pub(crate) fn polled(&self) {}
+
+        pub(crate) fn init(&self) -> Option<u64> {
+            None
+        }
+
+        pub(crate) fn last_poll(&self) -> Option<u64> {
+            None
+        }
+    }
+}
+
+pub(crate) use enabled::Sequence;
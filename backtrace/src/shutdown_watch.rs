@@ -0,0 +1,116 @@
+//! Reporting the tasks that are blocking a graceful shutdown, periodically,
+//! until they have all exited.
+//!
+//! Requires the `tokio` feature.
+//!
+//! ## Example
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! tokio::signal::ctrl_c().await.unwrap();
+//! // ...begin the graceful shutdown, and then:
+//! tokio::spawn(async_backtrace::shutdown_watch::run(
+//!     Duration::from_secs(5),
+//!     |_| true,
+//! ));
+//! # }
+//! ```
+
+use std::{fmt, time::Duration};
+
+use crate::{DumpDiff, TaskTree};
+
+/// Every `interval`, until none remain, writes a [`Report`] of the tasks that
+/// match `filter` to stderr.
+///
+/// See [`run_with`].
+pub async fn run<F>(interval: Duration, filter: F)
+where
+    F: FnMut(&TaskTree) -> bool,
+{
+    run_with(interval, filter, |report| eprintln!("{}", report)).await
+}
+
+/// Every `interval`, until none remain, passes a [`Report`] of the tasks that
+/// match `filter` to `sink`.
+///
+/// The first report is produced immediately. Each captures the tasks without
+/// waiting for those that are being polled (which are reported with only
+/// their roots), and includes what changed since the previous report. Once
+/// no task matches `filter`, no report is produced, and the watch returns.
+///
+/// The task that runs the watch (if it is framed) is never reported.
+pub async fn run_with<F, S>(interval: Duration, mut filter: F, mut sink: S)
+where
+    F: FnMut(&TaskTree) -> bool,
+    S: FnMut(&Report),
+{
+    let this = crate::root_task_id();
+    let mut last: Option<Vec<TaskTree>> = None;
+    loop {
+        let mut tasks: Vec<TaskTree> = crate::snapshot(false)
+            .into_iter()
+            .filter(|tree| Some(tree.id()) != this && filter(tree))
+            .collect();
+        if tasks.is_empty() {
+            return;
+        }
+        tasks.sort_by(TaskTree::cmp_by_root);
+        let changes = last.as_deref().map(|last| crate::diff(last, &tasks));
+        let report = Report { tasks, changes };
+        sink(&report);
+        last = Some(report.tasks);
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// The tasks blocking a shutdown, as reported by [`run_with`].
+///
+/// It is rendered as a header, the tree of each task, and (unless it is the
+/// first report) what changed since the previous report; e.g.:
+/// ```text
+/// async-backtrace: 1 task blocking shutdown
+/// ╼ app::flush::{{closure}} at src/main.rs:20:1
+///   └╼ app::write::{{closure}} at src/main.rs:30:1
+/// since the last report:
+/// - 1 task rooted at app::drain::{{closure}}@src/main.rs:10 exited
+/// ```
+#[derive(Debug, Clone)]
+pub struct Report {
+    tasks: Vec<TaskTree>,
+    changes: Option<DumpDiff>,
+}
+
+impl Report {
+    /// The tasks blocking the shutdown, ordered by
+    /// [`TaskTree::cmp_by_root`].
+    pub fn tasks(&self) -> &[TaskTree] {
+        &self.tasks
+    }
+
+    /// What changed since the previous report; `None`, for the first.
+    pub fn changes(&self) -> Option<&DumpDiff> {
+        self.changes.as_ref()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.tasks.len();
+        let plural = if count == 1 { "" } else { "s" };
+        write!(
+            f,
+            "async-backtrace: {} task{} blocking shutdown",
+            count, plural
+        )?;
+        for tree in &self.tasks {
+            write!(f, "\n{}", tree)?;
+        }
+        if let Some(changes) = &self.changes {
+            write!(f, "\nsince the last report:\n{}", changes)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+//! The sizes of the futures wrapped by frames, with which taskdumps double as
+//! lightweight profilers of future-size bloat.
+//!
+//! Sizes are only recorded with the `future-sizes` feature; otherwise,
+//! [`FutureSize`] is empty, and recording is free.
+
+use std::fmt;
+
+#[cfg(feature = "future-sizes")]
+mod enabled {
+    use std::convert::TryFrom;
+
+    /// The size of the future wrapped by a frame.
+    #[derive(Debug, Default)]
+    pub(crate) struct FutureSize(u32);
+
+    impl FutureSize {
+        /// Records the size of `F`, saturating at `u32::MAX` bytes.
+        pub(crate) fn of<F>() -> Self {
+            Self(u32::try_from(std::mem::size_of::<F>()).unwrap_or(u32::MAX))
+        }
+
+        /// The recorded size, in bytes.
+        pub(crate) fn get(&self) -> Option<u32> {
+            Some(self.0)
+        }
+    }
+}
+
+#[cfg(not(feature = "future-sizes"))]
+mod enabled {
+    /// The (unrecorded) size of the future wrapped by a frame.
+    // (braced, so that it is constructed as the recording variant is)
+    #[derive(Debug, Default)]
+    pub(crate) struct FutureSize {}
+
+    impl FutureSize {
+        #[allow(clippy::extra_unused_type_parameters)]
+        pub(crate) fn of<F>() -> Self {
+            Self {}
+        }
+
+        pub(crate) fn get(&self) -> Option<u32> {
+            None
+        }
+    }
+}
+
+pub(crate) use enabled::FutureSize;
+
+/// Formats a number of `bytes` for humans; e.g., `512 B`, or `18.2 KiB`.
+pub(crate) struct Bytes(pub(crate) u32);
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const KIB: f64 = 1024.0;
+        const MIB: f64 = KIB * 1024.0;
+        let bytes = f64::from(self.0);
+        if bytes < KIB {
+            write!(f, "{} B", self.0)
+        } else if bytes < MIB {
+            write!(f, "{:.1} KiB", bytes / KIB)
+        } else {
+            write!(f, "{:.1} MiB", bytes / MIB)
+        }
+    }
+}
+
+/// Produces the locations of the (at most) `n` largest futures wrapped by the
+/// frames of all tasks, with their sizes (in bytes), largest first. Each
+/// location appears once, with the largest of the futures framed there.
+///
+/// Tasks are not waited for; of tasks that are being polled, only the root
+/// frame is considered.
+///
+/// Requires the `future-sizes` feature, which records the size of each
+/// framed future in its frame.
+///
+/// ## Example
+/// ```
+/// #[async_backtrace::framed]
+/// async fn serve() {
+///     let buffer = [0u8; 16384];
+///     std::future::pending::<()>().await;
+///     std::hint::black_box(&buffer);
+/// }
+///
+/// for (location, size) in async_backtrace::largest_futures(10) {
+///     println!("{}: {} bytes", location, size);
+/// }
+/// ```
+#[cfg(feature = "future-sizes")]
+pub fn largest_futures(n: usize) -> Vec<(crate::Location, usize)> {
+    use crate::{FrameTree, Location};
+    use std::collections::HashMap;
+
+    fn visit(frame: &FrameTree, sizes: &mut HashMap<Location, usize>) {
+        if let Some(size) = frame.future_size() {
+            let largest = sizes.entry(frame.location()).or_default();
+            *largest = (*largest).max(size);
+        }
+        for child in frame.children() {
+            visit(child, sizes);
+        }
+    }
+
+    let mut sizes = HashMap::new();
+    for tree in crate::snapshot(false) {
+        visit(tree.root(), &mut sizes);
+    }
+    let mut sizes: Vec<_> = sizes.into_iter().collect();
+    sizes.sort_by(|(a, a_size), (b, b_size)| b_size.cmp(a_size).then(a.cmp(b)));
+    sizes.truncate(n);
+    sizes
+}
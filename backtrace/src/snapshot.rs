@@ -0,0 +1,1423 @@
+//! Owned snapshots of the trees of tasks.
+//!
+//! Taskdumps copy the tree of each task into a [`TaskTree`] while its root is
+//! locked, and then format the copy after the lock is released. This bounds
+//! the time a dump holds any root lock by the number of frames in its tree,
+//! rather than by the cost of formatting (or writing) them.
+//!
+//! If [`RegistryConfig::cache_last_tree`](crate::RegistryConfig) is set, the
+//! last-known tree of each task is also kept here, so that non-blocking dumps
+//! can show it in place of the tree of a task that is being polled.
+//!
+//! The copies are also public, via [`snapshot`], for callers that inspect
+//! (or ship elsewhere) the state of tasks, rather than render it.
+
+use std::{fmt, hash::BuildHasherDefault, time::Instant};
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use rustc_hash::FxHasher;
+
+use crate::{tasks::Wait, AnnotationValue, Frame, Location, LocationStyle, Verbosity};
+
+/// The last-known subframes of each task, by task id.
+static LAST_TREES: Lazy<DashMap<u64, LastTree, BuildHasherDefault<FxHasher>>> =
+    Lazy::new(DashMap::default);
+
+/// The last-known subframes of a task.
+struct LastTree {
+    /// The subframes of the root of the task, without their recorded errors.
+    children: Vec<FrameTree>,
+    /// When the subframes were captured.
+    at: Instant,
+}
+
+/// Remembers the subframes of the root `frame`.
+///
+/// # Safety
+/// The caller must ensure that `frame` is a root, and is locked.
+pub(crate) unsafe fn remember(frame: &Frame) {
+    if let Some(id) = frame.task_id() {
+        let children = frame
+            .subframes()
+            .map(|subframe| FrameTree::capture_locations(subframe))
+            .collect();
+        remember_children(id, children);
+    }
+}
+
+fn remember_children(id: u64, children: Vec<FrameTree>) {
+    let at = Instant::now();
+    LAST_TREES.insert(id, LastTree { children, at });
+}
+
+/// Forgets the last-known tree of the task `id`.
+pub(crate) fn evict(id: u64) {
+    LAST_TREES.remove(&id);
+}
+
+/// Captures the trees of all tasks, as of a single instant.
+///
+/// Each tree is copied while its task is locked; the copies are owned, and
+/// hold no locks (nor references into the registry of tasks), so they may be
+/// filtered, sorted (e.g., deterministically, by [`TaskTree::cmp_by_root`])
+/// and rendered at leisure, or sent to another thread.
+///
+/// As in a [taskdump](crate::TaskdumpOptions::dump), the tasks are listed once,
+/// when the snapshot begins; tasks spawned while it is in progress are not
+/// captured, nor are tasks that exit before they are reached.
+///
+/// If `wait_for_running_tasks` is `false`, the subframes of tasks that are
+/// being polled are not captured; such trees are [polling](TaskTree::is_polling),
+/// and have only their roots. Otherwise, the capture waits for running tasks
+/// to become idle, and (as for [`taskdump_tree`](crate::taskdump_tree)) may
+/// deadlock if any non-async lock is held which may also be held by a Framed
+/// task.
+///
+/// ## Example
+/// ```
+/// # futures::executor::block_on(async_backtrace::location!().frame(async {
+/// let trees = async_backtrace::snapshot(false);
+/// std::thread::spawn(move || {
+///     for tree in trees.iter().filter(|tree| !tree.is_polling()) {
+///         println!("task {} at {}", tree.id(), tree.root().location());
+///     }
+/// })
+/// .join()
+/// .unwrap();
+/// # }));
+/// ```
+pub fn snapshot(wait_for_running_tasks: bool) -> Vec<TaskTree> {
+    let epoch = Instant::now();
+    let wait = Wait::from(wait_for_running_tasks);
+    // the tasks are found (and waited for) one by one, as in a taskdump
+    crate::tasks::snapshot_roots(|_| true)
+        .into_iter()
+        .filter_map(|(id, _)| crate::tasks::snapshot_by_id(id, wait, epoch))
+        .collect()
+}
+
+/// An owned snapshot of the tree of a task, as captured by [`snapshot`].
+///
+/// Its [`Display`](fmt::Display) renders it as in
+/// [`taskdump_tree`](crate::taskdump_tree).
+#[derive(Debug, Clone)]
+pub struct TaskTree {
+    /// The id of the task.
+    id: u64,
+    /// The root frame of the task.
+    root: FrameTree,
+    /// If the task's lock was unavailable (e.g., as it was being polled), and
+    /// so its subframes could not be captured, why.
+    polling: Option<Polling>,
+    /// If the task was being polled, its last-known subframes (if any), and
+    /// their age (in seconds) as of the snapshot's epoch.
+    last_known: Option<(u64, Vec<FrameTree>)>,
+    /// If the tree was [pruned](TaskTree::prune), the depth beyond which its
+    /// frames were removed.
+    max_depth: Option<usize>,
+    /// How the tree is rendered.
+    style: Style,
+}
+
+/// How a [`TaskTree`] is rendered.
+#[derive(Debug, Copy, Clone)]
+struct Style {
+    /// How much of the tree is rendered.
+    verbosity: Verbosity,
+    /// `true` if the sequence numbers of frames are rendered.
+    sequence_numbers: bool,
+    /// `true` if identical, adjacent siblings are consolidated.
+    consolidate: bool,
+    /// The most children rendered beneath each frame, if they are limited.
+    max_children: Option<usize>,
+    /// The number of columns by which each level of the tree is indented.
+    indent_width: usize,
+    /// The size (in bytes) from which the sizes of frames' futures are
+    /// rendered, if they are.
+    future_size_threshold: Option<usize>,
+    /// `true` if the annotations of frames are rendered.
+    annotations: bool,
+}
+
+impl Style {
+    /// Produces the style set by
+    /// [`set_default_dump_options`](crate::set_default_dump_options).
+    fn default() -> Self {
+        Self {
+            verbosity: crate::taskdump::default_verbosity(),
+            sequence_numbers: crate::taskdump::default_sequence_numbers(),
+            consolidate: crate::taskdump::default_consolidate(),
+            max_children: crate::taskdump::default_max_children(),
+            indent_width: crate::taskdump::default_indent_width(),
+            future_size_threshold: crate::taskdump::default_future_size_threshold(),
+            annotations: false,
+        }
+    }
+
+    /// Produces `true` if the adjacent siblings `a` and `b` are rendered as
+    /// one, consolidated frame.
+    fn consolidates(&self, a: &FrameTree, b: &FrameTree) -> bool {
+        self.consolidate && a.deep_eq(b, self)
+    }
+}
+
+/// Why the subframes of a [polling](TaskTree::is_polling) task could not be
+/// captured.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum Polling {
+    /// The lock of the task was unavailable. Without the `thread-report`
+    /// feature, that is all that is known.
+    #[cfg(not(feature = "thread-report"))]
+    Unknown,
+    /// The task was being polled on the named thread.
+    #[cfg(feature = "thread-report")]
+    On(String),
+    /// The lock of the task was unavailable, yet no thread was polling it;
+    /// e.g., as the lock was held by the dumping thread itself.
+    #[cfg(feature = "thread-report")]
+    Busy,
+}
+
+impl Polling {
+    /// Produces why the lock of the task `id` was unavailable.
+    pub(crate) fn of(id: u64) -> Self {
+        #[cfg(feature = "thread-report")]
+        return match crate::threads::polling_thread(id) {
+            Some(thread) => Self::On(thread),
+            None => Self::Busy,
+        };
+        #[cfg(not(feature = "thread-report"))]
+        {
+            let _ = id;
+            Self::Unknown
+        }
+    }
+
+    /// Produces the value of `lock` in JSON (and so its serialization), if
+    /// the reason is known: `polling` or `busy`.
+    fn lock(&self) -> Option<&'static str> {
+        match self {
+            #[cfg(not(feature = "thread-report"))]
+            Self::Unknown => None,
+            #[cfg(feature = "thread-report")]
+            Self::On(_) => Some("polling"),
+            #[cfg(feature = "thread-report")]
+            Self::Busy => Some("busy"),
+        }
+    }
+
+    /// Produces the thread on which the task was being polled, if it is
+    /// known.
+    fn thread(&self) -> Option<&str> {
+        match self {
+            #[cfg(feature = "thread-report")]
+            Self::On(thread) => Some(thread),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Polling {
+    /// Renders the reason as the marker of a polling task, without its
+    /// brackets; e.g., `POLLING on worker-1`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(not(feature = "thread-report"))]
+            Self::Unknown => f.write_str("POLLING"),
+            #[cfg(feature = "thread-report")]
+            Self::On(thread) => write!(f, "POLLING on {thread}"),
+            #[cfg(feature = "thread-report")]
+            Self::Busy => f.write_str("BUSY: lock unavailable"),
+        }
+    }
+}
+
+/// An owned snapshot of a frame, and its subframes, within a [`TaskTree`].
+#[derive(Debug, Clone)]
+pub struct FrameTree {
+    location: Location,
+    /// The rendering of the last error recorded for this frame's location
+    /// (if any), with its age as of the snapshot's epoch.
+    last_error: Option<String>,
+    /// `true` if the frame is a [detail](crate::Location::frame_detail).
+    detail: bool,
+    /// `true` if a poll of the frame's future panicked.
+    panicked: bool,
+    /// The sequence numbers of the frame's initialization and of its last
+    /// poll, if they are recorded.
+    init_seq: Option<u64>,
+    last_poll_seq: Option<u64>,
+    /// The size of the frame's future, in bytes, if it is recorded.
+    future_size: Option<u32>,
+    /// The number of frames beneath this one that were removed when its tree
+    /// was [pruned](TaskTree::prune).
+    omitted: usize,
+    /// The source line of the frame's location, if it has been
+    /// [attached](TaskTree::attach_sources).
+    source: Option<String>,
+    /// The [counters](crate::counters) of the frame's location, if they have
+    /// been [attached](TaskTree::attach_counters).
+    counters: Vec<(&'static str, u64)>,
+    /// The [annotations](crate::annotate) of the frame (but not those it
+    /// inherits).
+    annotations: Vec<(&'static str, AnnotationValue)>,
+    children: Vec<FrameTree>,
+}
+
+impl TaskTree {
+    /// Captures the tree rooted at `frame`, of the task `id`. If
+    /// `subframes_locked` is `false`, only the root is captured, and the task
+    /// is marked as polling.
+    ///
+    /// # Safety
+    /// If `subframes_locked` is `true`, the caller must ensure that the root
+    /// of `frame` is locked.
+    pub(crate) unsafe fn capture(
+        frame: &Frame,
+        id: u64,
+        subframes_locked: bool,
+        epoch: Instant,
+    ) -> Self {
+        Self {
+            id,
+            root: FrameTree::capture(frame, subframes_locked, epoch),
+            polling: (!subframes_locked).then(|| Polling::of(id)),
+            last_known: None,
+            max_depth: None,
+            style: Style::default(),
+        }
+    }
+
+    /// Produces the tree of the task `id`, with only its root, at `location`,
+    /// and without reading any of the state of its frames.
+    pub(crate) fn root_only(location: Location, id: u64) -> Self {
+        Self {
+            id,
+            root: FrameTree {
+                location,
+                last_error: None,
+                detail: false,
+                panicked: false,
+                init_seq: None,
+                last_poll_seq: None,
+                future_size: None,
+                omitted: 0,
+                source: None,
+                counters: Vec::new(),
+                annotations: Vec::new(),
+                children: Vec::new(),
+            },
+            polling: None,
+            last_known: None,
+            max_depth: Some(0),
+            style: Style::default(),
+        }
+    }
+
+    /// Captures the trees rooted at each subframe of `frame`.
+    ///
+    /// These trees are not of tasks, and their ids are 0, which no task has.
+    ///
+    /// # Safety
+    /// The caller must ensure that the root of `frame` is locked.
+    #[cfg(feature = "tokio")]
+    pub(crate) unsafe fn capture_subframes(frame: &Frame, epoch: Instant) -> Vec<Self> {
+        frame
+            .subframes()
+            .map(|subframe| Self {
+                id: 0,
+                root: FrameTree::capture(subframe, true, epoch),
+                polling: None,
+                last_known: None,
+                max_depth: None,
+                style: Style::default(),
+            })
+            .collect()
+    }
+
+    /// Removes the frames (including those of the last-known tree) that are
+    /// more than `max_depth` frames beneath the root.
+    ///
+    /// Each frame at `max_depth` notes the number of frames removed beneath
+    /// it, which renders as `└┈ … 37 more frames`.
+    pub(crate) fn prune(&mut self, max_depth: usize) {
+        fn prune(frame: &mut FrameTree, depth: usize) {
+            match depth.checked_sub(1) {
+                None => {
+                    frame.omitted = frame.children.iter().map(FrameTree::len).sum();
+                    frame.children.clear();
+                }
+                Some(depth) => frame
+                    .children
+                    .iter_mut()
+                    .for_each(|child| prune(child, depth)),
+            }
+        }
+
+        self.max_depth = Some(max_depth);
+        prune(&mut self.root, max_depth);
+        if let Some((_, children)) = &mut self.last_known {
+            match max_depth.checked_sub(1) {
+                None => children.clear(),
+                Some(depth) => children.iter_mut().for_each(|child| prune(child, depth)),
+            }
+        }
+    }
+
+    /// Attaches the source line produced by `source` to each frame of this
+    /// tree (including those of the last-known tree), to render beneath it.
+    pub(crate) fn attach_sources<F>(&mut self, source: &mut F)
+    where
+        F: FnMut(Location) -> Option<String>,
+    {
+        self.for_each_frame_mut(&mut |frame| frame.source = source(frame.location));
+    }
+
+    /// Attaches the [counters](crate::counters) of the location of each
+    /// frame, to be rendered beside it.
+    pub(crate) fn attach_counters(&mut self) {
+        self.for_each_frame_mut(&mut |frame| frame.counters = crate::counters::of(frame.location));
+    }
+
+    /// Invokes `f` on each frame of the tree, and of its last-known tree.
+    fn for_each_frame_mut<F>(&mut self, f: &mut F)
+    where
+        F: FnMut(&mut FrameTree),
+    {
+        fn visit<F>(frame: &mut FrameTree, f: &mut F)
+        where
+            F: FnMut(&mut FrameTree),
+        {
+            f(frame);
+            for child in &mut frame.children {
+                visit(child, f);
+            }
+        }
+
+        visit(&mut self.root, f);
+        for child in self
+            .last_known
+            .iter_mut()
+            .flat_map(|(_, children)| children)
+        {
+            visit(child, f);
+        }
+    }
+
+    /// Produces the number of frames of this tree, including those pruned
+    /// from it; of a polling tree, only the root is counted.
+    pub(crate) fn frames(&self) -> usize {
+        // (the root of a polling tree has no children)
+        self.root.len()
+    }
+
+    /// Sets how much of this tree is rendered.
+    pub(crate) fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.style.verbosity = verbosity;
+    }
+
+    /// Sets whether the annotations of frames are rendered.
+    pub(crate) fn set_annotations(&mut self, annotations: bool) {
+        self.style.annotations = annotations;
+    }
+
+    /// Sets whether identical, adjacent siblings are consolidated.
+    pub(crate) fn set_consolidate(&mut self, consolidate: bool) {
+        self.style.consolidate = consolidate;
+    }
+
+    /// Sets the most children rendered beneath each frame.
+    pub(crate) fn set_max_children(&mut self, max_children: Option<usize>) {
+        self.style.max_children = max_children;
+    }
+
+    /// Sets the number of columns by which each level of this tree is
+    /// indented, which must be at least one.
+    pub(crate) fn set_indent_width(&mut self, indent_width: usize) {
+        self.style.indent_width = indent_width;
+    }
+
+    /// Sets whether the sequence numbers of frames are rendered.
+    #[cfg(feature = "sequence-numbers")]
+    pub(crate) fn set_sequence_numbers(&mut self, sequence_numbers: bool) {
+        self.style.sequence_numbers = sequence_numbers;
+    }
+
+    /// Sets the size (in bytes) from which the sizes of frames' futures are
+    /// rendered.
+    #[cfg(feature = "future-sizes")]
+    pub(crate) fn set_future_size_threshold(&mut self, threshold: Option<usize>) {
+        self.style.future_size_threshold = threshold;
+    }
+
+    /// Orders trees by the locations of their roots, and then by their ids,
+    /// as [sorted](crate::TaskdumpOptions::sort_tasks) dumps order tasks;
+    /// e.g., `trees.sort_by(TaskTree::cmp_by_root)`.
+    pub fn cmp_by_root(&self, other: &TaskTree) -> std::cmp::Ordering {
+        crate::tasks::root_order(
+            (self.root.location, self.id),
+            (other.root.location, other.id),
+        )
+    }
+
+    /// The [id](crate::Task::id) of the task.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The root frame of the task.
+    pub fn root(&self) -> &FrameTree {
+        &self.root
+    }
+
+    /// Produces `true` if the task was being polled (and was not waited
+    /// for), in which case only its root frame was captured. This includes
+    /// tasks whose lock was unavailable for another reason, which the
+    /// `thread-report` feature tells apart (see `TaskTree::polling_thread`).
+    pub fn is_polling(&self) -> bool {
+        self.polling.is_some()
+    }
+
+    /// Produces the name (or, for an unnamed thread, the id) of the thread
+    /// that was polling the task, if it was [polling](TaskTree::is_polling)
+    /// and a thread was polling it.
+    ///
+    /// Requires the `thread-report` feature.
+    #[cfg(feature = "thread-report")]
+    pub fn polling_thread(&self) -> Option<&str> {
+        self.polling.as_ref().and_then(Polling::thread)
+    }
+
+    /// Remembers the subframes of this (complete) tree, as the last-known
+    /// tree of the task `id`.
+    pub(crate) fn remember(&self, id: u64) {
+        let children = self.root.children.iter().map(FrameTree::without_errors);
+        remember_children(id, children.collect());
+    }
+
+    /// Recalls the last-known tree of the task `id`, to show in place of the
+    /// subframes of this (polling) tree.
+    pub(crate) fn recall(&mut self, id: u64, epoch: Instant) {
+        self.last_known = LAST_TREES.get(&id).map(|last| {
+            let age = epoch.saturating_duration_since(last.at).as_secs();
+            (age, last.children.clone())
+        });
+    }
+}
+
+impl TaskTree {
+    /// Writes this tree on a single line, nesting frames with `>`, branching
+    /// with `{…, …}`, and marking consolidated copies of a frame with `xN`;
+    /// e.g., `root@a.rs:1 > {child@a.rs:5 x2, other@a.rs:9 > leaf@b.rs:3}`.
+    /// Collapsed chains of detail frames are noted with `(via N)`.
+    pub(crate) fn write_compact<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        fn write_frame<W: fmt::Write>(
+            w: &mut W,
+            frame: &FrameTree,
+            copies: usize,
+            style: Style,
+        ) -> fmt::Result {
+            let (frame, skipped) = frame.collapse(style.verbosity);
+            write!(w, "{}", frame.location.as_compact())?;
+            if frame.panicked {
+                w.write_str(" [panicked]")?;
+            }
+            if copies != 1 {
+                write!(w, " x{}", copies)?;
+            }
+            if skipped != 0 {
+                write!(w, " (via {})", skipped)?;
+            }
+            write_children(w, &frame.children, style)
+        }
+
+        fn write_children<W: fmt::Write>(
+            w: &mut W,
+            children: &[FrameTree],
+            style: Style,
+        ) -> fmt::Result {
+            // consolidate adjacent, identical subframes, as the tree does
+            match &FrameTree::consolidate(children, style)[..] {
+                [] => Ok(()),
+                [(frame, copies)] => {
+                    w.write_str(" > ")?;
+                    write_frame(w, frame, *copies, style)
+                }
+                groups => {
+                    w.write_str(" > {")?;
+                    for (i, (frame, copies)) in groups.iter().enumerate() {
+                        if i > 0 {
+                            w.write_str(", ")?;
+                        }
+                        write_frame(w, frame, *copies, style)?;
+                    }
+                    w.write_char('}')
+                }
+            }
+        }
+
+        write!(w, "{}", self.root.location.as_compact())?;
+        if self.root.panicked {
+            w.write_str(" [panicked]")?;
+        }
+        // (sequence numbers are not rendered, and so do not tell frames apart)
+        let style = Style {
+            sequence_numbers: false,
+            ..self.style
+        };
+        write_children(w, &self.root.children, style)?;
+        if let Some(polling) = &self.polling {
+            write!(w, " [{}]", polling)?;
+        }
+        Ok(())
+    }
+}
+
+impl TaskTree {
+    /// Writes this tree, as the tree of the task `id`, as a JSON object.
+    ///
+    /// Frames are consolidated, and chains of detail frames collapsed, as
+    /// they are when this tree is rendered as text; a consolidated frame has
+    /// a `count` greater than one, and a frame beneath collapsed details
+    /// notes their number in `helper_frames`.
+    pub(crate) fn write_json<W: fmt::Write>(&self, w: &mut W, id: u64) -> fmt::Result {
+        fn write_frame<W: fmt::Write>(
+            w: &mut W,
+            frame: &FrameTree,
+            count: usize,
+            depth: usize,
+            tree: &TaskTree,
+        ) -> fmt::Result {
+            let style = tree.style;
+            let json = JsonFrame::new(frame, depth, tree);
+            let (frame, depth) = (json.frame, json.depth);
+            w.write_str("{\"name\":")?;
+            write_json_string(w, json.name)?;
+            w.write_str(",\"file\":")?;
+            write_json_string(w, json.file)?;
+            w.write_str(",\"line\":")?;
+            write_json_number(w, json.line.map(u64::from))?;
+            w.write_str(",\"column\":")?;
+            write_json_number(w, json.column.map(u64::from))?;
+            write!(
+                w,
+                ",\"count\":{},\"helper_frames\":{},\"panicked\":{}",
+                count, json.helper_frames, frame.panicked,
+            )?;
+            w.write_str(",\"last_error\":")?;
+            write_json_string(w, frame.last_error.as_deref())?;
+            w.write_str(",\"leaf_state\":")?;
+            write_json_string(w, json.leaf_state)?;
+            if style.future_size_threshold.is_some() {
+                w.write_str(",\"future_size\":")?;
+                write_json_number(w, frame.future_size.map(u64::from))?;
+            }
+            if style.annotations {
+                w.write_str(",\"annotations\":{")?;
+                for (i, (key, value)) in frame.annotations.iter().enumerate() {
+                    if i > 0 {
+                        w.write_char(',')?;
+                    }
+                    write_json_string(w, Some(key))?;
+                    w.write_char(':')?;
+                    write_json_value(w, value)?;
+                }
+                w.write_char('}')?;
+            }
+            if style.sequence_numbers {
+                w.write_str(",\"init_seq\":")?;
+                write_json_number(w, frame.init_seq)?;
+                w.write_str(",\"last_poll_seq\":")?;
+                write_json_number(w, frame.last_poll_seq)?;
+            }
+            w.write_str(",\"children\":")?;
+            write_children(w, &frame.children, depth + 1, tree)?;
+            w.write_char('}')
+        }
+
+        fn write_children<W: fmt::Write>(
+            w: &mut W,
+            children: &[FrameTree],
+            depth: usize,
+            tree: &TaskTree,
+        ) -> fmt::Result {
+            w.write_char('[')?;
+            let groups = FrameTree::consolidate(children, tree.style);
+            for (i, (frame, count)) in groups.into_iter().enumerate() {
+                if i > 0 {
+                    w.write_char(',')?;
+                }
+                write_frame(w, frame, count, depth, tree)?;
+            }
+            w.write_char(']')
+        }
+
+        write!(w, "{{\"id\":{},\"polling\":{},", id, self.is_polling())?;
+        if let Some(lock) = self.polling.as_ref().and_then(Polling::lock) {
+            write!(w, "\"lock\":\"{}\",", lock)?;
+        }
+        if let Some(thread) = self.polling.as_ref().and_then(Polling::thread) {
+            w.write_str("\"thread\":")?;
+            write_json_string(w, Some(thread))?;
+            w.write_char(',')?;
+        }
+        w.write_str("\"root\":")?;
+        write_frame(w, &self.root, 1, 0, self)?;
+        w.write_str(",\"last_known\":")?;
+        match &self.last_known {
+            Some((age, children)) => {
+                write!(w, "{{\"age_secs\":{},\"children\":", age)?;
+                write_children(w, children, 1, self)?;
+                w.write_char('}')?;
+            }
+            None => w.write_str("null")?,
+        }
+        w.write_char('}')
+    }
+}
+
+/// The fields of a frame, as it is rendered in JSON, after collapsing the
+/// chain of [detail](crate::Location::frame_detail) frames above it (if any).
+struct JsonFrame<'a> {
+    /// The frame beneath the collapsed chain.
+    frame: &'a FrameTree,
+    /// The depth of `frame` within its tree.
+    depth: usize,
+    /// The number of frames in the collapsed chain.
+    helper_frames: usize,
+    /// The parts of the frame's location, or `None` for those that the
+    /// [style](crate::set_location_style) of the process omits.
+    name: Option<&'a str>,
+    file: Option<&'a str>,
+    line: Option<u32>,
+    column: Option<u32>,
+    /// The [classification](crate::classify) of the frame, if it is a leaf.
+    leaf_state: Option<&'static str>,
+}
+
+impl<'a> JsonFrame<'a> {
+    /// Collapses `frame`, at `depth` within `tree`.
+    fn new(frame: &'a FrameTree, depth: usize, tree: &TaskTree) -> Self {
+        let (frame, helper_frames) = frame.collapse(tree.style.verbosity);
+        let depth = depth + helper_frames;
+        let location = &frame.location;
+        let (position, column) = match crate::location_style() {
+            LocationStyle::FileLineCol => (true, true),
+            LocationStyle::FileLine => (true, false),
+            LocationStyle::NameOnly => (location.name().is_none(), false),
+        };
+        // the root of a polling tree, and the frames at the depth at which
+        // a tree was pruned, may have children that were not captured
+        let leaf = frame.children.is_empty()
+            && !(depth == 0 && tree.is_polling())
+            && !matches!(tree.max_depth, Some(max_depth) if depth >= max_depth);
+        Self {
+            frame,
+            depth,
+            helper_frames,
+            name: location.name(),
+            file: Some(location.file()).filter(|_| position),
+            line: Some(location.line()).filter(|_| position),
+            column: Some(location.column()).filter(|_| column),
+            leaf_state: Some(*location)
+                .filter(|_| leaf)
+                .map(|location| crate::classify::classify(location).as_str()),
+        }
+    }
+}
+
+/// Writes `s` as a JSON string, or `null`.
+fn write_json_string<W: fmt::Write>(w: &mut W, s: Option<&str>) -> fmt::Result {
+    let s = match s {
+        Some(s) => s,
+        None => return w.write_str("null"),
+    };
+    w.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => w.write_str("\\\"")?,
+            '\\' => w.write_str("\\\\")?,
+            '\n' => w.write_str("\\n")?,
+            '\r' => w.write_str("\\r")?,
+            '\t' => w.write_str("\\t")?,
+            c if c.is_control() => write!(w, "\\u{:04x}", c as u32)?,
+            c => w.write_char(c)?,
+        }
+    }
+    w.write_char('"')
+}
+
+/// Writes `value` as the JSON value of its type.
+fn write_json_value<W: fmt::Write>(w: &mut W, value: &AnnotationValue) -> fmt::Result {
+    match value {
+        AnnotationValue::I64(n) => write!(w, "{}", n),
+        AnnotationValue::U64(n) => write!(w, "{}", n),
+        AnnotationValue::Bool(b) => write!(w, "{}", b),
+        _ => write_json_string(w, value.as_str()),
+    }
+}
+
+/// Writes `n` as a JSON number, or `null`.
+fn write_json_number<W: fmt::Write>(w: &mut W, n: Option<u64>) -> fmt::Result {
+    match n {
+        Some(n) => write!(w, "{}", n),
+        None => w.write_str("null"),
+    }
+}
+
+impl FrameTree {
+    /// The location of the frame.
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    /// The subframes of the frame, in the order in which they are rendered
+    /// by taskdumps.
+    pub fn children(&self) -> &[FrameTree] {
+        &self.children
+    }
+
+    /// The last [error recorded](crate::framed#arguments) for this frame's
+    /// location, with its age as of the snapshot; e.g., `last error 3s ago:
+    /// connection reset`.
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// Produces `true` if a poll of the frame's future panicked.
+    pub fn has_panicked(&self) -> bool {
+        self.panicked
+    }
+
+    /// The size of the frame's future, in bytes, if it is recorded (with the
+    /// `future-sizes` feature).
+    pub fn future_size(&self) -> Option<usize> {
+        self.future_size.map(|size| size as usize)
+    }
+
+    /// The [annotations](crate::annotate_value) of the frame, in the order in
+    /// which they were first attached; not those it inherits. Those of the
+    /// root of a polling tree are not captured.
+    pub fn annotations(&self) -> &[(&'static str, AnnotationValue)] {
+        &self.annotations
+    }
+}
+
+impl FrameTree {
+    /// # Safety
+    /// If `subframes_locked` is `true`, the caller must ensure that the root
+    /// of `frame` is locked.
+    unsafe fn capture(frame: &Frame, subframes_locked: bool, epoch: Instant) -> Self {
+        let location = frame.location();
+        let last_error = frame.parent().and_then(|parent| {
+            parent.with_metadata(|metadata| metadata.last_error(location, epoch))
+        });
+        let children = if subframes_locked {
+            frame
+                .subframes()
+                .map(|subframe| FrameTree::capture(subframe, true, epoch))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        // (the metadata of a polling root cannot be read)
+        let annotations = if subframes_locked {
+            frame.annotations()
+        } else {
+            Vec::new()
+        };
+        Self {
+            location,
+            last_error,
+            detail: frame.is_detail(),
+            // (the metadata of a polling root cannot be read)
+            panicked: subframes_locked && frame.has_panicked(),
+            init_seq: frame.sequence().init(),
+            last_poll_seq: frame.sequence().last_poll(),
+            future_size: frame.future_size(),
+            omitted: 0,
+            source: None,
+            counters: Vec::new(),
+            annotations,
+            children,
+        }
+    }
+
+    /// Captures `frame` and its subframes, without their recorded errors.
+    ///
+    /// # Safety
+    /// The caller must ensure that the root of `frame` is locked.
+    unsafe fn capture_locations(frame: &Frame) -> Self {
+        Self {
+            location: frame.location(),
+            last_error: None,
+            detail: frame.is_detail(),
+            panicked: frame.has_panicked(),
+            init_seq: frame.sequence().init(),
+            last_poll_seq: frame.sequence().last_poll(),
+            future_size: frame.future_size(),
+            omitted: 0,
+            source: None,
+            counters: Vec::new(),
+            annotations: frame.annotations(),
+            children: frame
+                .subframes()
+                .map(|subframe| Self::capture_locations(subframe))
+                .collect(),
+        }
+    }
+
+    /// Produces a copy of this tree, without its recorded errors.
+    fn without_errors(&self) -> Self {
+        Self {
+            location: self.location,
+            last_error: None,
+            detail: self.detail,
+            panicked: self.panicked,
+            init_seq: self.init_seq,
+            last_poll_seq: self.last_poll_seq,
+            future_size: self.future_size,
+            omitted: self.omitted,
+            source: None,
+            counters: Vec::new(),
+            annotations: self.annotations.clone(),
+            children: self.children.iter().map(Self::without_errors).collect(),
+        }
+    }
+
+    /// Produces the frame to render in place of this one, and the number of
+    /// frames skipped to reach it.
+    ///
+    /// If `verbosity` is [`Verbosity::Normal`], chains of detail frames that
+    /// each have a single child are skipped.
+    fn collapse(&self, verbosity: Verbosity) -> (&Self, usize) {
+        let mut frame = self;
+        let mut skipped = 0;
+        if verbosity == Verbosity::Normal {
+            while let (true, [child]) = (frame.detail, &frame.children[..]) {
+                frame = child;
+                skipped += 1;
+            }
+        }
+        (frame, skipped)
+    }
+
+    /// Produces the number of frames in the tree rooted at this frame
+    /// (including those already pruned from beneath it).
+    fn len(&self) -> usize {
+        1 + self.omitted + self.children.iter().map(Self::len).sum::<usize>()
+    }
+
+    /// Groups adjacent, identical (as by [`deep_eq`](Self::deep_eq)) frames
+    /// of `children`, each with its number of copies, if `style` consolidates
+    /// them.
+    fn consolidate(children: &[FrameTree], style: Style) -> Vec<(&FrameTree, usize)> {
+        let mut groups: Vec<(&FrameTree, usize)> = Vec::new();
+        for child in children {
+            match groups.last_mut() {
+                Some((frame, copies)) if style.consolidates(frame, child) => *copies += 1,
+                _ => groups.push((child, 1)),
+            }
+        }
+        groups
+    }
+
+    /// Produces `true` if `self` and `other` have the same locations,
+    /// markers, last errors, counters, sources and numbers of pruned frames
+    /// (and the same sequence numbers, future sizes and annotations, if
+    /// `style` renders them), in the same shape.
+    fn deep_eq(&self, other: &FrameTree, style: &Style) -> bool {
+        self.location == other.location
+            && self.last_error == other.last_error
+            && self.counters == other.counters
+            && self.source == other.source
+            && (style.future_size_threshold.is_none() || self.future_size == other.future_size)
+            && self.panicked == other.panicked
+            && self.omitted == other.omitted
+            && (!style.sequence_numbers
+                || (self.init_seq, self.last_poll_seq) == (other.init_seq, other.last_poll_seq))
+            && (!style.annotations || self.annotations == other.annotations)
+            && self.children.len() == other.children.len()
+            && self
+                .children
+                .iter()
+                .zip(&other.children)
+                .all(|(a, b)| a.deep_eq(b, style))
+    }
+}
+
+impl fmt::Display for TaskTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        /// Writes `prefix` followed by `line`, less their first
+        /// `style.indent_width` codepoints.
+        fn write_indent(
+            f: &mut fmt::Formatter<'_>,
+            style: Style,
+            prefix: &str,
+            line: &str,
+        ) -> fmt::Result {
+            let mut skip = style.indent_width;
+            for part in [prefix, line] {
+                match part.char_indices().nth(skip) {
+                    Some((start, _)) => {
+                        f.write_str(&part[start..])?;
+                        skip = 0;
+                    }
+                    None => skip -= part.chars().count(),
+                }
+            }
+            Ok(())
+        }
+
+        fn fmt_helper(
+            f: &mut fmt::Formatter<'_>,
+            frame: &FrameTree,
+            is_last: bool,
+            prefix: &mut String,
+            copies: usize,
+            skipped: usize,
+            style: Style,
+        ) -> fmt::Result {
+            write_indent(f, style, prefix, if is_last { "└╼ " } else { "├╼ " })?;
+            if copies != 1 {
+                write!(f, "{copies}x ")?;
+            }
+            write!(f, "{}", frame.location)?;
+            if style.annotations {
+                crate::metadata::write_annotations(f, &frame.annotations)?;
+            }
+            for (key, value) in &frame.counters {
+                write!(f, " [{key}={value}]")?;
+            }
+            if let Some(error) = &frame.last_error {
+                write!(f, " [{error}]")?;
+            }
+            if frame.panicked {
+                f.write_str(" [panicked]")?;
+            }
+            if let (Some(threshold), Some(size)) = (style.future_size_threshold, frame.future_size)
+            {
+                if size as usize >= threshold {
+                    write!(f, " [future: {}]", crate::size::Bytes(size))?;
+                }
+            }
+            if let (true, Some(init)) = (style.sequence_numbers, frame.init_seq) {
+                write!(f, " [init_seq={init}")?;
+                if let Some(last_poll) = frame.last_poll_seq {
+                    write!(f, " last_poll_seq={last_poll}")?;
+                }
+                f.write_str("]")?;
+            }
+            match skipped {
+                0 => {}
+                1 => f.write_str(" (via 1 helper frame)")?,
+                n => write!(f, " (via {n} helper frames)")?,
+            }
+
+            // the prefix of the subframes (and annotations) of this frame
+            let len = prefix.len();
+            prefix.push(if is_last { ' ' } else { '│' });
+            for _ in 1..style.indent_width {
+                prefix.push(' ');
+            }
+
+            if let Some(source) = &frame.source {
+                f.write_str("\n")?;
+                write_indent(f, style, prefix, "│ source: ")?;
+                crate::location::write_sanitized(f, source)?;
+            }
+
+            match frame.omitted {
+                0 => {}
+                1 => {
+                    f.write_str("\n")?;
+                    write_indent(f, style, prefix, "└┈ … 1 more frame")?;
+                }
+                n => {
+                    f.write_str("\n")?;
+                    write_indent(f, style, prefix, "└┈ … ")?;
+                    write!(f, "{n} more frames")?;
+                }
+            }
+
+            fmt_children(f, &frame.children, prefix, style)?;
+            prefix.truncate(len);
+            Ok(())
+        }
+
+        fn fmt_children(
+            f: &mut fmt::Formatter<'_>,
+            children: &[FrameTree],
+            prefix: &mut String,
+            style: Style,
+        ) -> fmt::Result {
+            let mut subframes = children.iter().peekable();
+            let mut copies = 1;
+            let mut rendered = 0;
+            while let Some(subframe) = subframes.next() {
+                if subframes
+                    .peek()
+                    .map(|next| style.consolidates(next, subframe))
+                    .unwrap_or(false)
+                {
+                    copies += 1;
+                } else if style.max_children == Some(rendered) {
+                    // (the copies of this frame, and all its later siblings)
+                    let rest = copies + subframes.count();
+                    writeln!(f)?;
+                    write_indent(f, style, prefix, "└╼ … ")?;
+                    return match rest {
+                        1 => f.write_str("and 1 more child"),
+                        n => write!(f, "and {n} more children"),
+                    };
+                } else {
+                    rendered += 1;
+                    writeln!(f)?;
+                    let is_last = subframes.peek().is_none();
+                    let (subframe, skipped) = subframe.collapse(style.verbosity);
+                    fmt_helper(f, subframe, is_last, prefix, copies, skipped, style)?;
+                    copies = 1;
+                }
+            }
+            Ok(())
+        }
+
+        // the prefix of the root, such that the line of each frame begins
+        // with its connector, indented by `indent_width` per level
+        let width = self.style.indent_width;
+        let mut prefix = " ".repeat(width - 1);
+        fmt_helper(f, &self.root, true, &mut prefix, 1, 0, self.style)?;
+
+        if let Some(polling) = &self.polling {
+            // the prefix of the subframes of the root
+            prefix.push_str(&" ".repeat(width));
+            writeln!(f)?;
+            if let Some((age, children)) = &self.last_known {
+                write_indent(f, self.style, &prefix, "├┈ ")?;
+                write!(
+                    f,
+                    "[{polling}] last known tree, {age}s old (possibly stale):"
+                )?;
+                fmt_children(f, children, &mut prefix, self.style)?;
+            } else {
+                write_indent(f, self.style, &prefix, "└┈ ")?;
+                write!(f, "[{polling}]")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Serializes the tree as the object of its task in
+/// [`dump_json`](crate::TaskdumpOptions::dump_json), with the same schema (and
+/// the same consolidation and collapsing of frames); deserialize it as an
+/// [`OwnedTaskTree`].
+///
+/// Requires the `serde` feature.
+///
+/// ## Example
+/// ```
+/// # futures::executor::block_on(async_backtrace::location!().frame(async {
+/// for tree in async_backtrace::snapshot(false) {
+///     println!("{}", serde_json::to_string(&tree).unwrap());
+/// }
+/// # }));
+/// ```
+#[cfg(feature = "serde")]
+impl serde::Serialize for TaskTree {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut task = serializer.serialize_struct("TaskTree", 4)?;
+        task.serialize_field("id", &self.id)?;
+        task.serialize_field("polling", &self.is_polling())?;
+        if let Some(lock) = self.polling.as_ref().and_then(Polling::lock) {
+            task.serialize_field("lock", lock)?;
+        }
+        if let Some(thread) = self.polling.as_ref().and_then(Polling::thread) {
+            task.serialize_field("thread", thread)?;
+        }
+        let root = SerializeFrame {
+            frame: &self.root,
+            count: 1,
+            depth: 0,
+            tree: self,
+        };
+        task.serialize_field("root", &root)?;
+        let last_known = self
+            .last_known
+            .as_ref()
+            .map(|(age_secs, children)| SerializeLastKnown {
+                age_secs: *age_secs,
+                children: SerializeChildren {
+                    children,
+                    depth: 1,
+                    tree: self,
+                },
+            });
+        task.serialize_field("last_known", &last_known)?;
+        task.end()
+    }
+}
+
+/// A frame of a [`TaskTree`], and the number of identical siblings it stands
+/// for, as serialized.
+#[cfg(feature = "serde")]
+struct SerializeFrame<'a> {
+    frame: &'a FrameTree,
+    count: usize,
+    depth: usize,
+    tree: &'a TaskTree,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SerializeFrame<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let style = self.tree.style;
+        let json = JsonFrame::new(self.frame, self.depth, self.tree);
+        let frame = json.frame;
+        let future_size = style.future_size_threshold.is_some();
+        let fields = 10
+            + usize::from(future_size)
+            + usize::from(style.annotations)
+            + 2 * usize::from(style.sequence_numbers);
+        let mut s = serializer.serialize_struct("FrameTree", fields)?;
+        s.serialize_field("name", &json.name)?;
+        s.serialize_field("file", &json.file)?;
+        s.serialize_field("line", &json.line)?;
+        s.serialize_field("column", &json.column)?;
+        s.serialize_field("count", &self.count)?;
+        s.serialize_field("helper_frames", &json.helper_frames)?;
+        s.serialize_field("panicked", &frame.panicked)?;
+        s.serialize_field("last_error", &frame.last_error)?;
+        s.serialize_field("leaf_state", &json.leaf_state)?;
+        if future_size {
+            s.serialize_field("future_size", &frame.future_size)?;
+        }
+        if style.annotations {
+            s.serialize_field("annotations", &AnnotationMap(&frame.annotations))?;
+        }
+        if style.sequence_numbers {
+            s.serialize_field("init_seq", &frame.init_seq)?;
+            s.serialize_field("last_poll_seq", &frame.last_poll_seq)?;
+        }
+        let children = SerializeChildren {
+            children: &frame.children,
+            depth: json.depth + 1,
+            tree: self.tree,
+        };
+        s.serialize_field("children", &children)?;
+        s.end()
+    }
+}
+
+/// The subframes of a frame of a [`TaskTree`], consolidated, as serialized.
+#[cfg(feature = "serde")]
+struct SerializeChildren<'a> {
+    children: &'a [FrameTree],
+    depth: usize,
+    tree: &'a TaskTree,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SerializeChildren<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let groups = FrameTree::consolidate(self.children, self.tree.style);
+        serializer.collect_seq(groups.into_iter().map(|(frame, count)| SerializeFrame {
+            frame,
+            count,
+            depth: self.depth,
+            tree: self.tree,
+        }))
+    }
+}
+
+/// The last-known subframes of a polling [`TaskTree`], as serialized.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct SerializeLastKnown<'a> {
+    age_secs: u64,
+    children: SerializeChildren<'a>,
+}
+
+/// Annotations, serialized as a map, in order.
+#[cfg(feature = "serde")]
+struct AnnotationMap<'a, K>(&'a [(K, AnnotationValue)]);
+
+#[cfg(feature = "serde")]
+impl<K: AsRef<str>> serde::Serialize for AnnotationMap<'_, K> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(key, value)| (key.as_ref(), value)))
+    }
+}
+
+/// An owned copy of a serialized [`TaskTree`], into which they are
+/// deserialized; e.g., for the offline analysis of taskdumps. Its fields are
+/// those of the tasks of [`dump_json`](crate::TaskdumpOptions::dump_json),
+/// which it also deserializes.
+///
+/// Requires the `serde` feature.
+///
+/// ## Example
+/// ```
+/// use async_backtrace::OwnedTaskTree;
+///
+/// # futures::executor::block_on(async_backtrace::location!().frame(async {
+/// for tree in async_backtrace::snapshot(false) {
+///     let json = serde_json::to_string(&tree).unwrap();
+///     let owned: OwnedTaskTree = serde_json::from_str(&json).unwrap();
+///     assert_eq!(owned.id, tree.id());
+///     assert_eq!(serde_json::to_string(&owned).unwrap(), json);
+/// }
+/// # }));
+/// ```
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OwnedTaskTree {
+    /// The id of the task.
+    pub id: u64,
+    /// `true` if the task was being polled, and so only its root was
+    /// captured.
+    pub polling: bool,
+    /// If the task was being polled, and the `thread-report` feature is
+    /// enabled, `polling` if a thread was polling it, or else `busy`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock: Option<String>,
+    /// The thread that was polling the task, if `lock` is `polling`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread: Option<String>,
+    /// The root frame of the task.
+    pub root: OwnedFrameTree,
+    /// If the task was being polled, its last-known subframes (if any).
+    pub last_known: Option<OwnedLastKnown>,
+}
+
+/// The last-known subframes of a polling [`OwnedTaskTree`].
+///
+/// Requires the `serde` feature.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OwnedLastKnown {
+    /// The age of the subframes, in seconds, as of the snapshot.
+    pub age_secs: u64,
+    /// The subframes of the root.
+    pub children: Vec<OwnedFrameTree>,
+}
+
+/// An owned copy of a serialized [`FrameTree`], within an [`OwnedTaskTree`].
+///
+/// The fields that a tree is only serialized with if it renders them (e.g.,
+/// `future_size`) are `None` if they are absent, and `Some(None)` if they are
+/// `null`.
+///
+/// Requires the `serde` feature.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OwnedFrameTree {
+    /// The name of the frame's location, if it has one.
+    pub name: Option<String>,
+    /// The file of the frame's location, unless the
+    /// [style](crate::set_location_style) of the process omitted it.
+    pub file: Option<String>,
+    /// The line of the frame's location, unless the style omitted it.
+    pub line: Option<u32>,
+    /// The column of the frame's location, unless the style omitted it.
+    pub column: Option<u32>,
+    /// The number of identical, adjacent siblings this frame stands for.
+    pub count: usize,
+    /// The number of [detail](crate::Location::frame_detail) frames collapsed
+    /// above this one.
+    pub helper_frames: usize,
+    /// `true` if a poll of the frame's future panicked.
+    pub panicked: bool,
+    /// The last error recorded for the frame's location, with its age.
+    pub last_error: Option<String>,
+    /// The [classification](crate::classify) of the frame, if it is a leaf.
+    pub leaf_state: Option<String>,
+    /// The size of the frame's future, in bytes.
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub future_size: Option<Option<u64>>,
+    /// The [annotations](crate::annotate_value) of the frame, in order.
+    #[serde(
+        default,
+        with = "annotation_map",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub annotations: Option<Vec<(String, AnnotationValue)>>,
+    /// The sequence number of the frame's initialization.
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub init_seq: Option<Option<u64>>,
+    /// The sequence number of the frame's last poll.
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_poll_seq: Option<Option<u64>>,
+    /// The subframes of the frame.
+    pub children: Vec<OwnedFrameTree>,
+}
+
+/// Deserializes a field that is present (even if it is `null`) as `Some`.
+#[cfg(feature = "serde")]
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// (De)serializes annotations as a map, preserving their order.
+#[cfg(feature = "serde")]
+mod annotation_map {
+    use super::AnnotationMap;
+    use crate::AnnotationValue;
+    use serde::{Deserializer, Serialize, Serializer};
+
+    type Annotations = Vec<(String, AnnotationValue)>;
+
+    pub(super) fn serialize<S: Serializer>(
+        annotations: &Option<Annotations>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        AnnotationMap(annotations.as_deref().unwrap_or_default()).serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Annotations>, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Annotations;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("a map of annotations")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut annotations = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    annotations.push(entry);
+                }
+                Ok(annotations)
+            }
+        }
+
+        deserializer.deserialize_map(Visitor).map(Some)
+    }
+}
@@ -0,0 +1,115 @@
+//! The source lines of frames, for taskdumps rendered with
+//! [`TaskdumpOptions::source_snippets`](crate::TaskdumpOptions::source_snippets).
+
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::Location;
+
+/// The number of files whose lines are cached.
+const CACHE_CAPACITY: usize = 16;
+
+/// The number of characters of a line beyond which it is truncated.
+const MAX_SNIPPET_CHARS: usize = 100;
+
+/// The lines of the most recently read files (or `None`, if a file could not
+/// be read), most recently used first.
+static CACHE: Lazy<Mutex<Cache>> = Lazy::new(Default::default);
+
+/// The cached lines of files, by their paths.
+type Cache = VecDeque<(PathBuf, Option<Lines>)>;
+
+/// The lines of a file.
+type Lines = Arc<[String]>;
+
+/// Where taskdumps find the source files of frames, to render the line of
+/// each frame beneath it; see
+/// [`TaskdumpOptions::source_snippets`](crate::TaskdumpOptions::source_snippets).
+///
+/// The file of a location (as by [`file!()`](core::file)) is resolved
+/// relative to the working directory of the process, unless it is
+/// [remapped](Self::remap_path_prefix).
+#[derive(Debug, Clone, Default)]
+pub struct SourceSnippets {
+    remaps: Vec<(PathBuf, PathBuf)>,
+}
+
+impl SourceSnippets {
+    /// Produces the default configuration, which resolves files relative to
+    /// the working directory of the process.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolves the files of locations beginning with `from` to those
+    /// beginning with `to` instead; e.g., to find the sources of a binary
+    /// built in another directory (or with `--remap-path-prefix`), or the
+    /// sources of a workspace member from another member. The first
+    /// matching prefix is used.
+    ///
+    /// ## Example
+    /// ```
+    /// use async_backtrace::SourceSnippets;
+    ///
+    /// // `file!()` is relative to the root of the workspace
+    /// let snippets = SourceSnippets::new().remap_path_prefix("", "..");
+    /// ```
+    pub fn remap_path_prefix(mut self, from: impl Into<PathBuf>, to: impl Into<PathBuf>) -> Self {
+        self.remaps.push((from.into(), to.into()));
+        self
+    }
+
+    /// Produces the (trimmed) line of `location`, or `None` if its file
+    /// cannot be read, or has no such (non-blank) line.
+    pub(crate) fn line(&self, location: Location) -> Option<String> {
+        let index = (location.line() as usize).checked_sub(1)?;
+        let lines = lines(&self.resolve(location.file()))?;
+        let line = lines.get(index)?.trim();
+        if line.is_empty() {
+            return None;
+        }
+        Some(match line.char_indices().nth(MAX_SNIPPET_CHARS) {
+            Some((end, _)) => format!("{}…", &line[..end]),
+            None => line.to_string(),
+        })
+    }
+
+    /// Produces the path of `file`, as remapped.
+    fn resolve(&self, file: &str) -> PathBuf {
+        let file = Path::new(file);
+        self.remaps
+            .iter()
+            .find_map(|(from, to)| Some(to.join(file.strip_prefix(from).ok()?)))
+            .unwrap_or_else(|| file.to_path_buf())
+    }
+}
+
+/// Produces the lines of the file at `path`, from the cache if they are
+/// there.
+///
+/// Files are read once, while they remain in the cache; files that cannot be
+/// read are remembered as such.
+fn lines(path: &Path) -> Option<Lines> {
+    {
+        let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(index) = cache.iter().position(|(cached, _)| cached == path) {
+            let entry = cache.remove(index)?;
+            let lines = entry.1.clone();
+            cache.push_front(entry);
+            return lines;
+        }
+    }
+    // (the file is read without holding the cache's lock)
+    let lines: Option<Lines> = std::fs::read_to_string(path)
+        .ok()
+        .map(|source| source.lines().map(str::to_string).collect());
+    let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    cache.push_front((path.to_path_buf(), lines.clone()));
+    cache.truncate(CACHE_CAPACITY);
+    lines
+}
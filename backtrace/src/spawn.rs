@@ -0,0 +1,134 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use dashmap::DashMap;
+use futures::channel::oneshot;
+use once_cell::sync::Lazy;
+use pin_project_lite::pin_project;
+use tokio::task::{AbortHandle, JoinHandle};
+
+use crate::{Framed, Location};
+
+/// The location of the frame that roots the tasks spawned by
+/// [`spawn_framed_abortable`].
+const LOCATION: Location = Location::from_components(
+    "async_backtrace::spawn_framed_abortable",
+    &(file!(), line!(), column!()),
+);
+
+/// The abort handles of the tasks spawned by [`spawn_framed_abortable`],
+/// keyed by task id.
+static ABORT_HANDLES: Lazy<DashMap<u64, AbortHandle>> = Lazy::new(DashMap::new);
+
+/// Spawns `future` onto the current tokio runtime, within a frame named
+/// `async_backtrace::spawn_framed_abortable`, such that it may be cancelled by
+/// [`abort_task`], by the [id](crate::Task::id) with which it appears in
+/// taskdumps.
+///
+/// The task becomes abortable once its frame has been registered as a task;
+/// i.e., upon its first poll. Its abort handle is forgotten once its frame is
+/// deregistered.
+///
+/// Requires the `tokio` feature.
+///
+/// ## Example
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// #[async_backtrace::framed]
+/// async fn serve() {
+///     std::future::pending::<()>().await
+/// }
+///
+/// let handle = async_backtrace::spawn_framed_abortable(serve());
+/// tokio::task::yield_now().await;
+///
+/// for id in async_backtrace::tasks_snapshot_ids() {
+///     async_backtrace::abort_task(id);
+/// }
+/// assert!(handle.await.unwrap_err().is_cancelled());
+/// # }
+/// ```
+///
+/// ## Panics
+/// Panics if called outside of a tokio runtime.
+#[track_caller]
+pub fn spawn_framed_abortable<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    let handle = tokio::spawn(Abortable {
+        future: Framed::new(future, LOCATION),
+        receiver: Some(receiver),
+        abort: None,
+    });
+    let _ = sender.send(handle.abort_handle());
+    handle
+}
+
+/// Aborts the task with the given `id`, if it was spawned by
+/// [`spawn_framed_abortable`] and has not yet been deregistered.
+///
+/// Produces `true` if the task was aborted. Like
+/// [`JoinHandle::abort`](tokio::task::JoinHandle::abort), the task is
+/// cancelled upon its next yield, if it is running; until then, it remains in
+/// taskdumps.
+///
+/// Requires the `tokio` feature.
+pub fn abort_task(id: u64) -> bool {
+    match ABORT_HANDLES.remove(&id) {
+        Some((_, handle)) => {
+            handle.abort();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Forgets the abort handle of the task with the given `id`, if any.
+pub(crate) fn forget(id: u64) {
+    ABORT_HANDLES.remove(&id);
+}
+
+pin_project! {
+    /// A future that records the abort handle of the task in which it is
+    /// spawned, once its frame has been registered as a task.
+    struct Abortable<F> {
+        #[pin]
+        future: Framed<F>,
+        // Receives the abort handle of the task from its spawner; the future
+        // is not polled until it has.
+        receiver: Option<oneshot::Receiver<AbortHandle>>,
+        // The abort handle of the task, until it has been recorded.
+        abort: Option<AbortHandle>,
+    }
+}
+
+impl<F: Future> Future for Abortable<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        // The task may be polled (on another thread) before its spawner has
+        // its handle.
+        if let Some(receiver) = this.receiver {
+            *this.abort = match Pin::new(receiver).poll(cx) {
+                Poll::Ready(abort) => abort.ok(),
+                Poll::Pending => return Poll::Pending,
+            };
+            *this.receiver = None;
+        }
+        let poll = this.future.as_mut().poll(cx);
+        // The frame is registered upon the first poll in which it is
+        // initialized, and deregistered only once it is dropped, after this.
+        if let Some(id) = this.future.as_ref().task_id() {
+            if let Some(abort) = this.abort.take() {
+                ABORT_HANDLES.insert(id, abort);
+            }
+        }
+        poll
+    }
+}
@@ -0,0 +1,1256 @@
+use std::fmt::{self, Write};
+use std::io;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::coalesce;
+use crate::location::Location;
+use crate::snapshot::TaskTree;
+use crate::source::SourceSnippets;
+use crate::tasks;
+use crate::tasks::Wait;
+use once_cell::sync::OnceCell;
+
+/// The default number of tasks dumped between invocations of a
+/// [progress](TaskdumpOptions::progress) callback.
+const DEFAULT_PROGRESS_INTERVAL: usize = 1000;
+
+/// The number of tasks after which a JSON Lines dump flushes its writer.
+const JSON_LINES_FLUSH_INTERVAL: usize = 64;
+
+/// A callback reporting the progress of a taskdump.
+type Progress<'a> = Box<dyn FnMut(usize, usize) -> ControlFlow<()> + 'a>;
+
+/// A predicate selecting the tasks of a taskdump by the locations of their
+/// roots.
+type Filter<'a> = Box<dyn Fn(&Location) -> bool + 'a>;
+
+/// How much of the trees of tasks taskdumps render.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Verbosity {
+    /// Chains of [detail](crate::Location::frame_detail) frames that each
+    /// have a single child are collapsed into a note on the frame beneath
+    /// them; e.g., `(via 3 helper frames)`.
+    #[default]
+    Normal,
+    /// Every frame is rendered.
+    Full,
+}
+
+/// How a taskdump renders each task.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum Format {
+    /// A human-readable tree; see [`TaskdumpOptions::dump`].
+    Tree,
+    /// A single line; see [`Task::compact_line`](crate::Task::compact_line).
+    Compact,
+    /// A JSON object; see [`TaskdumpOptions::dump_json`].
+    Json,
+    /// A JSON object per line; see [`TaskdumpOptions::dump_jsonl_to`].
+    JsonLines,
+    /// A fenced tree; see [`TaskdumpOptions::dump_markdown`].
+    Markdown,
+}
+
+/// Options for producing a taskdump.
+///
+/// [`taskdump_tree`](crate::taskdump_tree) is equivalent to:
+/// ```
+/// # let wait_for_running_tasks = false;
+/// async_backtrace::TaskdumpOptions::new()
+///     .wait_for_running_tasks(wait_for_running_tasks)
+///     .dump();
+/// ```
+///
+/// ## Example
+/// Reporting progress, and cancelling long dumps:
+/// ```
+/// use async_backtrace::TaskdumpOptions;
+/// use std::ops::ControlFlow;
+/// use std::time::{Duration, Instant};
+///
+/// let deadline = Instant::now() + Duration::from_secs(5);
+/// let dump = TaskdumpOptions::new()
+///     .progress_interval(2_000)
+///     .progress(|done, total| {
+///         println!("{}/{} tasks dumped", done, total);
+///         if Instant::now() < deadline {
+///             ControlFlow::Continue(())
+///         } else {
+///             ControlFlow::Break(())
+///         }
+///     })
+///     .dump();
+/// ```
+pub struct TaskdumpOptions<'a> {
+    settings: Settings,
+    progress: Option<Progress<'a>>,
+    sources: Option<SourceSnippets>,
+    filter: Option<Filter<'a>>,
+}
+
+/// The options of a taskdump, other than its progress callback and the
+/// locations of its sources.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Settings {
+    wait_for_running_tasks: bool,
+    wait_timeout: Option<Duration>,
+    progress_interval: usize,
+    verbosity: Verbosity,
+    sequence_numbers: bool,
+    consolidate: bool,
+    coalesce_window: Option<Duration>,
+    max_depth: Option<usize>,
+    max_children: Option<usize>,
+    indent_width: usize,
+    task_separator: &'static str,
+    future_size_threshold: Option<usize>,
+    trailing_newline: bool,
+    sort_tasks: bool,
+    summary_header: bool,
+    markdown_details: bool,
+    counters: bool,
+    annotations: bool,
+    threads: bool,
+}
+
+impl Settings {
+    const DEFAULT: Self = Self {
+        wait_for_running_tasks: false,
+        wait_timeout: None,
+        progress_interval: DEFAULT_PROGRESS_INTERVAL,
+        verbosity: Verbosity::Normal,
+        sequence_numbers: false,
+        consolidate: true,
+        coalesce_window: None,
+        max_depth: None,
+        max_children: None,
+        indent_width: 3,
+        task_separator: "\n",
+        future_size_threshold: None,
+        trailing_newline: false,
+        sort_tasks: false,
+        summary_header: false,
+        markdown_details: false,
+        counters: false,
+        annotations: false,
+        threads: false,
+    };
+}
+
+/// The counts of what a taskdump rendered; see
+/// [`TaskdumpOptions::dump_with_stats`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct DumpStats {
+    tasks: usize,
+    frames: usize,
+    polling: usize,
+}
+
+impl DumpStats {
+    /// The number of tasks dumped.
+    pub fn tasks(&self) -> usize {
+        self.tasks
+    }
+
+    /// The number of frames of the tasks dumped, including those that were
+    /// not rendered (e.g., beyond the [maximum depth](TaskdumpOptions::max_depth)),
+    /// but only the root of each task that was being polled.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// The number of tasks dumped that were being polled, whose subframes
+    /// were not captured.
+    pub fn polling(&self) -> usize {
+        self.polling
+    }
+
+    /// Counts `tree` among the tasks dumped.
+    fn record(&mut self, tree: &TaskTree) {
+        self.tasks += 1;
+        self.frames += tree.frames();
+        self.polling += usize::from(tree.is_polling());
+    }
+}
+
+impl fmt::Display for DumpStats {
+    /// Renders the counts as, e.g., `1342 tasks, 8790 frames, 3 polling`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        write!(
+            f,
+            "{} task{}, {} frame{}, {} polling",
+            self.tasks,
+            plural(self.tasks),
+            self.frames,
+            plural(self.frames),
+            self.polling
+        )
+    }
+}
+
+/// A rendered taskdump.
+#[derive(Debug, Clone)]
+pub(crate) struct Dump {
+    pub(crate) text: String,
+    /// The instant at which the traversal of the tasks began.
+    pub(crate) epoch: Instant,
+    pub(crate) stats: DumpStats,
+}
+
+/// The number of traversals of the tasks made by taskdumps.
+static TRAVERSALS: AtomicUsize = AtomicUsize::new(0);
+
+/// Produces the number of traversals of the tasks made by taskdumps so far.
+pub(crate) fn traversals() -> usize {
+    TRAVERSALS.load(Ordering::Relaxed)
+}
+
+/// The settings set by [`set_default_dump_options`].
+static DEFAULT_SETTINGS: OnceCell<Settings> = OnceCell::new();
+
+/// An error produced by [`set_default_dump_options`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum DefaultDumpOptionsError {
+    /// The default options have already been set, by a previous call to
+    /// [`set_default_dump_options`].
+    AlreadyInitialized,
+}
+
+impl fmt::Display for DefaultDumpOptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyInitialized => {
+                f.write_str("the default taskdump options are already initialized")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DefaultDumpOptionsError {}
+
+/// Sets the options of the taskdumps taken by the functions that are not
+/// given [`TaskdumpOptions`] explicitly: [`taskdump_tree`],
+/// [`taskdump_compact`], [`dump`], and the renderings of [`Task`]s (which
+/// take only the default [`verbosity`](TaskdumpOptions::verbosity)). This
+/// applies the house style of an application to the dumps taken by library
+/// code that it does not control.
+///
+/// Arguments of those functions take precedence over the defaults; e.g.,
+/// `taskdump_tree(false)` never waits for running tasks, whatever the
+/// defaults, but `taskdump_tree(true)` waits at most for the default
+/// [`wait_timeout`](TaskdumpOptions::wait_timeout) (if any). Options passed
+/// explicitly (e.g., to [`TaskdumpOptions::dump`] or [`dump_tasks`]) are used
+/// as given, and are not affected by the defaults.
+///
+/// The defaults may be set only once, typically at startup; subsequent calls
+/// fail with [`DefaultDumpOptionsError::AlreadyInitialized`]. A
+/// [`progress`](TaskdumpOptions::progress) callback in `options` is specific
+/// to one dump, and is not retained; nor are its
+/// [`source_snippets`](TaskdumpOptions::source_snippets), nor its
+/// [`filter`](TaskdumpOptions::filter).
+///
+/// [`taskdump_tree`]: crate::taskdump_tree
+/// [`taskdump_compact`]: crate::taskdump_compact
+/// [`dump`]: crate::dump
+/// [`Task`]: crate::Task
+///
+/// ## Example
+/// ```
+/// use async_backtrace::{set_default_dump_options, TaskdumpOptions, Verbosity};
+/// use std::time::Duration;
+///
+/// set_default_dump_options(
+///     TaskdumpOptions::new()
+///         .wait_timeout(Duration::from_secs(1))
+///         .verbosity(Verbosity::Full),
+/// )
+/// .unwrap();
+/// ```
+pub fn set_default_dump_options(
+    options: TaskdumpOptions<'_>,
+) -> Result<(), DefaultDumpOptionsError> {
+    DEFAULT_SETTINGS
+        .set(options.settings)
+        .map_err(|_| DefaultDumpOptionsError::AlreadyInitialized)
+}
+
+/// Produces the verbosity set by [`set_default_dump_options`] (if any).
+pub(crate) fn default_verbosity() -> Verbosity {
+    TaskdumpOptions::defaults().settings.verbosity
+}
+
+/// Produces `true` if the options set by [`set_default_dump_options`] (if any)
+/// render sequence numbers.
+pub(crate) fn default_sequence_numbers() -> bool {
+    TaskdumpOptions::defaults().settings.sequence_numbers
+}
+
+/// Produces `false` if the options set by [`set_default_dump_options`] (if
+/// any) render identical siblings individually.
+pub(crate) fn default_consolidate() -> bool {
+    TaskdumpOptions::defaults().settings.consolidate
+}
+
+/// Produces the most children rendered beneath each frame by the options set
+/// by [`set_default_dump_options`] (if any).
+pub(crate) fn default_max_children() -> Option<usize> {
+    TaskdumpOptions::defaults().settings.max_children
+}
+
+/// Produces the width by which the options set by [`set_default_dump_options`]
+/// (if any) indent each level of trees.
+pub(crate) fn default_indent_width() -> usize {
+    TaskdumpOptions::defaults().settings.indent_width
+}
+
+/// Produces the size from which the dumps of the default options render the
+/// sizes of frames' futures, if they do.
+pub(crate) fn default_future_size_threshold() -> Option<usize> {
+    TaskdumpOptions::defaults().settings.future_size_threshold
+}
+
+impl<'a> TaskdumpOptions<'a> {
+    /// Produces the default options, which neither wait for running tasks nor
+    /// report progress.
+    ///
+    /// These are the built-in defaults, and not those set by
+    /// [`set_default_dump_options`].
+    pub fn new() -> Self {
+        Self {
+            settings: Settings::DEFAULT,
+            progress: None,
+            sources: None,
+            filter: None,
+        }
+    }
+
+    /// Produces the options set by [`set_default_dump_options`], or else the
+    /// built-in defaults.
+    pub(crate) fn defaults() -> Self {
+        Self {
+            settings: DEFAULT_SETTINGS.get().copied().unwrap_or(Settings::DEFAULT),
+            progress: None,
+            sources: None,
+            filter: None,
+        }
+    }
+
+    /// If `wait` is `false` (the default), the dump displays only the
+    /// top-level location of currently-running tasks and a note that they are
+    /// "POLLING". Otherwise, the dump waits for currently-running tasks to
+    /// become idle.
+    ///
+    /// With the `thread-report` feature, the note tells a task being polled
+    /// on a thread, `[POLLING on worker-1]`, from one whose lock is
+    /// unavailable although no thread is polling it (e.g., as the dumping
+    /// thread itself holds it), `[BUSY: lock unavailable]`.
+    ///
+    /// # Safety
+    /// If `wait` is `true`, the dump may deadlock if any non-async lock is
+    /// held which may also be held by a Framed task.
+    pub fn wait_for_running_tasks(mut self, wait: bool) -> Self {
+        self.settings.wait_for_running_tasks = wait;
+        self
+    }
+
+    /// Bounds how long, in total, the dump waits for currently-running tasks
+    /// to become idle, if it [waits](Self::wait_for_running_tasks) for them
+    /// at all. Tasks that are still running once `timeout` has elapsed are
+    /// displayed as if the dump did not wait.
+    ///
+    /// This bounds (but does not prevent) the deadlock described by
+    /// [`wait_for_running_tasks`](Self::wait_for_running_tasks).
+    pub fn wait_timeout(mut self, timeout: Duration) -> Self {
+        self.settings.wait_timeout = Some(timeout);
+        self
+    }
+
+    /// Invokes `callback` with the number of tasks dumped so far, and the
+    /// total number of tasks to dump, after every
+    /// [`progress_interval`](Self::progress_interval) tasks, and once all
+    /// tasks have been dumped.
+    ///
+    /// If `callback` produces [`ControlFlow::Break`], the dump stops, and ends
+    /// with a marker noting how many of the tasks were dumped.
+    pub fn progress<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(usize, usize) -> ControlFlow<()> + 'a,
+    {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Sets the number of tasks dumped between invocations of the
+    /// [`progress`](Self::progress) callback; by default, 1000.
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero.
+    pub fn progress_interval(mut self, interval: usize) -> Self {
+        assert!(interval > 0, "the progress interval must be non-zero");
+        self.settings.progress_interval = interval;
+        self
+    }
+
+    /// Sets how much of the trees of tasks are rendered; by default,
+    /// [`Verbosity::Normal`].
+    pub fn verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.settings.verbosity = verbosity;
+        self
+    }
+
+    /// If `show` is `true`, renders the sequence numbers of the
+    /// initialization and last poll of each frame, drawn from a global
+    /// counter; e.g., `app::handle::{{closure}} at src/main.rs:12:1
+    /// [init_seq=41 last_poll_seq=97]`. From these, the order in which sibling
+    /// frames were created, or last polled, can be reconstructed. Frames that
+    /// differ only in their sequence numbers are no longer consolidated.
+    ///
+    /// Requires the `sequence-numbers` feature, which records the numbers at
+    /// the cost of one atomic increment upon the initialization, and upon
+    /// each poll, of every frame.
+    #[cfg(feature = "sequence-numbers")]
+    pub fn sequence_numbers(mut self, show: bool) -> Self {
+        self.settings.sequence_numbers = show;
+        self
+    }
+
+    /// If `consolidate` is `false`, renders each of a frame's identical,
+    /// adjacent children individually, rather than once, as `3x
+    /// app::fetch::{{closure}} at src/main.rs:12:1`. So, each copy may be
+    /// told apart, as by its [sequence numbers](Self::sequence_numbers).
+    ///
+    /// By default, identical siblings are consolidated.
+    pub fn consolidate(mut self, consolidate: bool) -> Self {
+        self.settings.consolidate = consolidate;
+        self
+    }
+
+    /// Renders the size of each frame's future that is at least `threshold`
+    /// bytes; e.g., `app::handle::{{closure}} at src/main.rs:12:1 [future:
+    /// 18.2 KiB]`. Since futures embed the futures they await, a large future
+    /// is often best shrunk at its largest descendant; see also
+    /// [`largest_futures`](crate::largest_futures).
+    ///
+    /// Requires the `future-sizes` feature, which records the size of the
+    /// future of each frame (in four more bytes per frame).
+    #[cfg(feature = "future-sizes")]
+    pub fn future_sizes(mut self, threshold: usize) -> Self {
+        self.settings.future_size_threshold = Some(threshold);
+        self
+    }
+
+    /// Shares the dump with other, identical dumps (i.e., with equal options)
+    /// that are in progress, or that began within `window` of it, rather than
+    /// traversing the tasks again. A shared dump may thus be older than the
+    /// call that produces it; e.g., it may omit tasks that were spawned just
+    /// before. By default, each dump traverses the tasks afresh.
+    ///
+    /// This is most useful in the options set by
+    /// [`set_default_dump_options`], so that the dumps requested at once by
+    /// several subsystems (e.g., a panic hook, a watchdog and a diagnostics
+    /// endpoint) traverse the tasks only once. Callers that need fresh dumps
+    /// may still give options without coalescing explicitly.
+    ///
+    /// Dumps that report their [`progress`](Self::progress), or that are
+    /// [filtered](Self::filter), are never shared.
+    pub fn coalesce(mut self, window: Duration) -> Self {
+        self.settings.coalesce_window = Some(window);
+        self
+    }
+
+    /// Limits the depth of the rendered trees: frames more than `depth` frames
+    /// beneath the root of their task are omitted. By default, the trees are
+    /// rendered in full.
+    ///
+    /// At depth 0, only the root of each task is rendered, one per line, like
+    /// a process listing:
+    /// ```text
+    /// ╼ app::serve::{{closure}} at src/main.rs:12:1
+    /// ╼ app::worker::{{closure}} at src/worker.rs:30:1
+    /// ```
+    /// This needs only the location of each task, so no task is locked (and
+    /// none is waited for, whatever [`wait_for_running_tasks`] says); the
+    /// dump is fast, and safe to take anywhere. Since the state of the roots
+    /// is not read, neither are running tasks marked `[POLLING]`, nor roots
+    /// that panicked `[panicked]`, nor are the frames beneath roots counted.
+    ///
+    /// At depth 1, the children of each root are rendered too, and so on; in
+    /// place of the frames beneath a frame at the limit, their number is
+    /// noted:
+    /// ```text
+    /// ╼ app::decode::{{closure}} at src/main.rs:8:1
+    ///   └╼ 2x app::decode::{{closure}} at src/main.rs:8:1
+    ///      └┈ … 37 more frames
+    /// ```
+    /// Frames are counted before chains of [detail](crate::Location::frame_detail)
+    /// frames are collapsed, and identical siblings are consolidated after
+    /// their omitted descendants are removed, but only if as many are omitted
+    /// beneath each; the number beneath a consolidated frame is that beneath
+    /// each of its copies.
+    ///
+    /// [`wait_for_running_tasks`]: Self::wait_for_running_tasks
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.settings.max_depth = Some(depth);
+        self
+    }
+
+    /// Renders at most `n` children beneath each frame of tree dumps, and
+    /// notes the number of the rest:
+    /// ```text
+    /// ╼ app::serve::{{closure}} at src/main.rs:8:1
+    ///   ├╼ 12x app::read::{{closure}} at src/main.rs:20:1
+    ///   ├╼ app::write::{{closure}} at src/main.rs:30:1
+    ///   └╼ … and 49987 more children
+    /// ```
+    /// The limit is applied after identical siblings are
+    /// [consolidated](Self::consolidate): a consolidated frame counts once
+    /// toward it, but its copies are all counted among the rest.
+    ///
+    /// By default, every child is rendered.
+    pub fn max_children(mut self, n: usize) -> Self {
+        self.settings.max_children = Some(n);
+        self
+    }
+
+    /// Indents each level of the trees of tasks by `width` columns; e.g., by
+    /// two:
+    /// ```text
+    /// ╼ app::serve::{{closure}} at src/main.rs:8:1
+    ///  └╼ app::handle::{{closure}} at src/main.rs:20:1
+    ///    └╼ app::read::{{closure}} at src/main.rs:30:1
+    /// ```
+    /// By default, each level is indented by three columns.
+    ///
+    /// ## Panics
+    /// Panics if `width` is zero.
+    pub fn indent_width(mut self, width: usize) -> Self {
+        assert!(width > 0, "the indent width must be at least one column");
+        self.settings.indent_width = width;
+        self
+    }
+
+    /// Separates the trees of consecutive tasks with `separator`, rather than
+    /// with a newline; e.g., with `"\n---\n"`, so that each tree may be split
+    /// into a record of its own. The separator is not written before the first
+    /// tree, nor after the last (but see [`trailing_newline`]), nor in
+    /// [JSON](Self::dump_json).
+    ///
+    /// [`trailing_newline`]: Self::trailing_newline
+    pub fn task_separator(mut self, separator: &'static str) -> Self {
+        self.settings.task_separator = separator;
+        self
+    }
+
+    /// If `trailing_newline` is `true`, ends every line of non-empty dumps
+    /// with a newline, including the last; so, dumps may be concatenated, or
+    /// followed by footers, without inserting separators. A dump of no tasks
+    /// remains empty (except in [JSON](Self::dump_json)), and the trees of
+    /// [`dump_tasks`] each end with a newline.
+    ///
+    /// By default, lines are only separated by newlines, and the last line
+    /// of a dump does not end with one.
+    pub fn trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.settings.trailing_newline = trailing_newline;
+        self
+    }
+
+    /// If `header` is `true`, begins text dumps with a line counting the
+    /// tasks and frames dumped, and the tasks that were being polled; e.g.:
+    /// ```text
+    /// == async-backtrace: 1342 tasks, 8790 frames, 3 polling ==
+    /// ╼ app::serve::{{closure}} at src/main.rs:8:1
+    /// ...
+    /// ```
+    /// The counts are those of [`DumpStats`], which are taken as the tasks
+    /// are rendered, so a dump with a header is built in memory before it is
+    /// [written](Self::dump_to). [JSON](Self::dump_json) dumps have no header.
+    pub fn summary_header(mut self, header: bool) -> Self {
+        self.settings.summary_header = header;
+        self
+    }
+
+    /// If `details` is `true`, [Markdown](Self::dump_markdown) dumps fold the
+    /// tree of each task into a `<details>` block, headed by its heading; so,
+    /// the pages (e.g., issues) into which large dumps are pasted remain
+    /// navigable.
+    pub fn markdown_details(mut self, details: bool) -> Self {
+        self.settings.markdown_details = details;
+        self
+    }
+
+    /// If `sort` is `true`, dumps tasks in a deterministic order: by the
+    /// locations of their roots (by file, line, column and name), and then
+    /// by [id](crate::Task::id); i.e., tasks spawned at the same site are
+    /// ordered by when they were first polled. So, successive dumps may be
+    /// diffed, and compared in tests.
+    ///
+    /// By default, tasks are dumped in the order of the registry of tasks,
+    /// which varies from dump to dump. The trees of
+    /// [`snapshot`](crate::snapshot) may be sorted likewise, by
+    /// [`TaskTree::cmp_by_root`](crate::TaskTree::cmp_by_root).
+    pub fn sort_tasks(mut self, sort: bool) -> Self {
+        self.settings.sort_tasks = sort;
+        self
+    }
+
+    /// Dumps only the tasks whose roots' locations satisfy `filter`; e.g.,
+    /// those spawned at one site.
+    ///
+    /// The predicate is evaluated before any task is locked, and tasks that
+    /// do not satisfy it are never locked (nor waited for); so, filtering
+    /// also reduces the contention of dumps with running tasks. Tasks that
+    /// are skipped are not counted in the totals reported to the
+    /// [`progress`](Self::progress) callback. Filtered dumps are never
+    /// [coalesced](Self::coalesce).
+    ///
+    /// ## Example
+    /// ```
+    /// use async_backtrace::TaskdumpOptions;
+    ///
+    /// let dump = TaskdumpOptions::new()
+    ///     .filter(|root| root.file().starts_with("src/server/"))
+    ///     .dump();
+    /// ```
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Location) -> bool + 'a,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Dumps only the tasks whose roots' names or files contain `pattern`,
+    /// as by [`filter`](Self::filter); e.g., `my_crate::server`, or
+    /// `src/server/`.
+    pub fn roots_containing(self, pattern: impl Into<String>) -> Self {
+        let pattern = pattern.into();
+        self.filter(move |root| {
+            root.name().is_some_and(|name| name.contains(&pattern))
+                || root.file().contains(&pattern)
+        })
+    }
+
+    /// Renders the source line of each frame beneath it, as read from the
+    /// files of `snippets`; e.g.:
+    /// ```text
+    /// ╼ app::serve::{{closure}} at src/main.rs:12:5
+    ///   │ source: tokio::select! {
+    ///   └╼ app::handle::{{closure}} at src/main.rs:30:9
+    ///      │ source: let request = read_request(&mut stream).await?;
+    /// ```
+    /// This makes dumps self-explanatory to readers who are unfamiliar with
+    /// the code. Lines are trimmed, and truncated to 100 characters. Frames
+    /// whose files cannot be read (or have no such line) are rendered
+    /// without a source line.
+    ///
+    /// The files are read after each tree is copied, and so never while a
+    /// task is locked. The lines of the 16 most recently read files are
+    /// cached, across dumps. Only the [tree](Self::dump) is annotated; dumps
+    /// with source snippets are never [coalesced](Self::coalesce).
+    pub fn source_snippets(mut self, snippets: SourceSnippets) -> Self {
+        self.sources = Some(snippets);
+        self
+    }
+
+    /// If `counters` is `true`, renders the [counters](crate::counters) of the
+    /// location of each frame beside it; e.g.:
+    /// ```text
+    /// ╼ app::serve::{{closure}} at src/main.rs:8:1
+    ///   └╼ app::fetch::{{closure}} at src/main.rs:20:1 [retries=12]
+    /// ```
+    /// The counters are read after each tree is copied, and so never while a
+    /// task is locked. Only the [tree](Self::dump) (and
+    /// [Markdown](Self::dump_markdown)) are annotated.
+    pub fn counters(mut self, counters: bool) -> Self {
+        self.settings.counters = counters;
+        self
+    }
+
+    /// If `annotations` is `true`, renders the [annotations](crate::annotate)
+    /// of each frame (but not those it inherits) beside it; e.g.:
+    /// ```text
+    /// ╼ app::handle::{{closure}} at src/main.rs:8:1 [query_id=42, retry=true]
+    /// ```
+    /// In [JSON](Self::dump_json), each frame then has an `annotations`
+    /// object, whose integer and boolean [values](crate::AnnotationValue) are
+    /// JSON numbers and booleans. Frames with different annotations are never
+    /// consolidated. The annotations of tasks that are being polled (and not
+    /// waited for) are not rendered.
+    pub fn annotations(mut self, annotations: bool) -> Self {
+        self.settings.annotations = annotations;
+        self
+    }
+
+    /// If `threads` is `true`, ends the dump with the frame active on each OS
+    /// thread, as reported by [`thread_report`](crate::thread_report); e.g.:
+    /// ```text
+    /// == threads ==
+    /// worker-1: task #3 at app::spin::{{closure}} at src/main.rs:8:1
+    /// ```
+    /// The section is omitted from [JSON](Self::dump_json) and
+    /// [Markdown](Self::dump_markdown) dumps.
+    ///
+    /// Requires the `thread-report` feature.
+    #[cfg(feature = "thread-report")]
+    pub fn threads(mut self, threads: bool) -> Self {
+        self.settings.threads = threads;
+        self
+    }
+
+    /// Produces how long a dump beginning at `epoch` waits for running tasks.
+    fn wait(&self, epoch: Instant) -> Wait {
+        match (
+            self.settings.wait_for_running_tasks,
+            self.settings.wait_timeout,
+        ) {
+            (false, _) => Wait::No,
+            (true, None) => Wait::Forever,
+            (true, Some(timeout)) => epoch
+                .checked_add(timeout)
+                .map_or(Wait::Forever, Wait::Until),
+        }
+    }
+
+    /// Captures the tree of the task `id`, as of `epoch`, to the configured
+    /// depth, or produces `None` if it has exited.
+    ///
+    /// The task is held only while it is captured, and not while waiting for
+    /// it to become idle.
+    fn capture(&self, id: u64, wait: Wait, epoch: Instant) -> Option<TaskTree> {
+        match self.settings.max_depth {
+            Some(0) => tasks::task(id).map(|task| TaskTree::root_only(task.location(), id)),
+            Some(depth) => {
+                let mut tree = tasks::snapshot_by_id(id, wait, epoch)?;
+                tree.prune(depth);
+                Some(tree)
+            }
+            None => tasks::snapshot_by_id(id, wait, epoch),
+        }
+    }
+
+    /// Renders the captured `tree`, in `format`, to `w`, and counts it in
+    /// `stats`.
+    fn render<W: Write>(
+        &self,
+        w: &mut W,
+        mut tree: TaskTree,
+        format: Format,
+        stats: &mut DumpStats,
+    ) -> fmt::Result {
+        if let (Format::Tree | Format::Markdown, Some(sources)) = (format, &self.sources) {
+            tree.attach_sources(&mut |location| sources.line(location));
+        }
+        if let (Format::Tree | Format::Markdown, true) = (format, self.settings.counters) {
+            tree.attach_counters();
+        }
+        stats.record(&tree);
+        tree.set_verbosity(self.settings.verbosity);
+        tree.set_consolidate(self.settings.consolidate);
+        tree.set_max_children(self.settings.max_children);
+        tree.set_indent_width(self.settings.indent_width);
+        tree.set_annotations(self.settings.annotations);
+        #[cfg(feature = "sequence-numbers")]
+        tree.set_sequence_numbers(self.settings.sequence_numbers);
+        #[cfg(feature = "future-sizes")]
+        tree.set_future_size_threshold(self.settings.future_size_threshold);
+        match format {
+            Format::Tree => write!(w, "{}", tree),
+            Format::Compact => tree.write_compact(w),
+            Format::Json | Format::JsonLines => tree.write_json(w, tree.id()),
+            Format::Markdown => {
+                writeln!(w, "```text\n{}", tree)?;
+                w.write_str("```")
+            }
+        }
+    }
+
+    /// Writes the Markdown heading of the task rooted at `location`, the
+    /// `index`th dumped, with which the tree rendered by
+    /// [`render`](Self::render) begins.
+    fn write_markdown_heading<W: Write>(
+        &self,
+        w: &mut W,
+        index: usize,
+        location: Location,
+    ) -> fmt::Result {
+        if self.settings.markdown_details {
+            writeln!(
+                w,
+                "<details><summary><b>Task {}:</b> <code>{}</code></summary>\n",
+                index, location
+            )
+        } else {
+            writeln!(w, "**Task {}:** `{}`", index, location)
+        }
+    }
+
+    /// Produces a human-readable tree of task states.
+    ///
+    /// The tasks to dump are listed once, when the dump begins: tasks spawned
+    /// while it is in progress are not included, and tasks that exit before
+    /// they are reached are omitted. The registry of tasks is held only while
+    /// they are listed, and not while the dump waits for a running task; so,
+    /// tasks may be created and destroyed (e.g., by the very poll the dump is
+    /// waiting for) without blocking on it.
+    ///
+    /// Ages (e.g., of [recorded errors](crate::framed#arguments)) are computed
+    /// relative to a single instant taken when the dump begins, so that they
+    /// are comparable across tasks, however long the dump takes.
+    ///
+    /// The trees of tasks are separated by newlines; the dump does not end
+    /// with one (unless [`trailing_newline`](Self::trailing_newline) is set),
+    /// and is empty if there are no tasks.
+    pub fn dump(self) -> String {
+        self.dump_timestamped().0
+    }
+
+    /// Produces a human-readable tree of task states, as does
+    /// [`dump`](Self::dump), along with the instant at which the dump began.
+    ///
+    /// If the dump is [coalesced](Self::coalesce), this is the instant at
+    /// which the shared dump began.
+    pub fn dump_timestamped(self) -> (String, Instant) {
+        let dump = self.dump_as(Format::Tree);
+        (dump.text, dump.epoch)
+    }
+
+    /// Produces a human-readable tree of task states, as does
+    /// [`dump`](Self::dump), along with the counts of the tasks and frames it
+    /// rendered, taken in the same traversal.
+    ///
+    /// ## Example
+    /// ```
+    /// let (dump, stats) = async_backtrace::TaskdumpOptions::new().dump_with_stats();
+    /// println!("{} tasks ({} polling):\n{}", stats.tasks(), stats.polling(), dump);
+    /// ```
+    pub fn dump_with_stats(self) -> (String, DumpStats) {
+        let dump = self.dump_as(Format::Tree);
+        (dump.text, dump.stats)
+    }
+
+    /// Produces the trees of task states as JSON, for tools (e.g.,
+    /// dashboards) that scrape them.
+    ///
+    /// The dump is an object, whose `tasks` are those of [`dump`](Self::dump),
+    /// in the same order:
+    /// ```json
+    /// {
+    ///   "tasks": [
+    ///     {
+    ///       "id": 1,
+    ///       "polling": false,
+    ///       "root": {
+    ///         "name": "app::serve::{{closure}}",
+    ///         "file": "src/main.rs",
+    ///         "line": 12,
+    ///         "column": 1,
+    ///         "count": 1,
+    ///         "helper_frames": 0,
+    ///         "panicked": false,
+    ///         "last_error": null,
+    ///         "leaf_state": "pending_on_timer",
+    ///         "children": []
+    ///       },
+    ///       "last_known": null
+    ///     }
+    ///   ],
+    ///   "truncated": null
+    /// }
+    /// ```
+    /// Frames are consolidated and collapsed as they are in the tree:
+    /// identical, adjacent siblings are rendered once, with their `count`
+    /// (the `3x` of the tree), and a frame beneath a collapsed chain of
+    /// [detail](crate::Location::frame_detail) frames notes their number in
+    /// `helper_frames`. The `name` of a frame (or of a frame with no name) is
+    /// `null`, as is its `last_error`, if it has none. The `leaf_state` of a
+    /// frame without children is its [classification](crate::classify) (e.g.,
+    /// `pending_on_io`); it is `null` for other frames, and for those whose
+    /// children were not captured (e.g., the root of a task being polled).
+    /// The parts of locations that the [style](crate::set_location_style) of
+    /// the process omits are `null`; e.g., every `column`, if it is
+    /// [`FileLine`](crate::LocationStyle::FileLine).
+    ///
+    /// A task that is being polled (which the dump did not wait for) has
+    /// `polling` set, and only its root frame; if
+    /// [`RegistryConfig::cache_last_tree`](crate::RegistryConfig) is set, its
+    /// `last_known` is its last-known tree, `{"age_secs": 3, "children":
+    /// [...]}`. With the `thread-report` feature, such a task also has a
+    /// `lock` of `"polling"` (and the `thread` polling it) or `"busy"`, as
+    /// [`wait_for_running_tasks`](Self::wait_for_running_tasks) describes. If the [sequence numbers](Self::sequence_numbers) of frames
+    /// are rendered, each frame also has an `init_seq` and a `last_poll_seq`;
+    /// if the [sizes of futures](Self::future_sizes) are, a `future_size` (in
+    /// bytes, whatever the threshold).
+    /// If the dump is cancelled by its [`progress`](Self::progress) callback,
+    /// `truncated` is `{"dumped": 10, "total": 20}`.
+    ///
+    /// The dump is a single line (ended by a newline, if
+    /// [`trailing_newline`](Self::trailing_newline) is set).
+    pub fn dump_json(self) -> String {
+        self.dump_as(Format::Json).text
+    }
+
+    /// Produces the trees of task states as Markdown, for pasting into issues
+    /// and chats, which would otherwise reflow the trees.
+    ///
+    /// The dump begins with the counts of [`DumpStats`], in bold; each task
+    /// follows, headed by its index and the location of its root, in bold,
+    /// with its tree (as rendered by [`dump`](Self::dump)) in a fenced code
+    /// block; e.g.:
+    /// ````markdown
+    /// **async-backtrace: 1 task, 2 frames, 0 polling**
+    ///
+    /// **Task 1:** `app::serve::{{closure}} at src/main.rs:8:1`
+    /// ```text
+    /// ╼ app::serve::{{closure}} at src/main.rs:8:1
+    ///   └╼ app::handle::{{closure}} at src/main.rs:20:1
+    /// ```
+    /// ````
+    /// Tasks are separated by blank lines, whatever the
+    /// [`task_separator`](Self::task_separator); if
+    /// [`markdown_details`](Self::markdown_details) is set, each is folded
+    /// into a `<details>` block. As with a [summary
+    /// header](Self::summary_header), the dump is built in memory before the
+    /// counts are written.
+    pub fn dump_markdown(self) -> String {
+        self.dump_as(Format::Markdown).text
+    }
+
+    /// Produces a taskdump with one line per task, as rendered by
+    /// [`Task::compact_line`](crate::Task::compact_line).
+    pub(crate) fn dump_compact(self) -> String {
+        self.dump_as(Format::Compact).text
+    }
+
+    /// Writes a human-readable tree of task states to `w`, as produced by
+    /// [`dump`](Self::dump), but without first building the whole dump in
+    /// memory; e.g., to stream the dump of many tasks into a file.
+    ///
+    /// The registry of tasks is only held while they are listed, when the
+    /// dump begins; each task is then captured and written in turn, so tasks
+    /// may be registered and deregistered while `w` is written (and while the
+    /// dump waits for running tasks). Tasks registered after the dump begins
+    /// are omitted, as are listed tasks that exit before they are captured. A
+    /// [coalesced](Self::coalesce) dump is built in memory, to be shared,
+    /// before it is written.
+    pub fn dump_to<W: Write>(self, w: &mut W) -> fmt::Result {
+        if self.coalesce_window().is_some() {
+            return w.write_str(&self.dump());
+        }
+        self.traverse_into(w, Format::Tree).map(drop)
+    }
+
+    /// Writes a human-readable tree of task states to `w`, as does
+    /// [`dump_to`](Self::dump_to).
+    pub fn dump_to_io<W: io::Write>(self, w: &mut W) -> io::Result<()> {
+        let mut adapter = IoAdapter::new(w, None);
+        let result = self.dump_to(&mut adapter);
+        adapter.finish(result)
+    }
+
+    /// Writes the trees of task states to `w` as [JSON Lines]: one line per
+    /// task, each the JSON object of the task in [`dump_json`](Self::dump_json)
+    /// (with the same schema); e.g., to process the dump of many tasks
+    /// incrementally.
+    ///
+    /// As in [`dump_to`](Self::dump_to), each task is written once it is
+    /// captured, and `w` is flushed after every 64 tasks and at the end of the
+    /// dump; so, the lines written before a crash mid-dump remain usable. If the dump is
+    /// cancelled by its [`progress`](Self::progress) callback, its last line
+    /// is `{"truncated": {"dumped": 10, "total": 20}}`. Every line (including
+    /// the last) ends with a newline, and a dump of no tasks is empty. The
+    /// dump is never [coalesced](Self::coalesce).
+    ///
+    /// [JSON Lines]: https://jsonlines.org
+    ///
+    /// ## Example
+    /// ```
+    /// let mut out = std::io::BufWriter::new(std::io::stdout());
+    /// async_backtrace::TaskdumpOptions::new().dump_jsonl_to(&mut out).unwrap();
+    /// ```
+    pub fn dump_jsonl_to<W: io::Write>(self, w: &mut W) -> io::Result<()> {
+        let mut adapter = IoAdapter::new(w, Some(JSON_LINES_FLUSH_INTERVAL));
+        let result = self
+            .traverse_tasks(&mut adapter, Format::JsonLines)
+            .map(drop);
+        let result = adapter.finish(result);
+        result.and_then(|()| w.flush())
+    }
+
+    /// Produces the window within which this dump may be shared, if it may
+    /// be.
+    fn coalesce_window(&self) -> Option<Duration> {
+        match (
+            self.settings.coalesce_window,
+            &self.progress,
+            &self.sources,
+            &self.filter,
+        ) {
+            (Some(window), None, None, None) => Some(window),
+            _ => None,
+        }
+    }
+
+    /// Produces a taskdump in `format`.
+    fn dump_as(self, format: Format) -> Dump {
+        match self.coalesce_window() {
+            Some(window) => {
+                let key = coalesce::Key {
+                    format,
+                    settings: self.settings,
+                };
+                coalesce::dump(key, window, || self.traverse(format))
+            }
+            None => self.traverse(format),
+        }
+    }
+
+    /// Traverses the tasks, rendering each in `format`.
+    fn traverse(self, format: Format) -> Dump {
+        let mut text = String::new();
+        let (epoch, stats) = self
+            .traverse_into(&mut text, format)
+            .expect("writing to a `String` cannot fail");
+        Dump { text, epoch, stats }
+    }
+
+    /// Traverses the tasks, rendering each in `format` to `w` (after the
+    /// [summary header](Self::summary_header), if it is rendered), and
+    /// produces the instant at which the traversal began, and its counts.
+    fn traverse_into<W: Write>(
+        self,
+        w: &mut W,
+        format: Format,
+    ) -> Result<(Instant, DumpStats), fmt::Error> {
+        let header = match format {
+            Format::Json | Format::JsonLines => false,
+            Format::Markdown => true,
+            Format::Tree | Format::Compact => self.settings.summary_header,
+        };
+        if !header {
+            return self.traverse_tasks(w, format);
+        }
+        // the counts are only known once every task is rendered
+        let trailing_newline = self.settings.trailing_newline;
+        let mut body = String::new();
+        let (epoch, stats) = self.traverse_tasks(&mut body, format)?;
+        if format == Format::Markdown {
+            write!(w, "**async-backtrace: {}**", stats)?;
+            if !body.is_empty() {
+                w.write_char('\n')?;
+            }
+        } else {
+            write!(w, "== async-backtrace: {} ==", stats)?;
+        }
+        if !body.is_empty() || trailing_newline {
+            w.write_char('\n')?;
+        }
+        w.write_str(&body)?;
+        Ok((epoch, stats))
+    }
+
+    /// Traverses the tasks, rendering each in `format` to `w`, and produces
+    /// the instant at which the traversal began, and its counts.
+    fn traverse_tasks<W: Write>(
+        mut self,
+        w: &mut W,
+        format: Format,
+    ) -> Result<(Instant, DumpStats), fmt::Error> {
+        TRAVERSALS.fetch_add(1, Ordering::Relaxed);
+        let epoch = Instant::now();
+        let wait = self.wait(epoch);
+        // the registry is held only while the tasks are listed; each is then
+        // found (and waited for) by id, so that a task polled meanwhile may
+        // spawn or finish tasks without blocking on the dump
+        let mut tasks = match &self.filter {
+            Some(filter) => tasks::snapshot_roots(|location| filter(location)),
+            None => tasks::snapshot_roots(|_| true),
+        };
+        if self.settings.sort_tasks {
+            tasks.sort_by(|&(a_id, a), &(b_id, b)| tasks::root_order((a, a_id), (b, b_id)));
+        }
+        let total = tasks.len();
+        let mut rendered = 0;
+        let mut truncated = None;
+        let mut stats = DumpStats::default();
+        if format == Format::Json {
+            w.write_str("{\"tasks\":[")?;
+        }
+        for (done, &(id, location)) in (1..).zip(&tasks) {
+            // (a task that exited since it was listed is skipped)
+            if let Some(tree) = self.capture(id, wait, epoch) {
+                rendered += 1;
+                if rendered > 1 {
+                    match format {
+                        Format::Json => w.write_char(',')?,
+                        Format::JsonLines => {}
+                        Format::Markdown => w.write_str("\n\n")?,
+                        _ => w.write_str(self.settings.task_separator)?,
+                    }
+                }
+                if format == Format::Markdown {
+                    self.write_markdown_heading(w, rendered, location)?;
+                }
+                self.render(w, tree, format, &mut stats)?;
+                match format {
+                    Format::Markdown if self.settings.markdown_details => {
+                        w.write_str("\n\n</details>")?
+                    }
+                    Format::JsonLines => w.write_char('\n')?,
+                    _ => {}
+                }
+            }
+            let report = done % self.settings.progress_interval == 0 || done == total;
+            if let Some(progress) = self.progress.as_mut().filter(|_| report) {
+                if progress(done, total).is_break() && done < total {
+                    truncated = Some(done);
+                    break;
+                }
+            }
+        }
+        match (format, truncated) {
+            (Format::Json, None) => w.write_str("],\"truncated\":null}")?,
+            (Format::Json, Some(done)) => write!(
+                w,
+                "],\"truncated\":{{\"dumped\":{},\"total\":{}}}}}",
+                done, total
+            )?,
+            (Format::JsonLines, Some(done)) => writeln!(
+                w,
+                "{{\"truncated\":{{\"dumped\":{},\"total\":{}}}}}",
+                done, total
+            )?,
+            (Format::Markdown, Some(done)) => {
+                write!(w, "\n\n*[TRUNCATED: {} of {} tasks dumped]*", done, total)?
+            }
+            (_, Some(done)) => write!(w, "\n[TRUNCATED: {} of {} tasks dumped]", done, total)?,
+            (_, None) => {}
+        }
+        let threads = self.settings.threads && matches!(format, Format::Tree | Format::Compact);
+        if threads {
+            if rendered > 0 {
+                w.write_char('\n')?;
+            }
+            crate::threads::write_report(w)?;
+        }
+        // (each line of JSON Lines is already ended by a newline)
+        let ended = format == Format::JsonLines;
+        if self.settings.trailing_newline
+            && !ended
+            && (format == Format::Json || rendered > 0 || threads)
+        {
+            w.write_char('\n')?;
+        }
+        Ok((epoch, stats))
+    }
+}
+
+/// Writes to an [`io::Write`] as an [`fmt::Write`], keeping the (first) I/O
+/// error, which [`fmt::Error`] cannot carry.
+struct IoAdapter<'w, W> {
+    inner: &'w mut W,
+    error: Option<io::Error>,
+    /// The number of lines after which `inner` is flushed, if it is.
+    flush_interval: Option<usize>,
+    /// The number of lines written since `inner` was last flushed.
+    lines: usize,
+}
+
+impl<'w, W: io::Write> IoAdapter<'w, W> {
+    fn new(inner: &'w mut W, flush_interval: Option<usize>) -> Self {
+        Self {
+            inner,
+            error: None,
+            flush_interval,
+            lines: 0,
+        }
+    }
+
+    /// Produces the result of a write that produced `result`.
+    fn finish(self, result: fmt::Result) -> io::Result<()> {
+        match result {
+            Ok(()) => Ok(()),
+            Err(fmt::Error) => Err(self
+                .error
+                .unwrap_or_else(|| io::Error::other("formatter error"))),
+        }
+    }
+}
+
+impl<W: io::Write> Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut result = self.inner.write_all(s.as_bytes());
+        if let (Ok(()), Some(interval)) = (&result, self.flush_interval) {
+            self.lines += s.matches('\n').count();
+            if self.lines >= interval {
+                self.lines = 0;
+                result = self.inner.flush();
+            }
+        }
+        result.map_err(|error| {
+            self.error.get_or_insert(error);
+            fmt::Error
+        })
+    }
+}
+
+/// Produces the trees of the tasks with the given `ids`, in order, each
+/// paired with its id; the tree of an id that is not (or is no longer) the id
+/// of a registered task is `None`.
+///
+/// Only the requested tasks are inspected (and locked); the rest are not
+/// touched. The trees are rendered as by [`TaskdumpOptions::dump`], with the
+/// given `options`; if their [progress](TaskdumpOptions::progress) callback
+/// cancels the dump, the trees of the remaining ids are omitted. Their
+/// [filter](TaskdumpOptions::filter) (if any) is ignored; the requested tasks
+/// are dumped whatever their roots.
+///
+/// ## Example
+/// ```
+/// use async_backtrace::{dump_tasks, tasks, TaskdumpOptions};
+///
+/// let ids: Vec<u64> = tasks().take(10).map(|task| task.id()).collect();
+/// for (id, tree) in dump_tasks(&ids, TaskdumpOptions::new()) {
+///     match tree {
+///         Some(tree) => println!("task {}:\n{}", id, tree),
+///         None => println!("task {} has exited", id),
+///     }
+/// }
+/// ```
+pub fn dump_tasks(ids: &[u64], mut options: TaskdumpOptions<'_>) -> Vec<(u64, Option<String>)> {
+    let epoch = Instant::now();
+    let wait = options.wait(epoch);
+    let total = ids.len();
+    let mut trees = Vec::with_capacity(total);
+    for (done, &id) in (1..).zip(ids) {
+        // each task is released before the next is found
+        let tree = options.capture(id, wait, epoch).map(|captured| {
+            let mut tree = String::new();
+            options
+                .render(&mut tree, captured, Format::Tree, &mut DumpStats::default())
+                .expect("writing to a `String` cannot fail");
+            if options.settings.trailing_newline {
+                tree.push('\n');
+            }
+            tree
+        });
+        trees.push((id, tree));
+        let report = done % options.settings.progress_interval == 0 || done == total;
+        if let Some(progress) = options.progress.as_mut().filter(|_| report) {
+            if progress(done, total).is_break() {
+                break;
+            }
+        }
+    }
+    trees
+}
+
+impl Default for TaskdumpOptions<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
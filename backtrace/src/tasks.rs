@@ -1,30 +1,270 @@
-use crate::Frame;
-use dashmap::DashSet as Set;
-use once_cell::sync::Lazy;
-use rustc_hash::FxHasher;
-use std::{hash::BuildHasherDefault, ops::Deref, ptr::NonNull};
+use crate::registry::TaskRegistry;
+use crate::snapshot::{Polling, TaskTree};
+use crate::{Frame, Location};
+use once_cell::sync::OnceCell;
+use std::{
+    fmt,
+    hash::Hash,
+    ops::Deref,
+    ptr::NonNull,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
 /// A top-level [framed](crate::framed) future.
-#[derive(Hash, Eq, PartialEq)]
+///
+/// The identity of a task is its [id](Task::id): two `Task`s are equal (and
+/// hash identically) if and only if they have the same id. Ids are never
+/// reused, so this remains true across snapshots, even if the memory of a
+/// completed task is reused by a newer one.
 #[repr(transparent)]
 pub struct Task(NonNull<Frame>);
 
 unsafe impl Send for Task {}
 unsafe impl Sync for Task {}
 
-static TASK_SET: Lazy<Set<Task, BuildHasherDefault<FxHasher>>> = Lazy::new(Set::default);
+/// How long to wait for a task that is being polled to become idle.
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum Wait {
+    /// Don't wait.
+    No,
+    /// Wait for as long as it takes.
+    Forever,
+    /// Wait until the given instant.
+    Until(Instant),
+}
+
+impl From<bool> for Wait {
+    fn from(block_until_idle: bool) -> Self {
+        if block_until_idle {
+            Wait::Forever
+        } else {
+            Wait::No
+        }
+    }
+}
+
+/// The id of the next task to be registered.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Produces a new, unique task id.
+pub(crate) fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+#[cfg(not(loom))]
+static TASK_SET: once_cell::sync::Lazy<crate::registry::DashRegistry> =
+    once_cell::sync::Lazy::new(|| {
+        crate::registry::DashRegistry::new(REGISTRY_CONFIG.get_or_init(RegistryConfig::default))
+    });
+
+#[cfg(loom)]
+loom::lazy_static! {
+    static ref TASK_SET: crate::registry::LoomRegistry =
+        crate::registry::LoomRegistry::new(REGISTRY_CONFIG.get_or_init(RegistryConfig::default));
+}
+
+static REGISTRY_CONFIG: OnceCell<RegistryConfig> = OnceCell::new();
+
+/// Tuning parameters of the global registry of tasks.
+///
+/// See [`configure_registry`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RegistryConfig {
+    /// The number of tasks the registry can hold without reallocating.
+    pub initial_capacity: usize,
+    /// The number of independently-locked shards of the registry. Must be a
+    /// power of two greater than one.
+    pub shard_amount: usize,
+    /// If `true`, the last-known tree of each task is kept, and shown by
+    /// non-blocking taskdumps in place of the tree of a task that is being
+    /// polled (which cannot be inspected).
+    ///
+    /// The tree of a task is copied whenever a poll of its root ends, and
+    /// whenever a non-blocking taskdump inspects it. This costs memory (for
+    /// each task, a copy of its tree) and time (for each poll of a task, a
+    /// traversal of its tree).
+    pub cache_last_tree: bool,
+    /// The most tasks that may be registered at once, if any.
+    ///
+    /// Beyond this limit, tasks are not registered: they are polled as
+    /// usual, but are invisible to taskdumps, and are counted by
+    /// [`MemoryEstimate::unregistered`]. Setting
+    /// [`initial_capacity`](RegistryConfig::initial_capacity) to the limit
+    /// ensures that the registry never grows (and so never allocates) within
+    /// the poll of a task.
+    pub max_tasks: Option<usize>,
+}
+
+impl Default for RegistryConfig {
+    /// No pre-allocated capacity, four shards per available CPU (rounded up
+    /// to a power of two), no caching of last-known trees, and no limit on
+    /// the number of tasks.
+    fn default() -> Self {
+        let parallelism = std::thread::available_parallelism().map_or(1, usize::from);
+        Self {
+            initial_capacity: 0,
+            shard_amount: (parallelism * 4).next_power_of_two(),
+            cache_last_tree: false,
+            max_tasks: None,
+        }
+    }
+}
+
+/// An error produced by [`configure_registry`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum RegistryConfigError {
+    /// The registry has already been initialized, either by a previous call
+    /// to [`configure_registry`] or by the registration of a task.
+    AlreadyInitialized,
+    /// The given shard amount was not a power of two greater than one.
+    InvalidShardAmount(usize),
+}
+
+impl fmt::Display for RegistryConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyInitialized => f.write_str("the task registry is already initialized"),
+            Self::InvalidShardAmount(amount) => write!(
+                f,
+                "shard amount must be a power of two greater than one, but was {amount}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RegistryConfigError {}
 
-/// Register a given root frame as a task.
+/// Configures the global registry of tasks.
+///
+/// Sizing the registry up front avoids the latency spikes of resizing it
+/// while tasks are being spawned. This routine must be called before the
+/// first task is registered (i.e., before any framed future is first polled
+/// outside of another framed future), typically at the start of `main`.
+/// Afterwards, it has no effect and produces
+/// [`RegistryConfigError::AlreadyInitialized`].
+///
+/// ## Example
+/// ```
+/// use async_backtrace::{configure_registry, RegistryConfig};
+///
+/// configure_registry(RegistryConfig {
+///     initial_capacity: 500_000,
+///     ..RegistryConfig::default()
+/// })
+/// .unwrap();
+/// ```
+pub fn configure_registry(config: RegistryConfig) -> Result<(), RegistryConfigError> {
+    if config.shard_amount < 2 || !config.shard_amount.is_power_of_two() {
+        return Err(RegistryConfigError::InvalidShardAmount(config.shard_amount));
+    }
+    REGISTRY_CONFIG
+        .set(config)
+        .map_err(|_| RegistryConfigError::AlreadyInitialized)
+}
+
+/// The number of tasks the registry can hold without reallocating.
+pub(crate) fn capacity() -> usize {
+    TASK_SET.capacity()
+}
+
+/// The number of registered tasks.
+static LIVE_TASKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Produces the number of registered tasks, without locking the registry.
+#[cfg(feature = "ffi")]
+pub(crate) fn live_tasks() -> usize {
+    LIVE_TASKS.load(Ordering::Relaxed)
+}
+
+/// The greatest number of tasks that have been registered at once, since the
+/// last [`reset_high_water_mark`].
+static HIGH_WATER_MARK: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of tasks that were not registered, since the process began.
+static UNREGISTERED: AtomicUsize = AtomicUsize::new(0);
+
+/// Register a given root frame as a task, unless the registry is at its
+/// [limit](RegistryConfig::max_tasks) or refuses it; then, the task is merely
+/// counted, and is invisible to taskdumps.
 ///
 /// **SAFETY:** You vow to remove the given frame prior to it being dropped.
 pub(crate) unsafe fn register(root_frame: &Frame) {
-    let unique = TASK_SET.insert(Task(NonNull::from(root_frame)));
-    debug_assert!(unique);
+    let max_tasks = REGISTRY_CONFIG
+        .get_or_init(RegistryConfig::default)
+        .max_tasks
+        .unwrap_or(usize::MAX);
+    // a slot is reserved before the registry is touched, so that it is never
+    // grown beyond the limit
+    let reserved = LIVE_TASKS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |live| {
+        (live < max_tasks).then(|| live + 1)
+    });
+    let live = match reserved {
+        Ok(live) => live + 1,
+        Err(_) => {
+            UNREGISTERED.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+    };
+    if TASK_SET
+        .try_register(Task(NonNull::from(root_frame)))
+        .is_err()
+    {
+        LIVE_TASKS.fetch_sub(1, Ordering::Relaxed);
+        UNREGISTERED.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    HIGH_WATER_MARK.fetch_max(live, Ordering::Relaxed);
+    #[cfg(feature = "test-utils")]
+    if let Some(id) = root_frame.task_id() {
+        crate::testing::registered(id);
+    }
 }
 
-/// De-register a given root frame as a task.
+/// De-register a given root frame as a task, if it was registered.
 pub(crate) fn deregister(root_frame: &Frame) {
-    TASK_SET.remove(&Task(NonNull::from(root_frame)));
+    if TASK_SET.deregister(&Task(NonNull::from(root_frame))) {
+        LIVE_TASKS.fetch_sub(1, Ordering::Relaxed);
+    }
+    if cache_last_tree() {
+        if let Some(id) = root_frame.task_id() {
+            crate::snapshot::evict(id);
+        }
+    }
+    #[cfg(feature = "tokio")]
+    if let Some(id) = root_frame.task_id() {
+        crate::spawn::forget(id);
+    }
+}
+
+/// Produces `true` if the last-known trees of tasks are cached; see
+/// [`RegistryConfig::cache_last_tree`].
+pub(crate) fn cache_last_tree() -> bool {
+    REGISTRY_CONFIG
+        .get()
+        .is_some_and(|config| config.cache_last_tree)
+}
+
+/// Orders tasks by the locations of their roots (by file, line, column and
+/// name), and then by id; i.e., tasks spawned at the same site are ordered
+/// by when they were first polled.
+pub(crate) fn root_order(
+    (a, a_id): (Location, u64),
+    (b, b_id): (Location, u64),
+) -> std::cmp::Ordering {
+    fn key(location: &Location) -> (&str, u32, u32, Option<&str>) {
+        (
+            location.file(),
+            location.line(),
+            location.column(),
+            location.name(),
+        )
+    }
+    key(&a).cmp(&key(&b)).then(a_id.cmp(&b_id))
 }
 
 /// An iterator over tasks.
@@ -35,14 +275,394 @@ pub fn tasks() -> impl Iterator<Item = impl Deref<Target = Task>> {
     TASK_SET.iter()
 }
 
+/// Produces the ids of the registered tasks, in no particular order.
+///
+/// This is the cheapest view of the registry: no task is locked, and the
+/// creation and destruction of tasks is only blocked while the ids are
+/// copied. A task may exit at any time after its id is produced, so a monitor
+/// that samples a few of the ids, and then inspects them with
+/// [`dump_tasks`](crate::dump_tasks), must tolerate ids that are stale.
+///
+/// ## Example
+/// ```
+/// use async_backtrace::{dump_tasks, tasks_snapshot_ids, TaskdumpOptions};
+/// use std::{
+///     collections::hash_map::RandomState,
+///     hash::{BuildHasher, Hasher},
+/// };
+///
+/// // pick (at most) 3 tasks at random, without replacement
+/// let mut ids = tasks_snapshot_ids();
+/// let mut picked = Vec::new();
+/// while picked.len() < 3 && !ids.is_empty() {
+///     let random = RandomState::new().build_hasher().finish();
+///     picked.push(ids.swap_remove(random as usize % ids.len()));
+/// }
+///
+/// for (id, tree) in dump_tasks(&picked, TaskdumpOptions::new()) {
+///     match tree {
+///         Some(tree) => println!("task {}:\n{}", id, tree),
+///         None => println!("task {} has exited", id),
+///     }
+/// }
+/// ```
+pub fn tasks_snapshot_ids() -> Vec<u64> {
+    let mut ids = Vec::with_capacity(LIVE_TASKS.load(Ordering::Relaxed));
+    ids.extend(TASK_SET.iter().map(|task| task.id()));
+    ids
+}
+
+/// Produces a reference to the registered task with the given `id`, if any.
+///
+/// **NOTE:** The creation and destruction of some or all tasks will be blocked
+/// for as long as the returned reference is live.
+pub(crate) fn task(id: u64) -> Option<TaskRef> {
+    TASK_SET.get(id).map(TaskRef)
+}
+
+/// Produces the id and root location of each registered task (for which
+/// `filter` produces `true`), in no particular order.
+///
+/// The registry is only held while they are copied; so, the tasks may be
+/// inspected one at a time, by [`snapshot_by_id`], without blocking the
+/// creation and destruction of the others meanwhile.
+pub(crate) fn snapshot_roots<P>(mut filter: P) -> Vec<(u64, Location)>
+where
+    P: FnMut(&Location) -> bool,
+{
+    TASK_SET
+        .iter()
+        .map(|task| (task.id(), task.location()))
+        .filter(|(_, location)| filter(location))
+        .collect()
+}
+
+/// Captures the tree of the registered task `id`, rendering ages relative to
+/// `epoch`, or produces `None` if it has exited.
+///
+/// If `wait` allows, this waits for the task to not be polled; see
+/// [`retry_by_id`].
+pub(crate) fn snapshot_by_id(id: u64, wait: impl Into<Wait>, epoch: Instant) -> Option<TaskTree> {
+    retry_by_id(
+        id,
+        wait,
+        |task| task.snapshot_if_idle(epoch),
+        |task| task.snapshot(Wait::No, epoch),
+    )
+}
+
+/// Applies `attempt` to the registered task `id` until it produces `Some`
+/// (i.e., until the task is not being polled), or, once `wait` is exhausted,
+/// applies `fallback`; or produces `None` if the task has exited.
+///
+/// Unlike [`Task::with_locked`], this waits without holding the registry:
+/// between attempts, the task is released, so that its poll (or any other)
+/// may create and destroy tasks. With [`Wait::Forever`], this blocks on the
+/// (shared) lock of the task until its poll is done, and then looks the task
+/// up again, as it may have exited (or been polled again) meanwhile; with
+/// [`Wait::Until`], which cannot block past its deadline, it backs off.
+pub(crate) fn retry_by_id<R>(
+    id: u64,
+    wait: impl Into<Wait>,
+    mut attempt: impl FnMut(&Task) -> Option<R>,
+    fallback: impl FnOnce(&Task) -> R,
+) -> Option<R> {
+    let wait = wait.into();
+    loop {
+        let task = task(id)?;
+        if let Some(result) = attempt(&task) {
+            return Some(result);
+        }
+        let mutex = match wait {
+            Wait::Forever => task.shared_mutex(),
+            Wait::Until(deadline) if Instant::now() < deadline => None,
+            Wait::No | Wait::Until(..) => return Some(fallback(&task)),
+        };
+        drop(task);
+        match mutex {
+            // poisoning is ignored, as in `Task::with_locked`
+            Some(mutex) => drop(mutex.lock()),
+            None => crate::sync::backoff(),
+        }
+    }
+}
+
+#[cfg(not(loom))]
+type RegistryRef = <crate::registry::DashRegistry as TaskRegistry>::Ref<'static>;
+
+#[cfg(loom)]
+type RegistryRef = <crate::registry::LoomRegistry as TaskRegistry>::Ref<'static>;
+
+/// A reference to a registered [`Task`].
+///
+/// **NOTE:** The creation and destruction of some or all tasks will be blocked
+/// for as long as this reference is live.
+pub struct TaskRef(RegistryRef);
+
+impl Deref for TaskRef {
+    type Target = Task;
+
+    fn deref(&self) -> &Task {
+        &self.0
+    }
+}
+
+/// Renders `stacks` as numbered stacks, for [`Task::pretty_stacks`].
+fn render_stacks(stacks: &[Vec<Location>], polling: Option<&Polling>) -> String {
+    use std::fmt::Write;
+
+    let mut rendered = String::new();
+    for (i, stack) in stacks.iter().enumerate() {
+        if i > 0 {
+            rendered.push_str("\n\n");
+        }
+        for (depth, location) in stack.iter().enumerate() {
+            if depth > 0 {
+                rendered.push('\n');
+            }
+            write!(rendered, "#{} {}", depth, location).unwrap();
+        }
+    }
+    if let Some(polling) = polling {
+        write!(rendered, " [{}]", polling).unwrap();
+    }
+    rendered
+}
+
+/// The tasks found by [`tasks_containing`].
+pub struct TasksContaining {
+    /// The tasks with a frame matching the predicate.
+    pub tasks: Vec<TaskRef>,
+    /// The number of tasks that were not searched, because they were being
+    /// polled.
+    pub skipped: usize,
+}
+
+/// Finds the tasks with any frame whose location matches `predicate`.
+///
+/// Unlike filtering [`tasks`] by their [location](Task::location), this
+/// searches the entire tree of every task. If `wait_for_running_tasks` is
+/// `false`, tasks that are being polled are skipped (and counted in
+/// [`TasksContaining::skipped`]); otherwise, this routine waits for them to
+/// become idle, and may deadlock in the same circumstances as
+/// [`taskdump_tree`](crate::taskdump_tree).
+///
+/// **NOTE:** The creation and destruction of some or all tasks will be blocked
+/// for as long as the returned [`TaskRef`]s are live.
+///
+/// ## Example
+/// ```
+/// use async_backtrace::tasks_containing;
+///
+/// let leaking = tasks_containing(|location| location.name() == Some("my_crate::leak"), false);
+/// for task in &leaking.tasks {
+///     println!("{}", task.pretty_tree(false));
+/// }
+/// println!("({} tasks were busy)", leaking.skipped);
+/// ```
+pub fn tasks_containing<P>(mut predicate: P, wait_for_running_tasks: bool) -> TasksContaining
+where
+    P: FnMut(&Location) -> bool,
+{
+    let mut matched = Vec::new();
+    let mut skipped = 0;
+    // the tasks are searched one at a time, and the matches are only
+    // referenced once all have been searched, so that the registry is not held
+    // while waiting for a task to become idle
+    for (id, _) in snapshot_roots(|_| true) {
+        let contains = retry_by_id(
+            id,
+            wait_for_running_tasks,
+            |task| task.contains(false, &mut predicate).map(Some),
+            |_| None,
+        );
+        match contains {
+            Some(Some(true)) => matched.push(id),
+            Some(Some(false)) | None => {}
+            Some(None) => skipped += 1,
+        }
+    }
+    TasksContaining {
+        // (tasks that have exited since they were searched are omitted)
+        tasks: matched.into_iter().filter_map(task).collect(),
+        skipped,
+    }
+}
+
+/// An estimate of the memory used by frames and the registry of tasks.
+///
+/// Produced by [`memory_estimate`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct MemoryEstimate {
+    /// The number of registered tasks.
+    pub tasks: usize,
+    /// The greatest number of tasks that have been registered at once, since
+    /// the process began or the last [`reset_high_water_mark`].
+    pub tasks_high_water_mark: usize,
+    /// The number of frames counted in the trees of the registered tasks.
+    pub frames: usize,
+    /// The number of tasks whose subframes were not counted, because they
+    /// were being polled; only their roots are counted in
+    /// [`frames`](MemoryEstimate::frames).
+    pub skipped: usize,
+    /// The estimated number of bytes occupied by the counted frames and the
+    /// registry of tasks. This does not include memory allocated by frames
+    /// (e.g., for [annotations](crate::annotate)).
+    pub bytes: usize,
+    /// The number of tasks that were not registered (and so are not counted
+    /// here), since the process began, because the registry was at its
+    /// [limit](RegistryConfig::max_tasks).
+    pub unregistered: usize,
+}
+
+/// Estimates the memory used by frames and the registry of tasks.
+///
+/// The frames of every task are counted by traversing its tree; tasks that
+/// are being polled are not waited for, but counted as
+/// [`skipped`](MemoryEstimate::skipped).
+///
+/// **NOTE:** The creation and destruction of some or all tasks will be blocked
+/// for the duration of the traversal.
+///
+/// ## Example
+/// ```
+/// let estimate = async_backtrace::memory_estimate();
+/// println!(
+///     "{} tasks (at most {}), {} frames, ~{} bytes",
+///     estimate.tasks, estimate.tasks_high_water_mark, estimate.frames, estimate.bytes,
+/// );
+/// ```
+pub fn memory_estimate() -> MemoryEstimate {
+    let mut estimate = MemoryEstimate {
+        tasks: 0,
+        tasks_high_water_mark: 0,
+        frames: 0,
+        skipped: 0,
+        bytes: 0,
+        unregistered: UNREGISTERED.load(Ordering::Relaxed),
+    };
+    for task in TASK_SET.iter() {
+        estimate.tasks += 1;
+        match task.frame_count(false) {
+            Some(frames) => estimate.frames += frames,
+            None => {
+                estimate.frames += 1;
+                estimate.skipped += 1;
+            }
+        }
+    }
+    // the high-water mark is read last, so that it is at least `tasks`
+    estimate.tasks_high_water_mark = HIGH_WATER_MARK.load(Ordering::Relaxed).max(estimate.tasks);
+    // each entry of the registry is a task and its id, and a byte of control
+    // data
+    let registry = capacity() * (std::mem::size_of::<(u64, Task)>() + 1);
+    estimate.bytes = estimate.frames * std::mem::size_of::<Frame>() + registry;
+    estimate
+}
+
+/// Resets the high-water mark of [`MemoryEstimate::tasks_high_water_mark`] to
+/// the number of currently-registered tasks, producing its previous value.
+pub fn reset_high_water_mark() -> usize {
+    HIGH_WATER_MARK.swap(LIVE_TASKS.load(Ordering::Relaxed), Ordering::Relaxed)
+}
+
+/// Captures the tree of the task of the currently-active frame (if any),
+/// rendering ages relative to `epoch`.
+///
+/// This never blocks: the root of the task is already locked by this thread,
+/// for the duration of the poll in progress.
+pub(crate) fn current_snapshot(epoch: Instant) -> Option<TaskTree> {
+    Frame::with_active(|maybe_frame| {
+        maybe_frame.map(|frame| {
+            let task = Task(NonNull::from(frame.root()));
+            debug_assert!(task.is_current());
+            task.snapshot(Wait::No, epoch)
+        })
+    })
+}
+
+/// The task of the currently-active frame, as produced by
+/// [`current_task`](crate::current_task).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TaskInfo {
+    pub(crate) id: u64,
+    pub(crate) location: Location,
+}
+
+impl TaskInfo {
+    /// The [id](Task::id) of the task, which is never reused within a
+    /// process; even if the task is not [registered](crate::tasks) (e.g.,
+    /// because the registry is at its [limit](RegistryConfig::max_tasks)).
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The location of the task's root frame.
+    pub fn location(&self) -> Location {
+        self.location
+    }
+}
+
 impl Task {
+    /// The unique identifier of this task.
+    ///
+    /// Ids are assigned in the order that tasks are first polled, and are
+    /// never reused within a process.
+    pub fn id(&self) -> u64 {
+        // safety: we promise to not inspect the subframes without first locking
+        let frame = unsafe { self.0.as_ref() };
+        frame
+            .task_id()
+            .expect("registered frames are roots, which have ids")
+    }
+
+    /// Produces `true` if `self` and `other` are the same task.
+    ///
+    /// This is equivalent to `self == other`.
+    pub fn same_as(&self, other: &Task) -> bool {
+        self.id() == other.id()
+    }
+
+    /// The root frame of this task.
+    ///
+    /// The caller must not inspect its subframes without first locking it.
+    #[cfg(any(debug_assertions, feature = "debug-validate"))]
+    pub(crate) fn frame(&self) -> &Frame {
+        // safety: the frame of a registered task is live until it deregisters
+        // itself, which it cannot do while its registry entry is borrowed
+        unsafe { self.0.as_ref() }
+    }
+
     /// The location of this task.
-    pub fn location(&self) -> crate::Location {
+    pub fn location(&self) -> Location {
         // safety: we promise to not inspect the subframes without first locking
         let frame = unsafe { self.0.as_ref() };
         frame.location()
     }
 
+    /// How this task was instrumented.
+    pub fn origin(&self) -> crate::Origin {
+        // safety: we promise to not inspect the subframes without first locking
+        let frame = unsafe { self.0.as_ref() };
+        frame.origin()
+    }
+
+    /// The [annotations](crate::annotate_value) of this task's root frame, in
+    /// the order in which they were first attached.
+    ///
+    /// If `block_until_idle` is `true`, this blocks until the task is not
+    /// being polled; otherwise, this produces `None` if it is.
+    pub fn metadata(
+        &self,
+        block_until_idle: bool,
+    ) -> Option<Vec<(&'static str, crate::AnnotationValue)>> {
+        self.with_locked(block_until_idle, |frame, subframes_locked| {
+            // safety: the metadata is only read if the root is locked
+            subframes_locked.then(|| unsafe { frame.annotations() })
+        })
+    }
+
     /// Pretty-prints this task as a tree.
     ///
     /// If `block_until_idle` is `true`, this routine will block until the task
@@ -52,39 +672,277 @@ impl Task {
     /// If `block_until_idle` is `false`, and the task is being polled, the
     /// output will not include the sub-frames, instead simply note that the
     /// task is being polled.
+    ///
+    /// The tree does not end with a newline.
     pub fn pretty_tree(&self, block_until_idle: bool) -> String {
+        self.pretty_tree_at(block_until_idle, Instant::now())
+    }
+
+    /// Pretty-prints this task as a tree, as [`pretty_tree`](Task::pretty_tree)
+    /// does, but rendering frames at most `depth_limit` (if any) beneath the
+    /// root. In place of the frames beneath a frame at the limit, their number
+    /// is noted; e.g.:
+    /// ```text
+    /// ╼ app::decode::{{closure}} at src/main.rs:8:1
+    ///   └╼ 2x app::decode::{{closure}} at src/main.rs:8:1
+    ///      └┈ … 37 more frames
+    /// ```
+    /// Identical siblings are consolidated only if as many frames are omitted
+    /// beneath each; the number beneath a consolidated frame is that beneath
+    /// each of its copies.
+    pub fn pretty_tree_with(&self, depth_limit: Option<usize>, block_until_idle: bool) -> String {
+        let mut tree = self.snapshot(block_until_idle, Instant::now());
+        if let Some(depth) = depth_limit {
+            tree.prune(depth);
+        }
+        tree.to_string()
+    }
+
+    /// Pretty-prints this task as a tree into `w`.
+    ///
+    /// The tree is copied while the task is locked, and written after the
+    /// lock is released; so, however slow `w` is, it does not delay polls of
+    /// the task. Otherwise, this behaves like [`pretty_tree`](Task::pretty_tree).
+    pub fn write_tree<W: fmt::Write>(&self, w: &mut W, block_until_idle: bool) -> fmt::Result {
+        write!(w, "{}", self.snapshot(block_until_idle, Instant::now()))
+    }
+
+    /// Renders this task on a single line, for log sinks that expect one line
+    /// per record; e.g.:
+    /// ```text
+    /// app::serve@src/main.rs:5 > {app::handle@src/main.rs:12 x2, app::accept@src/main.rs:20}
+    /// ```
+    /// Frames are nested with `>`, sibling frames are listed in `{…}`, and
+    /// identical, adjacent siblings are consolidated into one, marked `xN`.
+    /// Chains of [detail](Location::frame_detail) frames are collapsed, and
+    /// noted with `(via N)`.
+    ///
+    /// If `block_until_idle` is `false`, and the task is being polled, only
+    /// its root is rendered, followed by ` [POLLING]`; see
+    /// [`pretty_tree`](Task::pretty_tree).
+    pub fn compact_line(&self, block_until_idle: bool) -> String {
+        let mut line = String::new();
+        self.snapshot(block_until_idle, Instant::now())
+            .write_compact(&mut line)
+            .expect("writing to a `String` cannot fail");
+        line
+    }
+
+    /// Renders this task as numbered stacks, as by `pstack`: one per leaf
+    /// frame, from the leaf (`#0`) to the root of the task, separated by
+    /// blank lines; e.g.:
+    /// ```text
+    /// #0 app::read::{{closure}} at src/main.rs:30:1
+    /// #1 app::handle::{{closure}} at src/main.rs:12:1
+    /// #2 app::serve::{{closure}} at src/main.rs:8:1
+    ///
+    /// #0 app::tick::{{closure}} at src/main.rs:40:1
+    /// #1 app::serve::{{closure}} at src/main.rs:8:1
+    /// ```
+    /// The leaves are listed in the order of the [tree](Task::pretty_tree),
+    /// and each stack lists their ancestors as a
+    /// [`backtrace`](crate::backtrace) from the leaf would, but continuing
+    /// past [barrier](Location::frame_barrier) frames to the root.
+    ///
+    /// If `block_until_idle` is `false`, and the task is being polled, only
+    /// its root is rendered, as `#0`, followed by ` [POLLING]`; see
+    /// [`pretty_tree`](Task::pretty_tree). The stacks do not end with a
+    /// newline.
+    pub fn pretty_stacks(&self, block_until_idle: bool) -> String {
+        let (stacks, polling) = self.stacks(block_until_idle);
+        render_stacks(&stacks, polling.as_ref())
+    }
+
+    /// Renders this task as numbered stacks, as by
+    /// [`pretty_stacks`](Task::pretty_stacks), unless it is being polled.
+    pub(crate) fn pretty_stacks_if_idle(&self) -> Option<String> {
+        let (stacks, polling) = self.stacks(false);
+        polling.is_none().then(|| render_stacks(&stacks, None))
+    }
+
+    /// Copies the locations of the ancestors of each leaf frame of this task,
+    /// and, if it was being polled (in which case only its root is copied),
+    /// why its lock was unavailable.
+    fn stacks(&self, block_until_idle: bool) -> (Vec<Vec<Location>>, Option<Polling>) {
+        /// Pushes the locations of the ancestors of each leaf beneath `frame`
+        /// onto `stacks`.
+        ///
+        /// # Safety
+        /// The caller must ensure that the root of `frame` is locked.
+        unsafe fn leaves(frame: &Frame, stacks: &mut Vec<Vec<Location>>) {
+            let mut subframes = frame.subframes().peekable();
+            if subframes.peek().is_none() {
+                stacks.push(frame.ancestors().map(Frame::location).collect());
+            }
+            for subframe in subframes {
+                leaves(subframe, stacks);
+            }
+        }
+
+        // the locations are copied while the task is locked, and rendered
+        // once it is released
+        // safety: the subframes are only inspected if they are locked
+        self.with_locked(block_until_idle, |frame, subframes_locked| {
+            let mut stacks = Vec::new();
+            if subframes_locked {
+                unsafe { leaves(frame, &mut stacks) };
+            } else {
+                stacks.push(vec![frame.location()]);
+            }
+            (stacks, (!subframes_locked).then(|| Polling::of(self.id())))
+        })
+    }
+
+    /// Pretty-prints this task as a tree, rendering ages relative to `epoch`.
+    pub(crate) fn pretty_tree_at(&self, block_until_idle: bool, epoch: Instant) -> String {
+        self.snapshot(block_until_idle, epoch).to_string()
+    }
+
+    /// Captures the tree of this task, rendering ages relative to `epoch`.
+    ///
+    /// The root of the task is locked only for the duration of the capture.
+    pub(crate) fn snapshot(&self, wait: impl Into<Wait>, epoch: Instant) -> TaskTree {
+        // safety: the subframes are only captured if they are locked
+        let tree = self.with_locked(wait, |frame, subframes_locked| unsafe {
+            TaskTree::capture(frame, self.id(), subframes_locked, epoch)
+        });
+        self.cache(tree, epoch)
+    }
+
+    /// Captures the tree of this task, rendering ages relative to `epoch`,
+    /// unless it is being polled.
+    pub(crate) fn snapshot_if_idle(&self, epoch: Instant) -> Option<TaskTree> {
+        // safety: the subframes are only captured if they are locked
+        let tree = self.with_locked(Wait::No, |frame, subframes_locked| {
+            subframes_locked.then(|| unsafe { TaskTree::capture(frame, self.id(), true, epoch) })
+        })?;
+        Some(self.cache(tree, epoch))
+    }
+
+    /// Remembers `tree`, or (if the task was being polled) recalls its
+    /// last-known subframes, if the last-known trees of tasks are cached.
+    fn cache(&self, mut tree: TaskTree, epoch: Instant) -> TaskTree {
+        if cache_last_tree() {
+            if tree.is_polling() {
+                tree.recall(self.id(), epoch);
+            } else {
+                tree.remember(self.id());
+            }
+        }
+        tree
+    }
+
+    /// Produces `Some(true)` if any frame of this task matches `predicate`,
+    /// or `None` if the task is being polled and `block_until_idle` is
+    /// `false`.
+    pub(crate) fn contains<P>(&self, block_until_idle: bool, predicate: &mut P) -> Option<bool>
+    where
+        P: FnMut(&Location) -> bool,
+    {
+        /// # Safety
+        /// The caller must ensure that the root of `frame` is locked.
+        unsafe fn search<P>(frame: &Frame, predicate: &mut P) -> bool
+        where
+            P: FnMut(&Location) -> bool,
+        {
+            predicate(&frame.location())
+                || frame
+                    .subframes()
+                    .any(|subframe| search(subframe, &mut *predicate))
+        }
+
+        self.with_locked(block_until_idle, |frame, subframes_locked| {
+            // safety: the subframes are only searched if they are locked
+            subframes_locked.then(|| unsafe { search(frame, predicate) })
+        })
+    }
+
+    /// Produces the number of frames in the tree of this task, or `None` if
+    /// the task is being polled and `block_until_idle` is `false`.
+    pub(crate) fn frame_count(&self, block_until_idle: bool) -> Option<usize> {
+        /// # Safety
+        /// The caller must ensure that the root of `frame` is locked.
+        unsafe fn count(frame: &Frame) -> usize {
+            1 + frame
+                .subframes()
+                .map(|subframe| count(subframe))
+                .sum::<usize>()
+        }
+
+        self.with_locked(block_until_idle, |frame, subframes_locked| {
+            // safety: the subframes are only counted if they are locked
+            subframes_locked.then(|| unsafe { count(frame) })
+        })
+    }
+
+    /// Produces `true` if this is the task of the currently-active frame, whose
+    /// root is then already locked by this thread.
+    fn is_current(&self) -> bool {
+        Frame::with_active(|maybe_frame| {
+            maybe_frame.is_some_and(|frame| NonNull::from(frame.root()) == self.0)
+        })
+    }
+
+    /// Produces the lock of this task, which outlives it.
+    fn shared_mutex(&self) -> Option<Arc<crate::sync::Mutex<()>>> {
+        // safety: the lock is not used to inspect the subframes
+        unsafe { self.0.as_ref() }.mutex().cloned()
+    }
+
+    /// Invokes `f` with the root frame of this task, and whether its subframes
+    /// are locked (and so may be inspected).
+    ///
+    /// If `block_until_idle` is `true`, this blocks until the task is not
+    /// being polled. Otherwise, if the task is being polled, its subframes are
+    /// not locked.
+    fn with_locked<F, R>(&self, wait: impl Into<Wait>, f: F) -> R
+    where
+        F: FnOnce(&Frame, bool) -> R,
+    {
         use crate::sync::TryLockError;
 
         // safety: we promise to not inspect the subframes without first locking
         let frame = unsafe { self.0.as_ref() };
 
-        let current_task: Option<NonNull<Frame>> =
-            Frame::with_active(|maybe_frame| maybe_frame.map(|frame| frame.root().into()));
-
-        let maybe_lock = &frame
+        let maybe_lock = frame
             .mutex()
             // don't grab a lock if we're *in* the active task (it's already locked, then)
-            .filter(|_| Some(self.0) != current_task)
-            .map(|mutex| {
-                if block_until_idle {
-                    mutex.lock().map_err(TryLockError::from)
-                } else {
-                    mutex.try_lock()
-                }
+            .filter(|_| !self.is_current())
+            .map(|mutex| match wait.into() {
+                Wait::No => mutex.try_lock(),
+                Wait::Forever => mutex.lock().map_err(TryLockError::from),
+                Wait::Until(deadline) => loop {
+                    match mutex.try_lock() {
+                        Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                            crate::sync::backoff()
+                        }
+                        result => break result,
+                    }
+                },
             });
 
-        let subframes_locked = match maybe_lock {
-            None | Some(Ok(..)) => true,
+        // Poisoning is ignored, as in `activate`: a poll that unwound leaves
+        // the tree of its task consistent, and its (still held) guard locks
+        // the task all the same.
+        let subframes_locked = match &maybe_lock {
+            None | Some(Ok(..)) | Some(Err(TryLockError::Poisoned(..))) => true,
             Some(Err(TryLockError::WouldBlock)) => false,
-            Some(Err(err @ TryLockError::Poisoned(..))) => panic!("{:?}", err),
         };
 
-        let mut string = String::new();
+        f(frame, subframes_locked)
+    }
+}
 
-        unsafe {
-            frame.fmt(&mut string, subframes_locked).unwrap();
-        }
+impl PartialEq for Task {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_as(other)
+    }
+}
+
+impl Eq for Task {}
 
-        string
+impl Hash for Task {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id().hash(state)
     }
 }
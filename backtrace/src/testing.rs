@@ -0,0 +1,430 @@
+//! Utilities for testing code instrumented with `async-backtrace`.
+//!
+//! Comparing whole taskdumps against expected strings is brittle: they
+//! include every task in the process, file positions, and frames that are
+//! incidental to what is being tested. The utilities of this module instead
+//! inspect the *structure* of taskdumps, or, with a [`FrameProbe`], the
+//! frames of a future polled by hand. Where whole taskdumps are compared
+//! nonetheless, [`normalize`] them (or use
+//! [`assert_dump_eq!`](crate::assert_dump_eq)), so that they do not depend
+//! on file positions.
+//!
+//! Requires the `test-utils` feature.
+
+use std::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use crate::{tree_text::name_matches, FrameTree, Location, TaskTree};
+
+pub use crate::tree_text::{ParseError, ParsedFrame};
+
+/// Parses the text of a taskdump (as produced by
+/// [`taskdump_tree`](crate::taskdump_tree)) into the trees of its tasks, as
+/// by [`ParsedDump::from_tree_text`](crate::ParsedDump::from_tree_text).
+///
+/// ## Example
+/// ```
+/// let dump = "\
+/// ╼ app::serve::{{closure}} at src/main.rs:5:1
+///   └╼ app::handle::{{closure}} at src/main.rs:12:1";
+/// let tasks = async_backtrace::testing::parse_taskdump(dump).unwrap();
+/// assert_eq!(tasks.len(), 1);
+/// assert!(tasks[0].children()[0].matches("handle"));
+/// ```
+pub fn parse_taskdump(text: &str) -> Result<Vec<ParsedFrame>, ParseError> {
+    crate::ParsedDump::from_tree_text(text).map(crate::ParsedDump::into_tasks)
+}
+
+/// Something from which the trees of tasks can be produced, for the
+/// assertions of this module.
+pub trait Taskdump {
+    /// Produces the trees of the tasks of this taskdump.
+    fn parse(&self) -> Result<Vec<ParsedFrame>, ParseError>;
+}
+
+impl Taskdump for str {
+    fn parse(&self) -> Result<Vec<ParsedFrame>, ParseError> {
+        parse_taskdump(self)
+    }
+}
+
+impl Taskdump for String {
+    fn parse(&self) -> Result<Vec<ParsedFrame>, ParseError> {
+        parse_taskdump(self)
+    }
+}
+
+impl<T: Taskdump + ?Sized> Taskdump for &T {
+    fn parse(&self) -> Result<Vec<ParsedFrame>, ParseError> {
+        (**self).parse()
+    }
+}
+
+/// Produces `true` if, in any of `tasks`, a frame matching `ancestor` has a
+/// descendant (not necessarily a child) matching `descendant`.
+///
+/// Frames are matched with [`ParsedFrame::matches`].
+pub fn is_ancestor(tasks: &[ParsedFrame], ancestor: &str, descendant: &str) -> bool {
+    tasks
+        .iter()
+        .flat_map(ParsedFrame::iter)
+        .filter(|frame| frame.matches(ancestor))
+        .any(|frame| {
+            frame
+                .children()
+                .iter()
+                .flat_map(ParsedFrame::iter)
+                .any(|frame| frame.matches(descendant))
+        })
+}
+
+/// Invoked by [`assert_ancestor!`](crate::assert_ancestor).
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_ancestor<D: Taskdump + fmt::Display + ?Sized>(
+    dump: &D,
+    ancestor: &str,
+    descendant: &str,
+) {
+    let tasks = match dump.parse() {
+        Ok(tasks) => tasks,
+        Err(err) => panic!("could not parse taskdump ({}):\n{}", err, dump),
+    };
+    if !is_ancestor(&tasks, ancestor, descendant) {
+        panic!(
+            "expected a frame named `{}` to be an ancestor of one named `{}`, in:\n{}",
+            ancestor, descendant, dump
+        );
+    }
+}
+
+/// Normalizes the text of a taskdump for comparison against an expected
+/// string (e.g., in snapshot tests), by replacing the line and column of each
+/// location with `LINE` and `COL`; e.g., `app::serve::{{closure}} at
+/// src/main.rs:LINE:COL`. Locations rendered without their column (in the
+/// [`FileLine`](crate::LocationStyle::FileLine) style) become, e.g.,
+/// `src/main.rs:LINE`, and the thread of each `[POLLING on <thread>]` marker
+/// (of the `thread-report` feature) becomes `THREAD`. See [`NormalizeOptions`] to normalize dumps
+/// further, and [`assert_dump_eq!`](crate::assert_dump_eq) to compare them.
+pub fn normalize(dump: &str) -> String {
+    NormalizeOptions::new().normalize(dump)
+}
+
+/// Options for [normalizing](normalize) taskdumps.
+///
+/// ## Example
+/// ```
+/// use async_backtrace::testing::NormalizeOptions;
+///
+/// let dump = "\
+/// ╼ app::Pool<u8>::get::{{closure}} at src/pool.rs:20:1
+/// ╼ app::serve::{{closure}} at src/main.rs:8:1";
+/// assert_eq!(
+///     NormalizeOptions::new()
+///         .strip_generics(true)
+///         .sort_tasks(true)
+///         .normalize(dump),
+///     "\
+/// ╼ app::Pool::get::{{closure}} at src/pool.rs:LINE:COL
+/// ╼ app::serve::{{closure}} at src/main.rs:LINE:COL"
+/// );
+/// ```
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NormalizeOptions {
+    strip_generics: bool,
+    sort_tasks: bool,
+}
+
+impl NormalizeOptions {
+    /// Produces options that only replace lines and columns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// If `strip` is `true`, removes the generic parameters of names; e.g.,
+    /// `app::Pool<u8>::get` becomes `app::Pool::get`. Only parameters that
+    /// directly follow an identifier are removed, so qualified paths (e.g.,
+    /// `<app::Pool as Get>::get`) are left as they are.
+    pub fn strip_generics(mut self, strip: bool) -> Self {
+        self.strip_generics = strip;
+        self
+    }
+
+    /// If `sort` is `true`, sorts the trees of tasks (once normalized), so
+    /// that dumps of tasks in different orders compare equal. Each tree runs
+    /// from the line of its root (which begins with `╼`) to the next; any
+    /// lines before the first tree (e.g., a
+    /// [summary header](crate::TaskdumpOptions::summary_header)) stay first.
+    pub fn sort_tasks(mut self, sort: bool) -> Self {
+        self.sort_tasks = sort;
+        self
+    }
+
+    /// Produces `dump`, normalized.
+    pub fn normalize(&self, dump: &str) -> String {
+        let mut normalized = strip_threads(&strip_positions(dump));
+        if self.strip_generics {
+            normalized = strip_generics(&normalized);
+        }
+        if self.sort_tasks {
+            normalized = sort_tasks(&normalized);
+        }
+        normalized
+    }
+}
+
+/// Replaces each `:<line>:<column>` in `dump` with `:LINE:COL`, and each
+/// `:<line>` that directly follows a `.rs` file with `:LINE`.
+fn strip_positions(mut dump: &str) -> String {
+    /// Produces the length of the `:<digits>` prefix of `s`, if any.
+    fn number(s: &str) -> Option<usize> {
+        let digits = s
+            .strip_prefix(':')?
+            .bytes()
+            .take_while(u8::is_ascii_digit)
+            .count();
+        (digits > 0).then_some(1 + digits)
+    }
+
+    let mut stripped = String::with_capacity(dump.len());
+    while let Some(colon) = dump.find(':') {
+        stripped.push_str(&dump[..colon]);
+        dump = &dump[colon..];
+        let line = number(dump);
+        match line.map(|line| (line, number(&dump[line..]))) {
+            Some((line, Some(column))) => {
+                stripped.push_str(":LINE:COL");
+                dump = &dump[line + column..];
+            }
+            // a location rendered without its column
+            Some((line, None)) if stripped.ends_with(".rs") => {
+                stripped.push_str(":LINE");
+                dump = &dump[line..];
+            }
+            _ => {
+                stripped.push(':');
+                dump = &dump[1..];
+            }
+        }
+    }
+    stripped.push_str(dump);
+    stripped
+}
+
+/// Replaces the thread of each `[POLLING on <thread>]` in `dump` with
+/// `THREAD`.
+fn strip_threads(mut dump: &str) -> String {
+    const MARKER: &str = "[POLLING on ";
+    let mut stripped = String::with_capacity(dump.len());
+    while let Some(start) = dump.find(MARKER) {
+        let (before, after) = dump.split_at(start + MARKER.len());
+        stripped.push_str(before);
+        match after.find(']') {
+            Some(end) => {
+                stripped.push_str("THREAD");
+                dump = &after[end..];
+            }
+            None => dump = after,
+        }
+    }
+    stripped.push_str(dump);
+    stripped
+}
+
+/// Removes each `<…>` (with any nested within it) that directly follows an
+/// identifier in `dump`.
+fn strip_generics(dump: &str) -> String {
+    let mut stripped = String::with_capacity(dump.len());
+    let mut depth = 0;
+    let mut previous = None;
+    for c in dump.chars() {
+        match c {
+            '<' if depth > 0 => depth += 1,
+            '<' if previous.is_some_and(|p: char| p.is_alphanumeric() || p == '_') => depth = 1,
+            '>' if depth > 0 => depth -= 1,
+            // (a line never ends within generic parameters)
+            '\n' if depth > 0 => {
+                depth = 0;
+                stripped.push(c);
+            }
+            _ if depth > 0 => {}
+            _ => stripped.push(c),
+        }
+        previous = Some(c);
+    }
+    stripped
+}
+
+/// Sorts the trees of tasks of `dump`, after any lines that precede them.
+fn sort_tasks(dump: &str) -> String {
+    let mut lines = dump.split('\n');
+    let mut preamble = Vec::new();
+    let mut tasks: Vec<Vec<&str>> = Vec::new();
+    for line in &mut lines {
+        if line.trim_start().starts_with('╼') {
+            tasks.push(vec![line]);
+        } else if let Some(task) = tasks.last_mut() {
+            task.push(line);
+        } else {
+            preamble.push(line);
+        }
+    }
+    tasks.sort();
+    preamble
+        .into_iter()
+        .chain(tasks.into_iter().flatten())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Invoked by [`assert_dump_eq!`](crate::assert_dump_eq).
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_dump_eq(left: &str, right: &str) {
+    let (left, right) = (normalize(left), normalize(right));
+    if left != right {
+        panic!(
+            "taskdumps differ (once normalized):\n--- left ---\n{}\n--- right ---\n{}",
+            left, right
+        );
+    }
+}
+
+std::thread_local! {
+    /// The ids of the tasks registered so far by the poll of a [`FrameProbe`]
+    /// in progress on this thread, if any.
+    static REGISTERED: RefCell<Option<Vec<u64>>> = const { RefCell::new(None) };
+}
+
+/// Notes that the task `id` was registered, for the poll of a [`FrameProbe`]
+/// in progress on this thread (if any).
+pub(crate) fn registered(id: u64) {
+    let _ = REGISTERED.try_with(|registered| {
+        if let Some(ids) = registered.borrow_mut().as_mut() {
+            ids.push(id);
+        }
+    });
+}
+
+/// A future, polled by hand, whose frames may be inspected between polls;
+/// for the unit tests of combinators (and other futures) that embed frames.
+///
+/// The probe records the tasks registered by its polls (i.e., the framed
+/// futures that it polls as roots), and inspects their trees with the
+/// structured [`snapshot`](crate::snapshot) APIs, rather than by parsing
+/// taskdumps; so, it is unaffected by the other tasks of the process.
+///
+/// ## Example
+/// ```
+/// use async_backtrace::testing::FrameProbe;
+///
+/// #[async_backtrace::framed]
+/// async fn outer() {
+///     futures::join!(inner(), inner());
+/// }
+///
+/// #[async_backtrace::framed]
+/// async fn inner() {
+///     std::future::pending::<()>().await
+/// }
+///
+/// let mut probe = FrameProbe::new(outer());
+/// assert!(probe.poll().is_pending());
+///
+/// // `outer` is registered as a task...
+/// let roots = probe.registered_roots();
+/// assert_eq!(roots.len(), 1);
+/// // ...and is the parent of both `inner`s
+/// let children = probe.children_of("outer");
+/// assert_eq!(children.len(), 2);
+/// assert!(children.iter().all(|child| child.name().unwrap().contains("inner")));
+///
+/// // `outer` is deregistered once dropped
+/// assert!(probe.dropped_cleanly());
+/// ```
+pub struct FrameProbe<F> {
+    future: Pin<Box<F>>,
+    /// The ids of the tasks registered by the polls of `future`.
+    roots: Vec<u64>,
+}
+
+impl<F: Future> FrameProbe<F> {
+    /// Wraps `future`, which is not polled until [`poll`](Self::poll) is.
+    pub fn new(future: F) -> Self {
+        Self {
+            future: Box::pin(future),
+            roots: Vec::new(),
+        }
+    }
+
+    /// Polls the future once, with a waker that does nothing.
+    pub fn poll(&mut self) -> Poll<F::Output> {
+        let waker = futures::task::noop_waker();
+        self.poll_with(&mut Context::from_waker(&waker))
+    }
+
+    /// Polls the future once, with the waker of `cx`.
+    pub fn poll_with(&mut self, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // (probes may be polled within the polls of other probes)
+        let outer = REGISTERED.with(|registered| registered.replace(Some(Vec::new())));
+        let roots = &mut self.roots;
+        let _restore = crate::defer(|| {
+            let registered = REGISTERED.with(|registered| registered.replace(outer));
+            roots.extend(registered.unwrap_or_default());
+        });
+        self.future.as_mut().poll(cx)
+    }
+
+    /// Produces the trees of the tasks registered by the polls of the future,
+    /// that are registered still, in the order in which they were registered.
+    ///
+    /// Tasks that are being polled (e.g., on other threads) are not waited
+    /// for, and are [marked](crate::TaskTree::is_polling) as such.
+    pub fn registered_roots(&self) -> Vec<TaskTree> {
+        let epoch = Instant::now();
+        self.roots
+            .iter()
+            .filter_map(|&id| crate::tasks::task(id))
+            .map(|task| task.snapshot(false, epoch))
+            .collect()
+    }
+
+    /// Produces the locations of the children of the frames matching `path`
+    /// (as by [`ParsedFrame::matches`]), in the trees of
+    /// [`registered_roots`](Self::registered_roots), in the order in which
+    /// taskdumps render them.
+    pub fn children_of(&self, path: &str) -> Vec<Location> {
+        fn visit(frame: &FrameTree, path: &str, children: &mut Vec<Location>) {
+            let matches = frame
+                .location()
+                .name()
+                .is_some_and(|name| name_matches(name, path));
+            for child in frame.children() {
+                if matches {
+                    children.push(child.location());
+                }
+                visit(child, path, children);
+            }
+        }
+
+        let mut children = Vec::new();
+        for tree in self.registered_roots() {
+            visit(tree.root(), path, &mut children);
+        }
+        children
+    }
+
+    /// Drops the future, and produces `true` if every task registered by its
+    /// polls was deregistered.
+    pub fn dropped_cleanly(self) -> bool {
+        let Self { future, roots } = self;
+        drop(future);
+        roots.iter().all(|&id| crate::tasks::task(id).is_none())
+    }
+}
@@ -0,0 +1,238 @@
+//! The frame active on each OS thread (e.g., on each worker of a runtime), as
+//! reported by [`thread_report`](crate::thread_report).
+//!
+//! The active frame of each thread is mirrored into a global table of
+//! per-thread slots upon each activation, and restored upon its end; but only
+//! with the `thread-report` feature, as this costs an (uncontended) lock upon
+//! each poll of every frame. Otherwise, [`Mirror`] is empty, and mirroring is
+//! free.
+
+#[cfg(feature = "thread-report")]
+mod enabled {
+    use std::{
+        fmt,
+        sync::{Arc, Mutex},
+        thread::ThreadId,
+    };
+
+    use once_cell::sync::Lazy;
+
+    use crate::{Frame, Location};
+
+    /// The task and location of the frame active on a thread.
+    #[derive(Debug, Copy, Clone)]
+    struct Active {
+        task_id: u64,
+        location: Location,
+    }
+
+    /// The slot of a thread, into which its active frame is mirrored.
+    #[derive(Debug)]
+    struct Slot {
+        thread_id: ThreadId,
+        name: Option<String>,
+        active: Mutex<Option<Active>>,
+    }
+
+    impl Slot {
+        fn active(&self) -> std::sync::MutexGuard<'_, Option<Active>> {
+            // (nothing panics while the lock is held)
+            self.active.lock().unwrap_or_else(|err| err.into_inner())
+        }
+    }
+
+    /// The slot of every thread that has activated a frame, in the order in
+    /// which they first did.
+    static SLOTS: Lazy<Mutex<Vec<Arc<Slot>>>> = Lazy::new(Default::default);
+
+    /// The slot of a thread, which is removed from [`SLOTS`] when the thread
+    /// exits.
+    struct Registration(Arc<Slot>);
+
+    impl Registration {
+        fn new() -> Self {
+            let thread = std::thread::current();
+            let slot = Arc::new(Slot {
+                thread_id: thread.id(),
+                name: thread.name().map(str::to_owned),
+                active: Mutex::new(None),
+            });
+            slots().push(slot.clone());
+            Self(slot)
+        }
+    }
+
+    impl Drop for Registration {
+        fn drop(&mut self) {
+            slots().retain(|slot| !Arc::ptr_eq(slot, &self.0));
+        }
+    }
+
+    std::thread_local! {
+        static SLOT: Registration = Registration::new();
+    }
+
+    fn slots() -> std::sync::MutexGuard<'static, Vec<Arc<Slot>>> {
+        SLOTS.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// The frame that was active on this thread before an activation, to be
+    /// [restored](Mirror::restore) once it ends.
+    pub(crate) struct Mirror(Option<Active>);
+
+    impl Mirror {
+        /// Mirrors the activation of `frame` on this thread.
+        pub(crate) fn enter(frame: &Frame) -> Self {
+            // (the slot of a thread that is exiting cannot be reached)
+            let previous = SLOT.try_with(|slot| {
+                let mut active = slot.0.active();
+                let previous = *active;
+                // a subframe is (almost always) activated within the
+                // activation of its task, whose id is then already mirrored
+                let task_id = frame
+                    .task_id()
+                    .or_else(|| previous.map(|active| active.task_id))
+                    .or_else(|| frame.root().task_id());
+                *active = task_id.map(|task_id| Active {
+                    task_id,
+                    location: frame.location(),
+                });
+                previous
+            });
+            Self(previous.unwrap_or(None))
+        }
+
+        /// Restores the frame that was active before the activation.
+        pub(crate) fn restore(self) {
+            let _ = SLOT.try_with(|slot| *slot.0.active() = self.0);
+        }
+    }
+
+    /// Produces the frame active on each OS thread that has one (e.g., on each
+    /// busy worker of a runtime), in the order in which the threads first
+    /// activated a frame.
+    ///
+    /// Each is reported as of its most recent activation or return; a thread
+    /// that is not polling a framed future is omitted. Requires the
+    /// `thread-report` feature.
+    ///
+    /// ## Example
+    /// ```
+    /// for activity in async_backtrace::thread_report() {
+    ///     // prints, e.g.:
+    ///     // worker-1: task #3 at app::spin::{{closure}} at src/main.rs:8:1
+    ///     println!("{}", activity);
+    /// }
+    /// ```
+    pub fn thread_report() -> Vec<ThreadActivity> {
+        slots()
+            .iter()
+            .filter_map(|slot| {
+                let active = (*slot.active())?;
+                Some(ThreadActivity {
+                    thread_id: slot.thread_id,
+                    name: slot.name.clone(),
+                    task_id: active.task_id,
+                    location: active.location,
+                })
+            })
+            .collect()
+    }
+
+    /// Produces the name (or, for an unnamed thread, the id) of the thread on
+    /// which the task `task_id` is being polled, if any.
+    pub(crate) fn polling_thread(task_id: u64) -> Option<String> {
+        slots().iter().find_map(|slot| {
+            let active = (*slot.active())?;
+            (active.task_id == task_id).then(|| match &slot.name {
+                Some(name) => name.clone(),
+                None => format!("{:?}", slot.thread_id),
+            })
+        })
+    }
+
+    /// The frame active on a thread, as produced by [`thread_report`].
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    pub struct ThreadActivity {
+        thread_id: ThreadId,
+        name: Option<String>,
+        task_id: u64,
+        location: Location,
+    }
+
+    impl ThreadActivity {
+        /// The id of the thread.
+        pub fn thread_id(&self) -> ThreadId {
+            self.thread_id
+        }
+
+        /// The name of the thread, if it has one.
+        pub fn name(&self) -> Option<&str> {
+            self.name.as_deref()
+        }
+
+        /// The [id](crate::Task::id) of the task being polled on the thread.
+        pub fn task_id(&self) -> u64 {
+            self.task_id
+        }
+
+        /// The location of the frame active on the thread.
+        pub fn location(&self) -> Location {
+            self.location
+        }
+    }
+
+    /// Writes the section of [`thread_report`] with which taskdumps end, if
+    /// they [include it](crate::TaskdumpOptions::threads).
+    pub(crate) fn write_report<W: fmt::Write>(w: &mut W) -> fmt::Result {
+        w.write_str("== threads ==")?;
+        let report = thread_report();
+        if report.is_empty() {
+            return w.write_str("\n(no thread is polling a framed future)");
+        }
+        for activity in report {
+            write!(w, "\n{}", activity)?;
+        }
+        Ok(())
+    }
+
+    impl fmt::Display for ThreadActivity {
+        /// Renders the activity as, e.g.,
+        /// `worker-1: task #3 at app::spin::{{closure}} at src/main.rs:8:1`,
+        /// naming unnamed threads by their id.
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match &self.name {
+                Some(name) => f.write_str(name)?,
+                None => write!(f, "{:?}", self.thread_id)?,
+            }
+            write!(f, ": task #{} at {}", self.task_id, self.location)
+        }
+    }
+}
+
+#[cfg(not(feature = "thread-report"))]
+mod enabled {
+    use crate::Frame;
+
+    /// The (unmirrored) frame that was active on this thread.
+    pub(crate) struct Mirror {}
+
+    impl Mirror {
+        pub(crate) fn enter(_: &Frame) -> Self {
+            Self {}
+        }
+
+        pub(crate) fn restore(self) {}
+    }
+
+    /// Writes nothing, as no thread is reported.
+    pub(crate) fn write_report<W: std::fmt::Write>(_: &mut W) -> std::fmt::Result {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "thread-report")]
+pub(crate) use enabled::polling_thread;
+#[cfg(feature = "thread-report")]
+pub use enabled::{thread_report, ThreadActivity};
+pub(crate) use enabled::{write_report, Mirror};
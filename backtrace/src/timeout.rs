@@ -0,0 +1,127 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::fmt;
+use std::time::Duration;
+
+use pin_project_lite::pin_project;
+
+use crate::{Framed, Location};
+
+/// The location of the frame within which [`TimeoutFramed`] polls its future.
+const LOCATION: Location = Location::from_components(
+    "async_backtrace::timeout_framed",
+    &(file!(), line!(), column!()),
+);
+
+/// An extension trait for [`Future`]s, which times them out with
+/// [`tokio::time`], naming the frames that were cancelled.
+///
+/// Requires the `tokio` feature.
+pub trait TimeoutExt: Future + Sized {
+    /// Requires this future to complete within `duration`.
+    ///
+    /// Like [`tokio::time::timeout`], but if `duration` elapses, the trees of
+    /// the frames of this future are captured just before it is dropped, and
+    /// included in the [`Elapsed`] error; e.g.:
+    /// ```text
+    /// deadline has elapsed; cancelled:
+    /// ╼ app::fetch::{{closure}} at src/main.rs:10:1
+    ///   └╼ app::connect::{{closure}} at src/main.rs:20:1
+    /// ```
+    ///
+    /// ## Example
+    /// ```
+    /// use async_backtrace::TimeoutExt;
+    /// use std::time::Duration;
+    ///
+    /// #[async_backtrace::framed]
+    /// async fn fetch() {
+    ///     std::future::pending::<()>().await
+    /// }
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let err = fetch()
+    ///     .timeout_framed(Duration::from_millis(1))
+    ///     .await
+    ///     .unwrap_err();
+    /// assert!(err.to_string().contains("fetch"));
+    /// # }
+    /// ```
+    fn timeout_framed(self, duration: Duration) -> TimeoutFramed<Self> {
+        TimeoutFramed {
+            future: Some(Framed::new(self, LOCATION)),
+            sleep: tokio::time::sleep(duration),
+        }
+    }
+}
+
+impl<F: Future> TimeoutExt for F {}
+
+pin_project! {
+    /// A future produced by [`TimeoutExt::timeout_framed`].
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct TimeoutFramed<F> {
+        // The framed future; `None` once it has timed out.
+        #[pin]
+        future: Option<Framed<F>>,
+        #[pin]
+        sleep: tokio::time::Sleep,
+    }
+}
+
+impl<F: Future> Future for TimeoutFramed<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        let mut future = match this.future.as_mut().as_pin_mut() {
+            Some(future) => future,
+            None => panic!("`TimeoutFramed` polled after completion"),
+        };
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        if this.sleep.poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        // The frames of the future still exist; capture them before they are
+        // dropped with it.
+        let cancelled = future
+            .capture_subframes()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        this.future.set(None);
+        Poll::Ready(Err(Elapsed { cancelled }))
+    }
+}
+
+/// The error produced by [`TimeoutFramed`] when its deadline elapses.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Elapsed {
+    cancelled: String,
+}
+
+impl Elapsed {
+    /// Produces the trees of the frames that were cancelled, in the format of
+    /// [`taskdump_tree`](crate::taskdump_tree); empty if the future had no
+    /// frames.
+    pub fn cancelled(&self) -> &str {
+        &self.cancelled
+    }
+}
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadline has elapsed")?;
+        if !self.cancelled.is_empty() {
+            write!(f, "; cancelled:\n{}", self.cancelled)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Elapsed {}
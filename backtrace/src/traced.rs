@@ -0,0 +1,141 @@
+//! Errors that carry the backtrace of the frame in which they were wrapped,
+//! as produced by [`TracedError::new`] and [`ResultExt::trace_async`].
+
+use std::{error::Error, fmt};
+
+use crate::AsyncBacktrace;
+
+/// An error, along with the [`backtrace`](crate::backtrace) of the frame in
+/// which it was constructed.
+///
+/// The backtrace is captured when the error is wrapped, so it records the
+/// async context that produced the error, which is usually gone by the time
+/// the error is logged. Errors are wrapped by [`TracedError::new`], by
+/// [`ResultExt::trace_async`], or by `?`, via [`From`]; e.g.:
+/// ```
+/// use async_backtrace::TracedError;
+/// use std::io;
+///
+/// #[async_backtrace::framed]
+/// async fn connect() -> Result<(), TracedError<io::Error>> {
+///     // the backtrace is captured here, within `connect`
+///     Err(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"))?;
+///     Ok(())
+/// }
+///
+/// # futures::executor::block_on(async {
+/// let err = connect().await.unwrap_err();
+/// assert_eq!(err.backtrace().len(), 1);
+/// // prints, e.g.:
+/// // refused; async backtrace:
+/// // #0 app::connect::{{closure}} at src/main.rs:5:1
+/// println!("{}", err);
+/// # });
+/// ```
+///
+/// Outside of any frame (and [attached](crate::ContextHandle::attach)
+/// context), the backtrace is empty, and the error is rendered as the error
+/// it wraps.
+pub struct TracedError<E> {
+    error: E,
+    backtrace: AsyncBacktrace,
+}
+
+impl<E> TracedError<E> {
+    /// Wraps `error`, capturing the backtrace of the active frame (if any).
+    pub fn new(error: E) -> Self {
+        let backtrace = crate::backtrace().unwrap_or_else(|| AsyncBacktrace::new(Box::new([])));
+        Self { error, backtrace }
+    }
+
+    /// The backtrace captured when the error was wrapped, innermost frame
+    /// first; it is empty if no frame was active.
+    pub fn backtrace(&self) -> &AsyncBacktrace {
+        &self.backtrace
+    }
+
+    /// The wrapped error.
+    pub fn get_ref(&self) -> &E {
+        &self.error
+    }
+
+    /// Unwraps the error, discarding its backtrace.
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+
+    /// Writes the backtrace, if it is not empty, after the error.
+    fn write_backtrace(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.backtrace.is_empty() {
+            return Ok(());
+        }
+        write!(f, "; async backtrace:\n{}", self.backtrace)
+    }
+}
+
+impl<E> From<E> for TracedError<E> {
+    /// Wraps `error`, as by [`TracedError::new`]; so that `?` captures the
+    /// backtrace at the point of the `?`.
+    fn from(error: E) -> Self {
+        Self::new(error)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for TracedError<E> {
+    /// Renders the error, followed by its backtrace (if any); e.g.:
+    /// ```text
+    /// refused; async backtrace:
+    /// #0 app::connect::{{closure}} at src/main.rs:20:1
+    /// #1 app::serve::{{closure}} at src/main.rs:8:1
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        self.write_backtrace(f)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Debug for TracedError<E> {
+    /// Renders the error as [`Debug`](fmt::Debug), followed by its backtrace
+    /// (if any), as in [`Display`](fmt::Display).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.error)?;
+        self.write_backtrace(f)
+    }
+}
+
+impl<E: Error> Error for TracedError<E> {
+    /// The source of the wrapped error (if any).
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// An extension trait for [`Result`]s, which wraps their errors in
+/// [`TracedError`]s.
+pub trait ResultExt<T, E> {
+    /// Wraps the error (if any) in a [`TracedError`], capturing the backtrace
+    /// of the active frame.
+    ///
+    /// ## Example
+    /// ```
+    /// use async_backtrace::{ResultExt, TracedError};
+    /// use std::num::ParseIntError;
+    ///
+    /// #[async_backtrace::framed]
+    /// async fn parse(input: &str) -> Result<u32, TracedError<ParseIntError>> {
+    ///     input.parse().trace_async()
+    /// }
+    ///
+    /// # futures::executor::block_on(async {
+    /// let err = parse("x").await.unwrap_err();
+    /// assert_eq!(err.backtrace().len(), 1);
+    /// # });
+    /// ```
+    fn trace_async(self) -> Result<T, TracedError<E>>;
+}
+
+impl<T, E> ResultExt<T, E> for Result<T, E> {
+    fn trace_async(self) -> Result<T, TracedError<E>> {
+        self.map_err(TracedError::new)
+    }
+}
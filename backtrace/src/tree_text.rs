@@ -0,0 +1,554 @@
+//! Parsing the text of taskdumps (as produced by
+//! [`taskdump_tree`](crate::taskdump_tree)) back into the trees of their
+//! tasks; e.g., to analyze dumps that were archived as text.
+
+use std::fmt;
+
+/// The trees of the tasks of a taskdump, parsed from its text by
+/// [`ParsedDump::from_tree_text`].
+///
+/// Its [`Display`](fmt::Display) renders it as it was parsed, modulo the
+/// normalization described by [`from_tree_text`](ParsedDump::from_tree_text).
+/// Its tasks may be compared with those of another dump by
+/// [`diff`](crate::diff).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParsedDump {
+    tasks: Vec<ParsedFrame>,
+}
+
+/// A frame parsed from the text of a taskdump.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParsedFrame {
+    location: ParsedLocation,
+    /// The rest of the frame's line, after its name; e.g., its file, line
+    /// and column, and its last recorded error.
+    rest: String,
+    /// The bracketed annotations that follow the frame's location, without
+    /// their brackets.
+    annotations: Vec<String>,
+    /// The number of helper frames collapsed above this one.
+    helper_frames: usize,
+    /// The number of identical, adjacent copies of this frame.
+    copies: usize,
+    /// If this frame is the root of a task that was being polled, its marker,
+    /// without brackets; e.g., `POLLING`, or `POLLING on worker-1`.
+    polling: Option<String>,
+    /// If this frame is the root of a task that was being polled, the age
+    /// (in seconds) of its last-known subframes, if they were dumped.
+    last_known_age: Option<u64>,
+    children: Vec<ParsedFrame>,
+}
+
+/// The location of a [`ParsedFrame`], as far as its taskdump rendered it.
+///
+/// Unlike a [`Location`](crate::Location), its file, line and column are each
+/// omitted by some [styles](crate::LocationStyle) of dumps.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ParsedLocation {
+    /// The name of the frame's function.
+    name: String,
+    file: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+impl ParsedDump {
+    /// Parses the text of a taskdump, as produced by
+    /// [`taskdump_tree`](crate::taskdump_tree), into the trees of its tasks.
+    ///
+    /// Consolidated frames (e.g., `3x app::fetch`), `[POLLING]` markers (and
+    /// those of the `thread-report` feature, `[POLLING on <thread>]` and
+    /// `[BUSY: lock unavailable]`, with or without a last-known tree), and
+    /// the bracketed annotations and collapsed helper frames that follow
+    /// locations are parsed, as are locations rendered in each
+    /// [`LocationStyle`](crate::LocationStyle).
+    ///
+    /// The [`Display`](fmt::Display) of the dump reproduces `text`, except
+    /// that empty lines (e.g., of a [`task_separator`] of `"\n\n"`) and a
+    /// [summary header] are dropped, and the last line does not end with a
+    /// newline.
+    ///
+    /// Dumps with an [`indent_width`] other than the default, or with
+    /// [source snippets] or [pruned] frames, are not accepted.
+    ///
+    /// [`task_separator`]: crate::TaskdumpOptions::task_separator
+    /// [summary header]: crate::TaskdumpOptions::summary_header
+    /// [`indent_width`]: crate::TaskdumpOptions::indent_width
+    /// [source snippets]: crate::TaskdumpOptions::source_snippets
+    /// [pruned]: crate::TaskdumpOptions::max_depth
+    ///
+    /// ## Example
+    /// ```
+    /// use async_backtrace::ParsedDump;
+    ///
+    /// let dump = "\
+    /// ╼ app::serve::{{closure}} at src/main.rs:5:1
+    ///   └╼ 2x app::handle::{{closure}} at src/main.rs:12:1 [panicked]
+    /// ╼ app::poll::{{closure}} at src/main.rs:20:1
+    ///   └┈ [POLLING]";
+    /// let parsed = ParsedDump::from_tree_text(dump).unwrap();
+    /// let handle = &parsed.tasks()[0].children()[0];
+    /// assert_eq!(handle.copies(), 2);
+    /// assert_eq!(handle.line(), Some(12));
+    /// assert_eq!(handle.annotations(), ["panicked"]);
+    /// assert!(parsed.tasks()[1].is_polling());
+    /// assert_eq!(parsed.to_string(), dump);
+    /// ```
+    pub fn from_tree_text(text: &str) -> Result<Self, ParseError> {
+        let mut roots: Vec<ParsedFrame> = Vec::new();
+        // the path from the root to the most recently parsed frame
+        let mut path: Vec<ParsedFrame> = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let error = |message| ParseError {
+                line: index + 1,
+                message,
+            };
+
+            if line.is_empty() || (line.starts_with("== ") && line.ends_with(" ==")) {
+                continue;
+            }
+
+            let (depth, connector, rest) = if let Some(rest) = line.strip_prefix('╼') {
+                (0, '╼', rest)
+            } else {
+                let mut rest = line
+                    .strip_prefix("  ")
+                    .ok_or_else(|| error("expected a frame"))?;
+                let mut depth = 1;
+                while let Some(stripped) = rest
+                    .strip_prefix("   ")
+                    .or_else(|| rest.strip_prefix("│  "))
+                {
+                    rest = stripped;
+                    depth += 1;
+                }
+                let mut chars = rest.chars();
+                if !matches!(chars.next(), Some('├' | '└')) {
+                    return Err(error("expected `├` or `└`"));
+                }
+                match chars.next() {
+                    Some(connector @ ('╼' | '┈')) => (depth, connector, chars.as_str()),
+                    _ => return Err(error("expected `╼` or `┈`")),
+                }
+            };
+            let rest = rest
+                .strip_prefix(' ')
+                .ok_or_else(|| error("expected a space"))?;
+
+            // fold the frames that are deeper than this line into their parents
+            if depth > path.len() {
+                return Err(error("frame is indented beneath no parent"));
+            }
+            while path.len() > depth.max(1) {
+                let frame = path.pop().unwrap();
+                path.last_mut().unwrap().children.push(frame);
+            }
+
+            if connector == '┈' {
+                // `[POLLING]`, and perhaps a last-known tree, which follows
+                let root = path
+                    .first_mut()
+                    .filter(|_| depth == 1)
+                    .ok_or_else(|| error("expected `[POLLING]` beneath a root"))?;
+                let (marker, last_known) = rest
+                    .strip_prefix('[')
+                    .and_then(|rest| rest.split_once(']'))
+                    .filter(|(marker, _)| is_polling_marker(marker))
+                    .ok_or_else(|| error("expected `[POLLING]` beneath a root"))?;
+                root.polling = Some(marker.to_string());
+                root.last_known_age = match last_known {
+                    "" => None,
+                    last_known => Some(
+                        last_known
+                            .strip_prefix(" last known tree, ")
+                            .and_then(|age| age.strip_suffix("s old (possibly stale):"))
+                            .and_then(|age| age.parse().ok())
+                            .ok_or_else(|| error("expected the age of a last-known tree"))?,
+                    ),
+                };
+                continue;
+            }
+
+            let frame = ParsedFrame::parse(rest).map_err(error)?;
+            if depth == 0 {
+                if let Some(root) = fold(&mut path) {
+                    roots.push(root);
+                }
+            }
+            path.push(frame);
+        }
+
+        roots.extend(fold(&mut path));
+        Ok(Self { tasks: roots })
+    }
+
+    /// Produces the trees of the tasks of this dump, by their roots.
+    pub fn tasks(&self) -> &[ParsedFrame] {
+        &self.tasks
+    }
+
+    /// Produces the trees of the tasks of this dump, by their roots.
+    pub fn into_tasks(self) -> Vec<ParsedFrame> {
+        self.tasks
+    }
+}
+
+/// Produces `true` if `marker` marks a task that was being polled: `POLLING`,
+/// or, with the `thread-report` feature, `POLLING on <thread>` or
+/// `BUSY: lock unavailable`.
+fn is_polling_marker(marker: &str) -> bool {
+    matches!(marker, "POLLING" | "BUSY: lock unavailable") || marker.starts_with("POLLING on ")
+}
+
+/// Folds each frame of `path` into its parent, producing the root (if any).
+fn fold(path: &mut Vec<ParsedFrame>) -> Option<ParsedFrame> {
+    while path.len() > 1 {
+        let frame = path.pop().unwrap();
+        path.last_mut().unwrap().children.push(frame);
+    }
+    path.pop()
+}
+
+impl ParsedFrame {
+    /// Parses the line of a frame, after its connector; e.g.,
+    /// `3x app::fetch::{{closure}} at src/main.rs:8:1 [panicked]`.
+    fn parse(line: &str) -> Result<Self, &'static str> {
+        let (copies, line) = match line.split_once("x ") {
+            Some((copies, line))
+                if !copies.is_empty() && copies.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                let copies = copies.parse();
+                (copies.map_err(|_| "invalid number of copies")?, line)
+            }
+            _ => (1, line),
+        };
+
+        // the name ends before the location, or else (in the `NameOnly`
+        // style) before any annotations
+        let end = line
+            .find(" at ")
+            .or_else(|| line.find(" ["))
+            .or_else(|| line.find(" (via "))
+            .unwrap_or(line.len());
+        let (name, rest) = line.split_at(end);
+
+        let mut tail = rest;
+        let (mut file, mut line, mut column) = (None, None, None);
+        if let Some(position) = tail.strip_prefix(" at ") {
+            let end = [" [", " (via "]
+                .iter()
+                .filter_map(|marker| position.find(marker))
+                .min()
+                .unwrap_or(position.len());
+            let (file_name, line_number, column_number) = parse_position(&position[..end])
+                .ok_or("expected a position, as `file:line:column`")?;
+            file = Some(file_name.to_string());
+            line = Some(line_number);
+            column = column_number;
+            tail = &position[end..];
+        }
+
+        let mut annotations = Vec::new();
+        while let Some(annotation) = tail.strip_prefix(" [") {
+            // the annotation ends at the first `]` followed by the end of the
+            // line, or by another annotation
+            let end = annotation
+                .match_indices(']')
+                .map(|(i, _)| i)
+                .find(|&i| {
+                    let after = &annotation[i + 1..];
+                    after.is_empty() || after.starts_with(" [") || after.starts_with(" (via ")
+                })
+                .ok_or("expected `]`")?;
+            annotations.push(annotation[..end].to_string());
+            tail = &annotation[end + 1..];
+        }
+
+        let helper_frames = match tail {
+            "" => 0,
+            " (via 1 helper frame)" => 1,
+            _ => tail
+                .strip_prefix(" (via ")
+                .and_then(|n| n.strip_suffix(" helper frames)"))
+                .and_then(|n| n.parse().ok())
+                .ok_or("unexpected text after location")?,
+        };
+
+        Ok(Self {
+            location: ParsedLocation {
+                name: name.to_string(),
+                file,
+                line,
+                column,
+            },
+            rest: rest.trim_start().to_string(),
+            annotations,
+            helper_frames,
+            copies,
+            polling: None,
+            last_known_age: None,
+            children: Vec::new(),
+        })
+    }
+
+    /// Produces the location of this frame.
+    pub fn location(&self) -> &ParsedLocation {
+        &self.location
+    }
+
+    /// Produces the name of this frame's function; e.g.,
+    /// `my_crate::handle::{{closure}}`.
+    pub fn name(&self) -> &str {
+        &self.location.name
+    }
+
+    /// Produces the remainder of this frame's line in the taskdump, after its
+    /// name; e.g., `at src/lib.rs:8:1 [last error 8s ago: connection reset]`.
+    pub fn rest(&self) -> &str {
+        &self.rest
+    }
+
+    /// Produces the file of this frame's location, unless the
+    /// [style](crate::LocationStyle) of the dump omitted it.
+    pub fn file(&self) -> Option<&str> {
+        self.location.file()
+    }
+
+    /// Produces the line of this frame's location, unless the style of the
+    /// dump omitted it.
+    pub fn line(&self) -> Option<u32> {
+        self.location.line
+    }
+
+    /// Produces the column of this frame's location, unless the style of
+    /// the dump omitted it.
+    pub fn column(&self) -> Option<u32> {
+        self.location.column
+    }
+
+    /// Produces the bracketed annotations that follow this frame's location,
+    /// without their brackets, in order; e.g., `request_id=42`,
+    /// `last error 8s ago: connection reset` and `panicked`.
+    pub fn annotations(&self) -> &[String] {
+        &self.annotations
+    }
+
+    /// Produces the number of [detail](crate::Location::frame_detail) frames
+    /// collapsed above this one.
+    pub fn helper_frames(&self) -> usize {
+        self.helper_frames
+    }
+
+    /// Produces the number of identical, adjacent copies of this frame, which
+    /// taskdumps consolidate into one (e.g., `3x my_crate::fetch`).
+    pub fn copies(&self) -> usize {
+        self.copies
+    }
+
+    /// Produces `true` if this frame is the root of a task that was being
+    /// polled, and so whose subframes were not dumped.
+    pub fn is_polling(&self) -> bool {
+        self.polling.is_some()
+    }
+
+    /// If this frame is the root of a task that was being polled, produces
+    /// the age (in seconds) of its last-known subframes, if they were dumped.
+    pub fn last_known_age(&self) -> Option<u64> {
+        self.last_known_age
+    }
+
+    /// Produces the subframes of this frame. If this frame is the root of a
+    /// task that was being polled, these are its last-known subframes (if
+    /// any); see [`RegistryConfig::cache_last_tree`](crate::RegistryConfig).
+    pub fn children(&self) -> &[ParsedFrame] {
+        &self.children
+    }
+
+    /// Produces `true` if the name of this frame's function matches `path`.
+    ///
+    /// The trailing `::{{closure}}` segments of the name are ignored, and
+    /// `path` may omit any of its leading segments; e.g., `handle` and
+    /// `my_crate::handle` both match `my_crate::handle::{{closure}}`.
+    pub fn matches(&self, path: &str) -> bool {
+        name_matches(&self.location.name, path)
+    }
+
+    /// Produces an iterator over this frame and its descendants, in
+    /// depth-first order.
+    pub fn iter(&self) -> impl Iterator<Item = &ParsedFrame> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let frame = stack.pop()?;
+            stack.extend(frame.children.iter().rev());
+            Some(frame)
+        })
+    }
+
+    /// Writes this frame's line, after its connector.
+    fn write_line(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.copies != 1 {
+            write!(f, "{}x ", self.copies)?;
+        }
+        write!(f, "{}", self.location)?;
+        for annotation in &self.annotations {
+            write!(f, " [{annotation}]")?;
+        }
+        match self.helper_frames {
+            0 => Ok(()),
+            1 => f.write_str(" (via 1 helper frame)"),
+            n => write!(f, " (via {n} helper frames)"),
+        }
+    }
+}
+
+/// Produces `true` if `name` matches `path`, as by [`ParsedFrame::matches`].
+pub(crate) fn name_matches(mut name: &str, path: &str) -> bool {
+    while let Some(stripped) = name.strip_suffix("::{{closure}}") {
+        name = stripped;
+    }
+    name == path
+        || name
+            .strip_suffix(path)
+            .is_some_and(|prefix| prefix.ends_with("::"))
+}
+
+/// Splits a rendered position into its file, its line, and its column (if
+/// it was rendered); e.g., `src/main.rs:8:1`.
+fn parse_position(position: &str) -> Option<(&str, u32, Option<u32>)> {
+    let (rest, last) = position.rsplit_once(':')?;
+    let last = last.parse().ok()?;
+    let (file, line, column) = match rest.rsplit_once(':').map(|(f, l)| (f, l.parse())) {
+        Some((file, Ok(line))) => (file, line, Some(last)),
+        _ => (rest, last, None),
+    };
+    (!file.is_empty()).then_some((file, line, column))
+}
+
+impl ParsedLocation {
+    /// Produces the name of the frame's function; e.g.,
+    /// `my_crate::handle::{{closure}}`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Produces the file of this location, unless the
+    /// [style](crate::LocationStyle) of the dump omitted it.
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Produces the line of this location, unless the style of the dump
+    /// omitted it.
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// Produces the column of this location, unless the style of the dump
+    /// omitted it.
+    pub fn column(&self) -> Option<u32> {
+        self.column
+    }
+
+    /// Produces a compact rendering of this location, as
+    /// [`Location::as_compact`](crate::Location::as_compact) does: `name@file:line`,
+    /// or only as much of it as the dump rendered.
+    pub fn as_compact(&self) -> impl fmt::Display + '_ {
+        struct Compact<'a>(&'a ParsedLocation);
+
+        impl fmt::Display for Compact<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let location = self.0;
+                f.write_str(&location.name)?;
+                if let Some(file) = &location.file {
+                    write!(f, "@{file}")?;
+                    if let Some(line) = location.line {
+                        write!(f, ":{line}")?;
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        Compact(self)
+    }
+}
+
+/// Renders as the taskdump did; e.g., `my_crate::handle at src/lib.rs:8:1`.
+impl fmt::Display for ParsedLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if let Some(file) = &self.file {
+            write!(f, " at {file}")?;
+            for number in [self.line, self.column].iter().flatten() {
+                write!(f, ":{number}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ParsedDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn fmt_children(
+            f: &mut fmt::Formatter<'_>,
+            children: &[ParsedFrame],
+            prefix: &mut String,
+        ) -> fmt::Result {
+            for (i, child) in children.iter().enumerate() {
+                let is_last = i + 1 == children.len();
+                writeln!(f)?;
+                f.write_str(prefix)?;
+                f.write_str(if is_last { "└╼ " } else { "├╼ " })?;
+                child.write_line(f)?;
+                let len = prefix.len();
+                prefix.push_str(if is_last { "   " } else { "│  " });
+                fmt_children(f, &child.children, prefix)?;
+                prefix.truncate(len);
+            }
+            Ok(())
+        }
+
+        for (i, root) in self.tasks.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            f.write_str("╼ ")?;
+            root.write_line(f)?;
+            if let Some(marker) = &root.polling {
+                writeln!(f)?;
+                match root.last_known_age {
+                    Some(age) => write!(
+                        f,
+                        "  ├┈ [{marker}] last known tree, {age}s old (possibly stale):"
+                    )?,
+                    None => write!(f, "  └┈ [{marker}]")?,
+                }
+            }
+            fmt_children(f, &root.children, &mut "  ".to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// An error produced by [`ParsedDump::from_tree_text`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    line: usize,
+    message: &'static str,
+}
+
+impl ParseError {
+    /// Produces the (one-based) number of the line that could not be parsed.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,39 @@
+/// A test that a task spawned by `spawn_framed_abortable` may be aborted by
+/// its id, after which it disappears from taskdumps.
+mod util;
+
+#[tokio::test(flavor = "current_thread")]
+#[cfg_attr(any(miri, loom), ignore)]
+async fn abort_task() {
+    let handle = async_backtrace::spawn_framed_abortable(pending());
+    // the task is only registered upon its first poll
+    tokio::task::yield_now().await;
+
+    let ids = async_backtrace::tasks_snapshot_ids();
+    assert_eq!(ids.len(), 1);
+    let id = ids[0];
+    pretty_assertions::assert_str_eq!(
+        util::strip(async_backtrace::taskdump_tree(true)),
+        "\
+╼ async_backtrace::spawn_framed_abortable at backtrace/src/spawn.rs:LINE:COL
+  └╼ abort_task::pending::{{closure}} at backtrace/tests/abort-task.rs:LINE:COL"
+    );
+
+    assert!(async_backtrace::abort_task(id));
+    assert!(handle.await.unwrap_err().is_cancelled());
+    assert_eq!(async_backtrace::taskdump_tree(true), "");
+    // the task is forgotten once aborted
+    assert!(!async_backtrace::abort_task(id));
+
+    // tasks that complete are forgotten, too
+    let handle = async_backtrace::spawn_framed_abortable(async {});
+    tokio::task::yield_now().await;
+    handle.await.unwrap();
+    assert!(async_backtrace::tasks_snapshot_ids().is_empty());
+    assert!(!async_backtrace::abort_task(id + 1));
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    std::future::pending::<()>().await;
+}
@@ -0,0 +1,52 @@
+/// A test that inherited annotations are reported by descendant frames, and
+/// that the nearest ancestor's annotation wins.
+mod util;
+
+#[test]
+fn annotate_inherited() {
+    util::model(|| util::run(root()));
+}
+
+#[async_backtrace::framed]
+async fn root() {
+    async_backtrace::annotate_inherited("query_id", 42);
+    async_backtrace::annotate_inherited("tenant", "acme");
+    async_backtrace::annotate("user", "ferris");
+    middle().await;
+    leaf("root").await;
+}
+
+#[async_backtrace::framed]
+async fn middle() {
+    async_backtrace::annotate_inherited("tenant", "initech");
+    leaf("middle").await;
+}
+
+#[async_backtrace::framed]
+async fn leaf(caller: &'static str) {
+    async_backtrace::annotate("attempt", 1);
+
+    let backtrace = async_backtrace::backtrace_annotated().unwrap();
+    let rendered: Vec<_> = backtrace
+        .iter()
+        .map(|frame| util::strip(frame.to_string()))
+        .collect();
+    if caller == "middle" {
+        pretty_assertions::assert_eq!(
+            rendered,
+            [
+                "annotate_inherited::leaf::{{closure}} at backtrace/tests/annotate-inherited.rs:LINE:COL [attempt=1, tenant=initech, query_id=42]",
+                "annotate_inherited::middle::{{closure}} at backtrace/tests/annotate-inherited.rs:LINE:COL [tenant=initech, query_id=42]",
+                "annotate_inherited::root::{{closure}} at backtrace/tests/annotate-inherited.rs:LINE:COL [query_id=42, tenant=acme, user=ferris]",
+            ]
+        );
+    } else {
+        pretty_assertions::assert_eq!(
+            rendered,
+            [
+                "annotate_inherited::leaf::{{closure}} at backtrace/tests/annotate-inherited.rs:LINE:COL [attempt=1, query_id=42, tenant=acme]",
+                "annotate_inherited::root::{{closure}} at backtrace/tests/annotate-inherited.rs:LINE:COL [query_id=42, tenant=acme, user=ferris]",
+            ]
+        );
+    }
+}
@@ -0,0 +1,42 @@
+/// A test that annotations of every frame are included in annotated
+/// backtraces.
+mod util;
+
+#[test]
+fn annotate() {
+    util::model(|| {
+        assert!(!async_backtrace::annotate("outside", "a frame"));
+        assert!(async_backtrace::backtrace_annotated().is_none());
+        util::run(outer());
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    assert!(async_backtrace::annotate("query_id", 42));
+    assert!(async_backtrace::annotate("user", "ferris"));
+    inner().await;
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    async_backtrace::annotate("attempt", 1);
+    async_backtrace::annotate("attempt", 2);
+
+    let backtrace = async_backtrace::backtrace_annotated().unwrap();
+    let rendered: Vec<_> = backtrace
+        .iter()
+        .map(|frame| util::strip(frame.to_string()))
+        .collect();
+    pretty_assertions::assert_eq!(
+        rendered,
+        [
+            "annotate::inner::{{closure}} at backtrace/tests/annotate.rs:LINE:COL [attempt=2]",
+            "annotate::outer::{{closure}} at backtrace/tests/annotate.rs:LINE:COL [query_id=42, user=ferris]",
+        ]
+    );
+    assert_eq!(
+        backtrace[1].metadata()[0],
+        ("query_id", "42".to_string().into())
+    );
+}
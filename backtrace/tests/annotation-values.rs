@@ -0,0 +1,110 @@
+/// A test that `annotate_value` keeps the types of annotations, which are
+/// rendered by dumps with `TaskdumpOptions::annotations` (as native values, in
+/// JSON), and produced by `Task::metadata`.
+mod util;
+use async_backtrace::{AnnotationValue, TaskdumpOptions};
+use serde_json::{json, Value};
+
+#[test]
+fn annotation_values() {
+    util::model(|| {
+        // there is no frame to annotate
+        assert!(!async_backtrace::annotate_value("outside", 1u64));
+        util::run(handle());
+    });
+}
+
+#[async_backtrace::framed]
+async fn handle() {
+    assert!(async_backtrace::annotate_value("query_id", 42u64));
+    assert!(async_backtrace::annotate_value("offset", -3i32));
+    assert!(async_backtrace::annotate_value("retry", true));
+    assert!(async_backtrace::annotate_value("route", "/users"));
+    assert!(async_backtrace::annotate_value(
+        "user",
+        String::from("ferris")
+    ));
+    lookup().await;
+}
+
+#[async_backtrace::framed]
+async fn lookup() {
+    // frames carry a bounded number of annotations
+    for key in KEYS {
+        assert!(async_backtrace::annotate_value(key, 0u64));
+    }
+    assert!(!async_backtrace::annotate_value("one_too_many", 0u64));
+    // (but existing annotations may still be replaced)
+    assert!(async_backtrace::annotate_value("k0", 1u64));
+
+    let backtrace = async_backtrace::backtrace_annotated().unwrap();
+    assert_eq!(
+        backtrace[1]
+            .get("query_id")
+            .and_then(AnnotationValue::as_u64),
+        Some(42)
+    );
+    assert_eq!(
+        backtrace[1].get("offset").and_then(AnnotationValue::as_i64),
+        Some(-3)
+    );
+    assert_eq!(
+        backtrace[1].get("route"),
+        Some(&"/users".to_string().into())
+    );
+
+    // annotations are only rendered if requested
+    assert!(!TaskdumpOptions::new()
+        .wait_for_running_tasks(true)
+        .dump()
+        .contains("query_id"));
+    pretty_assertions::assert_str_eq!(
+        util::strip(
+            TaskdumpOptions::new()
+                .wait_for_running_tasks(true)
+                .annotations(true)
+                .dump()
+        ),
+        "\
+╼ annotation_values::handle::{{closure}} at backtrace/tests/annotation-values.rs:LINE:COL [query_id=42, offset=-3, retry=true, route=/users, user=ferris]
+  └╼ annotation_values::lookup::{{closure}} at backtrace/tests/annotation-values.rs:LINE:COL [k0=1, k1=0, k2=0, k3=0, k4=0, k5=0, k6=0, k7=0, k8=0, k9=0, k10=0, k11=0, k12=0, k13=0, k14=0, k15=0]"
+    );
+
+    let dump: Value = serde_json::from_str(
+        &TaskdumpOptions::new()
+            .wait_for_running_tasks(true)
+            .annotations(true)
+            .dump_json(),
+    )
+    .unwrap();
+    let root = &dump["tasks"][0]["root"];
+    assert_eq!(
+        root["annotations"],
+        json!({
+            "query_id": 42,
+            "offset": -3,
+            "retry": true,
+            "route": "/users",
+            "user": "ferris",
+        })
+    );
+    assert_eq!(root["children"][0]["annotations"]["k0"], 1);
+
+    let task = async_backtrace::tasks().next().unwrap();
+    let metadata = task.metadata(true).unwrap();
+    assert_eq!(
+        metadata,
+        [
+            ("query_id", AnnotationValue::U64(42)),
+            ("offset", AnnotationValue::I64(-3)),
+            ("retry", AnnotationValue::Bool(true)),
+            ("route", AnnotationValue::Str("/users")),
+            ("user", AnnotationValue::String("ferris".to_string())),
+        ]
+    );
+}
+
+static KEYS: [&str; 16] = [
+    "k0", "k1", "k2", "k3", "k4", "k5", "k6", "k7", "k8", "k9", "k10", "k11", "k12", "k13", "k14",
+    "k15",
+];
@@ -0,0 +1,27 @@
+/// A test that the arguments of `#[framed]` are reflected in taskdumps.
+mod util;
+
+#[test]
+fn args() {
+    util::model(|| util::run(outer()));
+}
+
+mod reexport {
+    pub use async_backtrace as backtrace;
+}
+
+#[async_backtrace::framed(name = "custom name")]
+async fn outer() {
+    inner().await;
+}
+
+#[async_backtrace::framed(crate = reexport::backtrace)]
+async fn inner() {
+    let dump = async_backtrace::taskdump_tree(true);
+    pretty_assertions::assert_str_eq!(
+        util::strip(dump),
+        "\
+╼ custom name at backtrace/tests/args.rs:LINE:COL
+  └╼ args::inner::{{closure}} at backtrace/tests/args.rs:LINE:COL"
+    );
+}
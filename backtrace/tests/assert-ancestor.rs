@@ -0,0 +1,103 @@
+/// A test of `assert_ancestor!`, and of the parsing of taskdumps it relies
+/// upon.
+mod util;
+use async_backtrace::testing::{is_ancestor, parse_taskdump};
+
+#[test]
+fn assert_ancestor() {
+    util::model(|| {
+        let dump = util::run(serve());
+        // nested
+        async_backtrace::assert_ancestor!(dump, "serve", "handle");
+        async_backtrace::assert_ancestor!(dump, "serve", "lookup");
+        async_backtrace::assert_ancestor!(dump, "assert_ancestor::handle", "lookup");
+
+        let tasks = parse_taskdump(&dump).unwrap();
+        // descendants are not ancestors
+        assert!(!is_ancestor(&tasks, "lookup", "handle"));
+        // absent
+        assert!(!is_ancestor(&tasks, "serve", "absent"));
+        // partial segments do not match
+        assert!(!is_ancestor(&tasks, "serve", "andle"));
+    });
+}
+
+#[test]
+#[should_panic(expected = "expected a frame named `handle` to be an ancestor of one named `serve`")]
+fn assert_ancestor_fails() {
+    util::model(|| {
+        async_backtrace::assert_ancestor!(
+            "\
+╼ app::serve::{{closure}} at src/main.rs:5:1
+  └╼ app::handle::{{closure}} at src/main.rs:12:1",
+            "handle",
+            "serve"
+        );
+    });
+}
+
+#[test]
+fn parse() {
+    util::model(|| {
+        let tasks = parse_taskdump(
+            "\
+╼ app::serve::{{closure}} at src/main.rs:5:1
+  ├╼ app::handle::{{closure}} at src/main.rs:12:1
+  │  └╼ 3x app::fetch::{{closure}} at src/main.rs:20:1 [last error 8s ago: connection reset]
+  └╼ app::log::{{closure}} at src/main.rs:30:1
+╼ app::poller::{{closure}} at src/main.rs:40:1
+  └┈ [POLLING]",
+        )
+        .unwrap();
+        assert_eq!(tasks.len(), 2);
+
+        let serve = &tasks[0];
+        assert_eq!(serve.name(), "app::serve::{{closure}}");
+        assert_eq!(serve.rest(), "at src/main.rs:5:1");
+        assert!(!serve.is_polling());
+        let [handle, log] = serve.children() else {
+            panic!("expected two children: {:?}", serve)
+        };
+        assert!(handle.matches("handle"));
+        assert!(log.matches("app::log"));
+        assert!(log.children().is_empty());
+        let [fetch] = handle.children() else {
+            panic!("expected one child: {:?}", handle)
+        };
+        assert!(fetch.matches("fetch"));
+        assert_eq!(fetch.copies(), 3);
+        assert_eq!(
+            fetch.rest(),
+            "at src/main.rs:20:1 [last error 8s ago: connection reset]"
+        );
+
+        // siblings are not ancestors
+        assert!(is_ancestor(&tasks, "serve", "fetch"));
+        assert!(!is_ancestor(&tasks, "handle", "log"));
+        assert!(!is_ancestor(&tasks, "log", "fetch"));
+
+        let poller = &tasks[1];
+        assert!(poller.matches("poller"));
+        assert!(poller.is_polling());
+        assert!(poller.children().is_empty());
+
+        let err =
+            parse_taskdump("╼ app::serve at src/main.rs:5:1\n     └╼ app::handle").unwrap_err();
+        assert_eq!(err.line(), 2);
+    });
+}
+
+#[async_backtrace::framed]
+async fn serve() -> String {
+    handle().await
+}
+
+#[async_backtrace::framed]
+async fn handle() -> String {
+    lookup().await
+}
+
+#[async_backtrace::framed]
+async fn lookup() -> String {
+    async_backtrace::taskdump_tree(true)
+}
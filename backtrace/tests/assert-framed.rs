@@ -0,0 +1,83 @@
+/// Tests of `assert_framed!` and `probe_child_frames`.
+mod util;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+mod handlers {
+    #[async_backtrace::framed]
+    pub async fn ping() {}
+
+    #[async_backtrace::framed]
+    pub async fn query(id: u64, verbose: bool) -> Option<u64> {
+        super::YieldNow(false).await;
+        Some(id).filter(|_| verbose)
+    }
+
+    pub async fn unframed() {
+        ping().await;
+    }
+}
+
+#[test]
+fn probe() {
+    util::model(|| {
+        // frames that complete within the probed poll are observed...
+        let children = async_backtrace::probe_child_frames(handlers::ping());
+        let names: Vec<_> = children.iter().map(|l| l.name()).collect();
+        assert_eq!(names, [Some("assert_framed::handlers::ping::{{closure}}")]);
+
+        // ...as are frames that are still pending...
+        let children = async_backtrace::probe_child_frames(handlers::query(1, true));
+        let names: Vec<_> = children.iter().map(|l| l.name()).collect();
+        assert_eq!(names, [Some("assert_framed::handlers::query::{{closure}}")]);
+
+        // ...but only immediate children of the probed future are reported.
+        let children = async_backtrace::probe_child_frames(async_backtrace::frame!(async {
+            handlers::ping().await;
+        }));
+        let names: Vec<_> = children.iter().map(|l| l.name()).collect();
+        assert_eq!(names, [Some("assert_framed::probe::{{closure}}")]);
+
+        assert!(async_backtrace::probe_child_frames(async {}).is_empty());
+    });
+}
+
+#[test]
+fn framed() {
+    util::model(|| {
+        async_backtrace::assert_framed!(handlers::ping);
+        async_backtrace::assert_framed!(|| handlers::query(Default::default(), true));
+        async_backtrace::assert_framed!(|| self::handlers::query(7, false,));
+    });
+}
+
+#[test]
+#[should_panic(
+    expected = "expected `handlers::unframed` to be framed, but its first poll \
+                           produced no frame named `unframed`; observed child frames: \
+                           [assert_framed::handlers::ping::{{closure}}"
+)]
+fn unframed() {
+    util::model(|| {
+        async_backtrace::assert_framed!(handlers::unframed);
+    });
+}
+
+/// A future that is pending exactly once.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if std::mem::replace(&mut self.0, true) {
+            Poll::Ready(())
+        } else {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
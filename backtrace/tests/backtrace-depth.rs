@@ -0,0 +1,51 @@
+/// A test that `backtrace_depth` agrees with the length of `backtrace`: within
+/// nested frames, beneath a barrier, within an attached context, and outside
+/// of any frame.
+mod util;
+
+#[test]
+fn backtrace_depth() {
+    util::model(|| {
+        assert_eq!(async_backtrace::backtrace_depth(), None);
+        let context = util::run(outer());
+        context.attach(|| {
+            // (an attached context is rendered beneath a marker entry)
+            assert_eq!(async_backtrace::backtrace_depth(), Some(4));
+            assert_eq!(
+                async_backtrace::backtrace_depth(),
+                async_backtrace::backtrace().map(|backtrace| backtrace.len())
+            );
+        });
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() -> async_backtrace::ContextHandle {
+    middle().await
+}
+
+#[async_backtrace::framed]
+async fn middle() -> async_backtrace::ContextHandle {
+    assert_eq!(async_backtrace::backtrace_depth(), Some(2));
+    let context = inner().await;
+    barrier().await;
+    context
+}
+
+#[async_backtrace::framed]
+async fn inner() -> async_backtrace::ContextHandle {
+    assert_eq!(async_backtrace::backtrace_depth(), Some(3));
+    async_backtrace::capture_context()
+}
+
+#[async_backtrace::framed(barrier)]
+async fn barrier() {
+    leaf().await
+}
+
+#[async_backtrace::framed]
+async fn leaf() {
+    // the backtrace stops at the barrier
+    assert_eq!(async_backtrace::backtrace_depth(), Some(2));
+    assert_eq!(async_backtrace::backtrace().unwrap().len(), 2);
+}
@@ -0,0 +1,36 @@
+/// A test that `backtrace` produces an `AsyncBacktrace` that is rendered with a
+/// numbered line per location, and iterated innermost first.
+mod util;
+use async_backtrace::Location;
+
+#[test]
+fn backtrace_display() {
+    util::model(|| util::run(outer()));
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    inner().await
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    let backtrace = async_backtrace::backtrace().unwrap();
+    assert_eq!(backtrace.len(), 2);
+    assert!(!backtrace.is_empty());
+    pretty_assertions::assert_str_eq!(
+        util::strip(backtrace.to_string()),
+        "\
+#0 backtrace_display::inner::{{closure}} at backtrace/tests/backtrace-display.rs:LINE:COL
+#1 backtrace_display::outer::{{closure}} at backtrace/tests/backtrace-display.rs:LINE:COL"
+    );
+
+    let names: Vec<_> = (&backtrace).into_iter().map(Location::name).collect();
+    let locations: Vec<Location> = backtrace.clone().into_iter().collect();
+    assert_eq!(
+        names,
+        locations.iter().map(Location::name).collect::<Vec<_>>()
+    );
+    let boxed: Box<[Location]> = backtrace.into();
+    assert_eq!(&boxed[..], &locations[..]);
+}
@@ -0,0 +1,41 @@
+/// A test that `backtrace_full` renders the native frames of the current
+/// poll, then the async backtrace of the active frame.
+mod util;
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn backtrace_full() {
+    util::model(|| {
+        let report = async_backtrace::backtrace_full();
+        assert!(report.logical().is_none());
+        assert!(report
+            .to_string()
+            .ends_with("\n== async backtrace ==\n(no active frame)"));
+
+        util::run(outer());
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    inner().await;
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    let report = async_backtrace::backtrace_full();
+    assert_eq!(report.logical().map(|logical| logical.len()), Some(2));
+
+    let rendered = report.to_string();
+    let (native, logical) = rendered.split_once("\n== async backtrace ==\n").unwrap();
+    // the native half includes the function that captured it...
+    assert!(native.contains("backtrace_full::inner::{{closure}}"));
+    assert_eq!(native, report.native().to_string().trim_end());
+    // ...and the async half its ancestry
+    pretty_assertions::assert_str_eq!(
+        util::strip(logical),
+        "\
+#0 backtrace_full::inner::{{closure}} at backtrace/tests/backtrace-full.rs:LINE:COL
+#1 backtrace_full::outer::{{closure}} at backtrace/tests/backtrace-full.rs:LINE:COL"
+    );
+}
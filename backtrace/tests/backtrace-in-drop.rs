@@ -0,0 +1,49 @@
+/// A test that the destructors of values held across `await`s by a framed
+/// future observe its frame, and its ancestors, when the future is cancelled.
+mod util;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::Context;
+
+static DROPPED: AtomicBool = AtomicBool::new(false);
+
+#[test]
+fn backtrace_in_drop() {
+    util::model(|| {
+        let mut future = Box::pin(outer());
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+
+        // cancel the task, outside of any poll
+        DROPPED.store(false, Ordering::Relaxed);
+        drop(future);
+        assert!(DROPPED.load(Ordering::Relaxed));
+        assert!(async_backtrace::backtrace().is_none());
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    inner().await;
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    let _guard = Guard;
+    futures::pending!();
+}
+
+struct Guard;
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        pretty_assertions::assert_str_eq!(
+            util::strip(async_backtrace::backtrace().unwrap().to_string()),
+            "\
+#0 backtrace_in_drop::inner::{{closure}} at backtrace/tests/backtrace-in-drop.rs:LINE:COL
+#1 backtrace_in_drop::outer::{{closure}} at backtrace/tests/backtrace-in-drop.rs:LINE:COL"
+        );
+        DROPPED.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,39 @@
+/// A test that `backtrace_with` lends the same locations that `backtrace`
+/// produces, including those of an attached context.
+mod util;
+
+#[test]
+fn backtrace_with() {
+    util::model(|| {
+        assert!(async_backtrace::backtrace_with(|frames| frames.is_none()));
+        util::run(outer());
+    });
+}
+
+/// Produces the locations lent by `backtrace_with`, if any.
+fn collect() -> Option<Vec<async_backtrace::Location>> {
+    async_backtrace::backtrace_with(|frames| frames.map(|frames| frames.collect()))
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    inner().await
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    let backtrace = async_backtrace::backtrace().unwrap();
+    assert_eq!(backtrace.len(), 2);
+    assert_eq!(collect().unwrap(), backtrace.to_vec());
+
+    let context = async_backtrace::capture_context();
+    util::thread::spawn(move || {
+        context.attach(|| {
+            let backtrace = async_backtrace::backtrace().unwrap();
+            assert_eq!(backtrace.len(), 3);
+            assert_eq!(collect().unwrap(), backtrace.to_vec());
+        })
+    })
+    .join()
+    .unwrap();
+}
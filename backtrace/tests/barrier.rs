@@ -0,0 +1,64 @@
+/// Tests that backtraces stop at barrier frames, unless they are captured
+/// through them, and that taskdumps and inherited annotations are unaffected.
+mod util;
+
+#[test]
+fn barrier() {
+    util::model(|| util::run(application()));
+}
+
+#[async_backtrace::framed]
+async fn application() {
+    async_backtrace::annotate_inherited("tenant", "acme");
+    framework().await;
+}
+
+#[async_backtrace::framed(barrier)]
+async fn framework() {
+    handler().await;
+}
+
+#[async_backtrace::framed]
+async fn handler() {
+    let names = |backtrace: Box<[async_backtrace::Location]>| -> Vec<String> {
+        backtrace
+            .iter()
+            .map(|location| location.name().unwrap().to_string())
+            .collect()
+    };
+
+    // ancestors beyond the barrier are omitted...
+    assert_eq!(
+        names(async_backtrace::backtrace().unwrap().into()),
+        [
+            "barrier::handler::{{closure}}",
+            "barrier::framework::{{closure}}"
+        ]
+    );
+    let annotated = async_backtrace::backtrace_annotated().unwrap();
+    assert_eq!(annotated.len(), 2);
+    // ...but their inherited annotations are not
+    assert_eq!(
+        annotated[0].metadata(),
+        [("tenant", "acme".to_string().into())]
+    );
+
+    // unless the backtrace is captured through barriers
+    assert_eq!(
+        names(async_backtrace::backtrace_through_barriers().unwrap()),
+        [
+            "barrier::handler::{{closure}}",
+            "barrier::framework::{{closure}}",
+            "barrier::application::{{closure}}",
+        ]
+    );
+
+    // taskdumps show the full tree
+    pretty_assertions::assert_str_eq!(
+        util::strip(async_backtrace::taskdump_tree(true)),
+        "\
+╼ barrier::application::{{closure}} at backtrace/tests/barrier.rs:LINE:COL
+  └╼ barrier::framework::{{closure}} at backtrace/tests/barrier.rs:LINE:COL
+     └╼ barrier::handler::{{closure}} at backtrace/tests/barrier.rs:LINE:COL"
+    );
+}
@@ -0,0 +1,65 @@
+/// A test that a blocking taskdump waits for the poll of a running task to
+/// finish, and then dumps the task if it is idle, or omits it if it has exited
+/// meanwhile (without keeping it alive while it waits).
+mod util;
+use std::{future::Future, sync::mpsc, task::Context, thread, time::Duration};
+
+#[test]
+// loom cannot model the polling thread, which blocks outside of its control
+#[cfg_attr(any(miri, loom), ignore)]
+fn blocking_dump() {
+    util::model(|| {
+        // a task that exits at the end of the poll that the dump waits for
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel();
+        let poller = thread::spawn(move || poll_once(stuck(entered_tx, release_rx, true)));
+        entered_rx.recv().unwrap();
+
+        let dumper = thread::spawn(|| async_backtrace::taskdump_tree(true));
+        // (the dump waits for the poll, whether or not it has begun to by now)
+        thread::sleep(Duration::from_millis(50));
+        release_tx.send(()).unwrap();
+        assert!(poller.join().unwrap().is_none());
+        // (the task exits once its future is dropped, after the poll; so, the
+        // dump may or may not find it, idle, after the poll)
+        let dump = util::strip(dumper.join().unwrap());
+        assert!(dump.is_empty() || dump == STUCK, "{}", dump);
+
+        // a task that is idle after it
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel();
+        let poller = thread::spawn(move || poll_once(stuck(entered_tx, release_rx, false)));
+        entered_rx.recv().unwrap();
+
+        let dumper = thread::spawn(|| async_backtrace::taskdump_tree(true));
+        thread::sleep(Duration::from_millis(50));
+        release_tx.send(()).unwrap();
+        let idle = poller.join().unwrap();
+        pretty_assertions::assert_str_eq!(util::strip(dumper.join().unwrap()), STUCK);
+        drop(idle);
+    });
+}
+
+/// The dump of an idle `stuck` task.
+const STUCK: &str =
+    "╼ blocking_dump::stuck::{{closure}} at backtrace/tests/blocking-dump.rs:LINE:COL";
+
+/// Polls `f` once, and produces it if it is pending, or drops it if it is
+/// ready.
+fn poll_once<F: Future>(f: F) -> Option<std::pin::Pin<Box<F>>> {
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut f = Box::pin(f);
+    f.as_mut().poll(&mut cx).is_pending().then_some(f)
+}
+
+/// Blocks mid-poll until `release` is signalled, and then exits or, unless
+/// `exit`, pends.
+#[async_backtrace::framed]
+async fn stuck(entered: mpsc::Sender<()>, release: mpsc::Receiver<()>, exit: bool) {
+    entered.send(()).unwrap();
+    release.recv().unwrap();
+    if !exit {
+        futures::pending!()
+    }
+}
@@ -0,0 +1,42 @@
+/// A test that `#[framed(boxed)]` functions produce one frame per call.
+mod util;
+use std::{future::Future, pin::Pin};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+trait Handler {
+    fn handle<'a>(&'a self, request: &'a str) -> BoxFuture<'a, String>;
+}
+
+struct Echo;
+
+impl Handler for Echo {
+    fn handle<'a>(&'a self, request: &'a str) -> BoxFuture<'a, String> {
+        echo(request)
+    }
+}
+
+#[test]
+fn boxed() {
+    util::model(|| {
+        let handler: Box<dyn Handler + Send + Sync> = Box::new(Echo);
+        assert_eq!(util::run(serve(&*handler)), "ping");
+    });
+}
+
+#[async_backtrace::framed]
+async fn serve(handler: &(dyn Handler + Send + Sync)) -> String {
+    handler.handle("ping").await
+}
+
+#[async_backtrace::framed(boxed)]
+async fn echo(request: &str) -> String {
+    let dump = async_backtrace::taskdump_tree(true);
+    pretty_assertions::assert_str_eq!(
+        util::strip(dump),
+        "\
+╼ boxed::serve::{{closure}} at backtrace/tests/boxed.rs:LINE:COL
+  └╼ boxed::echo<'_, '_> at backtrace/tests/boxed.rs:LINE:COL"
+    );
+    request.to_string()
+}
@@ -0,0 +1,49 @@
+/// A test that `catching_frame!` reports the backtraces of panics, at most once
+/// per location within the configured interval.
+mod util;
+use async_backtrace::PanicReport;
+use std::{panic, sync::Mutex, time::Duration};
+
+static REPORTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[test]
+#[cfg_attr(loom, ignore)]
+fn catching_frame() {
+    util::model(|| {
+        async_backtrace::set_panic_sink(Duration::from_secs(3600), |report: &PanicReport| {
+            REPORTS
+                .lock()
+                .unwrap()
+                .push(util::strip(report.to_string()));
+        });
+
+        for _ in 0..3 {
+            let result = panic::catch_unwind(|| {
+                util::run(async_backtrace::catching_frame!(outer()));
+            });
+            // the original payload is propagated
+            let payload = result.unwrap_err();
+            assert_eq!(payload.downcast_ref::<&str>(), Some(&"boom"));
+        }
+
+        let reports = REPORTS.lock().unwrap();
+        pretty_assertions::assert_eq!(
+            &reports[..],
+            ["\
+panicked: boom
+  at catching_frame::inner::{{closure}} at backtrace/tests/catching-frame.rs:LINE:COL
+  at catching_frame::outer::{{closure}} at backtrace/tests/catching-frame.rs:LINE:COL
+  at catching_frame::catching_frame::{{closure}}::{{closure}} at backtrace/tests/catching-frame.rs:LINE:COL"]
+        );
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    inner().await;
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    panic!("boom");
+}
@@ -0,0 +1,126 @@
+/// A test that leaf frames are classified by the registry of patterns, in the
+/// histogram of leaf states and in JSON taskdumps.
+mod util;
+use async_backtrace::{
+    classify::{self, LeafState},
+    Location, TaskdumpOptions,
+};
+use std::{future::Future, task::Context};
+
+static REST: (&str, u32, u32) = ("src/lib.rs", 1, 1);
+
+/// Produces the location named `name`.
+fn at(name: &'static str) -> Location {
+    Location::from_components(name, &REST)
+}
+
+#[test]
+fn classify() {
+    util::model(|| {
+        // the default patterns
+        let cases = [
+            ("tokio::time::sleep::Sleep::poll", LeafState::PendingOnTimer),
+            (
+                "<tokio::time::Timeout<F> as core::future::Future>::poll",
+                LeafState::PendingOnTimer,
+            ),
+            (
+                "tokio::sync::mpsc::Receiver<T>::recv::{{closure}}",
+                LeafState::PendingOnChannel,
+            ),
+            ("tokio::net::TcpStream::readable", LeafState::PendingOnIo),
+            ("app::idle::{{closure}}", LeafState::Unknown),
+            // patterns match whole path segments only
+            ("tokio::timeline::wait", LeafState::Unknown),
+            ("my_tokio::time::wait", LeafState::Unknown),
+        ];
+        for (name, state) in cases {
+            assert_eq!(classify::classify(at(name)), state, "{}", name);
+        }
+
+        // registered patterns, the latest of which take precedence
+        classify::register("app::db", LeafState::PendingOnIo);
+        classify::register("db::pool", LeafState::PendingOnChannel);
+        classify::register("tokio::time::sleep", LeafState::Unknown);
+        let cases = [
+            ("app::db::query::{{closure}}", LeafState::PendingOnIo),
+            (
+                "app::db::pool::get::{{closure}}",
+                LeafState::PendingOnChannel,
+            ),
+            ("app::dbx::query", LeafState::Unknown),
+            ("tokio::time::sleep::Sleep::poll", LeafState::Unknown),
+            (
+                "tokio::time::interval::Interval::tick",
+                LeafState::PendingOnTimer,
+            ),
+        ];
+        for (name, state) in cases {
+            assert_eq!(classify::classify(at(name)), state, "{}", name);
+        }
+        assert_eq!(
+            LeafState::PendingOnChannel.to_string(),
+            "pending_on_channel"
+        );
+
+        // only leaves are counted
+        let pending = || std::future::pending::<()>();
+        let first = at("app::serve").frame(async move {
+            futures::join!(
+                at("app::db::query").frame(pending()),
+                at("app::db::query").frame(pending()),
+                at("app::handle").frame(at("app::db::pool::get").frame(pending())),
+            )
+        });
+        let second = at("app::idle").frame(pending());
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut first = Box::pin(first);
+        let mut second = Box::pin(second);
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+        assert!(second.as_mut().poll(&mut cx).is_pending());
+
+        let histogram: Vec<(LeafState, usize)> =
+            classify::leaf_state_histogram().into_iter().collect();
+        assert_eq!(
+            histogram,
+            [
+                (LeafState::PendingOnTimer, 0),
+                (LeafState::PendingOnChannel, 1),
+                (LeafState::PendingOnIo, 2),
+                (LeafState::Unknown, 1),
+            ]
+        );
+
+        // JSON taskdumps classify their leaves
+        let dump: serde_json::Value =
+            serde_json::from_str(&async_backtrace::taskdump_json(true)).unwrap();
+        let mut states = Vec::new();
+        for task in dump["tasks"].as_array().unwrap() {
+            let mut frames = vec![&task["root"]];
+            while let Some(frame) = frames.pop() {
+                let children = frame["children"].as_array().unwrap();
+                states.push((frame["name"].as_str().unwrap(), &frame["leaf_state"]));
+                frames.extend(children);
+            }
+        }
+        states.sort_by_key(|&(name, _)| name);
+        assert_eq!(
+            states,
+            [
+                ("app::db::pool::get", &"pending_on_channel".into()),
+                ("app::db::query", &"pending_on_io".into()),
+                ("app::handle", &serde_json::Value::Null),
+                ("app::idle", &"unknown".into()),
+                ("app::serve", &serde_json::Value::Null),
+            ]
+        );
+
+        // frames whose children may have been pruned are not leaves, but
+        // frames above the depth at which the trees were pruned may be
+        let dump = TaskdumpOptions::new().max_depth(1).dump_json();
+        assert_eq!(dump.matches("\"leaf_state\":\"").count(), 1);
+        assert!(dump.contains("\"name\":\"app::idle\""));
+        assert!(dump.contains("\"leaf_state\":\"unknown\""));
+    });
+}
@@ -0,0 +1,59 @@
+/// A test that concurrent taskdumps share one traversal of the tasks, if their
+/// options coalesce them.
+mod util;
+use async_backtrace::{set_default_dump_options, ඞ::taskdump_traversals, TaskdumpOptions};
+use std::{sync::mpsc, thread, time::Duration};
+
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn concurrent_dumps() {
+    set_default_dump_options(TaskdumpOptions::new().coalesce(Duration::ZERO)).unwrap();
+
+    // a task that is stuck mid-poll, on which waiting dumps block
+    let (entered_tx, entered_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel();
+    // (the task outlives the poll, so that the dumps find it once it is idle)
+    let (exit_tx, exit_rx) = futures::channel::oneshot::channel();
+    let stuck = thread::spawn(move || {
+        futures::executor::block_on(stuck(entered_tx, release_rx, exit_rx));
+    });
+    entered_rx.recv().unwrap();
+
+    let before = taskdump_traversals();
+    let dumpers: Vec<_> = (0..4)
+        .map(|_| thread::spawn(|| async_backtrace::taskdump_tree(true)))
+        .collect();
+    // let every dumper join the dump in flight
+    thread::sleep(Duration::from_millis(100));
+    release_tx.send(()).unwrap();
+
+    let dumps: Vec<String> = dumpers.into_iter().map(|d| d.join().unwrap()).collect();
+    exit_tx.send(()).unwrap();
+    stuck.join().unwrap();
+    assert_eq!(taskdump_traversals() - before, 1);
+    assert!(dumps.iter().all(|dump| *dump == dumps[0]));
+    pretty_assertions::assert_str_eq!(
+        util::strip(&dumps[0]),
+        "╼ coalesce::stuck::{{closure}} at backtrace/tests/coalesce.rs:LINE:COL"
+    );
+
+    // once the shared dump has landed, dumps traverse the tasks afresh...
+    assert_eq!(async_backtrace::taskdump_tree(true), "");
+    assert_eq!(taskdump_traversals() - before, 2);
+
+    // ...as do those given options that do not coalesce them
+    TaskdumpOptions::new().dump();
+    assert_eq!(taskdump_traversals() - before, 3);
+}
+
+/// Blocks mid-poll until `release` is signalled, then idles until `exit` is.
+#[async_backtrace::framed]
+async fn stuck(
+    entered: mpsc::Sender<()>,
+    release: mpsc::Receiver<()>,
+    exit: futures::channel::oneshot::Receiver<()>,
+) {
+    entered.send(()).unwrap();
+    release.recv().unwrap();
+    exit.await.unwrap();
+}
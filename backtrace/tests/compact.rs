@@ -0,0 +1,52 @@
+/// A test that `Task::compact_line` renders a branching tree on one line.
+mod util;
+use std::{future::Future, task::Context};
+
+#[test]
+fn compact_line() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(outer());
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        let line = async_backtrace::tasks().next().unwrap().compact_line(true);
+        pretty_assertions::assert_str_eq!(
+            strip_lines(&line),
+            "compact::outer::{{closure}}@backtrace/tests/compact.rs:LINE > {\
+             compact::branch::{{closure}}@backtrace/tests/compact.rs:LINE \
+             > compact::leaf::{{closure}}@backtrace/tests/compact.rs:LINE, \
+             compact::leaf::{{closure}}@backtrace/tests/compact.rs:LINE x2}"
+        );
+        assert_eq!(async_backtrace::taskdump_compact(true), line);
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    futures::join!(leaf(), leaf(), branch());
+}
+
+#[async_backtrace::framed]
+async fn branch() {
+    leaf().await;
+}
+
+#[async_backtrace::framed]
+async fn leaf() {
+    std::future::pending::<()>().await;
+}
+
+/// Replaces each `:<line>` in `str` with `:LINE`.
+fn strip_lines(str: &str) -> String {
+    let mut stripped = String::with_capacity(str.len());
+    let mut chars = str.chars().peekable();
+    while let Some(c) = chars.next() {
+        stripped.push(c);
+        if c == ':' && chars.peek().is_some_and(char::is_ascii_digit) {
+            while chars.next_if(char::is_ascii_digit).is_some() {}
+            stripped.push_str("LINE");
+        }
+    }
+    stripped
+}
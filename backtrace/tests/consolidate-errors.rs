@@ -0,0 +1,63 @@
+/// A test that taskdump_tree() does not consolidate adjacent subframes whose
+/// subframes failed differently.
+mod util;
+
+#[test]
+fn consolidate_errors() {
+    util::model(|| util::run(selecting()));
+}
+
+#[async_backtrace::framed]
+async fn selecting() {
+    tokio::select! {
+        biased;
+        _ = retrying("connection reset") => {}
+        _ = retrying("connection refused") => {}
+        _ = retrying("connection reset") => {}
+        _ = ready() => {}
+    };
+}
+
+#[async_backtrace::framed]
+async fn retrying(error: &str) {
+    assert!(fetch(Some(error)).await.is_err());
+    fetch(None).await.unwrap();
+}
+
+#[async_backtrace::framed(record_err)]
+async fn fetch(error: Option<&str>) -> Result<(), String> {
+    match error {
+        Some(error) => Err(error.to_string()),
+        None => {
+            tokio::task::yield_now().await;
+            Ok(())
+        }
+    }
+}
+
+#[async_backtrace::framed]
+async fn ready() {
+    let dump = async_backtrace::taskdump_tree(true);
+    // normalize the ages of the errors
+    let dump = dump
+        .split("[last error ")
+        .enumerate()
+        .map(|(i, part)| match (i, part.split_once("s ago")) {
+            (0, _) | (_, None) => part.to_string(),
+            (_, Some((_, rest))) => format!("[last error Ns ago{}", rest),
+        })
+        .collect::<String>();
+
+    pretty_assertions::assert_str_eq!(
+        util::strip(dump),
+        "\
+╼ consolidate_errors::selecting::{{closure}} at backtrace/tests/consolidate-errors.rs:LINE:COL
+  ├╼ consolidate_errors::ready::{{closure}} at backtrace/tests/consolidate-errors.rs:LINE:COL
+  ├╼ consolidate_errors::retrying::{{closure}} at backtrace/tests/consolidate-errors.rs:LINE:COL
+  │  └╼ consolidate_errors::fetch::{{closure}} at backtrace/tests/consolidate-errors.rs:LINE:COL [last error Ns ago: connection reset]
+  ├╼ consolidate_errors::retrying::{{closure}} at backtrace/tests/consolidate-errors.rs:LINE:COL
+  │  └╼ consolidate_errors::fetch::{{closure}} at backtrace/tests/consolidate-errors.rs:LINE:COL [last error Ns ago: connection refused]
+  └╼ consolidate_errors::retrying::{{closure}} at backtrace/tests/consolidate-errors.rs:LINE:COL
+     └╼ consolidate_errors::fetch::{{closure}} at backtrace/tests/consolidate-errors.rs:LINE:COL [last error Ns ago: connection reset]"
+    );
+}
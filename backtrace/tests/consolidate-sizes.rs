@@ -0,0 +1,47 @@
+/// A test that taskdump_tree() does not consolidate adjacent subframes whose
+/// futures differ in size, if their sizes are rendered.
+mod util;
+use async_backtrace::{location_named, Location, TaskdumpOptions};
+use std::{future::Future, task::Context};
+
+/// The location of futures of two sizes.
+static SIZED: Location = location_named!("consolidate_sizes::sized");
+
+#[test]
+fn consolidate_sizes() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(async_backtrace::location!().frame(async {
+            futures::join!(SIZED.frame(small()), SIZED.frame(large()));
+        }));
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        // without their sizes, the frames are identical...
+        let dump = TaskdumpOptions::new().dump();
+        assert!(dump.contains("2x consolidate_sizes::sized"), "{}", dump);
+
+        // ...but with them, they are not
+        let dump = TaskdumpOptions::new().future_sizes(0).dump();
+        let sized: Vec<&str> = dump
+            .lines()
+            .filter(|line| line.contains("consolidate_sizes::sized"))
+            .collect();
+        assert_eq!(sized.len(), 2, "{}", dump);
+        assert!(sized.iter().all(|line| line.contains("[future: ")));
+        assert_ne!(
+            sized[0].split_once("[future: ").unwrap().1,
+            sized[1].split_once("[future: ").unwrap().1
+        );
+    });
+}
+
+async fn small() {
+    futures::future::pending::<()>().await
+}
+
+async fn large() {
+    let buffer = [0u8; 1024];
+    futures::future::pending::<()>().await;
+    std::hint::black_box(&buffer);
+}
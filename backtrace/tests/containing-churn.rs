@@ -0,0 +1,90 @@
+/// A test that `tasks_containing(.., true)` completes promptly, while the very
+/// task it waits for registers tasks from within its poll.
+mod util;
+use async_backtrace::{location, raw::Frame};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+
+const IDLE: usize = 1024;
+const BATCH: usize = 256;
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn containing_churn() {
+    // (so that a failed assertion does not leave the busy task running)
+    let _stop = Stop;
+    // (so that every shard of the registry holds a task)
+    let idle: Vec<_> = (0..IDLE)
+        .map(|_| {
+            let mut frame = Box::pin(Frame::new(location!()));
+            frame.as_mut().in_scope(|| {});
+            frame
+        })
+        .collect();
+    let busy = std::thread::spawn(|| util::run(busy()));
+    while async_backtrace::tasks().count() <= IDLE {
+        std::thread::yield_now();
+    }
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let found = async_backtrace::tasks_containing(
+            |location| location.name() == Some("containing_churn::inner::{{closure}}"),
+            true,
+        );
+        let ids: Vec<u64> = found.tasks.iter().map(|task| task.id()).collect();
+        let _ = tx.send((ids, found.skipped));
+    });
+    let (ids, skipped) = match rx.recv_timeout(Duration::from_secs(10)) {
+        Ok(found) => found,
+        Err(_) => {
+            // (dropping the idle tasks would block on the stuck search)
+            std::mem::forget(idle);
+            panic!("the search did not complete");
+        }
+    };
+
+    // the busy task was waited for and searched, not skipped
+    assert_eq!(ids.len(), 1);
+    assert_eq!(skipped, 0);
+
+    STOP.store(true, Ordering::Release);
+    busy.join().unwrap();
+    drop(idle);
+}
+
+struct Stop;
+
+impl Drop for Stop {
+    fn drop(&mut self) {
+        STOP.store(true, Ordering::Release);
+    }
+}
+
+/// Registers tasks, on another thread, within each poll of its subframe,
+/// until stopped.
+#[async_backtrace::framed]
+async fn busy() {
+    inner().await
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    while !STOP.load(Ordering::Acquire) {
+        // (the search, if any, is by now waiting for this poll)
+        std::thread::sleep(Duration::from_millis(1));
+        // (so that one is likely to be in the shard the dump holds, if any)
+        std::thread::spawn(|| {
+            for _ in 0..BATCH {
+                let mut frame = Box::pin(Frame::new(location!()));
+                frame.as_mut().in_scope(|| {});
+            }
+        })
+        .join()
+        .unwrap();
+        tokio::task::yield_now().await;
+    }
+}
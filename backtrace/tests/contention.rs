@@ -2,7 +2,7 @@
 ///
 /// In this test, two threads are spawned:
 /// 1. Thread 1 executes a `framed` future, which requests a blocking taskdump
-/// three times in different ways (immediately, in a sub-frame, and upon drop).
+///    three times in different ways (immediately, in a sub-frame, and upon drop).
 /// 2. Thread 2 requests a blocking taskdump.
 mod util;
 use async_backtrace::framed;
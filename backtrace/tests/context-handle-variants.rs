@@ -0,0 +1,81 @@
+/// A test that each variant of `backtrace` resolves the context attached to a
+/// thread, as `backtrace` does.
+mod util;
+use async_backtrace::Location;
+
+#[test]
+fn context_handle_variants() {
+    util::model(|| util::run(application()));
+}
+
+#[async_backtrace::framed]
+async fn application() {
+    async_backtrace::annotate_inherited("tenant", "acme");
+    framework().await;
+}
+
+#[async_backtrace::framed(barrier)]
+async fn framework() {
+    handler().await;
+}
+
+#[async_backtrace::framed]
+async fn handler() {
+    let expected = async_backtrace::backtrace().unwrap();
+    let through_barriers = async_backtrace::backtrace_through_barriers().unwrap();
+    let annotated = async_backtrace::backtrace_annotated().unwrap();
+    let context = async_backtrace::capture_context();
+
+    util::thread::spawn(move || {
+        context.attach(|| {
+            let names = |backtrace: &[Location]| -> Vec<String> {
+                backtrace
+                    .iter()
+                    .map(|location| location.name().unwrap().to_string())
+                    .collect()
+            };
+            let snapshot = "async_backtrace::ContextHandle::attach [snapshot]";
+
+            let backtrace = async_backtrace::backtrace().unwrap();
+            assert_eq!(
+                names(&backtrace),
+                [
+                    snapshot,
+                    "context_handle_variants::handler::{{closure}}",
+                    "context_handle_variants::framework::{{closure}}",
+                ]
+            );
+            assert_eq!(&backtrace[1..], &expected[..]);
+
+            let with =
+                async_backtrace::backtrace_with(|frames| frames.unwrap().collect::<Vec<_>>());
+            assert_eq!(with, &backtrace[..]);
+            assert_eq!(async_backtrace::backtrace_depth(), Some(backtrace.len()));
+
+            // the ancestors beyond the barrier were captured, too...
+            let attached = async_backtrace::backtrace_through_barriers().unwrap();
+            assert_eq!(attached[0].name(), Some(snapshot));
+            assert_eq!(&attached[1..], &through_barriers[..]);
+            assert_eq!(
+                names(&attached[1..]),
+                [
+                    "context_handle_variants::handler::{{closure}}",
+                    "context_handle_variants::framework::{{closure}}",
+                    "context_handle_variants::application::{{closure}}",
+                ]
+            );
+
+            // ...as were the annotations of the frames
+            let attached = async_backtrace::backtrace_annotated().unwrap();
+            assert_eq!(attached[0].location().name(), Some(snapshot));
+            assert!(attached[0].metadata().is_empty());
+            assert_eq!(&attached[1..], &annotated[..]);
+            assert_eq!(
+                attached[1].metadata(),
+                [("tenant", "acme".to_string().into())]
+            );
+        })
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,65 @@
+/// A test that a context captured by `capture_context` reports the backtrace,
+/// root and task of the frame it was captured in, once attached within a
+/// `spawn_blocking` closure.
+mod util;
+
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(any(miri, loom), ignore)]
+async fn context_handle() {
+    outer().await;
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    inner().await;
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    let expected = async_backtrace::backtrace().unwrap();
+    let task_id = async_backtrace::root_task_id();
+    let context = async_backtrace::capture_context();
+    assert!(!context.is_empty());
+    assert_eq!(context.backtrace(), Some(&expected[..]));
+    assert_eq!(context.task_id(), task_id);
+
+    let (backtrace, root, id, nested) = tokio::task::spawn_blocking(move || {
+        // outside of `attach`, the blocking thread has no context
+        assert!(async_backtrace::backtrace().is_none());
+        let captured = context.attach(|| {
+            (
+                async_backtrace::backtrace().unwrap(),
+                async_backtrace::root_location().unwrap(),
+                async_backtrace::root_task_id(),
+                // a context captured while attached is that context
+                async_backtrace::capture_context()
+                    .backtrace()
+                    .map(<[_]>::to_vec),
+            )
+        });
+        // the context is detached once `attach` returns
+        assert!(async_backtrace::backtrace().is_none());
+        assert!(async_backtrace::capture_context().is_empty());
+        captured
+    })
+    .await
+    .unwrap();
+
+    pretty_assertions::assert_str_eq!(
+        util::strip(
+            backtrace
+                .iter()
+                .map(|location| location.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+        "\
+async_backtrace::ContextHandle::attach [snapshot] at backtrace/src/attach.rs:LINE:COL
+context_handle::inner::{{closure}} at backtrace/tests/context-handle.rs:LINE:COL
+context_handle::outer::{{closure}} at backtrace/tests/context-handle.rs:LINE:COL"
+    );
+    assert_eq!(&backtrace[1..], &expected[..]);
+    assert_eq!(root.name(), Some("context_handle::outer::{{closure}}"));
+    assert_eq!(id, task_id);
+    assert_eq!(nested.as_deref(), Some(&expected[..]));
+}
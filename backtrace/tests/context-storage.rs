@@ -0,0 +1,88 @@
+/// A test that a custom `ContextStorage` keeps the frames of execution
+/// contexts interleaved on one thread apart.
+mod util;
+use async_backtrace::{ActiveFrame, ContextStorage, ContextStorageError};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// The identifier of the simulated fiber that is currently executing.
+static CURRENT: AtomicU64 = AtomicU64::new(0);
+
+/// Keeps the active frame of each simulated fiber.
+struct FiberStorage(Mutex<Option<HashMap<u64, ActiveFrame>>>);
+
+static STORAGE: FiberStorage = FiberStorage(Mutex::new(None));
+
+// SAFETY: Fibers are only switched on the thread that suspended them.
+unsafe impl ContextStorage for FiberStorage {
+    fn get(&self) -> Option<ActiveFrame> {
+        let frames = self.0.lock().unwrap();
+        frames
+            .as_ref()?
+            .get(&CURRENT.load(Ordering::SeqCst))
+            .copied()
+    }
+
+    fn set(&self, frame: Option<ActiveFrame>) {
+        let mut frames = self.0.lock().unwrap();
+        let frames = frames.get_or_insert_with(HashMap::new);
+        let fiber = CURRENT.load(Ordering::SeqCst);
+        match frame {
+            Some(frame) => frames.insert(fiber, frame),
+            None => frames.remove(&fiber),
+        };
+    }
+}
+
+/// Switches to the fiber `id`, producing that which was executing.
+fn switch_to(id: u64) -> u64 {
+    CURRENT.swap(id, Ordering::SeqCst)
+}
+
+#[test]
+fn interleaved() {
+    async_backtrace::set_context_storage(&STORAGE).unwrap();
+    assert_eq!(
+        async_backtrace::set_context_storage(&STORAGE),
+        Err(ContextStorageError::AlreadyInitialized)
+    );
+
+    util::model(|| {
+        switch_to(0);
+        util::run(outer());
+        assert!(async_backtrace::backtrace().is_none());
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    assert_eq!(names(), ["context_storage::outer::{{closure}}"]);
+
+    // switch fibers in the midst of `outer`'s poll; the other fiber's frames
+    // are not children of `outer`
+    let previous = switch_to(1);
+    assert!(async_backtrace::backtrace().is_none());
+    util::run(inner());
+    assert!(async_backtrace::backtrace().is_none());
+    switch_to(previous);
+
+    assert_eq!(names(), ["context_storage::outer::{{closure}}"]);
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    assert_eq!(names(), ["context_storage::inner::{{closure}}"]);
+}
+
+fn names() -> Vec<String> {
+    let backtrace = async_backtrace::backtrace().unwrap();
+    backtrace
+        .iter()
+        .map(|location| location.name().unwrap().to_string())
+        .collect()
+}
@@ -0,0 +1,70 @@
+/// A test that `counters::increment` attributes counts to the location of the
+/// active frame, as reported by `counters::report`, and rendered by dumps
+/// with `TaskdumpOptions::counters`.
+mod util;
+use async_backtrace::{counters, TaskdumpOptions};
+
+#[test]
+fn counters() {
+    util::model(|| {
+        // there is no frame to count
+        assert!(!counters::increment("retries", 1));
+        assert!(counters::report().is_empty());
+
+        util::run(serve());
+
+        let report = counters::report();
+        pretty_assertions::assert_str_eq!(
+            util::strip(
+                report
+                    .iter()
+                    .map(|counter| counter.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+            "\
+counters::serve::{{closure}} at backtrace/tests/counters.rs:LINE:COL [requests=1]
+counters::fetch::{{closure}} at backtrace/tests/counters.rs:LINE:COL [bytes=512]
+counters::fetch::{{closure}} at backtrace/tests/counters.rs:LINE:COL [retries=3]"
+        );
+        let fetch = report[2].location();
+        assert_eq!(counters::get(fetch, "retries"), 3);
+        assert_eq!(counters::get(fetch, "errors"), 0);
+    });
+}
+
+#[async_backtrace::framed]
+async fn serve() {
+    assert!(counters::increment_root("requests", 1));
+    fetch().await;
+}
+
+#[async_backtrace::framed]
+async fn fetch() {
+    for _ in 0..3 {
+        assert!(counters::increment("retries", 1));
+    }
+    assert!(counters::increment("bytes", 512));
+    dump().await;
+}
+
+#[async_backtrace::framed]
+async fn dump() {
+    pretty_assertions::assert_str_eq!(
+        util::strip(
+            TaskdumpOptions::new()
+                .wait_for_running_tasks(true)
+                .counters(true)
+                .dump()
+        ),
+        "\
+╼ counters::serve::{{closure}} at backtrace/tests/counters.rs:LINE:COL [requests=1]
+  └╼ counters::fetch::{{closure}} at backtrace/tests/counters.rs:LINE:COL [bytes=512] [retries=3]
+     └╼ counters::dump::{{closure}} at backtrace/tests/counters.rs:LINE:COL"
+    );
+    // counters are only rendered if requested
+    assert!(!TaskdumpOptions::new()
+        .wait_for_running_tasks(true)
+        .dump()
+        .contains("[retries=3]"));
+}
@@ -0,0 +1,78 @@
+/// A test that crash sections are delimited, summarized and truncated to
+/// their budget, and may be written outside of any runtime.
+mod util;
+use async_backtrace::report::{self, BEGIN, END};
+use std::{future::Future, task::Context};
+
+/// Produces the crash section, as a string.
+fn section() -> String {
+    let mut section = Vec::new();
+    report::write_crash_section(&mut section).unwrap();
+    String::from_utf8(section).unwrap()
+}
+
+#[test]
+fn crash_report() {
+    util::model(|| {
+        assert!(tokio::runtime::Handle::try_current().is_err());
+        report::set_crash_section_budget(report::DEFAULT_BUDGET);
+
+        pretty_assertions::assert_str_eq!(section(), format!("{}\n0 tasks\n{}\n", BEGIN, END));
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut tasks: Vec<_> = (0..3).map(|_| Box::pin(pending())).collect();
+        for task in &mut tasks {
+            assert!(task.as_mut().poll(&mut cx).is_pending());
+        }
+
+        let line =
+            "╼ crash_report::pending::{{closure}} at backtrace/tests/crash-report.rs:LINE:COL";
+        pretty_assertions::assert_str_eq!(
+            util::strip(section()),
+            format!(
+                "{}\n3 tasks\n{}\n{}\n{}\n{}\n",
+                BEGIN, line, line, line, END
+            )
+        );
+
+        // two lines of the dump fit within the budget, and the third does not
+        let len = async_backtrace::taskdump_tree(false)
+            .lines()
+            .next()
+            .unwrap()
+            .len();
+        report::set_crash_section_budget(3 * len);
+        let truncated = section();
+        let lines: Vec<&str> = truncated.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[..2], [BEGIN, "3 tasks"]);
+        assert_eq!(util::strip(lines[2]), line);
+        assert_eq!(util::strip(lines[3]), line);
+        assert_eq!(
+            lines[4],
+            format!("[TRUNCATED: {} of {} bytes]", 2 * len + 1, 3 * len + 2)
+        );
+        assert_eq!(lines[5], END);
+
+        // so do no lines, if the budget is less than one
+        report::set_crash_section_budget(len - 1);
+        pretty_assertions::assert_str_eq!(
+            section(),
+            format!(
+                "{}\n3 tasks\n[TRUNCATED: 0 of {} bytes]\n{}\n",
+                BEGIN,
+                3 * len + 2,
+                END
+            )
+        );
+
+        drop(tasks);
+        report::set_crash_section_budget(report::DEFAULT_BUDGET);
+    });
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    std::future::pending::<()>().await;
+}
@@ -0,0 +1,198 @@
+/// Tests and models of tasks that are polled, dumped and dropped on different
+/// threads over their lifetime, as under work-stealing runtimes.
+mod util;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+type Task = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+#[test]
+fn moved_between_polls() {
+    util::model(|| {
+        let task: Task = Box::pin(outer());
+
+        // the first poll, on one thread, initializes `outer` and `first`...
+        let task = util::thread::spawn(move || poll_pending(task))
+            .join()
+            .unwrap();
+        pretty_assertions::assert_str_eq!(
+            util::strip(dump()),
+            "\
+╼ cross_thread::outer::{{closure}} at backtrace/tests/cross-thread.rs:LINE:COL
+  └╼ cross_thread::first::{{closure}} at backtrace/tests/cross-thread.rs:LINE:COL"
+        );
+
+        // ...and the second, on another, replaces `first` with `second`
+        let task = util::thread::spawn(move || poll_pending(task))
+            .join()
+            .unwrap();
+        pretty_assertions::assert_str_eq!(
+            util::strip(dump()),
+            "\
+╼ cross_thread::outer::{{closure}} at backtrace/tests/cross-thread.rs:LINE:COL
+  └╼ cross_thread::second::{{closure}} at backtrace/tests/cross-thread.rs:LINE:COL"
+        );
+
+        util::thread::spawn(move || drop(task)).join().unwrap();
+        assert!(!async_backtrace::tasks().any(|task| is_outer(&task)));
+    });
+}
+
+#[test]
+fn dropped_while_dumped() {
+    util::model(|| {
+        let task: Task = Box::pin(outer());
+        let task = poll_pending(task);
+
+        // the subframes of the task are unlinked by its drop, outside of any
+        // poll, while they may be traversed by a dump
+        let handle = util::thread::spawn(move || drop(task));
+        for task in async_backtrace::tasks().filter(|task| is_outer(task)) {
+            let _ = task.pretty_tree(true);
+        }
+        handle.join().unwrap();
+        assert!(!async_backtrace::tasks().any(|task| is_outer(&task)));
+    });
+}
+
+#[test]
+fn subframe_dropped_while_dumped() {
+    util::model(|| {
+        // `holder` polls the future in the slot, so that it is a subframe of
+        // `holder` that may be dropped apart from the task
+        let slot: Slot = Arc::new(Mutex::new(Some(Box::pin(second()))));
+        let task = poll_pending(Box::pin(holder(slot.clone())));
+
+        // the subframe is unlinked by its drop, on another thread and outside
+        // of any poll, while the children of `holder` may be traversed by a
+        // dump
+        let handle = util::thread::spawn(move || drop(slot.lock().unwrap().take()));
+        let tree = async_backtrace::tasks()
+            .find(|task| is_holder(task))
+            .unwrap()
+            .pretty_tree(true);
+        assert!(tree.lines().count() <= 2, "{}", tree);
+        handle.join().unwrap();
+
+        pretty_assertions::assert_str_eq!(
+            util::strip(
+                async_backtrace::tasks()
+                    .find(|task| is_holder(task))
+                    .unwrap()
+                    .pretty_tree(true)
+            ),
+            "\
+╼ cross_thread::holder::{{closure}} at backtrace/tests/cross-thread.rs:LINE:COL"
+        );
+        drop(task);
+    });
+}
+
+#[test]
+// (a stress test of OS threads, which loom cannot model)
+#[cfg_attr(loom, ignore)]
+fn dropped_while_dumped_stress() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let done = Arc::new(AtomicBool::new(false));
+    let dumper = {
+        let done = done.clone();
+        std::thread::spawn(move || {
+            while !done.load(Ordering::Relaxed) {
+                for task in async_backtrace::tasks().filter(|task| is_stressed(task)) {
+                    let _ = task.pretty_tree(true);
+                }
+            }
+        })
+    };
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            std::thread::spawn(|| {
+                for _ in 0..500 {
+                    let slot: Slot = Arc::new(Mutex::new(Some(Box::pin(second()))));
+                    let task = poll_pending(Box::pin(STRESSED.frame(holder(slot.clone()))));
+                    // the subframe, and then the task, are dropped elsewhere
+                    std::thread::spawn(move || {
+                        drop(slot.lock().unwrap().take());
+                        drop(task);
+                    })
+                    .join()
+                    .unwrap();
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    done.store(true, Ordering::Relaxed);
+    dumper.join().unwrap();
+    assert!(!async_backtrace::tasks().any(|task| is_stressed(&task)));
+}
+
+/// The root of the tasks of `dropped_while_dumped_stress`, distinct from those
+/// of the other tests, which run concurrently.
+const STRESSED: async_backtrace::Location =
+    async_backtrace::Location::from_components("stressed", &("src/stress.rs", 1, 1));
+
+fn is_stressed(task: &async_backtrace::Task) -> bool {
+    task.location() == STRESSED
+}
+
+/// A future that may be taken from the task that polls it.
+type Slot = Arc<Mutex<Option<Task>>>;
+
+/// Polls `task`, which must be pending, on the current thread.
+fn poll_pending(mut task: Task) -> Task {
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    task
+}
+
+fn dump() -> String {
+    async_backtrace::tasks()
+        .find(|task| is_outer(task))
+        .unwrap()
+        .pretty_tree(true)
+}
+
+fn is_outer(task: &async_backtrace::Task) -> bool {
+    task.location().name() == Some("cross_thread::outer::{{closure}}")
+}
+
+fn is_holder(task: &async_backtrace::Task) -> bool {
+    task.location().name() == Some("cross_thread::holder::{{closure}}")
+}
+
+/// Polls the future in `slot` (while there is one), forever.
+#[async_backtrace::framed]
+async fn holder(slot: Slot) {
+    std::future::poll_fn(|cx| {
+        if let Some(future) = &mut *slot.lock().unwrap() {
+            let _ = future.as_mut().poll(cx);
+        }
+        Poll::<()>::Pending
+    })
+    .await
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    first().await;
+    second().await;
+}
+
+#[async_backtrace::framed]
+async fn first() {
+    futures::pending!();
+}
+
+#[async_backtrace::framed]
+async fn second() {
+    futures::future::pending::<()>().await
+}
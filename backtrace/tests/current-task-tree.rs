@@ -0,0 +1,43 @@
+/// A test that `current_task_tree` renders every branch of the task of the
+/// active frame (not just its ancestors), without blocking on the task's own
+/// lock, and that it is `None` outside of frames.
+mod util;
+
+#[test]
+fn current_task_tree() {
+    util::model(|| {
+        assert_eq!(async_backtrace::current_task_tree(), None);
+        util::run(race());
+        assert!(async_backtrace::current_task_snapshot().is_none());
+    });
+}
+
+#[async_backtrace::framed]
+async fn race() {
+    tokio::select! {
+        biased;
+        _ = pending() => {}
+        _ = ready() => {}
+    }
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    std::future::pending::<()>().await
+}
+
+#[async_backtrace::framed]
+async fn ready() {
+    pretty_assertions::assert_str_eq!(
+        util::strip(async_backtrace::current_task_tree().unwrap()),
+        "\
+╼ current_task_tree::race::{{closure}} at backtrace/tests/current-task-tree.rs:LINE:COL
+  ├╼ current_task_tree::ready::{{closure}} at backtrace/tests/current-task-tree.rs:LINE:COL
+  └╼ current_task_tree::pending::{{closure}} at backtrace/tests/current-task-tree.rs:LINE:COL"
+    );
+
+    let tree = async_backtrace::current_task_snapshot().unwrap();
+    assert!(!tree.is_polling());
+    assert_eq!(Some(tree.id()), async_backtrace::root_task_id());
+    assert_eq!(tree.root().children().len(), 2);
+}
@@ -0,0 +1,40 @@
+/// A test that `current_task` produces the id and root of the task of the
+/// active frame, and `None` outside of frames (including within
+/// `spawn_blocking` closures, unless a context is attached).
+mod util;
+
+#[tokio::test(flavor = "multi_thread")]
+#[cfg_attr(any(miri, loom), ignore)]
+async fn current_task() {
+    assert_eq!(async_backtrace::current_task(), None);
+    serve().await;
+    assert_eq!(async_backtrace::current_task(), None);
+}
+
+#[async_backtrace::framed]
+async fn serve() {
+    handle().await;
+}
+
+#[async_backtrace::framed]
+async fn handle() {
+    let task = async_backtrace::current_task().unwrap();
+    assert_eq!(Some(task.id()), async_backtrace::root_task_id());
+    assert_eq!(Some(task.location()), async_backtrace::root_location());
+    assert_eq!(
+        task.location().name(),
+        Some("current_task::serve::{{closure}}")
+    );
+
+    let context = async_backtrace::capture_context();
+    let (detached, attached) = tokio::task::spawn_blocking(move || {
+        (
+            async_backtrace::current_task(),
+            context.attach(async_backtrace::current_task),
+        )
+    })
+    .await
+    .unwrap();
+    assert_eq!(detached, None);
+    assert_eq!(attached, Some(task));
+}
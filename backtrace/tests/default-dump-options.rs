@@ -0,0 +1,60 @@
+/// A test that the options set by `set_default_dump_options` apply to
+/// `taskdump_tree`, but not to dumps given explicit options.
+mod util;
+use async_backtrace::{
+    set_default_dump_options, DefaultDumpOptionsError, TaskdumpOptions, Verbosity,
+};
+use std::{future::Future, task::Context};
+
+#[test]
+fn default_dump_options() {
+    set_default_dump_options(TaskdumpOptions::new().verbosity(Verbosity::Full)).unwrap();
+    assert_eq!(
+        set_default_dump_options(TaskdumpOptions::new()),
+        Err(DefaultDumpOptionsError::AlreadyInitialized)
+    );
+
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(outer());
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        // the defaults apply to `taskdump_tree`
+        pretty_assertions::assert_str_eq!(
+            util::strip(async_backtrace::taskdump_tree(true)),
+            "\
+╼ default_dump_options::outer::{{closure}} at backtrace/tests/default-dump-options.rs:LINE:COL
+  └╼ default_dump_options::helper::{{closure}} at backtrace/tests/default-dump-options.rs:LINE:COL
+     └╼ default_dump_options::inner::{{closure}} at backtrace/tests/default-dump-options.rs:LINE:COL"
+        );
+
+        // explicit options override them
+        pretty_assertions::assert_str_eq!(
+            util::strip(
+                TaskdumpOptions::new()
+                    .wait_for_running_tasks(true)
+                    .verbosity(Verbosity::Normal)
+                    .dump()
+            ),
+            "\
+╼ default_dump_options::outer::{{closure}} at backtrace/tests/default-dump-options.rs:LINE:COL
+  └╼ default_dump_options::inner::{{closure}} at backtrace/tests/default-dump-options.rs:LINE:COL (via 1 helper frame)"
+        );
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    helper().await;
+}
+
+#[async_backtrace::framed(detail)]
+async fn helper() {
+    inner().await;
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    std::future::pending::<()>().await;
+}
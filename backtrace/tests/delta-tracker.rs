@@ -0,0 +1,106 @@
+/// A test that `DeltaTracker::sample` reports the tasks added, removed, and
+/// whose leaves changed, between consecutive samples.
+mod util;
+use async_backtrace::{DeltaTracker, Location};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+#[test]
+fn delta_tracker() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut tracker = DeltaTracker::new();
+        let names = |tasks: &[async_backtrace::TaskDelta]| -> Vec<String> {
+            tasks
+                .iter()
+                .map(|task| task.root().name().unwrap().to_string())
+                .collect()
+        };
+        let leaves = |leaves: &[Location]| -> Vec<String> {
+            leaves
+                .iter()
+                .map(|leaf| leaf.name().unwrap().to_string())
+                .collect()
+        };
+
+        // sample 1: two tasks appear
+        let advance = Arc::new(AtomicBool::new(false));
+        let mut a = task("app::a", waiting(advance.clone()));
+        let mut b = task("app::b", waiting(advance.clone()));
+        assert!(a.as_mut().poll(&mut cx).is_pending());
+        assert!(b.as_mut().poll(&mut cx).is_pending());
+
+        let delta = tracker.sample();
+        assert_eq!(names(delta.added()), ["app::a", "app::b"]);
+        assert!(delta.removed().is_empty() && delta.changed().is_empty());
+        assert_eq!(leaves(delta.added()[0].leaves_after()), ["first"]);
+
+        // sample 2: `a` exits, `b` moves on to its second step, and `c` appears
+        drop(a);
+        advance.store(true, Ordering::SeqCst);
+        assert!(b.as_mut().poll(&mut cx).is_pending());
+        let mut c = task("app::c", waiting(Arc::new(AtomicBool::new(false))));
+        assert!(c.as_mut().poll(&mut cx).is_pending());
+
+        let delta = tracker.sample();
+        assert_eq!(names(delta.added()), ["app::c"]);
+        assert_eq!(names(delta.removed()), ["app::a"]);
+        assert_eq!(names(delta.changed()), ["app::b"]);
+        let change = &delta.changed()[0];
+        assert_eq!(leaves(change.leaves_before()), ["first"]);
+        assert_eq!(leaves(change.leaves_after()), ["second"]);
+        pretty_assertions::assert_str_eq!(
+            util::strip(delta.to_string())
+                .replace(&format!("task {}:", delta.added()[0].id()), "task C:")
+                .replace(&format!("task {}:", delta.removed()[0].id()), "task A:")
+                .replace(&format!("task {}:", change.id()), "task B:"),
+            "\
+1 added, 1 removed, 1 changed
++ task C: app::c at src/app.rs:LINE:COL
+- task A: app::a at src/app.rs:LINE:COL
+~ task B: app::b at src/app.rs:LINE:COL: first at src/app.rs:LINE:COL → second at src/app.rs:LINE:COL"
+        );
+
+        // sample 3: nothing changed
+        assert!(b.as_mut().poll(&mut cx).is_pending());
+        let delta = tracker.sample();
+        assert!(delta.is_empty(), "{}", delta);
+        assert_eq!(delta.to_string(), "no changes");
+
+        drop((b, c));
+        assert_eq!(names(tracker.sample().removed()), ["app::b", "app::c"]);
+    });
+}
+
+static ROOT: (&str, u32, u32) = ("src/app.rs", 1, 1);
+static FIRST: (&str, u32, u32) = ("src/app.rs", 10, 1);
+static SECOND: (&str, u32, u32) = ("src/app.rs", 20, 1);
+
+fn task(
+    name: &'static str,
+    future: impl Future<Output = ()> + 'static,
+) -> Pin<Box<dyn Future<Output = ()>>> {
+    Box::pin(Location::from_components(name, &ROOT).frame(future))
+}
+
+/// Waits in `first` until `advance` is set, and then in `second` forever.
+async fn waiting(advance: Arc<AtomicBool>) {
+    let first = std::future::poll_fn(move |_| match advance.load(Ordering::SeqCst) {
+        true => Poll::Ready(()),
+        false => Poll::Pending,
+    });
+    Location::from_components("first", &FIRST)
+        .frame(first)
+        .await;
+    Location::from_components("second", &SECOND)
+        .frame(std::future::pending::<()>())
+        .await
+}
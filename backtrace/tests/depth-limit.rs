@@ -0,0 +1,74 @@
+/// A test that depth limits note the number of frames omitted beneath the
+/// frames at the limit, and consolidate only siblings that omit as many.
+mod util;
+use std::{future::Future, pin::Pin, task::Context};
+
+#[test]
+fn depth_limit() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(root());
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        let task = async_backtrace::tasks().next().unwrap();
+        let tree = |depth_limit| util::strip(task.pretty_tree_with(depth_limit, true));
+
+        pretty_assertions::assert_str_eq!(
+            tree(Some(1)),
+            "\
+╼ depth_limit::root::{{closure}} at backtrace/tests/depth-limit.rs:LINE:COL
+  ├╼ depth_limit::decode::{{closure}} at backtrace/tests/depth-limit.rs:LINE:COL
+  │  └┈ … 3 more frames
+  └╼ 2x depth_limit::decode::{{closure}} at backtrace/tests/depth-limit.rs:LINE:COL
+     └┈ … 1 more frame"
+        );
+        pretty_assertions::assert_str_eq!(
+            tree(Some(0)),
+            "\
+╼ depth_limit::root::{{closure}} at backtrace/tests/depth-limit.rs:LINE:COL
+  └┈ … 8 more frames"
+        );
+        pretty_assertions::assert_str_eq!(
+            tree(Some(3)),
+            "\
+╼ depth_limit::root::{{closure}} at backtrace/tests/depth-limit.rs:LINE:COL
+  ├╼ depth_limit::decode::{{closure}} at backtrace/tests/depth-limit.rs:LINE:COL
+  │  └╼ depth_limit::decode::{{closure}} at backtrace/tests/depth-limit.rs:LINE:COL
+  │     └╼ depth_limit::decode::{{closure}} at backtrace/tests/depth-limit.rs:LINE:COL
+  │        └┈ … 1 more frame
+  └╼ 2x depth_limit::decode::{{closure}} at backtrace/tests/depth-limit.rs:LINE:COL
+     └╼ depth_limit::decode::{{closure}} at backtrace/tests/depth-limit.rs:LINE:COL"
+        );
+        assert_eq!(tree(Some(4)), tree(None));
+        assert_eq!(tree(None), util::strip(task.pretty_tree(true)));
+
+        // the same limits apply to taskdumps
+        let limited = tree(Some(1));
+        drop(task);
+        assert_eq!(
+            util::strip(async_backtrace::taskdump_tree_with(Some(1), true)),
+            limited
+        );
+        assert_eq!(
+            async_backtrace::taskdump_tree_with(None, true),
+            async_backtrace::taskdump_tree(true)
+        );
+    });
+}
+
+#[async_backtrace::framed]
+async fn root() {
+    futures::join!(decode(1), decode(1), decode(3));
+}
+
+/// Decodes `depth` levels of a recursive structure, in `depth + 1` frames.
+#[async_backtrace::framed]
+fn decode(depth: usize) -> Pin<Box<dyn Future<Output = ()>>> {
+    Box::pin(async move {
+        match depth.checked_sub(1) {
+            Some(depth) => decode(depth).await,
+            None => std::future::pending().await,
+        }
+    })
+}
@@ -0,0 +1,54 @@
+/// A test that a framed future is adopted by whichever frame first polls it,
+/// unless it is detached, in which case it is the root of a task of its own.
+mod util;
+use async_backtrace::TaskdumpOptions;
+use std::{future::Future, pin::Pin, task::Context};
+
+type Job = Pin<Box<dyn Future<Output = ()>>>;
+
+#[test]
+fn detached() {
+    util::model(|| {
+        // both jobs are constructed outside of any frame...
+        let jobs: Vec<Job> = vec![Box::pin(adopted()), Box::pin(detached_job())];
+
+        // ...and first polled by a framed executor
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut executor = Box::pin(executor(jobs));
+        assert!(executor.as_mut().poll(&mut cx).is_pending());
+
+        pretty_assertions::assert_str_eq!(
+            util::strip(TaskdumpOptions::new().sort_tasks(true).dump()),
+            "\
+╼ detached::executor::{{closure}} at backtrace/tests/detached.rs:LINE:COL
+  └╼ detached::adopted::{{closure}} at backtrace/tests/detached.rs:LINE:COL
+╼ detached::detached_job::{{closure}} at backtrace/tests/detached.rs:LINE:COL"
+        );
+    });
+}
+
+/// Polls each of `jobs` once, and then never completes.
+#[async_backtrace::framed]
+async fn executor(mut jobs: Vec<Job>) {
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    for job in &mut jobs {
+        assert!(job.as_mut().poll(&mut cx).is_pending());
+    }
+    futures::pending!()
+}
+
+#[async_backtrace::framed]
+async fn adopted() {
+    // the executor's frame is this frame's parent
+    assert_eq!(async_backtrace::backtrace().unwrap().len(), 2);
+    futures::pending!()
+}
+
+#[async_backtrace::framed(detached)]
+async fn detached_job() {
+    // this frame is the root of its task
+    assert_eq!(async_backtrace::backtrace().unwrap().len(), 1);
+    futures::pending!()
+}
@@ -0,0 +1,56 @@
+/// Tests that chains of detail frames are collapsed in taskdumps, unless they
+/// are rendered with `Verbosity::Full`.
+mod util;
+use async_backtrace::{TaskdumpOptions, Verbosity};
+use std::{future::Future, task::Context};
+
+#[test]
+fn detail() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(handler());
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        pretty_assertions::assert_str_eq!(
+            util::strip(TaskdumpOptions::new().wait_for_running_tasks(true).dump()),
+            "\
+╼ detail::handler::{{closure}} at backtrace/tests/detail.rs:LINE:COL
+  └╼ detail::query::{{closure}} at backtrace/tests/detail.rs:LINE:COL (via 2 helper frames)"
+        );
+
+        pretty_assertions::assert_str_eq!(
+            util::strip(
+                TaskdumpOptions::new()
+                    .wait_for_running_tasks(true)
+                    .verbosity(Verbosity::Full)
+                    .dump()
+            ),
+            "\
+╼ detail::handler::{{closure}} at backtrace/tests/detail.rs:LINE:COL
+  └╼ detail::retry::{{closure}} at backtrace/tests/detail.rs:LINE:COL
+     └╼ detail::instrument::{{closure}} at backtrace/tests/detail.rs:LINE:COL
+        └╼ detail::query::{{closure}} at backtrace/tests/detail.rs:LINE:COL"
+        );
+    });
+}
+
+#[async_backtrace::framed]
+async fn handler() {
+    retry().await;
+}
+
+#[async_backtrace::framed(detail)]
+async fn retry() {
+    instrument().await;
+}
+
+#[async_backtrace::framed(detail)]
+async fn instrument() {
+    query().await;
+}
+
+#[async_backtrace::framed]
+async fn query() {
+    std::future::pending::<()>().await;
+}
@@ -0,0 +1,135 @@
+/// A test that `diff` reports the tasks added and removed between two
+/// snapshots (or two parsed taskdumps), and the tasks whose leaves changed.
+mod util;
+use std::{future::Future, pin::Pin, task::Context};
+
+type Task = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+#[test]
+fn diff() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut poll = |task: &mut Task| assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        let mut advancing: Task = Box::pin(advancing());
+        let mut exiting: Task = Box::pin(pending());
+        poll(&mut advancing);
+        poll(&mut exiting);
+        let before = async_backtrace::snapshot(true);
+        assert!(async_backtrace::diff(&before, &before).is_empty());
+
+        // `advancing` moves from `first` to `second`, `exiting` is dropped,
+        // and two tasks are spawned at one site
+        poll(&mut advancing);
+        drop(exiting);
+        let mut spawned: Vec<Task> = vec![Box::pin(spawned()), Box::pin(spawned())];
+        spawned.iter_mut().for_each(&mut poll);
+        let after = async_backtrace::snapshot(true);
+
+        let diff = async_backtrace::diff(&before, &after);
+        assert_eq!(diff.added().len(), 2);
+        assert_eq!(diff.removed().len(), 1);
+        let changed = diff.changed();
+        assert_eq!(changed.len(), 1);
+        let names = |paths: &[Vec<async_backtrace::Location>]| -> Vec<Vec<_>> {
+            paths
+                .iter()
+                .map(|path| {
+                    path.iter()
+                        .map(|location| location.name().unwrap().to_string())
+                        .collect()
+                })
+                .collect()
+        };
+        assert_eq!(
+            names(changed[0].paths_before()),
+            [["diff::advancing::{{closure}}", "diff::first::{{closure}}"]]
+        );
+        assert_eq!(
+            names(changed[0].paths_after()),
+            [["diff::advancing::{{closure}}", "diff::second::{{closure}}"]]
+        );
+        let compact = |location: &async_backtrace::Location| location.as_compact().to_string();
+        pretty_assertions::assert_str_eq!(
+            diff.to_string(),
+            format!(
+                "\
++ 2 new tasks rooted at {}
+- 1 task rooted at {} exited
+~ task #{} advanced from {} to {}",
+                compact(diff.added()[0].root()),
+                compact(diff.removed()[0].root()),
+                changed[0].id().unwrap(),
+                compact(&changed[0].paths_before()[0][1]),
+                compact(&changed[0].paths_after()[0][1]),
+            )
+        );
+        assert!(diff.to_string().starts_with(
+            "+ 2 new tasks rooted at diff::spawned::{{closure}}@backtrace/tests/diff.rs:"
+        ));
+    });
+}
+
+#[test]
+fn parsed() {
+    let before = async_backtrace::ParsedDump::from_tree_text(
+        "\
+╼ app::serve::{{closure}} at src/main.rs:5:1
+  └╼ app::read::{{closure}} at src/main.rs:10:1
+╼ app::handle::{{closure}} at src/main.rs:20:1
+  └╼ app::read::{{closure}} at src/main.rs:10:1
+╼ app::warmup::{{closure}} at src/main.rs:40:1",
+    )
+    .unwrap();
+    // the tasks are reordered, `serve` advances, `warmup` exits, and another
+    // `handle` is spawned
+    let after = async_backtrace::ParsedDump::from_tree_text(
+        "\
+╼ app::handle::{{closure}} at src/main.rs:20:1
+  └╼ app::read::{{closure}} at src/main.rs:10:1
+╼ app::serve::{{closure}} at src/main.rs:5:1
+  └╼ app::write::{{closure}} at src/main.rs:33:1
+╼ app::handle::{{closure}} at src/main.rs:20:1
+  └╼ 2x app::read::{{closure}} at src/main.rs:10:1",
+    )
+    .unwrap();
+    assert!(async_backtrace::diff(before.tasks(), before.tasks()).is_empty());
+
+    let diff = async_backtrace::diff(before.tasks(), after.tasks());
+    assert_eq!(diff.changed()[0].id(), None);
+    assert_eq!(diff.added()[0].paths_after().len(), 2);
+    pretty_assertions::assert_str_eq!(
+        diff.to_string(),
+        "\
++ 1 new task rooted at app::handle::{{closure}}@src/main.rs:20
+- 1 task rooted at app::warmup::{{closure}}@src/main.rs:40 exited
+~ task rooted at app::serve::{{closure}}@src/main.rs:5 advanced from app::read::{{closure}}@src/main.rs:10 to app::write::{{closure}}@src/main.rs:33"
+    );
+}
+
+#[async_backtrace::framed]
+async fn advancing() {
+    first().await;
+    second().await;
+}
+
+#[async_backtrace::framed]
+async fn first() {
+    futures::pending!();
+}
+
+#[async_backtrace::framed]
+async fn second() {
+    std::future::pending::<()>().await
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    std::future::pending::<()>().await
+}
+
+#[async_backtrace::framed]
+async fn spawned() {
+    std::future::pending::<()>().await
+}
@@ -0,0 +1,101 @@
+/// A stress test that a blocking taskdump completes promptly, with a
+/// point-in-time set of tasks, while other threads register and deregister
+/// tasks as fast as they can, and while the very task it waits for registers
+/// tasks from within its poll.
+mod util;
+use async_backtrace::{location, raw::Frame, TaskdumpOptions};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+
+const CHURNERS: usize = 4;
+const IDLE: usize = 1024;
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn dump_churn() {
+    // (so that a failed assertion does not leave the threads churning)
+    let _stop = Stop;
+    // (so that every shard of the registry holds a task)
+    let idle: Vec<_> = (0..IDLE)
+        .map(|_| {
+            let mut frame = Box::pin(Frame::new(location!()));
+            frame.as_mut().in_scope(|| {});
+            frame
+        })
+        .collect();
+    let churners: Vec<_> = (0..CHURNERS).map(|_| std::thread::spawn(churn)).collect();
+    let busy = std::thread::spawn(|| util::run(busy()));
+    while async_backtrace::tasks().count() <= IDLE + CHURNERS {
+        std::thread::yield_now();
+    }
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let dump = TaskdumpOptions::new().wait_for_running_tasks(true).dump();
+        let _ = tx.send(dump);
+    });
+    let dump = match rx.recv_timeout(Duration::from_secs(10)) {
+        Ok(dump) => dump,
+        Err(_) => {
+            // (dropping the idle tasks would block on the stuck dump)
+            std::mem::forget(idle);
+            panic!("the dump did not complete");
+        }
+    };
+
+    // each churner has at most one task at a time, so no more can have been
+    // listed when the dump began
+    let churned = dump.matches("╼ dump_churn::churn").count();
+    assert!(
+        churned <= CHURNERS,
+        "{} churned tasks in:\n{}",
+        churned,
+        dump
+    );
+    // the busy task was waited for, not rendered as polling
+    assert!(dump.contains("╼ dump_churn::busy::{{closure}}"));
+    assert!(!dump.contains("[POLLING]"));
+
+    STOP.store(true, Ordering::Release);
+    for churner in churners {
+        churner.join().unwrap();
+    }
+    busy.join().unwrap();
+    drop(idle);
+}
+
+struct Stop;
+
+impl Drop for Stop {
+    fn drop(&mut self) {
+        STOP.store(true, Ordering::Release);
+    }
+}
+
+/// Registers and deregisters tasks, one after another, until stopped.
+fn churn() {
+    while !STOP.load(Ordering::Acquire) {
+        let mut frame = Box::pin(Frame::new(location!()));
+        frame.as_mut().in_scope(|| {});
+    }
+}
+
+/// Registers a task, on another thread, within each of its polls, until
+/// stopped.
+#[async_backtrace::framed]
+async fn busy() {
+    while !STOP.load(Ordering::Acquire) {
+        // (the dump, if any, is by now waiting for this poll)
+        std::thread::sleep(Duration::from_millis(1));
+        std::thread::spawn(|| {
+            let mut frame = Box::pin(Frame::new(location!()));
+            frame.as_mut().in_scope(|| {});
+        })
+        .join()
+        .unwrap();
+        tokio::task::yield_now().await;
+    }
+}
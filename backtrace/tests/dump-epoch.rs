@@ -0,0 +1,70 @@
+/// A test that the ages in a taskdump are relative to a single instant, taken
+/// when the dump begins, even if the dump is slow.
+mod util;
+use std::{
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+    task::Context,
+    time::Duration,
+};
+
+static BUSY: AtomicBool = AtomicBool::new(false);
+
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn dump_epoch() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        // two tasks record errors at (nearly) the same time...
+        let mut a = Box::pin(retrying());
+        let mut b = Box::pin(retrying());
+        assert!(a.as_mut().poll(&mut cx).is_pending());
+        assert!(b.as_mut().poll(&mut cx).is_pending());
+
+        // ...while a third task is busy for over a second, which slows the
+        // blocking dump that waits for it.
+        let busy = util::thread::spawn(|| util::run(busy()));
+        while !BUSY.load(Ordering::SeqCst) {
+            util::thread::yield_now();
+        }
+
+        let dump = async_backtrace::taskdump_tree(true);
+        busy.join().unwrap();
+
+        let ages: Vec<_> = dump
+            .lines()
+            .filter_map(|line| line.split(" [").nth(1))
+            .collect();
+        assert_eq!(
+            ages,
+            [
+                "last error 0s ago: unavailable]",
+                "last error 0s ago: unavailable]"
+            ],
+            "{}",
+            dump
+        );
+    });
+}
+
+#[async_backtrace::framed]
+async fn retrying() {
+    assert!(attempt(false).await.is_err());
+    let _ = attempt(true).await;
+}
+
+#[async_backtrace::framed(record_err)]
+async fn attempt(pending: bool) -> Result<(), &'static str> {
+    if pending {
+        futures::future::pending::<()>().await;
+    }
+    Err("unavailable")
+}
+
+#[async_backtrace::framed]
+async fn busy() {
+    BUSY.store(true, Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(1500));
+}
@@ -0,0 +1,91 @@
+/// A test that filtered taskdumps include only the tasks whose roots satisfy
+/// the filter, and never lock (nor wait for) the others.
+mod util;
+use async_backtrace::TaskdumpOptions;
+use std::{
+    cell::Cell,
+    future::Future,
+    ops::ControlFlow,
+    sync::mpsc,
+    task::{Context, Poll},
+};
+
+#[test]
+// loom cannot model the polling thread, which blocks outside of its control
+#[cfg_attr(loom, ignore)]
+fn dump_filter() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut server = Box::pin(server());
+        let mut worker = Box::pin(worker());
+        assert!(server.as_mut().poll(&mut cx).is_pending());
+        assert!(worker.as_mut().poll(&mut cx).is_pending());
+
+        // a task whose root is held locked by a poll on another thread
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let polling = std::thread::spawn(move || {
+            let blocking = futures::future::poll_fn(move |_| {
+                entered_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+                Poll::Ready(())
+            });
+            futures::executor::block_on(async_backtrace::location!().frame(blocking));
+        });
+        entered_rx.recv().unwrap();
+
+        // were the polling task locked, these would wait until it is released
+        let server_tree = "\
+╼ dump_filter::server::{{closure}} at backtrace/tests/dump-filter.rs:LINE:COL
+  └╼ dump_filter::handle::{{closure}} at backtrace/tests/dump-filter.rs:LINE:COL";
+        let dump = async_backtrace::taskdump_tree_filtered(true, |root| {
+            root.name() == Some("dump_filter::server::{{closure}}")
+        });
+        pretty_assertions::assert_str_eq!(util::strip(dump), server_tree);
+
+        let dump = TaskdumpOptions::new()
+            .wait_for_running_tasks(true)
+            .roots_containing("worker")
+            .dump();
+        pretty_assertions::assert_str_eq!(
+            util::strip(dump),
+            "╼ dump_filter::worker::{{closure}} at backtrace/tests/dump-filter.rs:LINE:COL"
+        );
+
+        // roots may be selected by file, too; the skipped tasks are not
+        // counted in the progress of the dump
+        let total = Cell::new(0);
+        let dump = TaskdumpOptions::new()
+            .roots_containing("tests/dump-filter.rs")
+            .progress(|_, all| {
+                total.set(all);
+                ControlFlow::Continue(())
+            })
+            .dump();
+        assert_eq!(dump.lines().count(), 5);
+        assert_eq!(total.get(), 3);
+        assert!(dump.contains("[POLLING on "));
+
+        let dump = TaskdumpOptions::new().roots_containing("client").dump();
+        assert_eq!(dump, "");
+
+        release_tx.send(()).unwrap();
+        polling.join().unwrap();
+    });
+}
+
+#[async_backtrace::framed]
+async fn server() {
+    handle().await;
+}
+
+#[async_backtrace::framed]
+async fn handle() {
+    std::future::pending::<()>().await;
+}
+
+#[async_backtrace::framed]
+async fn worker() {
+    std::future::pending::<()>().await;
+}
@@ -0,0 +1,54 @@
+/// A test that `dump_tasks` dumps the trees of live tasks by id, and nothing
+/// for stale ids.
+mod util;
+use async_backtrace::{dump_tasks, TaskdumpOptions};
+use std::{future::Future, task::Context};
+
+#[test]
+fn live_and_stale() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut live = Box::pin(outer());
+        let mut stale = Box::pin(outer());
+        assert!(live.as_mut().poll(&mut cx).is_pending());
+        assert!(stale.as_mut().poll(&mut cx).is_pending());
+        let ids: Vec<u64> = async_backtrace::tasks().map(|task| task.id()).collect();
+        let (live_id, stale_id) = (ids[0].min(ids[1]), ids[0].max(ids[1]));
+        drop(stale);
+
+        let trees = dump_tasks(&[stale_id, live_id, u64::MAX], TaskdumpOptions::new());
+        let trees: Vec<_> = trees
+            .into_iter()
+            .map(|(id, tree)| (id, tree.map(util::strip)))
+            .collect();
+        pretty_assertions::assert_eq!(
+            trees,
+            [
+                (stale_id, None),
+                (
+                    live_id,
+                    Some(
+                        "\
+╼ dump_tasks::outer::{{closure}} at backtrace/tests/dump-tasks.rs:LINE:COL
+  └╼ dump_tasks::inner::{{closure}} at backtrace/tests/dump-tasks.rs:LINE:COL"
+                            .to_string()
+                    )
+                ),
+                (u64::MAX, None),
+            ]
+        );
+        drop(live);
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    inner().await;
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    std::future::pending::<()>().await;
+}
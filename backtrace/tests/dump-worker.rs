@@ -0,0 +1,98 @@
+/// A test that `dump`, called within a tokio task outside of any frame (on a
+/// worker thread, or a blocking thread), does not wait indefinitely for a task
+/// that is stuck mid-poll, but that it waits, as on any other thread, when
+/// called within `block_on` outside of any task.
+mod util;
+use std::{future::Future, sync::mpsc, task::Context, time::Duration};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[cfg_attr(any(miri, loom), ignore)]
+async fn worker_thread() {
+    let (entered_tx, entered_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel();
+    let stuck = std::thread::spawn(move || {
+        futures::executor::block_on(stuck(entered_tx, release_rx, false));
+    });
+    entered_rx.recv().unwrap();
+
+    let dump = tokio::spawn(async { async_backtrace::dump() })
+        .await
+        .unwrap();
+    pretty_assertions::assert_str_eq!(
+        util::strip(dump),
+        "\
+╼ dump_worker::stuck::{{closure}} at backtrace/tests/dump-worker.rs:LINE:COL
+  └┈ [POLLING on THREAD]"
+    );
+
+    release_tx.send(()).unwrap();
+    stuck.join().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[cfg_attr(any(miri, loom), ignore)]
+async fn blocking_thread() {
+    let (entered_tx, entered_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel();
+    let stuck = std::thread::spawn(move || {
+        futures::executor::block_on(stuck(entered_tx, release_rx, false));
+    });
+    entered_rx.recv().unwrap();
+
+    // tokio runs blocking work as tasks, too
+    let dump = tokio::task::spawn_blocking(async_backtrace::dump)
+        .await
+        .unwrap();
+    pretty_assertions::assert_str_eq!(
+        util::strip(dump),
+        "\
+╼ dump_worker::stuck::{{closure}} at backtrace/tests/dump-worker.rs:LINE:COL
+  └┈ [POLLING on THREAD]"
+    );
+
+    release_tx.send(()).unwrap();
+    stuck.join().unwrap();
+}
+
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn block_on() {
+    let (entered_tx, entered_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel();
+    let stuck = std::thread::spawn(move || {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut stuck = Box::pin(stuck(entered_tx, release_rx, true));
+        assert!(stuck.as_mut().poll(&mut cx).is_pending());
+        stuck
+    });
+    entered_rx.recv().unwrap();
+
+    // the dump waits for the poll to finish, for longer than it would within
+    // a task
+    let releaser = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(300));
+        release_tx.send(()).unwrap();
+    });
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let dump = runtime.block_on(async { async_backtrace::dump() });
+    releaser.join().unwrap();
+    let idle = stuck.join().unwrap();
+    pretty_assertions::assert_str_eq!(
+        util::strip(dump),
+        "╼ dump_worker::stuck::{{closure}} at backtrace/tests/dump-worker.rs:LINE:COL"
+    );
+    drop(idle);
+}
+
+/// Blocks mid-poll until `release` is signalled, and then, if `pend`, pends.
+#[async_backtrace::framed]
+async fn stuck(entered: mpsc::Sender<()>, release: mpsc::Receiver<()>, pend: bool) {
+    entered.send(()).unwrap();
+    release.recv().unwrap();
+    if pend {
+        futures::pending!()
+    }
+}
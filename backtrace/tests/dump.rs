@@ -0,0 +1,50 @@
+/// Tests that `dump` renders the tree of the calling task from within it, and
+/// waits for idle tasks from a plain thread.
+mod util;
+use std::{future::Future, task::Context};
+
+#[test]
+fn dump() {
+    util::model(|| {
+        // from within a framed task, the caller's own tree is shown in full
+        let dump = util::run(outer());
+        pretty_assertions::assert_str_eq!(
+            util::strip(dump),
+            "\
+╼ dump::outer::{{closure}} at backtrace/tests/dump.rs:LINE:COL
+  └╼ dump::inner::{{closure}} at backtrace/tests/dump.rs:LINE:COL"
+        );
+
+        // from a plain thread, the trees of idle tasks are shown in full
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(pending());
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+        pretty_assertions::assert_str_eq!(
+            util::strip(async_backtrace::dump()),
+            "\
+╼ dump::pending::{{closure}} at backtrace/tests/dump.rs:LINE:COL
+  └╼ dump::leaf::{{closure}} at backtrace/tests/dump.rs:LINE:COL"
+        );
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() -> String {
+    inner().await
+}
+
+#[async_backtrace::framed]
+async fn inner() -> String {
+    async_backtrace::dump()
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    leaf().await;
+}
+
+#[async_backtrace::framed]
+async fn leaf() {
+    std::future::pending::<()>().await;
+}
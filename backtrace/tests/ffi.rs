@@ -0,0 +1,44 @@
+/// A test of the C ABI of the `ffi` feature, called through function pointers
+/// as foreign code would call it.
+mod util;
+use async_backtrace::{ffi, Location};
+use std::{future::Future, task::Context};
+
+static A: (&str, u32, u32) = ("src/app.rs", 1, 1);
+static B: (&str, u32, u32) = ("src/app.rs", 10, 1);
+
+#[test]
+fn ffi() {
+    util::model(|| {
+        let dump: unsafe extern "C" fn(*mut u8, usize) -> usize = ffi::async_backtrace_dump;
+        let task_count: extern "C" fn() -> usize = ffi::async_backtrace_task_count;
+
+        assert_eq!(task_count(), 0);
+        assert_eq!(unsafe { dump(std::ptr::null_mut(), 0) }, 0);
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let frame = |name, rest| Location::from_components(name, rest);
+        let mut a =
+            Box::pin(frame("a", &A).frame(frame("ß", &B).frame(std::future::pending::<()>())));
+        assert!(a.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(task_count(), 1);
+
+        let expected = "\
+╼ a at src/app.rs:1:1
+  └╼ ß at src/app.rs:10:1";
+        let mut buf = [0u8; 256];
+        let written = unsafe { dump(buf.as_mut_ptr(), buf.len()) };
+        assert_eq!(std::str::from_utf8(&buf[..written]).unwrap(), expected);
+
+        // a dump that does not fit is truncated at a character boundary
+        let len = expected.find('ß').unwrap() + 1;
+        let written = unsafe { dump(buf.as_mut_ptr(), len) };
+        assert_eq!(written, len - 1);
+        assert_eq!(&buf[..written], &expected.as_bytes()[..written]);
+
+        drop(a);
+        assert_eq!(task_count(), 0);
+        assert_eq!(unsafe { dump(buf.as_mut_ptr(), buf.len()) }, 0);
+    });
+}
@@ -0,0 +1,12 @@
+// A fixture for tests/source-snippets.rs, which renders its lines.
+async fn serve() {
+    tokio::select! {
+        _ = handle() => {}
+    }
+}
+
+async fn handle() {
+        let request = read_request(&mut stream).await?;   
+}
+
+fn long() { let a_line_that_goes_on_and_on = "for longer than one hundred characters, so that it is truncated"; }
@@ -0,0 +1,27 @@
+/// A test that `Location::frame_boxed` frames both unboxed and already-boxed
+/// futures.
+mod util;
+use std::{future::Future, pin::Pin};
+
+type Job = Pin<Box<dyn Future<Output = Vec<String>> + Send>>;
+
+#[test]
+fn frame_boxed() {
+    util::model(|| {
+        let queue: Vec<Job> = vec![
+            async_backtrace::location!().frame_boxed(backtrace()),
+            async_backtrace::location!().frame_boxed(Box::pin(backtrace()) as Job),
+        ];
+        for job in queue {
+            assert_eq!(util::run(job), ["frame_boxed::frame_boxed::{{closure}}"]);
+        }
+    });
+}
+
+async fn backtrace() -> Vec<String> {
+    async_backtrace::backtrace()
+        .unwrap()
+        .iter()
+        .map(|location| location.name().unwrap().to_string())
+        .collect()
+}
@@ -0,0 +1,69 @@
+/// A test that `Location::frame_with` constructs frames as configured by each
+/// combination of `FrameConfig` options, as the corresponding `frame_*`
+/// methods (and arguments of `framed`) do.
+mod util;
+use async_backtrace::{location, FrameConfig, TaskdumpOptions};
+use std::{future::Future, task::Context};
+
+#[test]
+fn frame_config() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(executor());
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        pretty_assertions::assert_str_eq!(
+            util::strip(
+                TaskdumpOptions::new()
+                    .wait_for_running_tasks(true)
+                    .sort_tasks(true)
+                    .dump()
+            ),
+            "\
+╼ frame_config::executor::{{closure}} at backtrace/tests/frame-config.rs:LINE:COL
+  ├╼ frame_config::leaf::{{closure}} at backtrace/tests/frame-config.rs:LINE:COL
+  └╼ frame_config::leaf::{{closure}} at backtrace/tests/frame-config.rs:LINE:COL (via 1 helper frame)
+╼ frame_config::job::{{closure}} at backtrace/tests/frame-config.rs:LINE:COL"
+        );
+    });
+}
+
+#[async_backtrace::framed]
+async fn executor() {
+    futures::join!(job(), helper(), bypassed());
+}
+
+/// The root of a task of its own, at which backtraces stop.
+async fn job() {
+    let config = FrameConfig::new().detached().barrier();
+    location!()
+        .frame_with(
+            async {
+                assert_eq!(async_backtrace::backtrace().unwrap().len(), 1);
+                futures::pending!()
+            },
+            config,
+        )
+        .await
+}
+
+/// A detail, collapsed in taskdumps.
+async fn helper() {
+    location!()
+        .frame_with(leaf(), FrameConfig::new().detail().must_poll())
+        .await
+}
+
+/// Not framed, as it is not the root of a task; its leaf is adopted by the
+/// executor.
+async fn bypassed() {
+    location!()
+        .frame_with(leaf(), FrameConfig::new().root_only())
+        .await
+}
+
+#[async_backtrace::framed]
+async fn leaf() {
+    futures::pending!()
+}
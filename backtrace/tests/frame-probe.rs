@@ -0,0 +1,81 @@
+/// A test that `FrameProbe` observes the registration, parenting and
+/// deregistration of the frames of a future that it polls, and only those.
+mod util;
+use async_backtrace::testing::FrameProbe;
+use std::{future::Future, task::Context};
+
+#[test]
+fn frame_probe() {
+    util::model(|| {
+        // a task that the probe does not poll
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut other = Box::pin(leaf());
+        assert!(other.as_mut().poll(&mut cx).is_pending());
+
+        let mut probe = FrameProbe::new(outer());
+        assert!(probe.registered_roots().is_empty());
+        assert!(probe.poll().is_pending());
+
+        let roots = probe.registered_roots();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(
+            roots[0].root().location().name(),
+            Some("frame_probe::outer::{{closure}}")
+        );
+        assert!(!roots[0].is_polling());
+
+        let names = |path| -> Vec<String> {
+            let children = probe.children_of(path).into_iter();
+            children
+                .map(|child| child.name().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(
+            names("outer"),
+            [
+                "frame_probe::middle::{{closure}}",
+                "frame_probe::leaf::{{closure}}"
+            ]
+        );
+        assert_eq!(
+            names("frame_probe::middle"),
+            ["frame_probe::leaf::{{closure}}"]
+        );
+        // leaves have no children, and paths match whole segments only
+        assert!(names("leaf").is_empty());
+        assert!(names("iddle").is_empty());
+
+        // probes within the polls of probes record their own tasks
+        let mut inner = None;
+        let mut nesting = FrameProbe::new(futures::future::poll_fn(|_| {
+            let mut probe = FrameProbe::new(leaf());
+            assert!(probe.poll().is_pending());
+            assert_eq!(probe.registered_roots().len(), 1);
+            inner = Some(probe);
+            std::task::Poll::Ready(())
+        }));
+        assert!(nesting.poll().is_ready());
+        assert!(nesting.registered_roots().is_empty());
+        assert!(inner.unwrap().dropped_cleanly());
+
+        assert!(probe.dropped_cleanly());
+        // the task that the probe did not poll remains
+        assert_eq!(async_backtrace::tasks().count(), 1);
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    futures::join!(leaf(), middle());
+}
+
+#[async_backtrace::framed]
+async fn middle() {
+    leaf().await;
+}
+
+#[async_backtrace::framed]
+async fn leaf() {
+    std::future::pending::<()>().await;
+}
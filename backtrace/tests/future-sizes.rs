@@ -0,0 +1,75 @@
+/// A test that the sizes of framed futures are recorded, rendered in dumps
+/// from a threshold, and ranked by `largest_futures`.
+mod util;
+use async_backtrace::TaskdumpOptions;
+use std::{future::Future, task::Context};
+
+#[test]
+// (the huge future overflows the small stacks of loom's threads)
+#[cfg_attr(loom, ignore)]
+fn future_sizes() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(outer());
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        // only the futures of at least 16 KiB are annotated
+        let dump = TaskdumpOptions::new().future_sizes(16 * 1024).dump();
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("future_sizes::outer::{{closure}}"));
+        assert!(lines[0].contains(" [future: 16."));
+        assert!(lines[1].contains("future_sizes::small::{{closure}}"));
+        assert!(!lines[1].contains("[future:"));
+        assert!(lines[2].contains("future_sizes::huge::{{closure}}"));
+        assert!(lines[2].ends_with(" KiB]"));
+
+        // small sizes are rendered in bytes
+        let dump = TaskdumpOptions::new().future_sizes(0).dump();
+        assert!(dump.lines().nth(1).unwrap().ends_with(" B]"));
+
+        // without a threshold, no sizes are rendered
+        assert!(!TaskdumpOptions::new().dump().contains("[future:"));
+
+        // the largest futures are ranked, outermost (which embeds the others)
+        // first
+        let largest = async_backtrace::largest_futures(2);
+        let names: Vec<_> = largest
+            .iter()
+            .map(|(location, _)| location.to_string())
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert!(names[0].starts_with("future_sizes::outer::{{closure}}"));
+        assert!(names[1].starts_with("future_sizes::huge::{{closure}}"));
+        assert!(largest[0].1 > largest[1].1);
+        assert!(largest[1].1 >= 16 * 1024);
+        assert_eq!(async_backtrace::largest_futures(10).len(), 3);
+
+        // JSON dumps include the size of every frame
+        let dump: serde_json::Value =
+            serde_json::from_str(&TaskdumpOptions::new().future_sizes(1 << 20).dump_json())
+                .unwrap();
+        let root = &dump["tasks"][0]["root"];
+        assert_eq!(root["future_size"], largest[0].1);
+        assert_eq!(root["children"][1]["future_size"], largest[1].1);
+        assert!(root["children"][0]["future_size"].as_u64().unwrap() < 1024);
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    futures::join!(huge(), small());
+}
+
+#[async_backtrace::framed]
+async fn huge() {
+    let buffer = [0u8; 16384];
+    std::future::pending::<()>().await;
+    std::hint::black_box(&buffer);
+}
+
+#[async_backtrace::framed]
+async fn small() {
+    std::future::pending::<()>().await;
+}
@@ -0,0 +1,34 @@
+/// A test that control characters in the names of frames cannot corrupt the
+/// structure of taskdumps.
+mod util;
+use async_backtrace::{location_named, Location};
+
+static HOSTILE: Location = location_named!("hostile::root\n╼ fake::root\tname \"quoted\"\r");
+
+#[test]
+fn hostile_names() {
+    util::model(|| {
+        let rendered = HOSTILE.to_string();
+        assert_eq!(rendered.len(), HOSTILE.len_hint());
+        assert_eq!(
+            util::strip(&rendered),
+            "hostile::root␊╼ fake::root␉name \"quoted\"␍ at backtrace/tests/hostile-names.rs:LINE:COL"
+        );
+        assert_eq!(
+            HOSTILE.as_compact().to_string().len(),
+            HOSTILE.as_compact().len_hint()
+        );
+
+        util::run(HOSTILE.frame(async {
+            let dump = async_backtrace::taskdump_tree(false);
+            let tasks = async_backtrace::testing::parse_taskdump(&dump).unwrap();
+            let task = tasks
+                .iter()
+                .find(|task| task.name().starts_with("hostile::root"))
+                .unwrap();
+            assert_eq!(task.name(), "hostile::root␊╼ fake::root␉name \"quoted\"␍");
+            assert!(task.children().is_empty());
+            assert_eq!(dump.lines().count(), tasks.len());
+        }));
+    });
+}
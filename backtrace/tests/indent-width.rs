@@ -0,0 +1,63 @@
+/// A test that `TaskdumpOptions::indent_width` and `task_separator` change the
+/// indentation of trees, and the separators between them.
+mod util;
+use async_backtrace::{Location, TaskdumpOptions};
+use std::{future::Future, task::Context};
+
+static A: (&str, u32, u32) = ("src/app.rs", 1, 1);
+static B: (&str, u32, u32) = ("src/app.rs", 10, 1);
+static C: (&str, u32, u32) = ("src/app.rs", 20, 1);
+static D: (&str, u32, u32) = ("src/app.rs", 30, 1);
+static Z: (&str, u32, u32) = ("src/app.rs", 40, 1);
+
+#[test]
+fn indent_width() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let frame = |name, rest| Location::from_components(name, rest);
+        let leaf = |name, rest| frame(name, rest).frame(std::future::pending::<()>());
+        // children are rendered in the reverse of the order of their polls
+        let mut a = Box::pin(frame("a", &A).frame(futures::future::join(
+            leaf("d", &D),
+            frame("b", &B).frame(leaf("c", &C)),
+        )));
+        let mut z = Box::pin(frame("z", &Z).frame(leaf("c", &C)));
+        assert!(a.as_mut().poll(&mut cx).is_pending());
+        assert!(z.as_mut().poll(&mut cx).is_pending());
+
+        let options = || TaskdumpOptions::new().sort_tasks(true);
+        pretty_assertions::assert_str_eq!(
+            options().dump(),
+            "\
+╼ a at src/app.rs:1:1
+  ├╼ b at src/app.rs:10:1
+  │  └╼ c at src/app.rs:20:1
+  └╼ d at src/app.rs:30:1
+╼ z at src/app.rs:40:1
+  └╼ c at src/app.rs:20:1"
+        );
+        pretty_assertions::assert_str_eq!(
+            options().indent_width(2).task_separator("\n---\n").dump(),
+            "\
+╼ a at src/app.rs:1:1
+ ├╼ b at src/app.rs:10:1
+ │ └╼ c at src/app.rs:20:1
+ └╼ d at src/app.rs:30:1
+---
+╼ z at src/app.rs:40:1
+ └╼ c at src/app.rs:20:1"
+        );
+        pretty_assertions::assert_str_eq!(
+            options().indent_width(5).trailing_newline(true).dump(),
+            "\
+╼ a at src/app.rs:1:1
+    ├╼ b at src/app.rs:10:1
+    │    └╼ c at src/app.rs:20:1
+    └╼ d at src/app.rs:30:1
+╼ z at src/app.rs:40:1
+    └╼ c at src/app.rs:20:1
+"
+        );
+    });
+}
@@ -0,0 +1,63 @@
+/// A test that `taskdump_jsonl` writes one JSON object per task, per line,
+/// each as in the tasks of `taskdump_json`, and notes a cancelled dump on its
+/// last line.
+mod util;
+use async_backtrace::{taskdump_jsonl, TaskdumpOptions};
+use serde_json::{json, Value};
+use std::{future::Future, ops::ControlFlow, task::Context};
+
+#[test]
+fn json_lines() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut server = Box::pin(server());
+        let mut worker = Box::pin(worker());
+        assert!(server.as_mut().poll(&mut cx).is_pending());
+        assert!(worker.as_mut().poll(&mut cx).is_pending());
+
+        let options = || TaskdumpOptions::new().sort_tasks(true);
+        let dump: Value = serde_json::from_str(&options().dump_json()).unwrap();
+
+        let mut lines = Vec::new();
+        taskdump_jsonl(&mut lines, options()).unwrap();
+        let lines = String::from_utf8(lines).unwrap();
+        assert!(lines.ends_with('\n'));
+        let tasks: Vec<Value> = lines
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(Value::Array(tasks), dump["tasks"]);
+
+        // a cancelled dump ends with a note of how far it got
+        let mut lines = Vec::new();
+        let cancelled = options()
+            .progress_interval(1)
+            .progress(|_, _| ControlFlow::Break(()));
+        taskdump_jsonl(&mut lines, cancelled).unwrap();
+        let lines: Vec<Value> = String::from_utf8(lines)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], dump["tasks"][0]);
+        assert_eq!(lines[1], json!({"truncated": {"dumped": 1, "total": 2}}));
+    });
+}
+
+#[async_backtrace::framed]
+async fn server() {
+    handle().await
+}
+
+#[async_backtrace::framed]
+async fn handle() {
+    std::future::pending::<()>().await
+}
+
+#[async_backtrace::framed]
+async fn worker() {
+    std::future::pending::<()>().await
+}
@@ -0,0 +1,87 @@
+/// A test that taskdump_json() produces valid JSON, with the consolidated and
+/// nested frames of the tree, and escaped names.
+mod util;
+use async_backtrace::Location;
+use serde_json::{json, Value};
+
+static REST: (&str, u32, u32) = ("src/lib.rs", 1234, 5);
+
+#[test]
+fn json() {
+    util::model(|| util::run(selecting()));
+}
+
+#[async_backtrace::framed]
+async fn selecting() {
+    let hostile = Location::from_components("a \"quoted\"\\name\n\u{1}", &REST);
+    tokio::select! {
+        biased;
+        _ = yielding() => {}
+        _ = yielding() => {}
+        _ = yielding() => {}
+        _ = hostile.frame(ready()) => {}
+    };
+}
+
+#[async_backtrace::framed]
+async fn yielding() {
+    tokio::task::yield_now().await;
+}
+
+#[async_backtrace::framed]
+async fn ready() {
+    let dump = async_backtrace::taskdump_json(true);
+    assert!(!dump.contains('\n'));
+    let dump: Value = serde_json::from_str(&dump).unwrap();
+
+    assert_eq!(dump["truncated"], Value::Null);
+    let tasks = dump["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 1);
+    let task = &tasks[0];
+    assert!(task["id"].is_u64());
+    assert_eq!(task["polling"], false);
+    assert_eq!(task["last_known"], Value::Null);
+
+    let root = &task["root"];
+    assert_eq!(root["name"], "json::selecting::{{closure}}");
+    assert!(root["file"].as_str().unwrap().ends_with("tests/json.rs"));
+    assert!(root["line"].is_u64());
+    assert!(root["column"].is_u64());
+    assert_eq!(root["count"], 1);
+    assert_eq!(root["helper_frames"], 0);
+    assert_eq!(root["panicked"], false);
+    assert_eq!(root["last_error"], Value::Null);
+    assert_eq!(root["leaf_state"], Value::Null);
+
+    let children = root["children"].as_array().unwrap();
+    assert_eq!(children.len(), 2);
+    assert_eq!(
+        children[0],
+        json!({
+            "name": "a \"quoted\"\\name\n\u{1}",
+            "file": "src/lib.rs",
+            "line": 1234,
+            "column": 5,
+            "count": 1,
+            "helper_frames": 0,
+            "panicked": false,
+            "last_error": null,
+            "leaf_state": null,
+            "children": [{
+                "name": "json::ready::{{closure}}",
+                "file": children[0]["children"][0]["file"],
+                "line": children[0]["children"][0]["line"],
+                "column": children[0]["children"][0]["column"],
+                "count": 1,
+                "helper_frames": 0,
+                "panicked": false,
+                "last_error": null,
+                "leaf_state": "unknown",
+                "children": [],
+            }],
+        })
+    );
+    assert_eq!(children[1]["name"], "json::yielding::{{closure}}");
+    assert_eq!(children[1]["count"], 3);
+    assert_eq!(children[1]["children"], json!([]));
+}
@@ -0,0 +1,89 @@
+/// A test that non-blocking taskdumps show the last-known tree of tasks that
+/// are being polled, if configured to cache them.
+mod util;
+use async_backtrace::{configure_registry, RegistryConfig};
+use std::{
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+    task::Context,
+};
+
+static BUSY: AtomicBool = AtomicBool::new(false);
+static RELEASE: AtomicBool = AtomicBool::new(false);
+
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn last_tree() {
+    configure_registry(RegistryConfig {
+        cache_last_tree: true,
+        ..RegistryConfig::default()
+    })
+    .unwrap();
+
+    util::model(|| {
+        // the tree of the task is remembered at the end of its first poll...
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(outer());
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        // ...and shown while it is polled again
+        let poller = util::thread::spawn(move || util::run(task));
+        while !BUSY.load(Ordering::SeqCst) {
+            util::thread::yield_now();
+        }
+        let dump = async_backtrace::taskdump_tree(false);
+        RELEASE.store(true, Ordering::SeqCst);
+        poller.join().unwrap();
+
+        // normalize the age of the tree
+        let (head, tail) = dump.split_once("last known tree, ").unwrap();
+        let (_, tail) = tail.split_once("s old").unwrap();
+        let dump = util::strip(format!("{}last known tree, Ns old{}", head, tail));
+        pretty_assertions::assert_str_eq!(
+            dump,
+            "\
+╼ last_tree::outer::{{closure}} at backtrace/tests/last-tree.rs:LINE:COL
+  ├┈ [POLLING on THREAD] last known tree, Ns old (possibly stale):
+  └╼ last_tree::first::{{closure}} at backtrace/tests/last-tree.rs:LINE:COL
+     └╼ last_tree::leaf::{{closure}} at backtrace/tests/last-tree.rs:LINE:COL"
+        );
+
+        // tasks that have never been inspected have no last-known tree
+        let dump = util::run(async_backtrace::location!().frame(async {
+            util::thread::spawn(|| async_backtrace::taskdump_tree(false))
+                .join()
+                .unwrap()
+        }));
+        pretty_assertions::assert_str_eq!(
+            util::strip(dump),
+            "\
+╼ last_tree::last_tree::{{closure}} at backtrace/tests/last-tree.rs:LINE:COL
+  └┈ [POLLING on THREAD]"
+        );
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    first().await;
+    busy().await;
+}
+
+#[async_backtrace::framed]
+async fn first() {
+    leaf().await
+}
+
+#[async_backtrace::framed]
+async fn leaf() {
+    futures::pending!()
+}
+
+#[async_backtrace::framed]
+async fn busy() {
+    BUSY.store(true, Ordering::SeqCst);
+    while !RELEASE.load(Ordering::SeqCst) {
+        std::thread::yield_now();
+    }
+}
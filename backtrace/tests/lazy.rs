@@ -0,0 +1,135 @@
+/// A test that `#[framed(lazy)]` only initializes frames for futures that are
+/// pending upon their first poll, that the subframes first polled by that poll
+/// are attached to the lazy frame's parent, and that roots are never lazy.
+mod util;
+use async_backtrace::testing::FrameProbe;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[test]
+fn ready_first_poll() {
+    util::model(|| {
+        assert_eq!(util::run(async_backtrace::location!().frame(ready())), 42);
+    });
+}
+
+#[test]
+fn pending_first_poll() {
+    util::model(|| {
+        let mut probe = FrameProbe::new(awaiting_pending_once());
+
+        assert!(probe.poll().is_pending());
+        // the frame is initialized as soon as the first poll is pending...
+        assert_eq!(
+            probe.children_of("awaiting_pending_once")[0].name(),
+            Some("lazy::pending_once::{{closure}}")
+        );
+
+        assert!(probe.poll().is_ready());
+        // ...and deregistered upon drop.
+        assert!(probe.dropped_cleanly());
+    });
+}
+
+#[test]
+fn subframes_of_first_poll() {
+    util::model(|| {
+        let mut probe = FrameProbe::new(awaiting_joining());
+        assert!(probe.poll().is_pending());
+
+        // the `pending`s first polled by the first poll of `joining` are
+        // children of its parent, as its siblings...
+        let siblings = probe.children_of("awaiting_joining");
+        let names: Vec<_> = siblings.iter().map(|child| child.name().unwrap()).collect();
+        assert_eq!(names.len(), 3);
+        assert_eq!(
+            names.iter().filter(|name| name.contains("joining")).count(),
+            1
+        );
+        assert_eq!(
+            names.iter().filter(|name| name.contains("pending")).count(),
+            2
+        );
+        // ...rather than its children
+        assert!(probe.children_of("lazy::joining").is_empty());
+
+        assert!(probe.dropped_cleanly());
+    });
+}
+
+#[test]
+fn root() {
+    util::model(|| {
+        // the root of a task is initialized before its first poll, so that the
+        // `pending`s first polled by it are its children, rather than the roots
+        // of tasks of their own
+        let mut probe = FrameProbe::new(joining());
+        assert!(probe.poll().is_pending());
+
+        let roots = probe.registered_roots();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(
+            roots[0].root().location().name(),
+            Some("lazy::joining::{{closure}}")
+        );
+        assert_eq!(probe.children_of("lazy::joining").len(), 2);
+
+        assert!(probe.dropped_cleanly());
+    });
+}
+
+#[async_backtrace::framed(lazy)]
+async fn ready() -> u32 {
+    // the first poll occurs outside of this function's frame
+    assert_eq!(async_backtrace::backtrace().unwrap().len(), 1);
+    42
+}
+
+#[async_backtrace::framed]
+async fn awaiting_pending_once() {
+    pending_once().await
+}
+
+#[async_backtrace::framed(lazy)]
+async fn pending_once() {
+    assert_eq!(async_backtrace::backtrace().unwrap().len(), 1);
+    YieldNow(false).await;
+    // subsequent polls occur within this function's frame
+    let backtrace = async_backtrace::backtrace().unwrap();
+    assert_eq!(backtrace.len(), 2);
+    assert_eq!(backtrace[0].name(), Some("lazy::pending_once::{{closure}}"));
+}
+
+#[async_backtrace::framed]
+async fn awaiting_joining() {
+    joining().await
+}
+
+#[async_backtrace::framed(lazy)]
+async fn joining() {
+    futures::join!(pending(), pending());
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    futures::future::pending::<()>().await
+}
+
+/// A future that is pending exactly once.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if std::mem::replace(&mut self.0, true) {
+            Poll::Ready(())
+        } else {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,33 @@
+/// A test that pins the renderings of `Location`, on which exporters rely.
+mod util;
+use async_backtrace::Location;
+
+static REST: (&str, u32, u32) = ("src/lib.rs", 1234, 5);
+
+#[test]
+fn location_fmt() {
+    util::model(|| {
+        let location = Location::from_components("crate::module::function::{{closure}}", &REST);
+
+        let expected = "crate::module::function::{{closure}} at src/lib.rs:1234:5";
+        assert_eq!(location.to_string(), expected);
+        assert_eq!(location.len_hint(), expected.len());
+        let mut buf = String::new();
+        location.write_to(&mut buf).unwrap();
+        assert_eq!(buf, expected);
+
+        let compact = location.as_compact();
+        let expected = "crate::module::function::{{closure}}@src/lib.rs:1234";
+        assert_eq!(compact.to_string(), expected);
+        assert_eq!(compact.len_hint(), expected.len());
+
+        // lengths are exact at the boundaries of each digit count
+        for line in [0, 9, 10, 99, 100, u32::MAX] {
+            let rest = Box::leak(Box::new(("a.rs", line, line)));
+            let location = Location::from_components("f", rest);
+            assert_eq!(location.len_hint(), location.to_string().len());
+            let compact = location.as_compact();
+            assert_eq!(compact.len_hint(), compact.to_string().len());
+        }
+    });
+}
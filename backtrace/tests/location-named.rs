@@ -0,0 +1,16 @@
+/// Tests that `location_named!` produces locations usable in `static`s.
+mod util;
+use async_backtrace::{location_named, Location};
+
+static ROOT: Location = location_named!("location_named::root");
+
+#[test]
+fn static_location() {
+    util::model(|| {
+        util::run(ROOT.frame(async {
+            let backtrace = async_backtrace::backtrace().unwrap();
+            let names: Vec<_> = backtrace.iter().map(Location::name).collect();
+            assert_eq!(names, [Some("location_named::root")]);
+        }));
+    });
+}
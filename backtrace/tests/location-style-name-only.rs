@@ -0,0 +1,61 @@
+/// A test that the `NameOnly` location style renders only the names of
+/// locations (and the positions of those without names) in backtraces,
+/// taskdumps and their JSON.
+mod util;
+use async_backtrace::{set_location_style, LocationStyle, OwnedLocation, TaskdumpOptions};
+use std::{future::Future, task::Context};
+
+#[test]
+fn location_style_name_only() {
+    set_location_style(LocationStyle::NameOnly).unwrap();
+
+    util::model(|| {
+        let location = async_backtrace::location!();
+        assert_eq!(location.to_string(), location.name().unwrap());
+        assert_eq!(location.to_string().len(), location.len_hint());
+        // a location with no name is rendered by its position, sans column
+        let unnamed = OwnedLocation {
+            name: None,
+            file: "src/main.rs".to_string(),
+            line: 12,
+            column: 1,
+        };
+        assert_eq!(unnamed.to_string(), "src/main.rs:12");
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(outer());
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        pretty_assertions::assert_str_eq!(
+            TaskdumpOptions::new().wait_for_running_tasks(true).dump(),
+            "\
+╼ location_style_name_only::outer::{{closure}}
+  └╼ location_style_name_only::inner::{{closure}}"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&TaskdumpOptions::new().dump_json()).unwrap();
+        let root = &json["tasks"][0]["root"];
+        assert_eq!(root["name"], "location_style_name_only::outer::{{closure}}");
+        assert!(root["file"].is_null());
+        assert!(root["line"].is_null());
+        assert!(root["column"].is_null());
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    inner().await
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    pretty_assertions::assert_str_eq!(
+        async_backtrace::backtrace().unwrap().to_string(),
+        "\
+#0 location_style_name_only::inner::{{closure}}
+#1 location_style_name_only::outer::{{closure}}"
+    );
+    futures::pending!()
+}
@@ -0,0 +1,68 @@
+/// A test that the `FileLine` location style omits the column from every
+/// rendering of a location (but not from its compact rendering), and that
+/// normalization redacts the line that remains.
+mod util;
+use async_backtrace::{set_location_style, LocationStyle, LocationStyleError, TaskdumpOptions};
+use std::{future::Future, task::Context};
+
+#[test]
+fn location_style() {
+    assert_eq!(
+        async_backtrace::location_style(),
+        LocationStyle::FileLineCol
+    );
+    set_location_style(LocationStyle::FileLine).unwrap();
+    assert_eq!(
+        set_location_style(LocationStyle::NameOnly),
+        Err(LocationStyleError::AlreadyInitialized)
+    );
+    assert_eq!(async_backtrace::location_style(), LocationStyle::FileLine);
+
+    util::model(|| {
+        let location = async_backtrace::location!();
+        let rendered = location.to_string();
+        assert_eq!(
+            rendered,
+            format!(
+                "{} at {}:{}",
+                location.name().unwrap(),
+                location.file(),
+                location.line()
+            )
+        );
+        assert_eq!(rendered.len(), location.len_hint());
+        assert!(location
+            .as_compact()
+            .to_string()
+            .ends_with(&format!(":{}", location.line())));
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(outer());
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        pretty_assertions::assert_str_eq!(
+            util::strip(TaskdumpOptions::new().wait_for_running_tasks(true).dump()),
+            "\
+╼ location_style::outer::{{closure}} at backtrace/tests/location-style.rs:LINE
+  └╼ location_style::inner::{{closure}} at backtrace/tests/location-style.rs:LINE"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&TaskdumpOptions::new().dump_json()).unwrap();
+        let root = &json["tasks"][0]["root"];
+        assert_eq!(root["file"], "backtrace/tests/location-style.rs");
+        assert!(root["line"].is_u64());
+        assert!(root["column"].is_null());
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    inner().await
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    futures::pending!()
+}
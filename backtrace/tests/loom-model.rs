@@ -0,0 +1,82 @@
+#![cfg(loom)]
+/// A test, as a downstream crate would write it with `--cfg loom`, that a
+/// combinator of framed futures may be modeled end-to-end with loom,
+/// including the registration of its task and a concurrent taskdump.
+///
+/// Run with the other loom models, as by
+/// `RUSTFLAGS="--cfg loom" cargo test --release --tests`.
+mod util;
+use loom::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+type Job = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// A downstream combinator: completes once both of its futures have,
+/// counting their completions in a counter shared with other threads.
+struct Both {
+    jobs: [Option<Job>; 2],
+    done: Arc<AtomicUsize>,
+}
+
+impl Future for Both {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let Both { jobs, done } = &mut *self;
+        for slot in jobs.iter_mut() {
+            if let Some(job) = slot {
+                if job.as_mut().poll(cx).is_ready() {
+                    *slot = None;
+                    done.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        }
+        if jobs.iter().all(Option::is_none) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[test]
+fn loom_model() {
+    loom::model(|| {
+        let done = Arc::new(AtomicUsize::new(0));
+        let both = Both {
+            jobs: [Some(Box::pin(step())), Some(Box::pin(step()))],
+            done: done.clone(),
+        };
+        let task = async_backtrace::location!().frame(both);
+        let handle = loom::thread::spawn(move || util::run(task));
+
+        // the task is dumped (if it is registered yet) whole, or as polling
+        let dump = async_backtrace::taskdump_tree(false);
+        assert!(dump.is_empty() || dump.contains("loom_model"), "{}", dump);
+
+        handle.join().unwrap();
+        assert_eq!(done.load(Ordering::SeqCst), 2);
+        assert_eq!(async_backtrace::taskdump_tree(false), "");
+    });
+}
+
+#[async_backtrace::framed]
+async fn step() {
+    let mut yielded = false;
+    std::future::poll_fn(|cx| {
+        if std::mem::replace(&mut yielded, true) {
+            Poll::Ready(())
+        } else {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+    .await
+}
@@ -0,0 +1,75 @@
+/// A test that `TaskdumpOptions::dump_markdown` heads the dump with its counts,
+/// and fences the tree of each task beneath a heading.
+mod util;
+use async_backtrace::{Location, TaskdumpOptions};
+use std::{future::Future, task::Context};
+
+static A: (&str, u32, u32) = ("src/app.rs", 1, 1);
+static B: (&str, u32, u32) = ("src/app.rs", 10, 1);
+static C: (&str, u32, u32) = ("src/app.rs", 20, 1);
+
+#[test]
+fn markdown() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let frame = |name, rest| Location::from_components(name, rest);
+        let leaf = |name, rest| frame(name, rest).frame(std::future::pending::<()>());
+        let mut a = Box::pin(frame("a", &A).frame(leaf("b", &B)));
+        let mut c = Box::pin(leaf("c", &C));
+        assert!(a.as_mut().poll(&mut cx).is_pending());
+        assert!(c.as_mut().poll(&mut cx).is_pending());
+
+        let options = || TaskdumpOptions::new().sort_tasks(true);
+        pretty_assertions::assert_str_eq!(
+            options().dump_markdown(),
+            "\
+**async-backtrace: 2 tasks, 3 frames, 0 polling**
+
+**Task 1:** `a at src/app.rs:1:1`
+```text
+╼ a at src/app.rs:1:1
+  └╼ b at src/app.rs:10:1
+```
+
+**Task 2:** `c at src/app.rs:20:1`
+```text
+╼ c at src/app.rs:20:1
+```"
+        );
+        pretty_assertions::assert_str_eq!(
+            options().markdown_details(true).dump_markdown(),
+            "\
+**async-backtrace: 2 tasks, 3 frames, 0 polling**
+
+<details><summary><b>Task 1:</b> <code>a at src/app.rs:1:1</code></summary>
+
+```text
+╼ a at src/app.rs:1:1
+  └╼ b at src/app.rs:10:1
+```
+
+</details>
+
+<details><summary><b>Task 2:</b> <code>c at src/app.rs:20:1</code></summary>
+
+```text
+╼ c at src/app.rs:20:1
+```
+
+</details>"
+        );
+        // the task separator does not apply to Markdown
+        assert_eq!(
+            options().task_separator("\n--\n").dump_markdown(),
+            options().dump_markdown()
+        );
+
+        // a dump of no tasks is only its counts
+        drop((a, c));
+        assert_eq!(
+            async_backtrace::taskdump_markdown(false),
+            "**async-backtrace: 0 tasks, 0 frames, 0 polling**"
+        );
+    });
+}
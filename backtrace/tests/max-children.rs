@@ -0,0 +1,69 @@
+/// A test that `TaskdumpOptions::max_children` limits the children rendered
+/// beneath each frame after consolidation, and counts every remaining copy.
+mod util;
+use async_backtrace::{Location, TaskdumpOptions};
+use std::{future::Future, task::Context};
+
+static ROOT: (&str, u32, u32) = ("src/app.rs", 1, 1);
+static CHILD: (&str, u32, u32) = ("src/app.rs", 10, 1);
+
+#[test]
+fn max_children() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        // children are rendered in the reverse of the order of their polls
+        let children = ["d", "c", "c", "b", "a", "a", "a"].map(|name| {
+            Location::from_components(name, &CHILD).frame(std::future::pending::<()>())
+        });
+        let root = Location::from_components("root", &ROOT);
+        let mut task = Box::pin(root.frame(futures::future::join_all(children)));
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        pretty_assertions::assert_str_eq!(
+            TaskdumpOptions::new().max_children(2).dump(),
+            "\
+╼ root at src/app.rs:1:1
+  ├╼ 3x a at src/app.rs:10:1
+  ├╼ b at src/app.rs:10:1
+  └╼ … and 3 more children"
+        );
+        pretty_assertions::assert_str_eq!(
+            TaskdumpOptions::new().max_children(3).dump(),
+            "\
+╼ root at src/app.rs:1:1
+  ├╼ 3x a at src/app.rs:10:1
+  ├╼ b at src/app.rs:10:1
+  ├╼ 2x c at src/app.rs:10:1
+  └╼ … and 1 more child"
+        );
+        // no note, if every child is rendered
+        pretty_assertions::assert_str_eq!(
+            TaskdumpOptions::new().max_children(4).dump(),
+            "\
+╼ root at src/app.rs:1:1
+  ├╼ 3x a at src/app.rs:10:1
+  ├╼ b at src/app.rs:10:1
+  ├╼ 2x c at src/app.rs:10:1
+  └╼ d at src/app.rs:10:1"
+        );
+        pretty_assertions::assert_str_eq!(
+            TaskdumpOptions::new().max_children(0).dump(),
+            "\
+╼ root at src/app.rs:1:1
+  └╼ … and 7 more children"
+        );
+        // without consolidation, each copy counts toward the limit
+        pretty_assertions::assert_str_eq!(
+            TaskdumpOptions::new()
+                .consolidate(false)
+                .max_children(2)
+                .dump(),
+            "\
+╼ root at src/app.rs:1:1
+  ├╼ a at src/app.rs:10:1
+  ├╼ a at src/app.rs:10:1
+  └╼ … and 5 more children"
+        );
+    });
+}
@@ -0,0 +1,86 @@
+/// A test that `TaskdumpOptions::max_depth` limits the depth of trees, noting
+/// the frames omitted, and that at depth 0 the dump locks no task, even if it
+/// is asked to wait for them.
+mod util;
+use async_backtrace::TaskdumpOptions;
+use std::{future::Future, sync::mpsc, task::Context, task::Poll};
+
+#[test]
+// loom cannot model the polling thread, which blocks outside of its control
+#[cfg_attr(loom, ignore)]
+fn max_depth() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut idle = Box::pin(outer());
+        assert!(idle.as_mut().poll(&mut cx).is_pending());
+
+        let dump = |depth: Option<usize>| {
+            let options = TaskdumpOptions::new().wait_for_running_tasks(true);
+            let options = match depth {
+                Some(depth) => options.max_depth(depth),
+                None => options,
+            };
+            util::strip(options.dump())
+        };
+
+        pretty_assertions::assert_str_eq!(
+            dump(None),
+            "\
+╼ max_depth::outer::{{closure}} at backtrace/tests/max-depth.rs:LINE:COL
+  └╼ 2x max_depth::middle::{{closure}} at backtrace/tests/max-depth.rs:LINE:COL
+     └╼ max_depth::inner::{{closure}} at backtrace/tests/max-depth.rs:LINE:COL"
+        );
+        pretty_assertions::assert_str_eq!(
+            dump(Some(1)),
+            "\
+╼ max_depth::outer::{{closure}} at backtrace/tests/max-depth.rs:LINE:COL
+  └╼ 2x max_depth::middle::{{closure}} at backtrace/tests/max-depth.rs:LINE:COL
+     └┈ … 1 more frame"
+        );
+        pretty_assertions::assert_str_eq!(
+            dump(Some(0)),
+            "╼ max_depth::outer::{{closure}} at backtrace/tests/max-depth.rs:LINE:COL"
+        );
+        assert_eq!(dump(Some(2)), dump(None));
+
+        // a task whose root is held locked by a poll on another thread
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let polling = std::thread::spawn(move || {
+            let blocking = futures::future::poll_fn(move |_| {
+                entered_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+                Poll::Ready(())
+            });
+            futures::executor::block_on(async_backtrace::location!().frame(blocking));
+        });
+        entered_rx.recv().unwrap();
+
+        // were any root locked, this would wait until the poll is released
+        let roots = dump(Some(0));
+        assert_eq!(roots.lines().count(), 2);
+        assert!(roots.lines().any(|line| line
+            == "╼ max_depth::outer::{{closure}} at backtrace/tests/max-depth.rs:LINE:COL"));
+        assert!(roots.lines().any(|line| line
+            == "╼ max_depth::max_depth::{{closure}}::{{closure}} at backtrace/tests/max-depth.rs:LINE:COL"));
+
+        release_tx.send(()).unwrap();
+        polling.join().unwrap();
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    futures::join!(middle(), middle());
+}
+
+#[async_backtrace::framed]
+async fn middle() {
+    inner().await;
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    std::future::pending::<()>().await;
+}
@@ -0,0 +1,49 @@
+/// A test that `memory_estimate` counts tasks and frames, and that the
+/// high-water mark of tasks stays at its peak until it is reset.
+mod util;
+use std::{future::Future, pin::Pin, task::Context};
+
+#[test]
+fn high_water_mark() {
+    util::model(|| {
+        async_backtrace::reset_high_water_mark();
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut tasks: Vec<Pin<Box<dyn Future<Output = ()>>>> =
+            vec![Box::pin(outer()), Box::pin(outer()), Box::pin(outer())];
+        for task in &mut tasks {
+            assert!(task.as_mut().poll(&mut cx).is_pending());
+        }
+
+        let estimate = async_backtrace::memory_estimate();
+        assert_eq!(estimate.tasks, 3);
+        assert_eq!(estimate.tasks_high_water_mark, 3);
+        assert_eq!(estimate.frames, 6);
+        assert_eq!(estimate.skipped, 0);
+        assert!(estimate.bytes >= 6 * std::mem::size_of::<async_backtrace::raw::Frame>());
+
+        tasks.truncate(1);
+        let estimate = async_backtrace::memory_estimate();
+        assert_eq!(estimate.tasks, 1);
+        assert_eq!(estimate.tasks_high_water_mark, 3);
+        assert_eq!(estimate.frames, 2);
+
+        assert_eq!(async_backtrace::reset_high_water_mark(), 3);
+        let estimate = async_backtrace::memory_estimate();
+        assert_eq!(estimate.tasks_high_water_mark, 1);
+
+        drop(tasks);
+        assert_eq!(async_backtrace::memory_estimate().tasks, 0);
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    inner().await;
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    std::future::pending::<()>().await;
+}
@@ -0,0 +1,169 @@
+/// Tests that `Location::metric_key` normalizes the names of locations, as
+/// captured in real contexts, to low-cardinality keys.
+mod util;
+use async_backtrace::{location, Location};
+
+static REST: (&str, u32, u32) = ("src/lib.rs", 1234, 5);
+
+/// Produces the metric key of a location named `name`.
+fn key(name: &'static str) -> String {
+    Location::from_components(name, &REST).metric_key()
+}
+
+trait Handler {
+    fn location(&self) -> Location;
+
+    fn default_location(&self) -> Location {
+        location!()
+    }
+}
+
+struct Plain;
+
+struct Generic<T>(T);
+
+impl Plain {
+    fn inherent() -> Location {
+        location!()
+    }
+}
+
+impl Handler for Plain {
+    fn location(&self) -> Location {
+        location!()
+    }
+}
+
+impl<T> Handler for Generic<T> {
+    fn location(&self) -> Location {
+        let closure = || location!();
+        closure()
+    }
+}
+
+impl Handler for &'static Plain {
+    fn location(&self) -> Location {
+        location!()
+    }
+}
+
+fn generic<T>(_: T) -> Location {
+    location!()
+}
+
+async fn nested() -> Location {
+    async {
+        let closure = || location!();
+        closure()
+    }
+    .await
+}
+
+#[async_backtrace::framed(boxed)]
+async fn boxed<'a>(_: &'a str) -> Option<Location> {
+    async_backtrace::backtrace().map(|backtrace| backtrace[0])
+}
+
+#[test]
+fn captured() {
+    util::model(|| {
+        let cases = [
+            (location!(), "metric_key::captured::{{closure}}"),
+            (Plain::inherent(), "metric_key::Plain::inherent"),
+            (
+                Plain.location(),
+                "<metric_key::Plain as metric_key::Handler>::location",
+            ),
+            (
+                Plain.default_location(),
+                "<metric_key::Plain as metric_key::Handler>::default_location",
+            ),
+            (
+                Generic(0u8).location(),
+                "<metric_key::Generic<u8> as metric_key::Handler>::location::{{closure}}",
+            ),
+            (
+                <&Plain as Handler>::location(&&Plain),
+                "<&metric_key::Plain as metric_key::Handler>::location",
+            ),
+            (
+                generic(Vec::<u8>::new()),
+                "metric_key::generic<alloc::vec::Vec<u8>>",
+            ),
+            (
+                util::run(nested()),
+                "metric_key::nested::{{closure}}::{{closure}}::{{closure}}",
+            ),
+            (util::run(boxed("")).unwrap(), "metric_key::boxed<'_, '_>"),
+        ];
+        let cases: Vec<_> = cases
+            .iter()
+            .map(|(location, name)| (location.name().unwrap(), location.metric_key(), *name))
+            .collect();
+        for (name, key, expected_name) in &cases {
+            assert_eq!(name, expected_name);
+            assert_eq!(*key, self::key(expected_name));
+        }
+        let keys: Vec<&str> = cases.iter().map(|(_, key, _)| key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "metric_key::captured",
+                "metric_key::Plain::inherent",
+                "metric_key::Plain::location",
+                "metric_key::Plain::default_location",
+                "metric_key::Generic::location",
+                "metric_key::Plain::location",
+                "metric_key::generic",
+                "metric_key::nested",
+                "metric_key::boxed",
+            ]
+        );
+    });
+}
+
+#[test]
+fn normalized() {
+    util::model(|| {
+        let cases = [
+            // already normalized
+            ("app::root", "app::root"),
+            ("main", "main"),
+            // closures
+            ("app::serve::{{closure}}", "app::serve"),
+            ("app::serve::{{closure}}::{{closure}}", "app::serve"),
+            // generic arguments, including nested and lifetime arguments
+            ("app::fetch<u8>", "app::fetch"),
+            ("app::fetch<'_>", "app::fetch"),
+            ("app::fetch<alloc::vec::Vec<(u8, [u8; 4])>>", "app::fetch"),
+            ("app::Pool<app::Conn>::get::{{closure}}", "app::Pool::get"),
+            // trait qualifications
+            ("<app::Pg as app::Db>::query::{{closure}}", "app::Pg::query"),
+            (
+                "<app::Pool<app::Pg> as app::Db<'_>>::query",
+                "app::Pool::query",
+            ),
+            (
+                "<app::Wrap<<app::Pg as app::Db>::Conn> as app::Db>::query",
+                "app::Wrap::query",
+            ),
+            ("<&mut app::Pg as app::Db>::query", "app::Pg::query"),
+            ("<dyn app::Db as app::Run>::run", "app::Db::run"),
+            (
+                "<fn() -> app::Pg as app::Run>::run::{{closure}}",
+                "fn() -> app::Pg::run",
+            ),
+            // implementations, as rendered by older toolchains
+            ("app::<impl app::Pg>::query", "app::Pg::query"),
+            (
+                "app::db::<impl app::Db for app::Pg>::query",
+                "app::Pg::query",
+            ),
+            // names qualified by `#[framed(with_type)]`
+            ("app::Pool<u8>::run", "app::Pool::run"),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(key(name), expected, "the key of {}", name);
+        }
+    });
+}
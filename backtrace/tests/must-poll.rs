@@ -0,0 +1,60 @@
+/// A test that futures that must be polled warn if, and only if, they are
+/// dropped without ever being polled.
+mod util;
+use async_backtrace::Warning;
+use std::{future::Future, sync::Mutex, task::Context};
+
+static NEVER_POLLED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[test]
+fn must_poll() {
+    util::model(|| {
+        NEVER_POLLED.lock().unwrap().clear();
+        async_backtrace::set_warning_hook(|warning| match warning {
+            Warning::NeverPolled { location, .. } => {
+                NEVER_POLLED.lock().unwrap().push(location.to_string())
+            }
+            _ => unreachable!(),
+        });
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        // polled to completion
+        util::run(send());
+
+        // polled, but dropped before completion
+        let mut future = Box::pin(pending());
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        drop(future);
+
+        // never polled
+        drop(send());
+        drop(async_backtrace::location!().frame_must_poll(async {}));
+
+        // never polled, but not required to be
+        drop(async_backtrace::location!().frame(async {}));
+
+        let never_polled: Vec<String> = NEVER_POLLED
+            .lock()
+            .unwrap()
+            .iter()
+            .map(util::strip)
+            .collect();
+        pretty_assertions::assert_eq!(
+            never_polled,
+            [
+                "must_poll::send<'_> at backtrace/tests/must-poll.rs:LINE:COL",
+                "must_poll::must_poll::{{closure}} at backtrace/tests/must-poll.rs:LINE:COL",
+            ]
+        );
+    });
+}
+
+#[async_backtrace::framed(must_poll, boxed)]
+async fn send() {}
+
+#[async_backtrace::framed(must_poll, boxed)]
+async fn pending() {
+    std::future::pending::<()>().await;
+}
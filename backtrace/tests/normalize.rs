@@ -0,0 +1,55 @@
+/// A test that `testing::normalize` strips the positions of locations, and
+/// optionally generic parameters and the order of tasks, as
+/// `assert_dump_eq!` compares dumps.
+mod util;
+use async_backtrace::{
+    testing::{normalize, NormalizeOptions},
+    Location, TaskdumpOptions,
+};
+use std::{future::Future, task::Context};
+
+static A: (&str, u32, u32) = ("src/app.rs", 1, 1);
+static B: (&str, u32, u32) = ("src/app.rs", 10, 1);
+
+#[test]
+fn normalize_dumps() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let leaf =
+            |name, rest| Location::from_components(name, rest).frame(std::future::pending::<()>());
+        let mut b = Box::pin(leaf("app::Pool<u8>::get", &B));
+        let mut a = Box::pin(leaf("<app::Conn as Read>::read", &A));
+        assert!(b.as_mut().poll(&mut cx).is_pending());
+        assert!(a.as_mut().poll(&mut cx).is_pending());
+
+        // whatever the order of the registry, the sorted dumps are equal
+        let options = NormalizeOptions::new()
+            .strip_generics(true)
+            .sort_tasks(true);
+        let dump = TaskdumpOptions::new().summary_header(true).dump();
+        pretty_assertions::assert_str_eq!(
+            options.normalize(&dump),
+            "\
+== async-backtrace: 2 tasks, 2 frames, 0 polling ==
+╼ <app::Conn as Read>::read at src/app.rs:LINE:COL
+╼ app::Pool::get at src/app.rs:LINE:COL"
+        );
+        async_backtrace::assert_dump_eq!(
+            TaskdumpOptions::new().sort_tasks(true).dump(),
+            "\
+╼ <app::Conn as Read>::read at src/app.rs:LINE:COL
+╼ app::Pool<u8>::get at src/app.rs:LINE:COL",
+        );
+    });
+
+    // only pairs of numbers are positions
+    assert_eq!(
+        normalize("╼ a at src/a.rs:12:5 [last error 3s ago: port :80]"),
+        "╼ a at src/a.rs:LINE:COL [last error 3s ago: port :80]"
+    );
+    let result = std::panic::catch_unwind(|| {
+        async_backtrace::assert_dump_eq!("╼ a at a.rs:1:1", "╼ b at a.rs:1:1")
+    });
+    assert!(result.is_err());
+}
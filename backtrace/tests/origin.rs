@@ -0,0 +1,52 @@
+/// A test that tasks record how they were instrumented.
+mod util;
+use async_backtrace::{location, Origin};
+use std::future::Future;
+
+#[test]
+fn origin() {
+    util::model(|| {
+        util::run(by_attribute());
+        util::run(by_macro());
+        util::run(by_location());
+        util::run(by_frame());
+    });
+}
+
+/// Produces the origin of the task whose root is named `name`.
+fn origin_of(name: &str) -> Option<Origin> {
+    async_backtrace::tasks()
+        .find(|task| task.location().name() == Some(name))
+        .map(|task| task.origin())
+}
+
+#[async_backtrace::framed]
+async fn by_attribute() {
+    assert_eq!(
+        origin_of("origin::by_attribute::{{closure}}"),
+        Some(Origin::Attribute)
+    );
+}
+
+fn by_macro() -> impl Future<Output = ()> {
+    async_backtrace::frame!(async {
+        assert_eq!(origin_of("origin::by_macro"), Some(Origin::Macro));
+    })
+}
+
+fn by_location() -> impl Future<Output = ()> {
+    location!().frame(async {
+        assert_eq!(origin_of("origin::by_location"), Some(Origin::Manual));
+    })
+}
+
+async fn by_frame() {
+    let frame = async_backtrace::raw::Frame::new(location!());
+    futures::pin_mut!(frame);
+    frame.in_scope(|| {
+        assert_eq!(
+            origin_of("origin::by_frame::{{closure}}"),
+            Some(Origin::Manual)
+        );
+    });
+}
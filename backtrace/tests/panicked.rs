@@ -0,0 +1,55 @@
+/// A test that a framed future that panicked refuses further polls, and that
+/// its frame remains in taskdumps, marked as panicked.
+mod util;
+use std::{
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    task::{Context, Poll},
+};
+
+#[test]
+// loom's mutexes do not support poisoning
+#[cfg_attr(loom, ignore)]
+fn panicked() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut child = Box::pin(async_backtrace::location!().frame(pending()));
+        let mut task = Box::pin(async_backtrace::location!().frame(futures::future::poll_fn(
+            move |cx| {
+                assert!(child.as_mut().poll(cx).is_pending());
+                panic!("boom") as Poll<()>
+            },
+        )));
+
+        let poll = panic::catch_unwind(AssertUnwindSafe(|| task.as_mut().poll(&mut cx)));
+        assert_eq!(*poll.unwrap_err().downcast::<&str>().unwrap(), "boom");
+
+        // the task remains, and its child (still owned by its future) with it
+        let dump = async_backtrace::taskdump_tree(false);
+        pretty_assertions::assert_str_eq!(
+            util::strip(dump),
+            "\
+╼ panicked::panicked::{{closure}} at backtrace/tests/panicked.rs:LINE:COL [panicked]
+  └╼ panicked::panicked::{{closure}} at backtrace/tests/panicked.rs:LINE:COL
+     └╼ panicked::pending::{{closure}} at backtrace/tests/panicked.rs:LINE:COL"
+        );
+
+        // a further poll is refused
+        let poll = panic::catch_unwind(AssertUnwindSafe(|| task.as_mut().poll(&mut cx)));
+        let message = *poll.unwrap_err().downcast::<String>().unwrap();
+        assert_eq!(
+            util::strip(message),
+            "panicked::panicked::{{closure}} at backtrace/tests/panicked.rs:LINE:COL polled after panic"
+        );
+
+        drop(task);
+        assert_eq!(async_backtrace::taskdump_tree(false), "");
+    });
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    std::future::pending::<()>().await;
+}
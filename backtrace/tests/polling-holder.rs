@@ -0,0 +1,88 @@
+/// A test that non-blocking taskdumps tell a task that is being polled on
+/// another thread (`[POLLING on <thread>]`) from one whose lock is otherwise
+/// unavailable (`[BUSY: lock unavailable]`), in text and in JSON.
+mod util;
+use async_backtrace::{tasks_containing, TaskdumpOptions};
+use std::{cell::RefCell, future::Future, sync::mpsc, task::Context};
+
+#[test]
+// loom cannot model the polling thread, which blocks outside of its control
+#[cfg_attr(any(miri, loom), ignore)]
+fn polling_holder() {
+    util::model(|| {
+        // a task that is polled on the thread `poller`
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel();
+        let poller = std::thread::Builder::new()
+            .name("poller".into())
+            .spawn(move || futures::executor::block_on(stuck(entered_tx, release_rx)))
+            .unwrap();
+        entered_rx.recv().unwrap();
+
+        pretty_assertions::assert_str_eq!(
+            util::strip(async_backtrace::taskdump_tree(false)),
+            "\
+╼ polling_holder::stuck::{{closure}} at backtrace/tests/polling-holder.rs:LINE:COL
+  └┈ [POLLING on THREAD]"
+        );
+        assert!(async_backtrace::taskdump_tree(false).contains("[POLLING on poller]"));
+        let json = TaskdumpOptions::new().dump_json();
+        assert!(
+            json.contains(r#""polling":true,"lock":"polling","thread":"poller","#),
+            "{}",
+            json
+        );
+
+        let trees = async_backtrace::snapshot(false);
+        assert_eq!(trees[0].polling_thread(), Some("poller"));
+
+        release_tx.send(()).unwrap();
+        poller.join().unwrap();
+
+        // a task whose lock is held by the dumping thread itself, which is
+        // not polling it
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut idle = Box::pin(idle());
+        assert!(idle.as_mut().poll(&mut cx).is_pending());
+
+        let dumps = RefCell::new(None);
+        tasks_containing(
+            |_| {
+                // (the predicate is called with the lock of the task held)
+                dumps.borrow_mut().get_or_insert_with(|| {
+                    (
+                        async_backtrace::taskdump_tree(false),
+                        TaskdumpOptions::new().dump_json(),
+                    )
+                });
+                false
+            },
+            false,
+        );
+        let (dump, json) = dumps.into_inner().unwrap();
+        pretty_assertions::assert_str_eq!(
+            util::strip(dump),
+            "\
+╼ polling_holder::idle::{{closure}} at backtrace/tests/polling-holder.rs:LINE:COL
+  └┈ [BUSY: lock unavailable]"
+        );
+        assert!(
+            json.contains(r#""polling":true,"lock":"busy","root""#),
+            "{}",
+            json
+        );
+    });
+}
+
+/// Blocks mid-poll until `release` is signalled.
+#[async_backtrace::framed]
+async fn stuck(entered: mpsc::Sender<()>, release: mpsc::Receiver<()>) {
+    entered.send(()).unwrap();
+    release.recv().unwrap();
+}
+
+#[async_backtrace::framed]
+async fn idle() {
+    futures::pending!()
+}
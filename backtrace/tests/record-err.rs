@@ -0,0 +1,58 @@
+/// A test that `#[framed(record_err)]` records errors on the frame of the
+/// caller, and that taskdumps show them.
+mod util;
+use std::io;
+
+#[test]
+fn record_err() {
+    util::model(|| {
+        util::run(retrying());
+        util::run(retrying_verbose());
+    });
+}
+
+#[async_backtrace::framed]
+async fn retrying() {
+    assert!(fetch_block(Some("connection reset")).await.is_err());
+    assert!(fetch_block(Some("connection reset")).await.is_err());
+    fetch_block(None).await.unwrap();
+}
+
+#[async_backtrace::framed(record_err)]
+async fn fetch_block(error: Option<&str>) -> io::Result<()> {
+    if let Some(error) = error {
+        return Err(io::Error::new(io::ErrorKind::ConnectionReset, error));
+    }
+
+    let dump = async_backtrace::taskdump_tree(true);
+    // normalize the age of the error
+    let (head, tail) = dump.split_once("[last error ").unwrap();
+    let (_, tail) = tail.split_once("s ago").unwrap();
+    let dump = util::strip(format!("{}[last error Ns ago{}", head, tail));
+    pretty_assertions::assert_str_eq!(
+        dump,
+        "\
+╼ record_err::retrying::{{closure}} at backtrace/tests/record-err.rs:LINE:COL
+  └╼ record_err::fetch_block::{{closure}} at backtrace/tests/record-err.rs:LINE:COL [last error Ns ago: connection reset]"
+    );
+    Ok(())
+}
+
+#[async_backtrace::framed]
+async fn retrying_verbose() {
+    assert!(verbose(true).await.is_err());
+    verbose(false).await.unwrap();
+}
+
+#[async_backtrace::framed(record_err)]
+async fn verbose(fail: bool) -> Result<(), String> {
+    if fail {
+        return Err("x".repeat(200));
+    }
+
+    // errors are truncated to 120 characters
+    let dump = async_backtrace::taskdump_tree(true);
+    let expected = format!("ago: {}…]", "x".repeat(120));
+    assert!(dump.ends_with(&expected), "{}", dump);
+    Ok(())
+}
@@ -1,7 +1,15 @@
-/// A test that a blocking threaddump does not deadlock a program when requested
-/// from within a `framed` task.
+/// Tests that a blocking threaddump does not deadlock a program when requested
+/// from within a `framed` task, and that a task may be re-entrantly polled, as
+/// by a waker that synchronously polls its task, without deadlocking.
 mod util;
 use async_backtrace::framed;
+use std::{
+    future::Future,
+    marker::PhantomPinned,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Wake, Waker},
+};
 
 #[test]
 fn reentrant() {
@@ -29,3 +37,103 @@ async fn inner() {
   └╼ reentrant::inner::{{closure}} at backtrace/tests/reentrant.rs:LINE:COL"
     );
 }
+
+/// A future that, upon its first poll, wakes its task, and whose re-entrant
+/// poll records the backtrace.
+///
+/// Its state is kept outside of it, since the re-entrant poll aliases it; and
+/// it is `!Unpin`, so that the `&mut` of each poll does not assert that it is
+/// unique.
+struct Reentrant(Arc<Mutex<State>>, PhantomPinned);
+
+#[derive(Default)]
+struct State {
+    polls: usize,
+    backtrace: Option<Vec<String>>,
+}
+
+impl Future for Reentrant {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let polls = {
+            let mut state = self.0.lock().unwrap();
+            state.polls += 1;
+            state.polls
+        };
+        if polls == 1 {
+            // the waker re-entrantly polls this task, before this poll ends
+            cx.waker().wake_by_ref();
+            assert_eq!(self.0.lock().unwrap().polls, 2);
+            assert_eq!(names(), ["reentrant::task"]);
+            Poll::Ready(())
+        } else {
+            self.0.lock().unwrap().backtrace = Some(names());
+            Poll::Pending
+        }
+    }
+}
+
+/// A task, which is polled only through [`poll`].
+type Task = *mut (dyn Future<Output = ()> + Send);
+
+/// A waker that synchronously polls its task.
+struct PollingWaker(Task);
+
+// SAFETY: The waker is only used on the thread that polls the task.
+unsafe impl Send for PollingWaker {}
+unsafe impl Sync for PollingWaker {}
+
+impl Wake for PollingWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let waker = Waker::from(self.clone());
+        let mut cx = Context::from_waker(&waker);
+        assert!(poll(self.0, &mut cx).is_pending());
+    }
+}
+
+/// Polls `task`.
+///
+/// Each poll (including a re-entrant one) borrows the task from the raw
+/// pointer, rather than from the borrow of the poll it re-enters.
+fn poll(task: Task, cx: &mut Context<'_>) -> Poll<()> {
+    // SAFETY: `task` is valid until it is freed, after its last poll, and is
+    // never moved; and (being `!Unpin`) it may be mutably borrowed by a
+    // re-entrant poll, which is what is under test.
+    unsafe { Pin::new_unchecked(&mut *task) }.poll(cx)
+}
+
+#[test]
+fn reentrant_poll() {
+    util::model(|| {
+        let state = Arc::new(Mutex::new(State::default()));
+        let future = Reentrant(state.clone(), PhantomPinned);
+        let location = async_backtrace::location_named!("reentrant::task");
+        let task: Task = Box::into_raw(Box::new(location.frame(future)));
+
+        let waker = Waker::from(Arc::new(PollingWaker(task)));
+        let mut cx = Context::from_waker(&waker);
+        assert!(poll(task, &mut cx).is_ready());
+        // SAFETY: `task` was produced by `Box::into_raw`, and is no longer
+        // polled.
+        drop(unsafe { Box::from_raw(task) });
+
+        assert_eq!(
+            state.lock().unwrap().backtrace.take().unwrap(),
+            ["reentrant::task"]
+        );
+        assert!(async_backtrace::backtrace().is_none());
+    });
+}
+
+fn names() -> Vec<String> {
+    let backtrace = async_backtrace::backtrace().unwrap();
+    backtrace
+        .iter()
+        .map(|location| location.name().unwrap().to_string())
+        .collect()
+}
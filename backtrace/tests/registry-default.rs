@@ -0,0 +1,23 @@
+/// A test that the registry of tasks grows as needed when it has not been
+/// pre-sized.
+use async_backtrace::location;
+
+const TASKS: usize = 100_000;
+
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn registry_default() {
+    let initial_capacity = async_backtrace::ඞ::registry_capacity();
+    assert!(initial_capacity < TASKS);
+
+    let frames: Vec<_> = (0..TASKS)
+        .map(|_| {
+            let mut frame = Box::pin(async_backtrace::raw::Frame::new(location!()));
+            frame.as_mut().in_scope(|| {});
+            frame
+        })
+        .collect();
+    assert_eq!(async_backtrace::tasks().count(), TASKS);
+    assert!(async_backtrace::ඞ::registry_capacity() >= TASKS);
+    drop(frames);
+}
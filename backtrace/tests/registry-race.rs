@@ -0,0 +1,65 @@
+/// Models of the registration and deregistration of tasks racing iteration
+/// over the registry.
+mod util;
+use std::{future::Future, task::Context};
+
+#[test]
+fn iterate_while_registering() {
+    util::model(|| {
+        let handle = util::thread::spawn(|| util::run(registering()));
+        // the task is either not yet registered, registered, or deregistered
+        assert!(count("registering") <= 1);
+        handle.join().unwrap();
+        assert_eq!(count("registering"), 0);
+    });
+}
+
+#[test]
+fn iterate_while_deregistering() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(deregistering());
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(count("deregistering"), 1);
+
+        let handle = util::thread::spawn(move || drop(task));
+        for task in async_backtrace::tasks() {
+            // tasks are not freed while they are referenced by an iteration
+            let _ = task.location();
+            let _ = task.pretty_tree(false);
+        }
+        handle.join().unwrap();
+        assert_eq!(count("deregistering"), 0);
+    });
+}
+
+#[test]
+fn register_while_deregistering() {
+    util::model(|| {
+        let handle_a = util::thread::spawn(|| util::run(concurrent()));
+        let handle_b = util::thread::spawn(|| util::run(concurrent()));
+        handle_a.join().unwrap();
+        handle_b.join().unwrap();
+        assert_eq!(count("concurrent"), 0);
+    });
+}
+
+#[async_backtrace::framed]
+async fn registering() {}
+
+#[async_backtrace::framed]
+async fn deregistering() {
+    futures::future::pending::<()>().await;
+}
+
+#[async_backtrace::framed]
+async fn concurrent() {}
+
+/// The number of registered tasks of the given function.
+fn count(function: &str) -> usize {
+    let name = format!("registry_race::{}::{{{{closure}}}}", function);
+    async_backtrace::tasks()
+        .filter(|task| task.location().name() == Some(&name))
+        .count()
+}
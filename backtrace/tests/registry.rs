@@ -0,0 +1,52 @@
+/// A test that the registry of tasks can be pre-sized, so that registering
+/// many tasks does not resize it.
+use async_backtrace::{configure_registry, location, RegistryConfig, RegistryConfigError};
+
+const TASKS: usize = 100_000;
+
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn registry() {
+    assert_eq!(
+        configure_registry(RegistryConfig {
+            initial_capacity: TASKS,
+            shard_amount: 3,
+            ..RegistryConfig::default()
+        }),
+        Err(RegistryConfigError::InvalidShardAmount(3))
+    );
+
+    configure_registry(RegistryConfig {
+        initial_capacity: TASKS,
+        shard_amount: 16,
+        ..RegistryConfig::default()
+    })
+    .unwrap();
+
+    let frames = register(1);
+    let capacity = async_backtrace::ඞ::registry_capacity();
+    assert!(capacity >= TASKS, "capacity {} < {}", capacity, TASKS);
+    drop(frames);
+
+    // registering as many tasks as were configured does not resize the registry
+    let frames = register(TASKS);
+    assert_eq!(async_backtrace::tasks().count(), TASKS);
+    assert_eq!(async_backtrace::ඞ::registry_capacity(), capacity);
+    drop(frames);
+
+    assert_eq!(
+        configure_registry(RegistryConfig::default()),
+        Err(RegistryConfigError::AlreadyInitialized)
+    );
+}
+
+/// Registers `n` raw root frames.
+fn register(n: usize) -> Vec<std::pin::Pin<Box<async_backtrace::raw::Frame>>> {
+    (0..n)
+        .map(|_| {
+            let mut frame = Box::pin(async_backtrace::raw::Frame::new(location!()));
+            frame.as_mut().in_scope(|| {});
+            frame
+        })
+        .collect()
+}
@@ -0,0 +1,43 @@
+/// A test that `root_location` and `root_task_id` name the root frame of the
+/// task of the active frame, past barriers, and are `None` outside of frames.
+mod util;
+use async_backtrace::Location;
+
+#[test]
+fn root_location() {
+    util::model(|| {
+        assert_eq!(async_backtrace::root_location(), None);
+        assert_eq!(async_backtrace::root_task_id(), None);
+        util::run(spawned());
+        assert_eq!(async_backtrace::root_location(), None);
+    });
+}
+
+#[async_backtrace::framed]
+async fn spawned() {
+    let root = async_backtrace::root_location().unwrap();
+    assert_eq!(root.name(), Some("root_location::spawned::{{closure}}"));
+    let id = async_backtrace::root_task_id().unwrap();
+    assert_eq!(async_backtrace::tasks().next().unwrap().id(), id);
+
+    middle().await;
+}
+
+#[async_backtrace::framed]
+async fn middle() {
+    Location::from_components("barrier", &("src/lib.rs", 1, 1))
+        .frame_barrier(leaf())
+        .await;
+}
+
+#[async_backtrace::framed]
+async fn leaf() {
+    // the backtrace stops at the barrier, but the root is beyond it
+    assert_eq!(async_backtrace::backtrace().unwrap().len(), 2);
+    let root = async_backtrace::root_location().unwrap();
+    assert_eq!(root.name(), Some("root_location::spawned::{{closure}}"));
+    assert!(root.file().ends_with("tests/root-location.rs"));
+
+    let id = async_backtrace::root_task_id().unwrap();
+    assert_eq!(async_backtrace::tasks().next().unwrap().id(), id);
+}
@@ -0,0 +1,52 @@
+/// A test that `#[framed(root_only)]` only frames futures that are the roots
+/// of tasks.
+mod util;
+use async_backtrace::testing::FrameProbe;
+
+#[test]
+fn as_root() {
+    util::model(|| {
+        let mut probe = FrameProbe::new(flush("root_only::flush::{{closure}}"));
+
+        assert!(probe.poll().is_pending());
+        let roots = probe.registered_roots();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(
+            roots[0].root().location().name(),
+            Some("root_only::flush::{{closure}}")
+        );
+        assert!(probe.children_of("flush").is_empty());
+
+        assert!(probe.poll().is_ready());
+        assert!(probe.dropped_cleanly());
+    });
+}
+
+#[test]
+fn within_frame() {
+    util::model(|| {
+        util::run(flush_all());
+    });
+}
+
+#[async_backtrace::framed]
+async fn flush_all() {
+    // `flush` is polled within the frame of `flush_all`; the decision to not
+    // frame it is made upon its first poll, and kept thereafter
+    flush("root_only::flush_all::{{closure}}").await;
+}
+
+#[async_backtrace::framed(root_only)]
+async fn flush(innermost: &'static str) {
+    assert_eq!(names(), [innermost]);
+    futures::pending!();
+    assert_eq!(names(), [innermost]);
+}
+
+fn names() -> Vec<String> {
+    let backtrace = async_backtrace::backtrace().unwrap();
+    backtrace
+        .iter()
+        .map(|location| location.name().unwrap().to_string())
+        .collect()
+}
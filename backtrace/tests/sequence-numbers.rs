@@ -0,0 +1,90 @@
+/// A test that sequence numbers follow the order in which frames are
+/// initialized and polled.
+mod util;
+use async_backtrace::TaskdumpOptions;
+use std::{future::Future, task::Context};
+
+#[test]
+fn sequence_numbers() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(outer());
+
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+        let first = frames();
+        // the frames are listed as they are rendered: children in reverse
+        let names: Vec<&str> = first.iter().map(|frame| frame.0.as_str()).collect();
+        assert_eq!(names, ["outer", "third", "second", "first"]);
+        // each frame is initialized, and first polled, in turn
+        let [outer, third, second, first_child] = &first[..] else {
+            unreachable!()
+        };
+        assert!(outer.1 < first_child.1);
+        assert!(first_child.1 < second.1);
+        assert!(second.1 < third.1);
+        // the parent is entered before its children are polled
+        assert!(outer.2 < first_child.2);
+        assert!(first_child.2 < second.2 && second.2 < third.2);
+
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+        let second_poll = frames();
+        for (before, after) in first.iter().zip(&second_poll) {
+            // initializations are not renumbered, but polls are
+            assert_eq!(before.1, after.1);
+            assert!(before.2 < after.2);
+            assert!(after.2 > third.2);
+        }
+    });
+}
+
+/// Produces the name, `init_seq` and `last_poll_seq` of each frame of the
+/// dump, in order.
+fn frames() -> Vec<(String, u64, u64)> {
+    let dump = TaskdumpOptions::new()
+        .wait_for_running_tasks(true)
+        .sequence_numbers(true)
+        .dump();
+    dump.lines()
+        .map(|line| {
+            let name = line
+                .split("sequence_numbers::")
+                .nth(1)
+                .and_then(|rest| rest.split("::").next())
+                .unwrap()
+                .to_string();
+            (
+                name,
+                number(line, "init_seq="),
+                number(line, "last_poll_seq="),
+            )
+        })
+        .collect()
+}
+
+/// Produces the number following `key` in `line`.
+fn number(line: &str, key: &str) -> u64 {
+    let rest = &line[line.find(key).unwrap() + key.len()..];
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().unwrap()
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    futures::join!(first(), second(), third());
+}
+
+#[async_backtrace::framed]
+async fn first() {
+    std::future::pending::<()>().await
+}
+
+#[async_backtrace::framed]
+async fn second() {
+    std::future::pending::<()>().await
+}
+
+#[async_backtrace::framed]
+async fn third() {
+    std::future::pending::<()>().await
+}
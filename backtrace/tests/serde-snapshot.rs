@@ -0,0 +1,73 @@
+/// A test that the trees of `snapshot` serialize as the tasks of
+/// `dump_json`, byte for byte, and round-trip through `OwnedTaskTree`.
+mod util;
+use async_backtrace::{set_default_dump_options, OwnedTaskTree, TaskdumpOptions};
+use std::{future::Future, task::Context};
+
+#[test]
+fn serde_snapshot() {
+    // (so that the optional fields of frames are serialized)
+    let options = || {
+        TaskdumpOptions::new()
+            .sequence_numbers(true)
+            .future_sizes(0)
+    };
+    set_default_dump_options(options()).unwrap();
+
+    util::model(move || {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut server = Box::pin(server());
+        let mut worker = Box::pin(worker());
+        assert!(server.as_mut().poll(&mut cx).is_pending());
+        assert!(worker.as_mut().poll(&mut cx).is_pending());
+
+        let mut trees = async_backtrace::snapshot(true);
+        assert_eq!(trees.len(), 2);
+        trees.sort_by(|a, b| a.cmp_by_root(b));
+        let tasks: Vec<String> = trees
+            .iter()
+            .map(|tree| serde_json::to_string(tree).unwrap())
+            .collect();
+        assert_eq!(
+            format!("{{\"tasks\":[{}],\"truncated\":null}}", tasks.join(",")),
+            options().sort_tasks(true).dump_json()
+        );
+
+        for (tree, json) in trees.iter().zip(&tasks) {
+            let owned: OwnedTaskTree = serde_json::from_str(json).unwrap();
+            assert_eq!(owned.id, tree.id());
+            assert!(owned.root.init_seq.is_some());
+            assert_eq!(&serde_json::to_string(&owned).unwrap(), json);
+        }
+
+        let server: OwnedTaskTree = serde_json::from_str(&tasks[0]).unwrap();
+        assert_eq!(
+            server.root.name.as_deref(),
+            Some("serde_snapshot::server::{{closure}}")
+        );
+        assert_eq!(server.root.leaf_state, None);
+        // (the subframes differ in their sequence numbers, and so are not
+        // consolidated)
+        assert_eq!(server.root.children.len(), 2);
+        for child in &server.root.children {
+            assert_eq!(child.count, 1);
+            assert_eq!(child.leaf_state.as_deref(), Some("unknown"));
+        }
+    });
+}
+
+#[async_backtrace::framed]
+async fn server() {
+    futures::join!(pending(), pending());
+}
+
+#[async_backtrace::framed]
+async fn worker() {
+    pending().await
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    std::future::pending::<()>().await
+}
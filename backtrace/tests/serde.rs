@@ -0,0 +1,51 @@
+/// A test that backtraces serialize, and deserialize as `OwnedLocation`s.
+mod util;
+use async_backtrace::{Location, OwnedLocation};
+
+#[test]
+fn serde() {
+    util::model(|| util::run(outer()));
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    inner().await;
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    let backtrace: Box<[Location]> = async_backtrace::backtrace().unwrap().into();
+    let json = serde_json::to_string(&backtrace).unwrap();
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let inner = &value[0];
+    assert_eq!(inner["name"], "serde::inner::{{closure}}");
+    assert_eq!(inner["file"], "backtrace/tests/serde.rs");
+    assert_eq!(inner["line"], backtrace[0].line());
+    assert_eq!(inner["column"], backtrace[0].column());
+
+    let owned: Vec<OwnedLocation> = serde_json::from_str(&json).unwrap();
+    let expected: Vec<OwnedLocation> = backtrace.iter().copied().map(Into::into).collect();
+    assert_eq!(owned, expected);
+    let names: Vec<Option<&str>> = owned.iter().map(|owned| owned.name.as_deref()).collect();
+    assert_eq!(
+        names,
+        [
+            Some("serde::inner::{{closure}}"),
+            Some("serde::outer::{{closure}}")
+        ]
+    );
+    for (owned, location) in owned.iter().zip(backtrace.iter()) {
+        assert_eq!(owned.to_string(), location.to_string());
+    }
+
+    // locations without names round-trip too
+    let nameless: OwnedLocation =
+        serde_json::from_str(r#"{"name":null,"file":"src/lib.rs","line":1,"column":2}"#).unwrap();
+    assert_eq!(nameless.name, None);
+    assert_eq!(nameless.to_string(), "src/lib.rs:1:2");
+    assert_eq!(
+        serde_json::to_string(&nameless).unwrap(),
+        r#"{"name":null,"file":"src/lib.rs","line":1,"column":2}"#
+    );
+}
@@ -0,0 +1,89 @@
+/// A test that `shutdown_watch::run_with` reports the matching tasks every
+/// interval, with what changed since the last report, until none remain.
+mod util;
+use async_backtrace::shutdown_watch;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+#[tokio::test(start_paused = true)]
+#[cfg_attr(any(miri, loom), ignore)]
+async fn shutdown_watch() {
+    let start = Instant::now();
+    tokio::spawn(drain());
+    tokio::spawn(flush());
+    tokio::spawn(ignored());
+    tokio::task::yield_now().await;
+
+    let mut reports = Vec::new();
+    shutdown_watch::run_with(
+        Duration::from_secs(5),
+        |tree| tree.root().location().name() != Some("shutdown_watch::ignored::{{closure}}"),
+        |report| reports.push((start.elapsed().as_secs(), strip(report.to_string()))),
+    )
+    .await;
+
+    // the watch ends at the first report without any matching task
+    assert_eq!(start.elapsed(), Duration::from_secs(15));
+    let reports: Vec<_> = reports
+        .iter()
+        .map(|(at, report)| format!("[{}s]\n{}", at, report))
+        .collect();
+    pretty_assertions::assert_str_eq!(
+        reports.join("\n"),
+        "\
+[0s]
+async-backtrace: 2 tasks blocking shutdown
+╼ shutdown_watch::drain::{{closure}} at backtrace/tests/shutdown-watch.rs:LINE:COL
+╼ shutdown_watch::flush::{{closure}} at backtrace/tests/shutdown-watch.rs:LINE:COL
+  └╼ shutdown_watch::write::{{closure}} at backtrace/tests/shutdown-watch.rs:LINE:COL
+[5s]
+async-backtrace: 2 tasks blocking shutdown
+╼ shutdown_watch::drain::{{closure}} at backtrace/tests/shutdown-watch.rs:LINE:COL
+╼ shutdown_watch::flush::{{closure}} at backtrace/tests/shutdown-watch.rs:LINE:COL
+  └╼ shutdown_watch::write::{{closure}} at backtrace/tests/shutdown-watch.rs:LINE:COL
+since the last report:
+no changes
+[10s]
+async-backtrace: 1 task blocking shutdown
+╼ shutdown_watch::flush::{{closure}} at backtrace/tests/shutdown-watch.rs:LINE:COL
+  └╼ shutdown_watch::write::{{closure}} at backtrace/tests/shutdown-watch.rs:LINE:COL
+since the last report:
+- 1 task rooted at shutdown_watch::drain::{{closure}}@backtrace/tests/shutdown-watch.rs:LINE exited"
+    );
+}
+
+/// Like `util::strip`, but also replaces the line of each compact location in
+/// what changed since the last report (e.g., `@src/main.rs:10 exited`).
+fn strip(report: String) -> String {
+    util::strip(report)
+        .lines()
+        .map(|line| match line.split_once(".rs:") {
+            Some((head, tail)) if line.ends_with(" exited") => {
+                let (_, tail) = tail.split_once(' ').unwrap();
+                format!("{}.rs:LINE {}", head, tail)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[async_backtrace::framed]
+async fn drain() {
+    sleep(Duration::from_secs(7)).await;
+}
+
+#[async_backtrace::framed]
+async fn flush() {
+    write().await;
+}
+
+#[async_backtrace::framed]
+async fn write() {
+    sleep(Duration::from_secs(12)).await;
+}
+
+#[async_backtrace::framed]
+async fn ignored() {
+    std::future::pending::<()>().await
+}
@@ -0,0 +1,67 @@
+/// A test that snapshot() captures owned trees of all tasks, which may be sent
+/// to other threads, and in which running tasks are marked as polling.
+mod util;
+use async_backtrace::{framed, TaskTree};
+use std::{future::Future, task::Context};
+
+#[test]
+fn snapshot() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut idle = Box::pin(idle());
+        assert!(idle.as_mut().poll(&mut cx).is_pending());
+        util::run(outer());
+    });
+}
+
+/// Produces the name of the frame at `path` (of child indices) in `tree`.
+fn name(tree: &TaskTree, path: &[usize]) -> String {
+    let frame = path
+        .iter()
+        .fold(tree.root(), |frame, &index| &frame.children()[index]);
+    frame.location().name().unwrap().to_string()
+}
+
+#[framed]
+async fn outer() {
+    // the trees outlive the registry's locks, and may be sent elsewhere; the
+    // tree of this task, which is being polled, is not waited for
+    let mut trees = util::thread::spawn(|| async_backtrace::snapshot(false))
+        .join()
+        .unwrap();
+    trees.sort_by_key(TaskTree::id);
+    assert_eq!(trees.len(), 2);
+
+    let (idle, outer) = (&trees[0], &trees[1]);
+    assert!(outer.is_polling());
+    assert_eq!(name(outer, &[]), "snapshot::outer::{{closure}}");
+    assert!(outer.root().children().is_empty());
+
+    assert!(!idle.is_polling());
+    assert_eq!(name(idle, &[]), "snapshot::idle::{{closure}}");
+    assert_eq!(idle.root().children().len(), 2);
+    assert_eq!(name(idle, &[0]), "snapshot::pending::{{closure}}");
+    assert_eq!(name(idle, &[1]), "snapshot::pending::{{closure}}");
+    assert!(idle.root().children()[0].children().is_empty());
+    assert!(!idle.root().has_panicked());
+    assert_eq!(idle.root().last_error(), None);
+
+    // the trees render as in taskdumps
+    pretty_assertions::assert_str_eq!(
+        util::strip(idle.to_string()),
+        "\
+╼ snapshot::idle::{{closure}} at backtrace/tests/snapshot.rs:LINE:COL
+  └╼ 2x snapshot::pending::{{closure}} at backtrace/tests/snapshot.rs:LINE:COL"
+    );
+}
+
+#[framed]
+async fn idle() {
+    futures::join!(pending(), pending());
+}
+
+#[framed]
+async fn pending() {
+    std::future::pending::<()>().await;
+}
@@ -0,0 +1,59 @@
+/// A test that `TaskdumpOptions::sort_tasks` dumps tasks by the locations of
+/// their roots, and then by id, as `TaskTree::cmp_by_root` orders snapshots.
+mod util;
+use async_backtrace::{Location, TaskTree, TaskdumpOptions};
+use std::{future::Future, pin::Pin, task::Context};
+
+static B_10: (&str, u32, u32) = ("src/b.rs", 10, 1);
+static A_20: (&str, u32, u32) = ("src/a.rs", 20, 1);
+static A_3: (&str, u32, u32) = ("src/a.rs", 3, 1);
+static A_3_9: (&str, u32, u32) = ("src/a.rs", 3, 9);
+
+#[test]
+fn sort_tasks() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let spawn = |name, rest, tag| -> Pin<Box<dyn Future<Output = ()>>> {
+            let location = Location::from_components(name, rest);
+            Box::pin(location.frame(async move {
+                let tag = Location::from_components(tag, &B_10);
+                tag.frame(std::future::pending::<()>()).await
+            }))
+        };
+        // tasks spawned at the same site are told apart by their children
+        let mut tasks = vec![
+            spawn("app::b", &B_10, "first"),
+            spawn("app::a20", &A_20, "only"),
+            spawn("app::b", &B_10, "second"),
+            spawn("app::a3_9", &A_3_9, "only"),
+            spawn("app::a3", &A_3, "only"),
+        ];
+        for task in &mut tasks {
+            assert!(task.as_mut().poll(&mut cx).is_pending());
+        }
+
+        let expected = "\
+╼ app::a3 at src/a.rs:3:1
+  └╼ only at src/b.rs:10:1
+╼ app::a3_9 at src/a.rs:3:9
+  └╼ only at src/b.rs:10:1
+╼ app::a20 at src/a.rs:20:1
+  └╼ only at src/b.rs:10:1
+╼ app::b at src/b.rs:10:1
+  └╼ first at src/b.rs:10:1
+╼ app::b at src/b.rs:10:1
+  └╼ second at src/b.rs:10:1";
+        for _ in 0..3 {
+            let dump = TaskdumpOptions::new().sort_tasks(true).dump();
+            pretty_assertions::assert_str_eq!(dump, expected);
+        }
+
+        // snapshots are sorted likewise
+        let mut trees = async_backtrace::snapshot(true);
+        trees.sort_by(TaskTree::cmp_by_root);
+        let sorted: Vec<String> = trees.iter().map(TaskTree::to_string).collect();
+        pretty_assertions::assert_str_eq!(sorted.join("\n"), expected);
+        assert!(trees[3].id() < trees[4].id());
+    });
+}
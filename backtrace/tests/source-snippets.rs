@@ -0,0 +1,62 @@
+/// A test that `TaskdumpOptions::source_snippets` renders the source line of
+/// each frame beneath it, from remapped files, skipping unreadable files.
+mod util;
+use async_backtrace::{Location, SourceSnippets, TaskdumpOptions};
+use std::{future::Future, task::Context};
+
+static SERVE: (&str, u32, u32) = ("src/app.rs", 3, 5);
+static HANDLE: (&str, u32, u32) = ("src/app.rs", 9, 9);
+static LONG: (&str, u32, u32) = ("src/app.rs", 12, 1);
+static BLANK: (&str, u32, u32) = ("src/app.rs", 7, 1);
+static MISSING: (&str, u32, u32) = ("src/missing.rs", 1, 1);
+
+#[test]
+fn source_snippets() {
+    util::model(|| {
+        let location = |name, rest| Location::from_components(name, rest);
+        let pending = || std::future::pending::<()>();
+        let task = location("app::serve", &SERVE).frame(async move {
+            futures::join!(
+                location("app::handle", &HANDLE).frame(pending()),
+                location("app::long", &LONG).frame(pending()),
+                location("app::blank", &BLANK).frame(pending()),
+                location("app::missing", &MISSING).frame(pending()),
+                framed(),
+            )
+        });
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(task);
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        let snippets = SourceSnippets::new()
+            .remap_path_prefix(
+                "src",
+                concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"),
+            )
+            .remap_path_prefix("backtrace", env!("CARGO_MANIFEST_DIR"));
+        let dump = TaskdumpOptions::new().source_snippets(snippets).dump();
+        pretty_assertions::assert_str_eq!(
+            util::strip(dump),
+            "\
+╼ app::serve at src/app.rs:LINE:COL
+  │ source: tokio::select! {
+  ├╼ source_snippets::framed::{{closure}} at backtrace/tests/source-snippets.rs:LINE:COL
+  │  │ source: #[async_backtrace::framed]
+  ├╼ app::missing at src/missing.rs:LINE:COL
+  ├╼ app::blank at src/app.rs:LINE:COL
+  ├╼ app::long at src/app.rs:LINE:COL
+  │  │ source: fn long() { let a_line_that_goes_on_and_on = \"for longer than one hundred characters, so that it is …
+  └╼ app::handle at src/app.rs:LINE:COL
+     │ source: let request = read_request(&mut stream).await?;"
+        );
+
+        // without snippets, the dump is as usual
+        assert!(!TaskdumpOptions::new().dump().contains("source:"));
+    });
+}
+
+#[async_backtrace::framed]
+async fn framed() {
+    std::future::pending::<()>().await;
+}
@@ -0,0 +1,80 @@
+/// A test that `taskdump_stacks(true)` completes promptly, while the very task
+/// it waits for registers tasks from within its poll.
+mod util;
+use async_backtrace::{location, raw::Frame};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+
+const IDLE: usize = 1024;
+const BATCH: usize = 256;
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn stacks_churn() {
+    // (so that a failed assertion does not leave the busy task running)
+    let _stop = Stop;
+    // (so that every shard of the registry holds a task)
+    let idle: Vec<_> = (0..IDLE)
+        .map(|_| {
+            let mut frame = Box::pin(Frame::new(location!()));
+            frame.as_mut().in_scope(|| {});
+            frame
+        })
+        .collect();
+    let busy = std::thread::spawn(|| util::run(busy()));
+    while async_backtrace::tasks().count() <= IDLE {
+        std::thread::yield_now();
+    }
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(async_backtrace::taskdump_stacks(true));
+    });
+    let dump = match rx.recv_timeout(Duration::from_secs(10)) {
+        Ok(dump) => dump,
+        Err(_) => {
+            // (dropping the idle tasks would block on the stuck dump)
+            std::mem::forget(idle);
+            panic!("the dump did not complete");
+        }
+    };
+
+    // the busy task was waited for, not rendered as polling
+    assert!(dump.contains("#0 stacks_churn::busy::{{closure}}"));
+    assert!(!dump.contains("[POLLING]"));
+
+    STOP.store(true, Ordering::Release);
+    busy.join().unwrap();
+    drop(idle);
+}
+
+struct Stop;
+
+impl Drop for Stop {
+    fn drop(&mut self) {
+        STOP.store(true, Ordering::Release);
+    }
+}
+
+/// Registers tasks, on another thread, within each of its polls, until
+/// stopped.
+#[async_backtrace::framed]
+async fn busy() {
+    while !STOP.load(Ordering::Acquire) {
+        // (the dump, if any, is by now waiting for this poll)
+        std::thread::sleep(Duration::from_millis(1));
+        // (so that one is likely to be in the shard the dump holds, if any)
+        std::thread::spawn(|| {
+            for _ in 0..BATCH {
+                let mut frame = Box::pin(Frame::new(location!()));
+                frame.as_mut().in_scope(|| {});
+            }
+        })
+        .join()
+        .unwrap();
+        tokio::task::yield_now().await;
+    }
+}
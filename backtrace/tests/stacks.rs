@@ -0,0 +1,52 @@
+/// A test that `Task::pretty_stacks` renders a numbered stack per leaf, from
+/// the leaf to the root, in the order of the tree.
+mod util;
+
+#[test]
+fn stacks() {
+    util::model(|| util::run(selecting()));
+}
+
+#[async_backtrace::framed]
+async fn selecting() {
+    tokio::select! {
+        biased;
+        _ = yielding_outer() => {}
+        _ = ready() => {}
+    };
+}
+
+#[async_backtrace::framed]
+async fn yielding_outer() {
+    yielding_inner().await;
+}
+
+#[async_backtrace::framed]
+async fn yielding_inner() {
+    tokio::task::yield_now().await;
+}
+
+#[async_backtrace::framed]
+async fn ready() {
+    let task = async_backtrace::tasks()
+        .find(|task| task.location().name() == Some("stacks::selecting::{{closure}}"))
+        .unwrap();
+    pretty_assertions::assert_str_eq!(
+        util::strip(task.pretty_stacks(true)),
+        "\
+#0 stacks::ready::{{closure}} at backtrace/tests/stacks.rs:LINE:COL
+#1 stacks::selecting::{{closure}} at backtrace/tests/stacks.rs:LINE:COL
+
+#0 stacks::yielding_inner::{{closure}} at backtrace/tests/stacks.rs:LINE:COL
+#1 stacks::yielding_outer::{{closure}} at backtrace/tests/stacks.rs:LINE:COL
+#2 stacks::selecting::{{closure}} at backtrace/tests/stacks.rs:LINE:COL"
+    );
+    pretty_assertions::assert_str_eq!(
+        util::strip(async_backtrace::taskdump_stacks(true)),
+        format!(
+            "Task {}:\n{}",
+            task.id(),
+            util::strip(task.pretty_stacks(true))
+        )
+    );
+}
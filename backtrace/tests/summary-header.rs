@@ -0,0 +1,79 @@
+/// A test that `TaskdumpOptions::summary_header` counts the tasks, frames
+/// and polling tasks of a dump, as `dump_with_stats` does.
+mod util;
+use async_backtrace::{Location, TaskdumpOptions};
+use std::{future::Future, sync::mpsc, task::Context};
+
+static A: (&str, u32, u32) = ("src/app.rs", 1, 1);
+static B: (&str, u32, u32) = ("src/app.rs", 10, 1);
+static C: (&str, u32, u32) = ("src/app.rs", 20, 1);
+static D: (&str, u32, u32) = ("src/app.rs", 30, 1);
+static Z: (&str, u32, u32) = ("src/app.rs", 40, 1);
+
+#[test]
+// (the polling task blocks its thread on a channel, which loom cannot model)
+#[cfg_attr(loom, ignore)]
+fn summary_header() {
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let frame = |name, rest| Location::from_components(name, rest);
+    let leaf = |name, rest| frame(name, rest).frame(std::future::pending::<()>());
+    let mut a = Box::pin(frame("a", &A).frame(futures::future::join(
+        leaf("d", &D),
+        frame("b", &B).frame(leaf("c", &C)),
+    )));
+    assert!(a.as_mut().poll(&mut cx).is_pending());
+
+    let options = || TaskdumpOptions::new().summary_header(true);
+    pretty_assertions::assert_str_eq!(
+        options().dump(),
+        "\
+== async-backtrace: 1 task, 4 frames, 0 polling ==
+╼ a at src/app.rs:1:1
+  ├╼ b at src/app.rs:10:1
+  │  └╼ c at src/app.rs:20:1
+  └╼ d at src/app.rs:30:1"
+    );
+    // frames beyond the maximum depth are counted, though not rendered
+    let (dump, stats) = options().max_depth(1).dump_with_stats();
+    assert!(dump.starts_with("== async-backtrace: 1 task, 4 frames, 0 polling ==\n"));
+    assert_eq!((stats.tasks(), stats.frames(), stats.polling()), (1, 4, 0));
+
+    // a task being polled counts only its root
+    let (entered_tx, entered_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let polling = std::thread::spawn(move || {
+        util::run(frame("z", &Z).frame(leaf_blocking(entered_tx, release_rx)))
+    });
+    entered_rx.recv().unwrap();
+    let (dump, stats) = TaskdumpOptions::new().sort_tasks(true).dump_with_stats();
+    assert_eq!(stats.to_string(), "2 tasks, 5 frames, 1 polling");
+    assert_eq!(dump, TaskdumpOptions::new().sort_tasks(true).dump());
+    release_tx.send(()).unwrap();
+    polling.join().unwrap();
+
+    // a dump of no tasks is only its header
+    drop(a);
+    assert_eq!(
+        options().dump(),
+        "== async-backtrace: 0 tasks, 0 frames, 0 polling =="
+    );
+    assert_eq!(
+        options().trailing_newline(true).dump(),
+        "== async-backtrace: 0 tasks, 0 frames, 0 polling ==\n"
+    );
+    assert_eq!(
+        async_backtrace::taskdump_tree_with_stats(false),
+        (String::new(), Default::default())
+    );
+}
+
+/// Blocks its poll, within a child frame, until released.
+async fn leaf_blocking(entered: mpsc::Sender<()>, release: mpsc::Receiver<()>) {
+    let block = std::future::poll_fn(move |_| {
+        entered.send(()).unwrap();
+        release.recv().unwrap();
+        std::task::Poll::Ready(())
+    });
+    Location::from_components("c", &C).frame(block).await
+}
@@ -0,0 +1,85 @@
+/// A test that the task exit hook reports whether each task completed, was
+/// cancelled, or panicked.
+mod util;
+use async_backtrace::{Outcome, TaskExit};
+use std::{
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    sync::Mutex,
+    task::Context,
+};
+
+static EXITS: Mutex<Vec<TaskExit>> = Mutex::new(Vec::new());
+
+#[test]
+fn outcomes() {
+    util::model(|| {
+        EXITS.lock().unwrap().clear();
+        async_backtrace::set_task_exit_hook(|exit| EXITS.lock().unwrap().push(*exit));
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        // completed
+        util::run(completes());
+
+        // cancelled
+        let mut future = Box::pin(cancelled());
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        let id = async_backtrace::tasks()
+            .find(|task| task.location().name() == Some("task_exit::cancelled::{{closure}}"))
+            .unwrap()
+            .id();
+        drop(future);
+
+        // panicked
+        let mut future = Box::pin(panics());
+        let result = panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(&mut cx)));
+        assert!(result.is_err());
+        drop(future);
+
+        let exits: Vec<_> = EXITS
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|exit| (exit.location().name().unwrap().to_string(), exit.outcome()))
+            .collect();
+        assert_eq!(
+            exits,
+            [
+                (
+                    "task_exit::completes::{{closure}}".to_string(),
+                    Outcome::Completed
+                ),
+                (
+                    "task_exit::cancelled::{{closure}}".to_string(),
+                    Outcome::Cancelled
+                ),
+                (
+                    "task_exit::panics::{{closure}}".to_string(),
+                    Outcome::Panicked
+                ),
+            ]
+        );
+        assert_eq!(EXITS.lock().unwrap()[1].id(), id);
+    });
+}
+
+#[async_backtrace::framed]
+async fn completes() {
+    // subframes do not report their exits
+    child().await;
+}
+
+#[async_backtrace::framed]
+async fn child() {}
+
+#[async_backtrace::framed]
+async fn cancelled() {
+    std::future::pending::<()>().await;
+}
+
+#[async_backtrace::framed]
+async fn panics() {
+    panic!("oh no");
+}
@@ -0,0 +1,60 @@
+/// A test that tasks are identified by their ids, rather than by their
+/// addresses.
+mod util;
+use async_backtrace::{location, raw::Frame};
+use std::pin::Pin;
+
+#[test]
+fn task_identity() {
+    util::model(|| {
+        let mut storage = Frame::new(location!());
+        let address = &storage as *const Frame;
+
+        // SAFETY: `storage` is never moved; it is only dropped in place by
+        // assignment, which upholds the drop guarantee of `Pin`.
+        let before = unsafe { Pin::new_unchecked(&mut storage) }.in_scope(current_id);
+        let again = unsafe { Pin::new_unchecked(&mut storage) }.in_scope(current_id);
+        assert_eq!(before, again);
+
+        // reuse the memory of the previous task for a new task
+        storage = Frame::new(location!());
+        assert_eq!(&storage as *const Frame, address);
+        let after = unsafe { Pin::new_unchecked(&mut storage) }.in_scope(current_id);
+        assert_ne!(before, after);
+        assert!(after > before);
+    });
+}
+
+#[test]
+fn task_equality() {
+    util::model(|| {
+        let a = Box::pin(Frame::new(location!()));
+        let b = Box::pin(Frame::new(location!()));
+        let mut frames = [a, b];
+        for frame in &mut frames {
+            frame.as_mut().in_scope(|| {});
+        }
+
+        let mine = |task: &async_backtrace::Task| {
+            task.location().file() == file!()
+                && task.location().name() == Some("task_identity::task_equality::{{closure}}")
+        };
+        let first: Vec<_> = async_backtrace::tasks().filter(|task| mine(task)).collect();
+        let second: Vec<_> = async_backtrace::tasks().filter(|task| mine(task)).collect();
+        assert_eq!(first.len(), 2);
+        for task in &first {
+            let same: Vec<_> = second.iter().filter(|other| task.same_as(other)).collect();
+            assert_eq!(same.len(), 1);
+            assert!(**task == **same[0]);
+            assert_eq!(task.id(), same[0].id());
+        }
+        assert!(*first[0] != *first[1]);
+    });
+}
+
+fn current_id() -> u64 {
+    async_backtrace::tasks()
+        .find(|task| task.location().name() == Some("task_identity::task_identity::{{closure}}"))
+        .map(|task| task.id())
+        .unwrap()
+}
@@ -0,0 +1,56 @@
+/// A test that the ids produced by `tasks_snapshot_ids` are those of the
+/// registered tasks, and that they resolve, unless their task has since
+/// exited.
+mod util;
+use async_backtrace::{dump_tasks, tasks_snapshot_ids, TaskdumpOptions};
+use std::{future::Future, task::Context};
+
+#[test]
+fn task_ids() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut live = Box::pin(pending());
+        let mut exiting = Box::pin(pending());
+        assert!(live.as_mut().poll(&mut cx).is_pending());
+        assert!(exiting.as_mut().poll(&mut cx).is_pending());
+        let mut ours: Vec<u64> = async_backtrace::tasks()
+            .filter(|task| task.location().name() == Some("task_ids::pending::{{closure}}"))
+            .map(|task| task.id())
+            .collect();
+        ours.sort_unstable();
+
+        // the ids of the registered tasks, and nothing else
+        let ids = tasks_snapshot_ids();
+        let mut sampled: Vec<u64> = ids.iter().copied().filter(|id| ours.contains(id)).collect();
+        sampled.sort_unstable();
+        assert_eq!(sampled, ours);
+
+        // ...which resolve, unless their task exits in the meantime
+        let handle = util::thread::spawn(move || drop(exiting));
+        let trees = dump_tasks(&ids, TaskdumpOptions::new());
+        handle.join().unwrap();
+        // (`live` was polled, and so registered, first)
+        let live_id = ours[0];
+        for (id, tree) in trees {
+            if id == live_id {
+                assert!(tree.is_some());
+            }
+        }
+
+        assert_eq!(
+            tasks_snapshot_ids()
+                .iter()
+                .filter(|id| ours.contains(id))
+                .count(),
+            1
+        );
+        drop(live);
+    });
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    std::future::pending::<()>().await;
+}
@@ -0,0 +1,69 @@
+/// A test that tasks beyond the configured limit are not registered, but are
+/// polled as usual, invisibly to taskdumps.
+mod util;
+use async_backtrace::{configure_registry, RegistryConfig};
+use std::{future::Future, task::Context};
+
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn task_limit() {
+    configure_registry(RegistryConfig {
+        initial_capacity: 1,
+        max_tasks: Some(1),
+        ..RegistryConfig::default()
+    })
+    .unwrap();
+
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    // the first task is registered...
+    let mut first = Box::pin(pending());
+    assert!(first.as_mut().poll(&mut cx).is_pending());
+    let estimate = async_backtrace::memory_estimate();
+    assert_eq!((estimate.tasks, estimate.unregistered), (1, 0));
+
+    // ...but the second, beyond the limit, is not; it is polled as usual,
+    // and knows its own task id, but is invisible to taskdumps
+    let (value, id) = util::run(counted());
+    assert_eq!(value, 3);
+    let estimate = async_backtrace::memory_estimate();
+    assert_eq!((estimate.tasks, estimate.unregistered), (1, 1));
+    pretty_assertions::assert_str_eq!(
+        util::strip(async_backtrace::taskdump_tree(true)),
+        "\
+╼ task_limit::pending::{{closure}} at backtrace/tests/task-limit.rs:LINE:COL"
+    );
+    assert!(async_backtrace::tasks_snapshot_ids()
+        .iter()
+        .all(|&task| task != id));
+
+    // once the first task exits, tasks are registered again
+    drop(first);
+    let registered = util::run(
+        async_backtrace::location!().frame(async { async_backtrace::tasks_snapshot_ids().len() }),
+    );
+    assert_eq!(registered, 1);
+    let estimate = async_backtrace::memory_estimate();
+    assert_eq!((estimate.tasks, estimate.unregistered), (0, 1));
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    futures::pending!()
+}
+
+#[async_backtrace::framed]
+async fn counted() -> (usize, u64) {
+    let mut value = 0;
+    for _ in 0..3 {
+        value += step().await;
+    }
+    (value, async_backtrace::root_task_id().unwrap())
+}
+
+#[async_backtrace::framed]
+async fn step() -> usize {
+    futures::pending!();
+    1
+}
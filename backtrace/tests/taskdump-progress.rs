@@ -0,0 +1,52 @@
+/// A test that taskdumps report their progress, and may be cancelled.
+mod util;
+use async_backtrace::TaskdumpOptions;
+use std::{future::Future, ops::ControlFlow, task::Context};
+
+#[test]
+fn progress() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut tasks: Vec<_> = (0..5).map(|_| Box::pin(pending())).collect();
+        for task in &mut tasks {
+            assert!(task.as_mut().poll(&mut cx).is_pending());
+        }
+
+        // progress is reported every `progress_interval` tasks, and at the end
+        let mut reports = Vec::new();
+        let dump = TaskdumpOptions::new()
+            .progress_interval(2)
+            .progress(|done, total| {
+                reports.push((done, total));
+                ControlFlow::Continue(())
+            })
+            .dump();
+        assert_eq!(reports, [(2, 5), (4, 5), (5, 5)]);
+        assert_eq!(roots(&dump), 5);
+        assert!(!dump.contains("TRUNCATED"));
+
+        // breaking stops the dump
+        let mut reports = Vec::new();
+        let dump = TaskdumpOptions::new()
+            .progress_interval(2)
+            .progress(|done, total| {
+                reports.push((done, total));
+                ControlFlow::Break(())
+            })
+            .dump();
+        assert_eq!(reports, [(2, 5)]);
+        assert_eq!(roots(&dump), 2);
+        assert!(dump.ends_with("\n[TRUNCATED: 2 of 5 tasks dumped]"));
+    });
+}
+
+/// Counts the trees in `dump`.
+fn roots(dump: &str) -> usize {
+    dump.lines().filter(|line| line.starts_with('╼')).count()
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    futures::future::pending::<()>().await
+}
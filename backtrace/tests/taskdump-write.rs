@@ -0,0 +1,94 @@
+/// A test that taskdump_write() and taskdump_write_io() stream the dump of
+/// taskdump_tree() into writers, and report the errors of those writers.
+mod util;
+use std::{fmt, future::Future, io, task::Context};
+
+/// A writer that records the lengths of the writes made to it, and fails
+/// after `capacity` bytes.
+struct Limited {
+    writes: Vec<usize>,
+    capacity: usize,
+}
+
+impl Limited {
+    fn new(capacity: usize) -> Self {
+        Self {
+            writes: Vec::new(),
+            capacity,
+        }
+    }
+
+    fn write(&mut self, len: usize) -> bool {
+        self.writes.push(len);
+        self.writes.iter().sum::<usize>() <= self.capacity
+    }
+}
+
+impl fmt::Write for Limited {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s.len()).then_some(()).ok_or(fmt::Error)
+    }
+}
+
+impl io::Write for Limited {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.write(buf.len()) {
+            true => Ok(buf.len()),
+            false => Err(io::Error::new(io::ErrorKind::WriteZero, "full")),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn taskdump_write() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut tasks: Vec<_> = (0..2).map(|_| Box::pin(outer())).collect();
+        for task in &mut tasks {
+            assert!(task.as_mut().poll(&mut cx).is_pending());
+        }
+        let expected = async_backtrace::taskdump_tree(true);
+
+        let mut dump = String::new();
+        async_backtrace::taskdump_write(&mut dump, true).unwrap();
+        pretty_assertions::assert_str_eq!(dump, expected);
+
+        let mut dump = Vec::new();
+        async_backtrace::taskdump_write_io(&mut dump, true).unwrap();
+        pretty_assertions::assert_str_eq!(String::from_utf8(dump).unwrap(), expected);
+
+        // the dump is streamed in pieces (of at most a line), rather than
+        // written at once
+        let mut writer = Limited::new(usize::MAX);
+        async_backtrace::taskdump_write(&mut writer, true).unwrap();
+        assert_eq!(writer.writes.iter().sum::<usize>(), expected.len());
+        let longest = expected.lines().map(str::len).max().unwrap();
+        assert!(writer.writes.iter().all(|&len| len <= longest));
+
+        // the errors of writers are propagated
+        let mut writer = Limited::new(expected.len() / 2);
+        assert_eq!(
+            async_backtrace::taskdump_write(&mut writer, true),
+            Err(fmt::Error)
+        );
+        let mut writer = Limited::new(expected.len() / 2);
+        let error = async_backtrace::taskdump_write_io(&mut writer, true).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WriteZero);
+        assert_eq!(error.to_string(), "full");
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    inner().await;
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    std::future::pending::<()>().await;
+}
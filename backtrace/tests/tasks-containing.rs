@@ -0,0 +1,88 @@
+/// A test that `tasks_containing` finds the tasks with a matching frame
+/// anywhere in their tree, and skips tasks that are being polled.
+mod util;
+use async_backtrace::{tasks_containing, Location};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::Context,
+};
+
+static BUSY: AtomicBool = AtomicBool::new(false);
+static RELEASE: AtomicBool = AtomicBool::new(false);
+
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn tasks_containing_() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut tasks: [Pin<Box<dyn Future<Output = ()>>>; 3] =
+            [Box::pin(outer()), Box::pin(pending()), Box::pin(pending())];
+        for task in &mut tasks {
+            assert!(task.as_mut().poll(&mut cx).is_pending());
+        }
+
+        // the needle is two levels beneath the root of the first task
+        let found = tasks_containing(is_needle, true);
+        assert_eq!(found.skipped, 0);
+        assert_eq!(found.tasks.len(), 1);
+        assert_eq!(
+            found.tasks[0].location().name(),
+            Some("tasks_containing::outer::{{closure}}")
+        );
+        drop(found);
+
+        assert!(tasks_containing(|_| false, true).tasks.is_empty());
+
+        // a task that is being polled on another thread cannot be searched
+        // without waiting for it
+        let poller = util::thread::spawn(|| util::run(busy()));
+        while !BUSY.load(Ordering::SeqCst) {
+            util::thread::yield_now();
+        }
+        let found = tasks_containing(is_busy, false);
+        assert!(found.tasks.is_empty());
+        assert!(found.skipped >= 1);
+        RELEASE.store(true, Ordering::SeqCst);
+        poller.join().unwrap();
+    });
+}
+
+fn is_busy(location: &Location) -> bool {
+    location.name() == Some("tasks_containing::busy::{{closure}}")
+}
+
+fn is_needle(location: &Location) -> bool {
+    location.name() == Some("tasks_containing::needle::{{closure}}")
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    middle().await
+}
+
+#[async_backtrace::framed]
+async fn middle() {
+    needle().await
+}
+
+#[async_backtrace::framed]
+async fn needle() {
+    pending().await
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    futures::future::pending::<()>().await
+}
+
+#[async_backtrace::framed]
+async fn busy() {
+    BUSY.store(true, Ordering::SeqCst);
+    while !RELEASE.load(Ordering::SeqCst) {
+        std::thread::yield_now();
+    }
+}
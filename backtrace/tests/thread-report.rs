@@ -0,0 +1,82 @@
+/// A test that `thread_report` attributes a busy framed task to exactly the
+/// one runtime worker that is polling it, as the `threads` section of a
+/// taskdump does.
+mod util;
+use async_backtrace::TaskdumpOptions;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+static TASK_ID: AtomicU64 = AtomicU64::new(0);
+static STOP: AtomicBool = AtomicBool::new(false);
+
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn thread_report() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .thread_name("worker")
+        .build()
+        .unwrap();
+    // (so that a failed assertion does not leave the worker spinning)
+    let _stop = Stop;
+    let task = runtime.spawn(spawned());
+    while TASK_ID.load(Ordering::Acquire) == 0 {
+        std::thread::yield_now();
+    }
+    let id = TASK_ID.load(Ordering::Acquire);
+
+    let report = async_backtrace::thread_report();
+    let busy: Vec<_> = report
+        .iter()
+        .filter(|activity| activity.task_id() == id)
+        .collect();
+    assert_eq!(busy.len(), 1);
+    assert_eq!(busy[0].name(), Some("worker"));
+    assert_eq!(
+        busy[0].location().name(),
+        Some("thread_report::spin::{{closure}}")
+    );
+
+    // the busy task is marked as polled on its worker
+    let dump = TaskdumpOptions::new().threads(true).dump();
+    assert!(dump.contains("[POLLING on worker]"), "{}", dump);
+    pretty_assertions::assert_str_eq!(
+        util::strip(dump),
+        format!(
+            "\
+╼ thread_report::spawned::{{{{closure}}}} at backtrace/tests/thread-report.rs:LINE:COL
+  └┈ [POLLING on THREAD]
+== threads ==
+worker: task #{} at thread_report::spin::{{{{closure}}}} at backtrace/tests/thread-report.rs:LINE:COL",
+            id
+        )
+    );
+
+    STOP.store(true, Ordering::Release);
+    runtime.block_on(task).unwrap();
+    // the worker no longer has an active frame
+    assert!(async_backtrace::thread_report()
+        .iter()
+        .all(|activity| activity.task_id() != id));
+}
+
+struct Stop;
+
+impl Drop for Stop {
+    fn drop(&mut self) {
+        STOP.store(true, Ordering::Release);
+    }
+}
+
+#[async_backtrace::framed]
+async fn spawned() {
+    spin().await;
+}
+
+#[async_backtrace::framed]
+async fn spin() {
+    let id = async_backtrace::current_task().unwrap().id();
+    TASK_ID.store(id, Ordering::Release);
+    while !STOP.load(Ordering::Acquire) {
+        std::hint::spin_loop();
+    }
+}
@@ -0,0 +1,52 @@
+/// Tests that `timeout_framed` names the frames it cancels.
+mod util;
+use async_backtrace::TimeoutExt;
+use std::time::Duration;
+
+#[tokio::test]
+#[cfg_attr(any(miri, loom), ignore)]
+async fn names_cancelled_frames() {
+    let err = fetch()
+        .timeout_framed(Duration::from_millis(10))
+        .await
+        .unwrap_err();
+
+    pretty_assertions::assert_str_eq!(
+        util::strip(err.to_string()),
+        "\
+deadline has elapsed; cancelled:
+╼ timeout::fetch::{{closure}} at backtrace/tests/timeout.rs:LINE:COL
+  └╼ timeout::connect::{{closure}} at backtrace/tests/timeout.rs:LINE:COL"
+    );
+}
+
+#[tokio::test]
+#[cfg_attr(any(miri, loom), ignore)]
+async fn ready_in_time() {
+    let output = async { 42 }
+        .timeout_framed(Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert_eq!(output, 42);
+}
+
+#[tokio::test]
+#[cfg_attr(any(miri, loom), ignore)]
+async fn unframed() {
+    let err = std::future::pending::<()>()
+        .timeout_framed(Duration::from_millis(10))
+        .await
+        .unwrap_err();
+    assert_eq!(err.cancelled(), "");
+    assert_eq!(err.to_string(), "deadline has elapsed");
+}
+
+#[async_backtrace::framed]
+async fn fetch() {
+    connect().await;
+}
+
+#[async_backtrace::framed]
+async fn connect() {
+    std::future::pending::<()>().await
+}
@@ -0,0 +1,90 @@
+/// A test that `TracedError` captures the backtrace of the frame in which an
+/// error is propagated by `?` (or by `trace_async`), renders it after the
+/// error, delegates its source, and is empty outside of any frame.
+mod util;
+use async_backtrace::{ResultExt, TracedError};
+use std::{error::Error, fmt};
+
+#[test]
+fn traced_error() {
+    util::model(|| {
+        let err = util::run(outer()).unwrap_err();
+        let names: Vec<_> = err
+            .backtrace()
+            .iter()
+            .map(|location| location.name().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "traced_error::inner::{{closure}}",
+                "traced_error::outer::{{closure}}"
+            ]
+        );
+        pretty_assertions::assert_str_eq!(
+            util::strip(err.to_string()),
+            "\
+refused; async backtrace:
+#0 traced_error::inner::{{closure}} at backtrace/tests/traced-error.rs:LINE:COL
+#1 traced_error::outer::{{closure}} at backtrace/tests/traced-error.rs:LINE:COL"
+        );
+        assert!(util::strip(format!("{:?}", err))
+            .starts_with("Refused(Timeout); async backtrace:\n#0 traced_error::inner"));
+        assert_eq!(err.source().unwrap().to_string(), "timed out");
+
+        let err = util::run(traced()).unwrap_err();
+        assert_eq!(err.backtrace().len(), 1);
+
+        // outside of any frame, the backtrace is empty
+        let err = Err::<(), _>(Refused(Timeout)).trace_async().unwrap_err();
+        assert!(err.backtrace().is_empty());
+        assert_eq!(err.to_string(), "refused");
+        assert_eq!(err.into_inner().0, Timeout);
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() -> Result<(), TracedError<Refused>> {
+    inner().await
+}
+
+#[async_backtrace::framed]
+async fn inner() -> Result<(), TracedError<Refused>> {
+    connect()?;
+    Ok(())
+}
+
+#[async_backtrace::framed]
+async fn traced() -> Result<(), TracedError<Refused>> {
+    connect().trace_async()
+}
+
+fn connect() -> Result<(), Refused> {
+    Err(Refused(Timeout))
+}
+
+#[derive(Debug)]
+struct Refused(Timeout);
+
+impl fmt::Display for Refused {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("refused")
+    }
+}
+
+impl Error for Refused {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Timeout;
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("timed out")
+    }
+}
+
+impl Error for Timeout {}
@@ -0,0 +1,61 @@
+/// A test that framing is transparent to `#[track_caller]`, so that panics in
+/// child futures report locations in user code.
+mod util;
+use std::{
+    future::Future,
+    panic,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+static PANICKED_AT: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[test]
+#[cfg_attr(loom, ignore)]
+fn track_caller() {
+    util::model(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(|info| {
+            let location = info.location().unwrap();
+            PANICKED_AT
+                .lock()
+                .unwrap()
+                .push(location.file().to_string());
+        }));
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        // a child polled directly by a `Framed`...
+        let mut framed = Box::pin(async_backtrace::frame!(Child));
+        let framed = framed.as_mut();
+        assert!(panic::catch_unwind(panic::AssertUnwindSafe(|| framed.poll(&mut cx))).is_err());
+
+        // ...and by a framed async function.
+        let mut framed = Box::pin(parent());
+        let framed = framed.as_mut();
+        assert!(panic::catch_unwind(panic::AssertUnwindSafe(|| framed.poll(&mut cx))).is_err());
+
+        panic::set_hook(previous);
+        let panicked_at = std::mem::take(&mut *PANICKED_AT.lock().unwrap());
+        assert_eq!(panicked_at, [file!(), file!()]);
+    });
+}
+
+#[async_backtrace::framed]
+async fn parent() {
+    Child.await
+}
+
+/// A future whose `poll` panics at the location of its caller.
+struct Child;
+
+impl Future for Child {
+    type Output = ();
+
+    #[track_caller]
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        panic!("child panicked");
+    }
+}
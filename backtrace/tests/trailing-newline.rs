@@ -0,0 +1,81 @@
+/// A test that dumps separate the trees of tasks with newlines, without
+/// ending with one, unless `TaskdumpOptions::trailing_newline` is set, for no,
+/// one, and many tasks.
+mod util;
+use async_backtrace::TaskdumpOptions;
+use std::{future::Future, ops::ControlFlow, task::Context};
+
+#[test]
+fn trailing_newline() {
+    util::model(|| {
+        let dump = |trailing| TaskdumpOptions::new().trailing_newline(trailing).dump();
+        let json = |trailing| {
+            TaskdumpOptions::new()
+                .trailing_newline(trailing)
+                .dump_json()
+        };
+        let written = |trailing| {
+            let mut dump = String::new();
+            TaskdumpOptions::new()
+                .trailing_newline(trailing)
+                .dump_to(&mut dump)
+                .unwrap();
+            dump
+        };
+
+        // no tasks
+        assert_eq!(dump(false), "");
+        assert_eq!(dump(true), "");
+        assert_eq!(json(false), "{\"tasks\":[],\"truncated\":null}");
+        assert_eq!(json(true), "{\"tasks\":[],\"truncated\":null}\n");
+
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut first = Box::pin(outer());
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+
+        // one task
+        let one = "\
+╼ trailing_newline::outer::{{closure}} at backtrace/tests/trailing-newline.rs:LINE:COL
+  └╼ trailing_newline::inner::{{closure}} at backtrace/tests/trailing-newline.rs:LINE:COL";
+        pretty_assertions::assert_str_eq!(util::strip(dump(false)), one);
+        pretty_assertions::assert_str_eq!(util::strip(dump(true)), format!("{}\n", one));
+        assert_eq!(written(true), dump(true));
+        assert_eq!(json(true), format!("{}\n", json(false)));
+
+        // many tasks
+        let mut second = Box::pin(outer());
+        assert!(second.as_mut().poll(&mut cx).is_pending());
+        pretty_assertions::assert_str_eq!(util::strip(dump(false)), format!("{}\n{}", one, one));
+        pretty_assertions::assert_str_eq!(util::strip(dump(true)), format!("{}\n{}\n", one, one));
+        assert_eq!(written(true), dump(true));
+
+        // dumps that are cancelled end with their note
+        let cancelled = TaskdumpOptions::new()
+            .trailing_newline(true)
+            .progress_interval(1)
+            .progress(|_, _| ControlFlow::Break(()))
+            .dump();
+        pretty_assertions::assert_str_eq!(
+            util::strip(cancelled),
+            format!("{}\n[TRUNCATED: 1 of 2 tasks dumped]\n", one)
+        );
+
+        // the trees of `dump_tasks` each end with a newline
+        let ids = async_backtrace::tasks_snapshot_ids();
+        let options = TaskdumpOptions::new().trailing_newline(true);
+        for (_, tree) in async_backtrace::dump_tasks(&ids, options) {
+            pretty_assertions::assert_str_eq!(util::strip(tree.unwrap()), format!("{}\n", one));
+        }
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    inner().await;
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    std::future::pending::<()>().await;
+}
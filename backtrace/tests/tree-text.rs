@@ -0,0 +1,106 @@
+/// A test that ParsedDump::from_tree_text() parses taskdumps back into the
+/// trees of their tasks, and that their Display reproduces them.
+mod util;
+use async_backtrace::{ParsedDump, TaskdumpOptions};
+use std::{future::Future, task::Context};
+
+#[test]
+fn tree_text() {
+    // the dumps of the crate's examples
+    for (name, fixture) in [
+        ("taskdump", include_str!("fixtures/taskdumps/taskdump.txt")),
+        ("select", include_str!("fixtures/taskdumps/select.txt")),
+        ("multiple", include_str!("fixtures/taskdumps/multiple.txt")),
+    ] {
+        let parsed = ParsedDump::from_tree_text(fixture).unwrap();
+        pretty_assertions::assert_str_eq!(parsed.to_string(), fixture.trim_end(), "{}", name);
+    }
+
+    let select = ParsedDump::from_tree_text(include_str!("fixtures/taskdumps/select.txt")).unwrap();
+    let selecting = &select.tasks()[0];
+    assert_eq!(selecting.name(), "select::selecting::{{closure}}");
+    assert_eq!(selecting.file(), Some("backtrace/examples/select.rs"));
+    assert_eq!((selecting.line(), selecting.column()), (Some(8), Some(1)));
+    assert_eq!(selecting.children()[1].copies(), 2);
+    assert!(selecting.children()[1].matches("yielding"));
+
+    // the markers, annotations and location styles of other dumps
+    let dump = "\
+╼ app::serve::{{closure}} at src/main.rs:8
+  ├┈ [POLLING] last known tree, 3s old (possibly stale):
+  └╼ app::handle::{{closure}} at src/main.rs:12 [last error 1s ago: reset] [panicked]
+╼ app::pool::{{closure}} [future: 2.0 KiB] (via 2 helper frames)
+  └╼ … and 3 more children";
+    let parsed = ParsedDump::from_tree_text(dump).unwrap();
+    pretty_assertions::assert_str_eq!(parsed.to_string(), dump);
+    let (serve, pool) = (&parsed.tasks()[0], &parsed.tasks()[1]);
+    assert_eq!(serve.last_known_age(), Some(3));
+    let handle = &serve.children()[0];
+    assert_eq!((handle.line(), handle.column()), (Some(12), None));
+    assert_eq!(
+        handle.annotations(),
+        ["last error 1s ago: reset", "panicked"]
+    );
+    assert_eq!((pool.file(), pool.helper_frames()), (None, 2));
+    assert_eq!(
+        ParsedDump::from_tree_text("╼ app::serve at src/main.rs")
+            .unwrap_err()
+            .line(),
+        1
+    );
+
+    util::model(|| {
+        // an idle task, whose tree is dumped...
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut idle = Box::pin(selecting_idle());
+        assert!(idle.as_mut().poll(&mut cx).is_pending());
+
+        // ...and a task that is being polled
+        let dump = util::run(async_backtrace::location!().frame(async {
+            util::thread::spawn(|| TaskdumpOptions::new().annotations(true).dump())
+                .join()
+                .unwrap()
+        }));
+        drop(idle);
+
+        let parsed = ParsedDump::from_tree_text(&dump).unwrap();
+        pretty_assertions::assert_str_eq!(parsed.to_string(), dump);
+
+        let idle = parsed
+            .tasks()
+            .iter()
+            .find(|task| task.matches("selecting_idle"))
+            .unwrap();
+        assert_eq!(idle.annotations(), ["request_id=42"]);
+        assert_eq!(idle.children()[0].copies(), 2);
+        assert!(idle.children()[0].children()[0].matches("leaf"));
+        let polling = parsed
+            .tasks()
+            .iter()
+            .find(|task| task.matches("tree_text"))
+            .unwrap();
+        assert!(polling.is_polling());
+        assert!(polling.children().is_empty());
+    });
+}
+
+#[async_backtrace::framed]
+async fn selecting_idle() {
+    async_backtrace::annotate("request_id", 42);
+    tokio::select! {
+        biased;
+        _ = pending_outer() => {}
+        _ = pending_outer() => {}
+    };
+}
+
+#[async_backtrace::framed]
+async fn pending_outer() {
+    leaf().await;
+}
+
+#[async_backtrace::framed]
+async fn leaf() {
+    futures::pending!()
+}
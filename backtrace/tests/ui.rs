@@ -0,0 +1,9 @@
+/// Tests that misuse of `#[framed]` produces helpful compile errors, and that
+/// its expansions compile without warnings.
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+    t.pass("tests/ui/pass/*.rs");
+}
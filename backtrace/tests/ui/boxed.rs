@@ -0,0 +1,9 @@
+#[async_backtrace::framed(boxed)]
+fn not_async() {}
+
+#[async_backtrace::framed(boxed)]
+async fn not_send(value: std::rc::Rc<u32>) -> u32 {
+    *value
+}
+
+fn main() {}
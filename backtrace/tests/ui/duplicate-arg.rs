@@ -0,0 +1,4 @@
+#[async_backtrace::framed(name = "foo", name = "bar")]
+async fn foo() {}
+
+fn main() {}
@@ -0,0 +1,4 @@
+#[async_backtrace::framed(lazy, detached)]
+async fn foo() {}
+
+fn main() {}
@@ -0,0 +1,4 @@
+#[async_backtrace::framed(lazy, root_only)]
+async fn foo() {}
+
+fn main() {}
@@ -0,0 +1,10 @@
+#[async_backtrace::framed(name = foo)]
+async fn foo() {}
+
+#[async_backtrace::framed(name)]
+async fn bar() {}
+
+#[async_backtrace::framed(name = "baz" crate = async_backtrace)]
+async fn baz() {}
+
+fn main() {}
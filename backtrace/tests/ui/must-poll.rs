@@ -0,0 +1,4 @@
+#[async_backtrace::framed(must_poll)]
+async fn unboxed() {}
+
+fn main() {}
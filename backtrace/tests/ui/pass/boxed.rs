@@ -0,0 +1,65 @@
+#![deny(warnings)]
+use std::{fmt::Display, future::Future, pin::Pin};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+struct Store(Vec<String>);
+
+impl Store {
+    // the elided lifetime of the output is that of `&self`
+    #[async_backtrace::framed(boxed)]
+    async fn get(&self, index: usize) -> &str {
+        &self.0[index]
+    }
+
+    #[async_backtrace::framed(boxed)]
+    async fn push(&mut self, value: impl Display + Send) {
+        self.0.push(value.to_string());
+    }
+
+    #[async_backtrace::framed(boxed)]
+    async fn into_len(self) -> usize {
+        self.0.len()
+    }
+}
+
+// borrowed arguments, with elided, anonymous and named lifetimes
+#[async_backtrace::framed(boxed)]
+async fn concat<'a>(a: &'a str, b: &str, c: &'_ str) -> String {
+    format!("{}{}{}", a, b, c)
+}
+
+// the elided lifetime of the output is that of the sole borrowed argument
+#[async_backtrace::framed(boxed)]
+async fn first(values: &[u32]) -> Option<&u32> {
+    values.first()
+}
+
+// generic arguments, and patterns
+#[async_backtrace::framed(boxed)]
+async fn sum<T: Into<u64> + Send>((a, b): (T, T), mut acc: u64) -> u64 {
+    acc += a.into() + b.into();
+    acc
+}
+
+#[async_backtrace::framed(boxed_local)]
+async fn local(value: std::rc::Rc<u32>) -> u32 {
+    *value
+}
+
+fn dyn_friendly<'a>(store: &'a Store) -> BoxFuture<'a, &'a str> {
+    store.get(0)
+}
+
+fn main() {
+    futures::executor::block_on(async {
+        let mut store = Store(Vec::new());
+        store.push(1).await;
+        assert_eq!(dyn_friendly(&store).await, "1");
+        assert_eq!(store.into_len().await, 1);
+        assert_eq!(concat("a", "b", "c").await, "abc");
+        assert_eq!(first(&[1, 2]).await, Some(&1));
+        assert_eq!(sum((1u8, 2u8), 3).await, 6);
+        assert_eq!(local(std::rc::Rc::new(4)).await, 4);
+    });
+}
@@ -0,0 +1,17 @@
+#![deny(warnings)]
+
+#[async_backtrace::framed]
+#[inline(always)]
+async fn always() -> u32 {
+    42
+}
+
+#[inline]
+#[async_backtrace::framed]
+async fn hint() -> u32 {
+    always().await
+}
+
+fn main() {
+    let _ = futures::executor::block_on(hint());
+}
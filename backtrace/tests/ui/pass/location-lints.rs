@@ -0,0 +1,9 @@
+#![deny(unused, unused_qualifications, unused_results, warnings)]
+#![no_implicit_prelude]
+
+static ROOT: ::async_backtrace::Location = ::async_backtrace::location_named!("root");
+
+fn main() {
+    let _ = ::async_backtrace::location!();
+    let _ = ROOT;
+}
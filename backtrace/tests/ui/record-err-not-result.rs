@@ -0,0 +1,6 @@
+#[async_backtrace::framed(record_err)]
+async fn foo() -> u32 {
+    42
+}
+
+fn main() {}
@@ -0,0 +1,4 @@
+#[async_backtrace::framed(foo)]
+async fn foo() {}
+
+fn main() {}
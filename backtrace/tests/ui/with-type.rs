@@ -0,0 +1,4 @@
+#[async_backtrace::framed(with_type)]
+async fn free() {}
+
+fn main() {}
@@ -0,0 +1,56 @@
+/// A test that `TaskdumpOptions::consolidate(false)` renders adjacent
+/// identical subframes individually.
+mod util;
+use async_backtrace::TaskdumpOptions;
+
+#[test]
+fn unconsolidated() {
+    util::model(|| util::run(selecting()));
+}
+
+#[async_backtrace::framed]
+async fn selecting() {
+    tokio::select! {
+        biased;
+        _ = yielding_outer() => {}
+        _ = yielding_outer() => {}
+        _ = ready() => {}
+    };
+}
+
+#[async_backtrace::framed]
+async fn yielding_outer() {
+    yielding_inner().await;
+}
+
+#[async_backtrace::framed]
+async fn yielding_inner() {
+    tokio::task::yield_now().await;
+}
+
+#[async_backtrace::framed]
+async fn ready() {
+    let dump = TaskdumpOptions::new()
+        .wait_for_running_tasks(true)
+        .consolidate(false)
+        .dump();
+
+    pretty_assertions::assert_str_eq!(
+        util::strip(dump),
+        "\
+╼ unconsolidated::selecting::{{closure}} at backtrace/tests/unconsolidated.rs:LINE:COL
+  ├╼ unconsolidated::ready::{{closure}} at backtrace/tests/unconsolidated.rs:LINE:COL
+  ├╼ unconsolidated::yielding_outer::{{closure}} at backtrace/tests/unconsolidated.rs:LINE:COL
+  │  └╼ unconsolidated::yielding_inner::{{closure}} at backtrace/tests/unconsolidated.rs:LINE:COL
+  └╼ unconsolidated::yielding_outer::{{closure}} at backtrace/tests/unconsolidated.rs:LINE:COL
+     └╼ unconsolidated::yielding_inner::{{closure}} at backtrace/tests/unconsolidated.rs:LINE:COL"
+    );
+
+    // as does JSON
+    let json = TaskdumpOptions::new()
+        .wait_for_running_tasks(true)
+        .consolidate(false)
+        .dump_json();
+    assert_eq!(json.matches("\"count\":2").count(), 0);
+    assert_eq!(json.matches("yielding_outer").count(), 2);
+}
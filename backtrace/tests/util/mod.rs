@@ -6,6 +6,12 @@ pub(crate) fn model<F>(f: F)
 where
     F: Fn() + Sync + Send + 'static,
 {
+    // after each scenario, check that the frame forest is still consistent
+    let f = move || {
+        f();
+        #[cfg(debug_assertions)]
+        async_backtrace::debug::validate().unwrap();
+    };
     #[cfg(not(loom))]
     f();
     #[cfg(loom)]
@@ -33,9 +39,9 @@ pub fn run<F: Future>(f: F) -> <F as Future>::Output {
     }
 }
 
+/// Replaces each `:<line>:<column>` in `str` with `:LINE:COL`.
 pub fn strip(str: impl AsRef<str>) -> String {
-    let re = regex::Regex::new(r":\d+:\d+").unwrap();
-    re.replace_all(str.as_ref(), ":LINE:COL").to_string()
+    async_backtrace::testing::normalize(str.as_ref())
 }
 
 pub fn defer<F: FnOnce() -> R, R>(f: F) -> impl Drop {
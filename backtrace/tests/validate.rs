@@ -0,0 +1,31 @@
+#![cfg(any(debug_assertions, feature = "debug-validate"))]
+/// A test that `debug::validate` accepts consistent trees, from both within
+/// and outside of them.
+mod util;
+use util::thread;
+
+#[test]
+fn validate() {
+    util::model(|| {
+        let handle = thread::spawn(|| util::run(outer()));
+        // the tree of `outer` may or may not be busy; either way, it is valid
+        async_backtrace::debug::validate().unwrap();
+        handle.join().unwrap();
+    });
+}
+
+#[async_backtrace::framed]
+async fn outer() {
+    futures::join!(inner(), inner(), async_backtrace::frame!(inner()));
+}
+
+#[async_backtrace::framed]
+async fn inner() {
+    async_backtrace::debug::validate().unwrap();
+    futures::join!(leaf(), leaf());
+}
+
+#[async_backtrace::framed]
+async fn leaf() {
+    async_backtrace::debug::validate().unwrap();
+}
@@ -0,0 +1,64 @@
+/// A test that `#[framed(with_type)]` qualifies the names of frames with the
+/// type of `Self`, for each implementation of a trait.
+mod util;
+
+trait Handler {
+    async fn run(&self);
+}
+
+struct Fast;
+
+struct Slow;
+
+struct Wrapper<T>(T);
+
+impl Handler for Fast {
+    #[async_backtrace::framed(with_type)]
+    async fn run(&self) {
+        pending().await
+    }
+}
+
+impl Handler for Slow {
+    #[async_backtrace::framed(with_type, name = "handle")]
+    async fn run(&self) {
+        pending().await
+    }
+}
+
+impl<T> Handler for Wrapper<T> {
+    #[async_backtrace::framed(with_type)]
+    async fn run(&self) {
+        pending().await
+    }
+}
+
+#[test]
+fn with_type() {
+    util::model(|| util::run(root()));
+}
+
+#[async_backtrace::framed]
+async fn root() {
+    let handlers = futures::future::join3(Fast.run(), Slow.run(), Wrapper(0u8).run());
+    // the handlers never complete, but the dump does
+    futures::future::select(Box::pin(handlers), Box::pin(dump())).await;
+}
+
+#[async_backtrace::framed]
+async fn dump() {
+    let dump = async_backtrace::taskdump_tree(true);
+    pretty_assertions::assert_str_eq!(
+        util::strip(dump),
+        "\
+╼ with_type::root::{{closure}} at backtrace/tests/with-type.rs:LINE:COL
+  ├╼ with_type::dump::{{closure}} at backtrace/tests/with-type.rs:LINE:COL
+  ├╼ with_type::Wrapper<u8>::run at backtrace/tests/with-type.rs:LINE:COL
+  ├╼ with_type::Slow::handle at backtrace/tests/with-type.rs:LINE:COL
+  └╼ with_type::Fast::run at backtrace/tests/with-type.rs:LINE:COL"
+    );
+}
+
+async fn pending() {
+    std::future::pending::<()>().await;
+}
@@ -0,0 +1,70 @@
+/// A test that a slow writer of a taskdump does not block polls of the task
+/// being written.
+mod util;
+use std::{
+    fmt,
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+    task::Context,
+    time::Duration,
+};
+
+static WRITING: AtomicBool = AtomicBool::new(false);
+static WRITTEN: AtomicBool = AtomicBool::new(false);
+
+#[test]
+#[cfg_attr(any(miri, loom), ignore)]
+fn write_tree() {
+    util::model(|| {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut task = Box::pin(pending());
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+
+        let writer = util::thread::spawn(|| {
+            let task = async_backtrace::tasks()
+                .find(|task| task.location().name() == Some("write_tree::pending::{{closure}}"))
+                .unwrap();
+            let mut writer = SlowWriter(String::new());
+            task.write_tree(&mut writer, true).unwrap();
+            WRITTEN.store(true, Ordering::SeqCst);
+            writer.0
+        });
+
+        while !WRITING.load(Ordering::SeqCst) {
+            util::thread::yield_now();
+        }
+        // the task can be polled while its tree is being written
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+        assert!(!WRITTEN.load(Ordering::SeqCst));
+
+        let tree = writer.join().unwrap();
+        pretty_assertions::assert_str_eq!(
+            util::strip(tree),
+            "\
+╼ write_tree::pending::{{closure}} at backtrace/tests/write-tree.rs:LINE:COL
+  └╼ write_tree::leaf::{{closure}} at backtrace/tests/write-tree.rs:LINE:COL"
+        );
+    });
+}
+
+#[async_backtrace::framed]
+async fn pending() {
+    leaf().await
+}
+
+#[async_backtrace::framed]
+async fn leaf() {
+    futures::future::pending::<()>().await
+}
+
+/// A writer that takes a long time to write.
+struct SlowWriter(String);
+
+impl fmt::Write for SlowWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        WRITING.store(true, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(100));
+        self.0.write_str(s)
+    }
+}
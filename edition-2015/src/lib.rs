@@ -0,0 +1,51 @@
+//! An edition-2015 crate that uses the macros of `async-backtrace`.
+//!
+//! Edition 2015 has neither `async fn`s nor `async` blocks, so its futures
+//! are combinators, which are framed with `frame!` and `catching_frame!`, and
+//! `#[framed]` applies only to functions that return futures.
+#[macro_use]
+extern crate async_backtrace;
+extern crate futures;
+
+use futures::future::{self, Future, FutureExt};
+
+/// Produces a future that resolves to the current backtrace, framed twice.
+pub fn backtrace() -> impl Future<Output = Vec<String>> {
+    frame!(future::lazy(|_| ()).then(|()| nested::backtrace()))
+}
+
+/// Produces the location of this function.
+pub fn location() -> async_backtrace::Location {
+    location!()
+}
+
+/// Produces a location named `legacy::root`.
+pub fn location_named() -> async_backtrace::Location {
+    location_named!("legacy::root")
+}
+
+/// Produces a future that resolves to `42`, and reports any panic within it.
+pub fn catching() -> impl Future<Output = u32> {
+    catching_frame!(future::ready(42))
+}
+
+/// Produces a future that resolves to `7`.
+#[async_backtrace::framed]
+pub fn framed() -> impl Future<Output = u32> {
+    future::ready(7)
+}
+
+mod nested {
+    use async_backtrace;
+    use futures::future::{self, Future};
+
+    pub fn backtrace() -> impl Future<Output = Vec<String>> {
+        async_backtrace::frame!(future::lazy(|_| {
+            async_backtrace::backtrace()
+                .unwrap()
+                .iter()
+                .map(|location| location.to_string())
+                .collect()
+        }))
+    }
+}
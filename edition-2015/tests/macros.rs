@@ -0,0 +1,38 @@
+/// A test that the macros of `async-backtrace` expand, and behave as they do
+/// in later editions, in edition-2015 crates.
+extern crate async_backtrace_edition_2015 as legacy;
+extern crate futures;
+
+use futures::executor::block_on;
+
+#[test]
+fn macros() {
+    assert_eq!(
+        legacy::location().to_string(),
+        format!("async_backtrace_edition_2015::location at {}:19:5", file())
+    );
+    assert_eq!(
+        legacy::location_named().to_string(),
+        format!("legacy::root at {}:24:5", file())
+    );
+    assert_eq!(
+        block_on(legacy::backtrace()),
+        [
+            format!(
+                "async_backtrace_edition_2015::nested::backtrace at {}:43:9",
+                file()
+            ),
+            format!("async_backtrace_edition_2015::backtrace at {}:14:5", file()),
+        ]
+    );
+    assert_eq!(block_on(legacy::catching()), 42);
+    assert_eq!(block_on(legacy::framed()), 7);
+}
+
+fn file() -> &'static str {
+    if cfg!(windows) {
+        "edition-2015\\src\\lib.rs"
+    } else {
+        "edition-2015/src/lib.rs"
+    }
+}